@@ -45,6 +45,7 @@ fn benchmark_transforms(c: &mut Criterion) {
         origin: Vector2::new(0.0, 0.0),
         offset: Vector2::new(transform.translation_x, transform.translation_y),
         scale: ((transform.scale_x + transform.scale_y) / 2.0), // Average scale as GerberTransform uses uniform scaling
+        anchor: None,
     };
 
     let matrix = gerber_transform.to_matrix();
@@ -3,11 +3,11 @@ use std::io::BufReader;
 use eframe::emath::Rect;
 use eframe::epaint::Color32;
 use egui::ViewportBuilder;
-use nalgebra::Vector2;
+use nalgebra::{Point2, Vector2};
 use gerber_viewer::gerber_parser::parse;
-use gerber_viewer::{draw_arrow, draw_crosshair, draw_marker, draw_outline, GerberLayer, GerberRenderer, RenderConfiguration, ToPosition, UiState, ViewState};
+use gerber_viewer::{draw_arrow, draw_bounding_box_dimensions, draw_grid, draw_measurement, draw_outline, draw_styled_marker, DimensionSettings, MarkerShape, MarkerSize, MarkerStyle, MeasurementSettings, OverlayColors, GerberLayer, GridSettings, GerberRenderer, RenderConfiguration, ToPosition, UiState, ViewState};
 use gerber_viewer::BoundingBox;
-use gerber_viewer::GerberTransform;
+use gerber_viewer::{GerberTransform, TransformAnchor};
 
 const ENABLE_UNIQUE_SHAPE_COLORS: bool = true;
 const ENABLE_VERTEX_NUMBERING: bool = false;
@@ -29,10 +29,11 @@ const DESIGN_OFFSET: Vector2<f64> = Vector2::new(-5.0, -10.0);
 //const DESIGN_OFFSET: Vector2<f64> = Vector2::new(-10.0, -10.0);
 
 // radius of the markers, in gerber coordinates
-const MARKER_RADIUS: f32 = 2.5;
+const MARKER_RADIUS: f64 = 2.5;
 
 struct DemoApp {
     gerber_layer: GerberLayer,
+    renderer: GerberRenderer,
     renderer_configuration: RenderConfiguration,
     view_state: ViewState,
     ui_state: UiState,
@@ -41,6 +42,35 @@ struct DemoApp {
     last_frame_time: std::time::Instant,
 }
 
+struct DemoMarkers {
+    origin: MarkerStyle,
+    center: MarkerStyle,
+    design_offset: MarkerStyle,
+    design_origin: MarkerStyle,
+    datum: MarkerStyle,
+}
+
+impl DemoMarkers {
+    fn new(colors: &OverlayColors) -> Self {
+        Self {
+            origin: MarkerStyle::origin(colors),
+            center: MarkerStyle::center(colors),
+            design_offset: MarkerStyle {
+                size: MarkerSize::Board(MARKER_RADIUS),
+                ..MarkerStyle::new(colors)
+            }
+            .with_label("Design offset"),
+            design_origin: MarkerStyle {
+                shape: MarkerShape::Cross,
+                size: MarkerSize::Board(MARKER_RADIUS),
+                ..MarkerStyle::new(colors)
+            }
+            .with_label("Design origin"),
+            datum: MarkerStyle::datum(colors),
+        }
+    }
+}
+
 impl DemoApp {
     pub fn new() -> Self {
         let demo_str = include_str!("../assets/demo.gbr").as_bytes();
@@ -82,21 +112,27 @@ impl DemoApp {
             .. RenderConfiguration::default()
         };
 
-        let origin = CENTER_OFFSET - DESIGN_OFFSET;
-
         let transform = GerberTransform {
             rotation: INITIAL_ROTATION,
             mirroring: MIRRORING.into(),
-            origin,
+            // the anchor is relative to the offset design, the origin is computed from it by the renderer
+            anchor: Some(TransformAnchor::Custom(CENTER_OFFSET.to_position())),
             offset: DESIGN_OFFSET,
             ..GerberTransform::default()
         };
 
+        // show the cursor position in millimeters, whatever the units of the gerber file
+        let mut ui_state = UiState::default();
+        if let Some(units) = gerber_layer.units() {
+            ui_state.readout.layer_units = units;
+        }
+
         Self {
             gerber_layer,
+            renderer: GerberRenderer::default(),
             renderer_configuration: renderer_config,
             view_state: Default::default(),
-            ui_state: Default::default(),
+            ui_state,
             needs_view_fitting: true,
             transform,
             last_frame_time: std::time::Instant::now(),
@@ -128,6 +164,11 @@ impl eframe::App for DemoApp {
             ctx.request_repaint();
         }
 
+        // follow the dark or light mode of egui, so the overlays remain visible
+        let colors = OverlayColors::from_visuals(&ctx.style().visuals);
+        let markers = DemoMarkers::new(&colors);
+        self.renderer_configuration.set_overlay_colors(&colors);
+
         //
         // Compute bounding box and outline
         //
@@ -135,11 +176,7 @@ impl eframe::App for DemoApp {
         let bbox = self.gerber_layer.bounding_box();
 
         // Compute rotated outline (GREEN)
-        let outline_vertices: Vec<_> = bbox
-            .vertices()
-            .into_iter()
-            .map(|v| self.transform.apply_to_position(v))
-            .collect();
+        let outline_vertices = bbox.transform_vertices(&self.transform);
 
         // Compute transformed AABB (RED)
         let bbox = BoundingBox::from_points(&outline_vertices);
@@ -163,9 +200,25 @@ impl eframe::App for DemoApp {
             ui.heading("Gerber Viewer Demo");
             ui.label("by Dominic Clifton (2025)");
 
-            ui.label("Pan by using left-mouse button + drag, zoom using scroll wheel.");
+            ui.label("Pan by using left-mouse or middle-mouse button + drag, or space + drag, zoom using scroll wheel.");
 
-            ui.label(format!("coords: {:?}", self.ui_state.cursor_gerber_coords));
+            ui.label("Press 'D' to set the datum at the cursor, the cursor position is shown relative to it, 'Escape' to clear it.");
+
+            ui.label(self.ui_state.cursor_readout().unwrap_or_default());
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.ui_state.measure_mode, "Measure by dragging with the left-mouse button");
+                if let Some(measurement) = &self.ui_state.measurement {
+                    ui.label(measurement.format(&self.ui_state.readout));
+                }
+            });
+
+            // painted after this panel, so these are the stats of the previous frame
+            let stats = self.renderer.render_stats();
+            ui.label(format!(
+                "primitives: {}, culled: {}, vertices: {}, paint time: {:?}",
+                stats.primitives, stats.culled, stats.vertices, stats.paint_time
+            ));
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -182,16 +235,28 @@ impl eframe::App for DemoApp {
                 //
                 self.ui_state.update(ui, &viewport, &response, &mut self.view_state);
 
+                if response.hovered() {
+                    if ui.input(|i| i.key_pressed(egui::Key::D)) {
+                        self.view_state.datum = self.ui_state.cursor_gerber_coords;
+                    }
+                    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        self.view_state.datum = None;
+                    }
+                }
+
                 //
                 // Show the gerber layer and other overlays
                 //
 
                 let painter = ui.painter().with_clip_rect(viewport);
+
+                draw_grid(&painter, viewport, &self.view_state, &GridSettings::default());
                 
-                draw_crosshair(&painter, self.ui_state.origin_screen_pos, Color32::BLUE);
-                draw_crosshair(&painter, self.ui_state.center_screen_pos, Color32::LIGHT_GRAY);
+                draw_styled_marker(&painter, &self.view_state, Point2::new(0.0, 0.0), &markers.origin);
+                let center = self.view_state.screen_to_gerber_coords(self.ui_state.center_screen_pos);
+                draw_styled_marker(&painter, &self.view_state, center, &markers.center);
 
-                GerberRenderer::default().paint_layer(
+                self.renderer.paint_layer(
                     &painter,
                     self.view_state,
                     &self.gerber_layer,
@@ -202,17 +267,46 @@ impl eframe::App for DemoApp {
                 
                 // if you want to display multiple layers, call `paint_layer` for each layer. 
 
-                draw_outline(&painter, bbox_vertices_screen, Color32::RED);
-                draw_outline(&painter, outline_vertices_screen, Color32::GREEN);
+                // highlight the primitive under the cursor, and show its info in a tooltip
+                if let Some(hover_position) = response.hover_pos() {
+                    if let Some(summary) = self.renderer.paint_hover(
+                        &painter,
+                        self.view_state,
+                        &self.gerber_layer,
+                        &self.transform,
+                        hover_position,
+                        2.0,
+                        Color32::from_rgba_unmultiplied(255, 255, 0, 128),
+                    ) {
+                        response.on_hover_text_at_pointer(summary.to_string());
+                    }
+                }
 
-                let screen_radius = MARKER_RADIUS * self.view_state.scale;
+                draw_outline(&painter, bbox_vertices_screen, colors.bounding_box);
+                draw_outline(&painter, outline_vertices_screen, colors.outline);
+
+                // the size of the transformed AABB, in the units of the cursor readout
+                draw_bounding_box_dimensions(
+                    &painter,
+                    &self.view_state,
+                    &DimensionSettings::default(),
+                    &bbox,
+                    &self.ui_state.readout,
+                );
+
+                if let Some(measurement) = &self.ui_state.measurement {
+                    draw_measurement(&painter, &self.view_state, &MeasurementSettings::default(), measurement, &self.ui_state.readout);
+                }
 
                 let design_offset_screen_position = self.view_state.gerber_to_screen_coords(DESIGN_OFFSET.to_position());
-                draw_arrow(&painter, design_offset_screen_position, self.ui_state.origin_screen_pos, Color32::ORANGE);
-                draw_marker(&painter, design_offset_screen_position, Color32::ORANGE, Color32::YELLOW, screen_radius);
+                draw_arrow(&painter, design_offset_screen_position, self.ui_state.origin_screen_pos, colors.marker);
+                draw_styled_marker(&painter, &self.view_state, DESIGN_OFFSET.to_position(), &markers.design_offset);
+
+                draw_styled_marker(&painter, &self.view_state, (CENTER_OFFSET - DESIGN_OFFSET).to_position(), &markers.design_origin);
 
-                let design_origin_screen_position = self.view_state.gerber_to_screen_coords((CENTER_OFFSET - DESIGN_OFFSET).to_position());
-                draw_marker(&painter, design_origin_screen_position, Color32::PURPLE, Color32::MAGENTA, screen_radius);
+                if let Some(datum) = self.view_state.datum {
+                    draw_styled_marker(&painter, &self.view_state, datum, &markers.datum);
+                }
             });
         });
     }
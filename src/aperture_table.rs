@@ -0,0 +1,138 @@
+use egui::{Grid, Ui};
+use gerber_types::Unit;
+
+use crate::{sort_apertures, ApertureInfo, ApertureSortKey, GerberLayer};
+
+/// A table of the apertures of a layer, with their D-code, shape, size and usage, sorted by clicking the column
+/// headers.  Clicking an aperture selects it, clicking it again clears the selection.
+///
+/// Keep the table between frames, e.g. in your app state, and highlight the flashes of the selected aperture after
+/// painting the layer, e.g.
+/// ```ignore
+/// self.aperture_table.show(ui, &layer);
+/// // ...
+/// renderer.paint_layer(&painter, view, &layer, color, &configuration, &transform);
+/// let flashes = self.aperture_table.highlighted_primitives(&layer);
+/// renderer.paint_highlight(&painter, view, &layer, &transform, flashes, Color32::YELLOW);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ApertureTable {
+    pub sort: ApertureSortKey,
+    /// `false` by default, i.e. ascending.
+    pub descending: bool,
+    selected: Option<i32>,
+    /// The apertures of the layer with the id, sorted.
+    apertures: Option<(u64, Vec<ApertureInfo>)>,
+    /// The flashes of the aperture of the layer with the id.
+    flashes: Option<(u64, i32, Vec<usize>)>,
+}
+
+impl ApertureTable {
+    /// The D-code of the selected aperture.
+    pub fn selected(&self) -> Option<i32> {
+        self.selected
+    }
+
+    pub fn set_selected(&mut self, code: Option<i32>) {
+        self.selected = code;
+    }
+
+    /// The indices of the primitives of the flashes of the selected aperture, for
+    /// [`crate::GerberRenderer::paint_highlight`], empty if no aperture is selected.
+    ///
+    /// The primitives are cached until another aperture is selected or the layer is rebuilt.
+    pub fn highlighted_primitives(&mut self, layer: &GerberLayer) -> &[usize] {
+        let Some(code) = self.selected else {
+            return &[];
+        };
+
+        if !matches!(&self.flashes, Some((id, cached_code, _)) if *id == layer.id() && *cached_code == code) {
+            self.flashes = Some((layer.id(), code, layer.flashes_of_aperture(code)));
+        }
+        self.flashes
+            .as_ref()
+            .map(|(_, _, flashes)| flashes.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Shows the table, returns the D-code of the aperture clicked this frame.
+    pub fn show(&mut self, ui: &mut Ui, layer: &GerberLayer) -> Option<i32> {
+        if !matches!(&self.apertures, Some((id, _)) if *id == layer.id()) {
+            let mut apertures = layer.apertures();
+            sort_apertures(&mut apertures, self.sort, !self.descending);
+            self.apertures = Some((layer.id(), apertures));
+            self.selected = None;
+        }
+
+        let units = match layer.units() {
+            Some(Unit::Millimeters) => " mm",
+            Some(Unit::Inches) => " in",
+            None => "",
+        };
+
+        let mut clicked = None;
+        let mut sort = None;
+        Grid::new(ui.id().with("aperture_table"))
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for (key, name) in [
+                    (ApertureSortKey::Code, "D-code"),
+                    (ApertureSortKey::Shape, "Shape"),
+                    (ApertureSortKey::Size, "Size"),
+                    (ApertureSortKey::Usage, "Usage"),
+                ] {
+                    let text = match (self.sort == key, self.descending) {
+                        (true, false) => format!("{name} ⏶"),
+                        (true, true) => format!("{name} ⏷"),
+                        (false, _) => name.to_string(),
+                    };
+                    if ui
+                        .selectable_label(self.sort == key, text)
+                        .clicked()
+                    {
+                        sort = Some(key);
+                    }
+                }
+                ui.end_row();
+
+                let Some((_, apertures)) = &self.apertures else {
+                    return;
+                };
+                for aperture in apertures {
+                    let selected = self.selected == Some(aperture.code);
+                    if ui
+                        .selectable_label(selected, format!("D{}", aperture.code))
+                        .clicked()
+                    {
+                        clicked = Some(aperture.code);
+                    }
+                    ui.label(aperture.shape.to_string());
+                    ui.label(format!("{:.3} x {:.3}{units}", aperture.width, aperture.height));
+                    ui.label(format!(
+                        "{} flashes, {} draws",
+                        aperture.usage.flashes, aperture.usage.draws
+                    ))
+                    .on_hover_text("D03 flashes and D01 draws");
+                    ui.end_row();
+                }
+            });
+
+        if let Some(key) = sort {
+            // clicking the sorted column reverses the order
+            self.descending = self.sort == key && !self.descending;
+            self.sort = key;
+            if let Some((_, apertures)) = &mut self.apertures {
+                sort_apertures(apertures, self.sort, !self.descending);
+            }
+        }
+        if let Some(code) = clicked {
+            self.selected = match self.selected == Some(code) {
+                true => None,
+                false => Some(code),
+            };
+        }
+
+        clicked
+    }
+}
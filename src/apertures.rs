@@ -0,0 +1,209 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+use gerber_types::{Aperture, Command, DCode, FunctionCode, Operation};
+
+use crate::geometry::BoundingBox;
+use crate::{ApertureUsage, GerberLayer};
+
+/// The shape of an aperture, see [`ApertureInfo`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApertureShape {
+    Circle,
+    Rectangle,
+    Obround,
+    Polygon {
+        vertices: u32,
+    },
+    /// The name of the macro.
+    Macro(String),
+}
+
+impl Display for ApertureShape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApertureShape::Circle => write!(f, "Circle"),
+            ApertureShape::Rectangle => write!(f, "Rectangle"),
+            ApertureShape::Obround => write!(f, "Obround"),
+            ApertureShape::Polygon {
+                vertices,
+            } => write!(f, "Polygon ({vertices} vertices)"),
+            ApertureShape::Macro(name) => write!(f, "Macro {name}"),
+        }
+    }
+}
+
+/// An aperture of a layer, see [`GerberLayer::apertures`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApertureInfo {
+    /// The D-code.
+    pub code: i32,
+    pub shape: ApertureShape,
+    /// The size of the bounding box of a flash, in the units of the layer, zero for macros that could not be evaluated.
+    pub width: f64,
+    pub height: f64,
+    pub usage: ApertureUsage,
+}
+
+/// How apertures are sorted, see [`sort_apertures`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApertureSortKey {
+    #[default]
+    Code,
+    Shape,
+    /// By area, then by width.
+    Size,
+    /// By the number of flashes and draws.
+    Usage,
+}
+
+impl GerberLayer {
+    /// Returns the standard and macro apertures defined in the layer, in the order they were defined, with their size
+    /// and usage, e.g. for an aperture table.
+    pub fn apertures(&self) -> Vec<ApertureInfo> {
+        self.aperture_definitions()
+            .map(|definition| {
+                let (shape, width, height) = match &definition.aperture {
+                    Aperture::Circle(circle) => (ApertureShape::Circle, circle.diameter, circle.diameter),
+                    Aperture::Rectangle(rectangle) => (ApertureShape::Rectangle, rectangle.x, rectangle.y),
+                    Aperture::Obround(rectangle) => (ApertureShape::Obround, rectangle.x, rectangle.y),
+                    Aperture::Polygon(polygon) => (
+                        ApertureShape::Polygon {
+                            vertices: polygon.vertices as u32,
+                        },
+                        polygon.diameter,
+                        polygon.diameter,
+                    ),
+                    Aperture::Macro(name, _) => {
+                        let mut bbox = BoundingBox::default();
+                        for primitive in self
+                            .macro_aperture_primitives(definition.code)
+                            .into_iter()
+                            .flatten()
+                        {
+                            bbox.expand(&primitive.bounding_box());
+                        }
+                        let (width, height) = match bbox.is_empty() {
+                            true => (0.0, 0.0),
+                            false => (bbox.width(), bbox.height()),
+                        };
+                        (ApertureShape::Macro(name.clone()), width, height)
+                    }
+                };
+
+                ApertureInfo {
+                    code: definition.code,
+                    shape,
+                    width,
+                    height,
+                    usage: self.aperture_usage(definition.code),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the indices of the primitives created by flashing the aperture, all the primitives of each flash of a
+    /// macro aperture are included, e.g. to highlight the pads that use an aperture.
+    pub fn flashes_of_aperture(&self, code: i32) -> Vec<usize> {
+        (0..self.gerber_primitives().len())
+            .filter(|index| {
+                self.primitive_info(*index)
+                    .is_some_and(|info| info.aperture == Some(code))
+                    && matches!(
+                        self.command_for_primitive(*index),
+                        Some(Command::FunctionCode(FunctionCode::DCode(DCode::Operation(
+                            Operation::Flash(..)
+                        ))))
+                    )
+            })
+            .collect()
+    }
+}
+
+/// Sorts the apertures by the key, ties are sorted by D-code.
+pub fn sort_apertures(apertures: &mut [ApertureInfo], key: ApertureSortKey, ascending: bool) {
+    apertures.sort_by(|a, b| {
+        let ordering = match key {
+            ApertureSortKey::Code => Ordering::Equal,
+            ApertureSortKey::Shape => a
+                .shape
+                .to_string()
+                .cmp(&b.shape.to_string()),
+            ApertureSortKey::Size => (a.width * a.height)
+                .total_cmp(&(b.width * b.height))
+                .then(a.width.total_cmp(&b.width)),
+            ApertureSortKey::Usage => (a.usage.flashes + a.usage.draws).cmp(&(b.usage.flashes + b.usage.draws)),
+        }
+        .then(a.code.cmp(&b.code));
+
+        match ascending {
+            true => ordering,
+            false => ordering.reverse(),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Circle, GCode, InterpolationMode, Rectangular, Unit};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::testing::{aperture, flash, header, interpolate};
+
+    /// D10, a circle, is flashed twice and drawn once, D11, a rectangle, is flashed once.
+    fn layer() -> GerberLayer {
+        let mut commands = header(Unit::Millimeters);
+        commands.push(Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(
+            InterpolationMode::Linear,
+        ))));
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(0.5))));
+        commands.extend([flash(0.0, 0.0), interpolate(5.0, 0.0), flash(10.0, 0.0)]);
+        commands.extend(aperture(11, Aperture::Rectangle(Rectangular::new(2.0, 1.0))));
+        commands.push(flash(20.0, 0.0));
+        GerberLayer::new(commands)
+    }
+
+    #[test]
+    fn test_apertures() {
+        // given
+        let layer = layer();
+
+        // when
+        let apertures = layer.apertures();
+
+        // then
+        assert_eq!(apertures.len(), 2);
+        assert_eq!(apertures[0].code, 10);
+        assert_eq!(apertures[0].shape, ApertureShape::Circle);
+        assert_eq!(apertures[0].usage, ApertureUsage {
+            flashes: 2,
+            draws: 1,
+        });
+        assert_eq!((apertures[1].width, apertures[1].height), (2.0, 1.0));
+
+        // and
+        assert_eq!(layer.flashes_of_aperture(10), vec![0, 2]);
+        assert_eq!(layer.flashes_of_aperture(11), vec![3]);
+    }
+
+    #[rstest]
+    #[case(ApertureSortKey::Code, false, vec![11, 10])]
+    #[case(ApertureSortKey::Size, true, vec![10, 11])]
+    #[case(ApertureSortKey::Usage, false, vec![10, 11])]
+    #[case(ApertureSortKey::Shape, false, vec![11, 10])]
+    fn test_sort_apertures(#[case] key: ApertureSortKey, #[case] ascending: bool, #[case] expected: Vec<i32>) {
+        // given
+        let mut apertures = layer().apertures();
+
+        // when
+        sort_apertures(&mut apertures, key, ascending);
+
+        // then
+        let codes = apertures
+            .iter()
+            .map(|aperture| aperture.code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, expected);
+    }
+}
@@ -0,0 +1,106 @@
+use geo::Area;
+use gerber_types::Unit;
+
+use crate::outline::signed_area;
+use crate::GerberLayer;
+
+/// The area of the merged primitives of a layer, see [`GerberLayer::copper_area`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopperArea {
+    /// mm²
+    pub total: f64,
+    /// The area of each merged polygon, excluding holes, in the same order as [`GerberLayer::merged_polygons`], mm²
+    pub regions: Vec<f64>,
+}
+
+impl CopperArea {
+    /// The total area as a percentage of the board area, see [`GerberLayer::board_area`].
+    pub fn percent_of(&self, board_area: f64) -> f64 {
+        match board_area > 0.0 {
+            true => self.total / board_area * 100.0,
+            false => 0.0,
+        }
+    }
+}
+
+impl GerberLayer {
+    /// The area of the merged primitives of the layer, see [`GerberLayer::merged_polygons`], e.g. for plating and
+    /// thermal estimates.
+    ///
+    /// Returns `None` if the layer has no units.
+    pub fn copper_area(&self) -> Option<CopperArea> {
+        let scale = self.scale_to(Unit::Millimeters)?;
+        let area_scale = scale * scale;
+
+        let regions = self
+            .merged_polygons()
+            .iter()
+            .map(|polygon| polygon.unsigned_area() * area_scale)
+            .collect::<Vec<_>>();
+
+        Some(CopperArea {
+            total: regions.iter().sum(),
+            regions,
+        })
+    }
+
+    /// The area enclosed by the board outline, when this layer is an outline layer, mm².
+    ///
+    /// The board is the largest contour of the outline, see [`GerberLayer::board_outline`] for `tolerance`.  The
+    /// center-line of the outline is used, so the width of the outline stroke does not change the area, and cut-outs
+    /// in the board are not subtracted.
+    ///
+    /// Returns `None` if the layer has no units, or has no closed outline.
+    pub fn board_area(&self, tolerance: f64) -> Option<f64> {
+        let scale = self.scale_to(Unit::Millimeters)?;
+
+        self.board_outline(tolerance)
+            .contours
+            .first()
+            .map(|contour| signed_area(contour) * scale * scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Command, FunctionCode, GCode, InterpolationMode, Rectangular};
+
+    use super::*;
+    use crate::testing::{aperture, flash_layer, header, interpolate, move_to};
+
+    #[test]
+    fn test_copper_area_is_in_square_millimeters() {
+        // given
+        let layer = flash_layer(Unit::Inches, Aperture::Rectangle(Rectangular::new(1.0, 1.0)), 0.0, 0.0);
+
+        // when
+        let area = layer.copper_area().unwrap();
+
+        // then
+        assert!((area.total - 25.4 * 25.4).abs() < 1e-6);
+        assert_eq!(area.regions.len(), 1);
+    }
+
+    #[test]
+    fn test_board_area() {
+        // given
+        // a 10x10 outline, drawn with a 0.2 width aperture
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(0.2))));
+        commands.extend([
+            Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(InterpolationMode::Linear))),
+            move_to(0.0, 0.0),
+            interpolate(10.0, 0.0),
+            interpolate(10.0, 10.0),
+            interpolate(0.0, 10.0),
+            interpolate(0.0, 0.0),
+        ]);
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let area = layer.board_area(0.01).unwrap();
+
+        // then
+        assert_eq!(area, 100.0);
+    }
+}
@@ -0,0 +1,271 @@
+use gerber_types::{Command, ExtendedCode, PartialGerberCode};
+use log::warn;
+
+/// A single Gerber X2 attribute, e.g. `.FileFunction,Copper,L1,Top` or `.N,GND`.
+///
+/// The name includes the leading '.' for standard attributes, user-defined attributes have no leading '.'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl Attribute {
+    /// Returns the first value, if any.
+    pub fn value(&self) -> Option<&str> {
+        self.values.first().map(String::as_str)
+    }
+
+    /// Converts a typed attribute, e.g. a `gerber_types::FileAttribute`, using the content of its attribute command,
+    /// so that every kind of attribute, including user-defined attributes, is handled uniformly.
+    fn from_typed(attribute: &impl PartialGerberCode<Vec<u8>>) -> Option<Self> {
+        let mut content = Vec::new();
+        attribute
+            .serialize_partial(&mut content)
+            .inspect_err(|error| warn!("Unable to serialize attribute. error: {:?}", error))
+            .ok()?;
+
+        Self::parse(&String::from_utf8(content).ok()?)
+    }
+
+    /// Parses the content of an attribute command without the command code, e.g. `.N,GND,VCC`.
+    fn parse(content: &str) -> Option<Self> {
+        let mut parts = content.split(',');
+        let name = parts.next()?.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            name: name.to_string(),
+            values: parts
+                .map(|value| value.trim().to_string())
+                .collect(),
+        })
+    }
+}
+
+/// An ordered attribute dictionary.
+///
+/// Gerber spec 2024.05 - 5.1 - "An attribute with the same name replaces the previous one"
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Attributes(Vec<Attribute>);
+
+impl Attributes {
+    pub fn get(&self, name: &str) -> Option<&Attribute> {
+        self.0
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Attribute> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub(crate) fn set(&mut self, attribute: Attribute) {
+        match self
+            .0
+            .iter_mut()
+            .find(|candidate| candidate.name == attribute.name)
+        {
+            Some(existing) => *existing = attribute,
+            None => self.0.push(attribute),
+        }
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) -> bool {
+        let len = self.0.len();
+        self.0
+            .retain(|attribute| attribute.name != name);
+        self.0.len() != len
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// The attributes attached to a primitive, i.e. the aperture attributes of the aperture used to create it and the
+/// object attributes that were in effect when it was created.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrimitiveAttributes {
+    pub aperture: Attributes,
+    pub object: Attributes,
+}
+
+impl PrimitiveAttributes {
+    /// Object attributes are searched first, then aperture attributes.
+    pub fn get(&self, name: &str) -> Option<&Attribute> {
+        self.object
+            .get(name)
+            .or_else(|| self.aperture.get(name))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aperture.is_empty() && self.object.is_empty()
+    }
+
+    /// The net names from the `.N` object attribute.
+    ///
+    /// Gerber spec 2024.05 - 5.6.13 - a `.N` attribute with an empty value indicates the object is not connected to a
+    /// net, an empty iterator is returned in that case.
+    pub fn nets(&self) -> impl Iterator<Item = &str> {
+        self.object
+            .get(NET_ATTRIBUTE)
+            .into_iter()
+            .flat_map(|attribute| attribute.values.iter())
+            .map(String::as_str)
+            .filter(|net| !net.is_empty())
+    }
+
+    /// The first net name from the `.N` object attribute.
+    pub fn net(&self) -> Option<&str> {
+        self.nets().next()
+    }
+
+    pub fn is_on_net(&self, name: &str) -> bool {
+        self.nets().any(|net| net == name)
+    }
+}
+
+/// Object attribute for the net name(s)
+pub const NET_ATTRIBUTE: &str = ".N";
+
+/// The attribute commands, TF, TA, TO and TD.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AttributeCommand {
+    File(Attribute),
+    Aperture(Attribute),
+    Object(Attribute),
+    /// `None` deletes all aperture and object attributes.
+    Delete(Option<String>),
+}
+
+impl AttributeCommand {
+    /// Returns `None` for non-attribute commands.
+    pub(crate) fn from_command(command: &Command) -> Option<Self> {
+        match command {
+            Command::ExtendedCode(ExtendedCode::FileAttribute(attribute)) => {
+                Attribute::from_typed(attribute).map(AttributeCommand::File)
+            }
+            Command::ExtendedCode(ExtendedCode::ApertureAttribute(attribute)) => {
+                Attribute::from_typed(attribute).map(AttributeCommand::Aperture)
+            }
+            Command::ExtendedCode(ExtendedCode::ObjectAttribute(attribute)) => {
+                Attribute::from_typed(attribute).map(AttributeCommand::Object)
+            }
+            Command::ExtendedCode(ExtendedCode::DeleteAttribute(name)) => {
+                let name = name.trim();
+                Some(AttributeCommand::Delete(match name.is_empty() {
+                    true => None,
+                    false => Some(name.to_string()),
+                }))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Collects the file attributes (TF) from the commands.
+///
+/// File attributes are immutable and apply to the whole file regardless of their position.
+pub(crate) fn collect_file_attributes(commands: &[Command]) -> Attributes {
+    let mut attributes = Attributes::default();
+    for command in commands {
+        if let Some(AttributeCommand::File(attribute)) = AttributeCommand::from_command(command) {
+            attributes.set(attribute);
+        }
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("%TF.FileFunction,Copper,L1,Top*%", AttributeCommand::File(Attribute { name: ".FileFunction".to_string(), values: vec!["Copper".to_string(), "L1".to_string(), "Top".to_string()] }))]
+    #[case("%TA.AperFunction,SMDPad,CuDef*%", AttributeCommand::Aperture(Attribute { name: ".AperFunction".to_string(), values: vec!["SMDPad".to_string(), "CuDef".to_string()] }))]
+    #[case("%TO.N,GND*%", AttributeCommand::Object(Attribute { name: ".N".to_string(), values: vec!["GND".to_string()] }))]
+    #[case("%TOUserName*%", AttributeCommand::Object(Attribute { name: "UserName".to_string(), values: vec![] }))]
+    #[case("%TD.N*%", AttributeCommand::Delete(Some(".N".to_string())))]
+    #[case("%TD*%", AttributeCommand::Delete(None))]
+    #[cfg(feature = "parser")]
+    fn test_from_command(#[case] source: &str, #[case] expected: AttributeCommand) {
+        // given
+        let doc = gerber_parser::parse(std::io::BufReader::new(source.as_bytes())).unwrap();
+
+        // when
+        let command = doc
+            .into_commands()
+            .iter()
+            .find_map(AttributeCommand::from_command);
+
+        // then
+        assert_eq!(command, Some(expected));
+    }
+
+    #[test]
+    fn test_set_replaces_attribute_with_same_name() {
+        // given
+        let mut attributes = Attributes::default();
+        attributes.set(Attribute::parse(".N,GND").unwrap());
+
+        // when
+        attributes.set(Attribute::parse(".N,VCC").unwrap());
+
+        // then
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(
+            attributes
+                .get(".N")
+                .and_then(Attribute::value),
+            Some("VCC")
+        );
+    }
+
+    #[rstest]
+    #[case(".N,GND", vec!["GND"])]
+    #[case(".N,Net1,Net2", vec!["Net1", "Net2"])]
+    #[case(".N,", vec![])]
+    fn test_nets(#[case] content: &str, #[case] expected: Vec<&str>) {
+        // given
+        let mut attributes = PrimitiveAttributes::default();
+        attributes
+            .object
+            .set(Attribute::parse(content).unwrap());
+
+        // expect
+        assert_eq!(attributes.nets().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_object_attributes_take_precedence() {
+        // given
+        let mut attributes = PrimitiveAttributes::default();
+        attributes
+            .aperture
+            .set(Attribute::parse("Custom,Aperture").unwrap());
+        attributes
+            .object
+            .set(Attribute::parse("Custom,Object").unwrap());
+
+        // expect
+        assert_eq!(
+            attributes
+                .get("Custom")
+                .and_then(Attribute::value),
+            Some("Object")
+        );
+    }
+}
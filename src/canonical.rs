@@ -0,0 +1,276 @@
+use std::collections::HashSet;
+
+use gerber_types::{
+    ApertureBlock, ApertureDefinition, Command, CoordinateFormat, CoordinateNumber, CoordinateOffset, Coordinates,
+    DCode, ExtendedCode, FunctionCode, GCode, InterpolationMode, Operation, Polarity, QuadrantMode,
+};
+use log::{debug, warn};
+use nalgebra::Point2;
+
+/// Normalizes a command stream, e.g. before diffing or re-exporting, without changing the image.
+///
+/// * Redundant mode changes are removed, i.e. G01/G02/G03, G74/G75, LPD/LPC and aperture selections (Dnn) that don't
+///   change the current mode.
+/// * Unused apertures are removed, i.e. aperture definitions and block apertures that are never flashed or drawn.
+/// * All coordinates use the coordinate format (FS) of the file, and specify both X and Y, instead of relying on the
+///   previous position.
+///
+/// The modes are tracked separately within block apertures (AB), since the contents of a block are replayed where the
+/// block is flashed. For the same reason, coordinates within blocks are not made absolute.
+pub fn canonicalize_commands(commands: Vec<Command>) -> Vec<Command> {
+    let used_apertures = used_apertures(&commands);
+    let format = commands
+        .iter()
+        .find_map(|command| match command {
+            Command::ExtendedCode(ExtendedCode::CoordinateFormat(format)) => Some(*format),
+            _ => None,
+        });
+
+    let mut result = Vec::with_capacity(commands.len());
+    let mut state = ModalState::default();
+    let mut position = Point2::new(0.0, 0.0);
+    let mut block_depth = 0_usize;
+    // the commands within a block don't change the position outside the block
+    let mut outer_position = position;
+    // > 0 while skipping the commands of an unused block aperture
+    let mut skip_depth = 0_usize;
+
+    for command in commands {
+        match &command {
+            Command::ExtendedCode(ExtendedCode::ApertureBlock(ApertureBlock::Open {
+                code,
+            })) => {
+                if skip_depth > 0 || !used_apertures.contains(code) {
+                    skip_depth += 1;
+                    continue;
+                }
+                if block_depth == 0 {
+                    outer_position = position;
+                }
+                block_depth += 1;
+                state = ModalState::default();
+            }
+            Command::ExtendedCode(ExtendedCode::ApertureBlock(ApertureBlock::Close)) => {
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                    continue;
+                }
+                block_depth = block_depth.saturating_sub(1);
+                if block_depth == 0 {
+                    position = outer_position;
+                }
+                state = ModalState::default();
+            }
+            _ if skip_depth > 0 => continue,
+            Command::ExtendedCode(ExtendedCode::ApertureDefinition(ApertureDefinition {
+                code, ..
+            })) => {
+                if !used_apertures.contains(code) {
+                    debug!("Removing unused aperture. code: {}", code);
+                    continue;
+                }
+            }
+            Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(mode))) => {
+                if state.interpolation_mode == Some(*mode) {
+                    continue;
+                }
+                state.interpolation_mode = Some(*mode);
+            }
+            Command::FunctionCode(FunctionCode::GCode(GCode::QuadrantMode(mode))) => {
+                if state.quadrant_mode == Some(*mode) {
+                    continue;
+                }
+                state.quadrant_mode = Some(*mode);
+            }
+            Command::ExtendedCode(ExtendedCode::LoadPolarity(polarity)) => {
+                if state.polarity == Some(*polarity) {
+                    continue;
+                }
+                state.polarity = Some(*polarity);
+            }
+            Command::FunctionCode(FunctionCode::DCode(DCode::SelectAperture(code))) => {
+                if state.aperture == Some(*code) || !used_apertures.contains(code) {
+                    continue;
+                }
+                state.aperture = Some(*code);
+            }
+            Command::FunctionCode(FunctionCode::DCode(DCode::Operation(operation))) => {
+                if let Some(format) = format {
+                    let operation = canonical_operation(operation, format, &mut position, block_depth == 0);
+                    result.push(Command::FunctionCode(FunctionCode::DCode(DCode::Operation(operation))));
+                    continue;
+                }
+            }
+            _ => {}
+        }
+
+        result.push(command);
+    }
+
+    result
+}
+
+#[derive(Debug, Default)]
+struct ModalState {
+    interpolation_mode: Option<InterpolationMode>,
+    quadrant_mode: Option<QuadrantMode>,
+    polarity: Option<Polarity>,
+    aperture: Option<i32>,
+}
+
+/// An aperture is used if it is selected when a flash (D03), or a draw (D01) outside of a region, is made.
+fn used_apertures(commands: &[Command]) -> HashSet<i32> {
+    let mut used = HashSet::new();
+    let mut current_aperture = None;
+    let mut in_region = false;
+
+    for command in commands {
+        match command {
+            Command::FunctionCode(FunctionCode::DCode(DCode::SelectAperture(code))) => current_aperture = Some(*code),
+            Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(enabled))) => in_region = *enabled,
+            Command::FunctionCode(FunctionCode::DCode(DCode::Operation(Operation::Flash(..)))) => {
+                used.extend(current_aperture);
+            }
+            Command::FunctionCode(FunctionCode::DCode(DCode::Operation(Operation::Interpolate(..)))) if !in_region => {
+                used.extend(current_aperture);
+            }
+            _ => {}
+        }
+    }
+
+    used
+}
+
+fn canonical_operation(
+    operation: &Operation,
+    format: CoordinateFormat,
+    position: &mut Point2<f64>,
+    absolute: bool,
+) -> Operation {
+    match operation {
+        Operation::Move(coordinates) => Operation::Move(canonical_coordinates(coordinates, format, position, absolute)),
+        Operation::Flash(coordinates) => {
+            Operation::Flash(canonical_coordinates(coordinates, format, position, absolute))
+        }
+        Operation::Interpolate(coordinates, offset) => Operation::Interpolate(
+            canonical_coordinates(coordinates, format, position, absolute),
+            // the numbers are format independent, the format is only used when the coordinates are serialized
+            offset
+                .as_ref()
+                .map(|offset| CoordinateOffset {
+                    x: offset.x,
+                    y: offset.y,
+                    format,
+                }),
+        ),
+    }
+}
+
+fn canonical_coordinates(
+    coordinates: &Coordinates,
+    format: CoordinateFormat,
+    position: &mut Point2<f64>,
+    absolute: bool,
+) -> Coordinates {
+    let x = coordinates
+        .x
+        .map(f64::from)
+        .unwrap_or(position.x);
+    let y = coordinates
+        .y
+        .map(f64::from)
+        .unwrap_or(position.y);
+    *position = Point2::new(x, y);
+
+    if !absolute {
+        return Coordinates {
+            x: coordinates.x,
+            y: coordinates.y,
+            format,
+        };
+    }
+
+    Coordinates {
+        x: to_coordinate_number(x),
+        y: to_coordinate_number(y),
+        format,
+    }
+}
+
+fn to_coordinate_number(value: f64) -> Option<CoordinateNumber> {
+    CoordinateNumber::try_from(value)
+        .inspect_err(|error| warn!("Invalid coordinate. value: {}, error: {:?}", value, error))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Unit};
+
+    use super::*;
+
+    fn format() -> CoordinateFormat {
+        CoordinateFormat::new(2, 4)
+    }
+
+    fn coordinates(x: Option<f64>, y: Option<f64>) -> Coordinates {
+        Coordinates {
+            x: x.map(|value| CoordinateNumber::try_from(value).unwrap()),
+            y: y.map(|value| CoordinateNumber::try_from(value).unwrap()),
+            format: format(),
+        }
+    }
+
+    fn define_circle(code: i32) -> Command {
+        Command::ExtendedCode(ExtendedCode::ApertureDefinition(ApertureDefinition::new(
+            code,
+            Aperture::Circle(Circle::new(0.1)),
+        )))
+    }
+
+    fn select(code: i32) -> Command {
+        Command::FunctionCode(FunctionCode::DCode(DCode::SelectAperture(code)))
+    }
+
+    fn linear() -> Command {
+        Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(InterpolationMode::Linear)))
+    }
+
+    fn flash(x: Option<f64>, y: Option<f64>) -> Command {
+        Command::FunctionCode(FunctionCode::DCode(DCode::Operation(Operation::Flash(coordinates(
+            x, y,
+        )))))
+    }
+
+    #[test]
+    fn test_canonicalize_commands() {
+        // given
+        let commands = vec![
+            Command::ExtendedCode(ExtendedCode::CoordinateFormat(format())),
+            Command::ExtendedCode(ExtendedCode::Unit(Unit::Millimeters)),
+            define_circle(10),
+            define_circle(11),
+            linear(),
+            select(11),
+            select(10),
+            linear(),
+            flash(Some(1.0), Some(2.0)),
+            select(10),
+            flash(Some(3.0), None),
+        ];
+
+        // when
+        let result = canonicalize_commands(commands);
+
+        // then
+        assert_eq!(result, vec![
+            Command::ExtendedCode(ExtendedCode::CoordinateFormat(format())),
+            Command::ExtendedCode(ExtendedCode::Unit(Unit::Millimeters)),
+            define_circle(10),
+            linear(),
+            select(10),
+            flash(Some(1.0), Some(2.0)),
+            flash(Some(3.0), Some(2.0)),
+        ]);
+    }
+}
@@ -0,0 +1,386 @@
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::split_contours;
+use crate::layer::{
+    CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGerberPrimitive,
+    RectangleGerberPrimitive,
+};
+use crate::PrimitiveView;
+
+/// The gap between two shapes, see [`PrimitiveView::clearance_to`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clearance {
+    /// The minimum distance between the edges of the shapes, zero if they touch or overlap.
+    pub distance: f64,
+    /// The closest point on the first shape.
+    pub from: Point2<f64>,
+    /// The closest point on the second shape.
+    ///
+    /// When the shapes overlap this is the same as `from`, and is a point in the overlap.
+    pub to: Point2<f64>,
+}
+
+impl GerberLayer {
+    /// The clearance between the primitives at index `a` and `b`, e.g. for a measurement overlay.
+    ///
+    /// Each copy of a step-and-repeat primitive is considered, the closest pair is returned.
+    ///
+    /// Returns `None` if either index is out of range.
+    pub fn clearance(&self, a: usize, b: usize) -> Option<Clearance> {
+        let primitive_a = self.gerber_primitives().get(a)?;
+        let primitive_b = self.gerber_primitives().get(b)?;
+
+        let outlines_b = self
+            .primitive_offsets(b)
+            .into_iter()
+            .map(|offset| Outline::from_primitive(primitive_b, offset))
+            .collect::<Vec<_>>();
+
+        self.primitive_offsets(a)
+            .into_iter()
+            .map(|offset| Outline::from_primitive(primitive_a, offset))
+            .flat_map(|outline_a| {
+                outlines_b
+                    .iter()
+                    .map(move |outline_b| outline_a.clearance_to(outline_b))
+            })
+            .min_by(|left, right| left.distance.total_cmp(&right.distance))
+    }
+}
+
+impl PrimitiveView<'_> {
+    /// The clearance between this primitive and another primitive, ignoring the exposure of both, e.g. of primitives of
+    /// different layers.  See [`GerberLayer::clearance`] for primitives of the same layer, including the copies of
+    /// step-and-repeat primitives.
+    ///
+    /// Arcs are approximated by line segments, see [`crate::ArcView::points`].
+    pub fn clearance_to(&self, other: &PrimitiveView<'_>) -> Clearance {
+        self.primitive()
+            .clearance_to(other.primitive())
+    }
+
+    /// The clearance between this primitive and a point.
+    pub fn clearance_to_point(&self, point: Point2<f64>) -> Clearance {
+        self.primitive()
+            .clearance_to_point(point)
+    }
+
+    /// The clearance between this primitive and a filled polygon, the vertices may contain cut-ins, like the vertices of
+    /// a region.
+    pub fn clearance_to_polygon(&self, vertices: &[Point2<f64>]) -> Clearance {
+        self.primitive()
+            .clearance_to_polygon(vertices)
+    }
+}
+
+impl GerberPrimitive {
+    /// The clearance between this primitive and another primitive, ignoring the exposure of both.
+    pub(crate) fn clearance_to(&self, other: &GerberPrimitive) -> Clearance {
+        let zero = Vector2::new(0.0, 0.0);
+        Outline::from_primitive(self, zero).clearance_to(&Outline::from_primitive(other, zero))
+    }
+
+    /// The clearance between this primitive and a point.
+    pub(crate) fn clearance_to_point(&self, point: Point2<f64>) -> Clearance {
+        Outline::from_primitive(self, Vector2::new(0.0, 0.0)).clearance_to(&Outline::point(point))
+    }
+
+    /// The clearance between this primitive and a filled polygon, the vertices may contain cut-ins, like the vertices of
+    /// a region.
+    pub(crate) fn clearance_to_polygon(&self, vertices: &[Point2<f64>]) -> Clearance {
+        Outline::from_primitive(self, Vector2::new(0.0, 0.0)).clearance_to(&Outline::polygon(split_contours(vertices)))
+    }
+}
+
+/// A shape described by a set of line segments, expanded by a radius, e.g. a line is a single segment expanded by half
+/// the line width.
+struct Outline {
+    segments: Vec<(Point2<f64>, Point2<f64>)>,
+    radius: f64,
+    /// The contours of filled shapes, for testing if a point is inside the shape.
+    contours: Vec<Vec<Point2<f64>>>,
+}
+
+impl Outline {
+    fn from_primitive(primitive: &GerberPrimitive, offset: Vector2<f64>) -> Self {
+        match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => Self {
+                radius: diameter / 2.0,
+                ..Self::point(*center + offset)
+            },
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let origin = *origin + offset;
+                let corners = [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)]
+                    .map(|(x, y)| origin + Vector2::new(x, y))
+                    .to_vec();
+                Self::polygon(vec![corners])
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => Self {
+                segments: vec![(*start + offset, *end + offset)],
+                radius: width / 2.0,
+                contours: vec![],
+            },
+            GerberPrimitive::Arc(arc) => {
+                let center = arc.center + offset;
+                let points = arc
+                    .generate_points()
+                    .into_iter()
+                    .map(|point| center + point.coords)
+                    .collect::<Vec<_>>();
+                Self {
+                    segments: points
+                        .windows(2)
+                        .map(|segment| (segment[0], segment[1]))
+                        .collect(),
+                    radius: arc.width / 2.0,
+                    contours: vec![],
+                }
+            }
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let center = *center + offset;
+                let contours = split_contours(&geometry.relative_vertices)
+                    .into_iter()
+                    .map(|contour| {
+                        contour
+                            .into_iter()
+                            .map(|vertex| center + vertex.coords)
+                            .collect()
+                    })
+                    .collect();
+                Self::polygon(contours)
+            }
+        }
+    }
+
+    fn point(point: Point2<f64>) -> Self {
+        Self {
+            segments: vec![(point, point)],
+            radius: 0.0,
+            contours: vec![],
+        }
+    }
+
+    fn polygon(contours: Vec<Vec<Point2<f64>>>) -> Self {
+        let segments = contours
+            .iter()
+            .flat_map(|contour| {
+                (0..contour.len()).map(move |index| (contour[index], contour[(index + 1) % contour.len()]))
+            })
+            .collect();
+
+        Self {
+            segments,
+            radius: 0.0,
+            contours,
+        }
+    }
+
+    fn contains(&self, point: Point2<f64>) -> bool {
+        let mut inside = false;
+        for contour in &self.contours {
+            for (index, start) in contour.iter().enumerate() {
+                let end = contour[(index + 1) % contour.len()];
+                if (start.y > point.y) != (end.y > point.y)
+                    && point.x < start.x + (point.y - start.y) * (end.x - start.x) / (end.y - start.y)
+                {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    fn clearance_to(&self, other: &Outline) -> Clearance {
+        // one shape entirely inside a filled shape, without touching any edges
+        for (outer, inner) in [(self, other), (other, self)] {
+            if let Some((point, _)) = inner.segments.first() {
+                if outer.contains(*point) {
+                    return Clearance {
+                        distance: 0.0,
+                        from: *point,
+                        to: *point,
+                    };
+                }
+            }
+        }
+
+        let Some((from, to)) = self
+            .segments
+            .iter()
+            .flat_map(|(start_a, end_a)| {
+                other
+                    .segments
+                    .iter()
+                    .map(move |(start_b, end_b)| closest_points_on_segments(*start_a, *end_a, *start_b, *end_b))
+            })
+            .min_by(|(a1, b1), (a2, b2)| {
+                (*b1 - *a1)
+                    .norm()
+                    .total_cmp(&(*b2 - *a2).norm())
+            })
+        else {
+            return Clearance {
+                distance: f64::INFINITY,
+                from: Point2::origin(),
+                to: Point2::origin(),
+            };
+        };
+
+        let gap = to - from;
+        let length = gap.norm();
+        let direction = match length > 0.0 {
+            true => gap / length,
+            false => Vector2::new(0.0, 0.0),
+        };
+
+        let distance = length - self.radius - other.radius;
+        if distance <= 0.0 {
+            // a point inside both shapes
+            let point = from + direction * self.radius.min(length);
+            return Clearance {
+                distance: 0.0,
+                from: point,
+                to: point,
+            };
+        }
+
+        Clearance {
+            distance,
+            from: from + direction * self.radius,
+            to: to - direction * other.radius,
+        }
+    }
+}
+
+/// The closest pair of points on two line segments, see "Real-Time Collision Detection", Christer Ericson, 5.1.9.
+fn closest_points_on_segments(
+    start_a: Point2<f64>,
+    end_a: Point2<f64>,
+    start_b: Point2<f64>,
+    end_b: Point2<f64>,
+) -> (Point2<f64>, Point2<f64>) {
+    let direction_a = end_a - start_a;
+    let direction_b = end_b - start_b;
+    let between = start_a - start_b;
+    let length_a = direction_a.norm_squared();
+    let length_b = direction_b.norm_squared();
+    let f = direction_b.dot(&between);
+
+    let (s, t) = if length_a == 0.0 && length_b == 0.0 {
+        (0.0, 0.0)
+    } else if length_a == 0.0 {
+        (0.0, (f / length_b).clamp(0.0, 1.0))
+    } else {
+        let c = direction_a.dot(&between);
+        if length_b == 0.0 {
+            ((-c / length_a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = direction_a.dot(&direction_b);
+            let denominator = length_a * length_b - b * b;
+            // parallel segments have no single closest pair, any point will do
+            let s = match denominator != 0.0 {
+                true => ((b * f - c * length_b) / denominator).clamp(0.0, 1.0),
+                false => 0.0,
+            };
+            let t = (b * s + f) / length_b;
+            if t < 0.0 {
+                ((-c / length_a).clamp(0.0, 1.0), 0.0)
+            } else if t > 1.0 {
+                (((b - c) / length_a).clamp(0.0, 1.0), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+
+    (start_a + direction_a * s, start_b + direction_b * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::Exposure;
+
+    fn circle(x: f64, y: f64, diameter: f64) -> GerberPrimitive {
+        GerberPrimitive::Circle(CircleGerberPrimitive {
+            center: Point2::new(x, y),
+            diameter,
+            exposure: Exposure::Add,
+        })
+    }
+
+    fn assert_points_eq(actual: Point2<f64>, expected: Point2<f64>) {
+        assert!((actual - expected).norm() < 1e-9, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn test_line_to_circle_clearance() {
+        // given
+        let line = GerberPrimitive::Line(LineGerberPrimitive {
+            start: Point2::new(0.0, 0.0),
+            end: Point2::new(10.0, 0.0),
+            width: 1.0,
+            exposure: Exposure::Add,
+        });
+
+        // when
+        let clearance = line.clearance_to(&circle(5.0, 3.0, 2.0));
+
+        // then
+        assert!((clearance.distance - 1.5).abs() < 1e-9);
+        assert_points_eq(clearance.from, Point2::new(5.0, 0.5));
+        assert_points_eq(clearance.to, Point2::new(5.0, 2.0));
+    }
+
+    #[rstest]
+    // overlapping
+    #[case(circle(1.5, 0.0, 2.0))]
+    // inside
+    #[case(circle(3.0, 3.0, 1.0))]
+    fn test_rectangle_overlap_clearance(#[case] other: GerberPrimitive) {
+        // given
+        let rectangle = GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+            origin: Point2::new(0.0, 0.0),
+            width: 10.0,
+            height: 10.0,
+            exposure: Exposure::Add,
+        });
+
+        // when
+        let clearance = rectangle.clearance_to(&other);
+
+        // then
+        assert_eq!(clearance.distance, 0.0);
+        assert_eq!(clearance.from, clearance.to);
+        assert_eq!(rectangle.distance_to(clearance.from), 0.0);
+        assert_eq!(other.distance_to(clearance.from), 0.0);
+    }
+
+    #[test]
+    fn test_point_clearance() {
+        // expect
+        let clearance = circle(0.0, 0.0, 2.0).clearance_to_point(Point2::new(0.0, -4.0));
+        assert!((clearance.distance - 3.0).abs() < 1e-9);
+        assert_points_eq(clearance.from, Point2::new(0.0, -1.0));
+    }
+}
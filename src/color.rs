@@ -1,6 +1,7 @@
 #[cfg(feature = "egui")]
 use egui::epaint::Color32;
 
+use crate::layer_set::LayerType;
 use crate::types::Exposure;
 
 #[cfg(feature = "egui")]
@@ -50,6 +51,8 @@ pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
 }
 
 impl Exposure {
+    /// The color of a tessellated primitive, clear primitives are black, since egui can't erase what was painted
+    /// before, layers with clear primitives are composited instead, see [`crate::GerberRenderer`].
     #[cfg(feature = "egui")]
     pub fn to_color(&self, color: &Color32) -> Color32 {
         match self {
@@ -59,6 +62,320 @@ impl Exposure {
     }
 }
 
+/// How a layer is combined with the layers below it, e.g. to keep stacked copper, mask and silkscreen layers readable.
+///
+/// Multiply assumes the layers below are opaque, e.g. drawn over a background, where the layers below are transparent
+/// the layer is not visible.
+///
+/// Only the wgpu backend supports blend modes, see [`crate::GpuRenderer::paint_layer`], egui can only blend shapes and
+/// textures normally, so [`crate::GerberRenderer`] always uses [`BlendMode::Normal`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// The layer covers the layers below.
+    #[default]
+    Normal,
+    /// Multiplies the colors, darkening the layers below, white has no effect.
+    Multiply,
+    /// Multiplies the inverse of the colors, lightening the layers below, black has no effect.
+    Screen,
+    /// Adds the colors, overlapping areas become brighter.
+    Additive,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 4] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Additive,
+    ];
+
+    /// Blends a `source` color over a `destination` color, both are premultiplied RGBA with components from 0.0 to 1.0.
+    pub fn blend(&self, source: [f32; 4], destination: [f32; 4]) -> [f32; 4] {
+        let source_alpha = source[3];
+        let mut result = [0.0, 0.0, 0.0, source_alpha + destination[3] * (1.0 - source_alpha)];
+        for channel in 0..3 {
+            let (source, destination) = (source[channel], destination[channel]);
+            result[channel] = match self {
+                BlendMode::Normal => source + destination * (1.0 - source_alpha),
+                BlendMode::Multiply => source * destination + destination * (1.0 - source_alpha),
+                BlendMode::Screen => source * (1.0 - destination) + destination,
+                BlendMode::Additive => (source + destination).min(1.0),
+            };
+        }
+        result
+    }
+}
+
+/// Named color schemes that map the function of a layer to a color, so applications don't have to define their own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColorTheme {
+    /// Green soldermask, copper colored top copper and white silkscreen, like a typical PCB.
+    #[default]
+    Classic,
+    /// Similar to the default colors of the KiCad PCB editor.
+    KiCad,
+    /// Saturated colors on a black background, e.g. for presentations or low-vision users.
+    HighContrast,
+    /// Uses the Okabe-Ito palette, which can be distinguished with the common forms of color blindness.
+    ColorblindSafe,
+}
+
+impl ColorTheme {
+    pub const ALL: [ColorTheme; 4] = [
+        ColorTheme::Classic,
+        ColorTheme::KiCad,
+        ColorTheme::HighContrast,
+        ColorTheme::ColorblindSafe,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorTheme::Classic => "Classic",
+            ColorTheme::KiCad => "KiCad",
+            ColorTheme::HighContrast => "High contrast",
+            ColorTheme::ColorblindSafe => "Colorblind safe",
+        }
+    }
+
+    /// The color of the layer, RGBA, not premultiplied, e.g. for [`crate::RasterOptions`].
+    ///
+    /// Soldermask colors are translucent, so the copper below remains visible.
+    pub fn rgba(&self, layer_type: LayerType) -> [u8; 4] {
+        match self {
+            ColorTheme::Classic => match layer_type {
+                LayerType::TopCopper => [200, 130, 50, 255],
+                LayerType::BottomCopper => [60, 110, 200, 255],
+                LayerType::InnerCopper(_) | LayerType::InnerPlane(_) => [170, 170, 90, 255],
+                LayerType::TopSolderMask | LayerType::BottomSolderMask => [0, 140, 60, 160],
+                LayerType::TopSilkscreen | LayerType::BottomSilkscreen => [240, 240, 240, 255],
+                LayerType::TopPaste | LayerType::BottomPaste => [160, 160, 170, 255],
+                LayerType::Drill => [30, 30, 30, 255],
+                LayerType::Outline => [230, 230, 0, 255],
+                LayerType::Other => [180, 80, 180, 255],
+            },
+            ColorTheme::KiCad => match layer_type {
+                LayerType::TopCopper => [200, 52, 52, 255],
+                LayerType::BottomCopper => [77, 127, 196, 255],
+                LayerType::InnerCopper(_) | LayerType::InnerPlane(_) => [127, 200, 127, 255],
+                LayerType::TopSolderMask => [216, 100, 255, 102],
+                LayerType::BottomSolderMask => [2, 255, 238, 102],
+                LayerType::TopSilkscreen => [242, 237, 161, 255],
+                LayerType::BottomSilkscreen => [232, 178, 167, 255],
+                LayerType::TopPaste => [180, 160, 154, 230],
+                LayerType::BottomPaste => [0, 194, 194, 230],
+                LayerType::Drill => [227, 183, 46, 255],
+                LayerType::Outline => [208, 210, 205, 255],
+                LayerType::Other => [194, 194, 194, 255],
+            },
+            ColorTheme::HighContrast => match layer_type {
+                LayerType::TopCopper => [255, 255, 0, 255],
+                LayerType::BottomCopper => [0, 255, 255, 255],
+                LayerType::InnerCopper(_) | LayerType::InnerPlane(_) => [255, 0, 255, 255],
+                LayerType::TopSolderMask | LayerType::BottomSolderMask => [255, 255, 255, 80],
+                LayerType::TopSilkscreen | LayerType::BottomSilkscreen => [255, 255, 255, 255],
+                LayerType::TopPaste | LayerType::BottomPaste => [128, 128, 128, 255],
+                LayerType::Drill => [255, 0, 0, 255],
+                LayerType::Outline => [0, 255, 0, 255],
+                LayerType::Other => [255, 128, 0, 255],
+            },
+            ColorTheme::ColorblindSafe => match layer_type {
+                LayerType::TopCopper => [230, 159, 0, 255],
+                LayerType::BottomCopper => [0, 114, 178, 255],
+                LayerType::InnerCopper(_) | LayerType::InnerPlane(_) => [0, 158, 115, 255],
+                LayerType::TopSolderMask | LayerType::BottomSolderMask => [86, 180, 233, 100],
+                LayerType::TopSilkscreen | LayerType::BottomSilkscreen => [240, 228, 66, 255],
+                LayerType::TopPaste | LayerType::BottomPaste => [204, 121, 167, 255],
+                LayerType::Drill => [255, 255, 255, 255],
+                LayerType::Outline => [213, 94, 0, 255],
+                LayerType::Other => [153, 153, 153, 255],
+            },
+        }
+    }
+
+    /// The color to draw the layers over, RGBA.
+    pub fn background_rgba(&self) -> [u8; 4] {
+        match self {
+            ColorTheme::Classic => [10, 20, 10, 255],
+            ColorTheme::KiCad => [0, 16, 35, 255],
+            ColorTheme::HighContrast | ColorTheme::ColorblindSafe => [0, 0, 0, 255],
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    pub fn color(&self, layer_type: LayerType) -> Color32 {
+        let [r, g, b, a] = self.rgba(layer_type);
+        Color32::from_rgba_unmultiplied(r, g, b, a)
+    }
+
+    #[cfg(feature = "egui")]
+    pub fn background_color(&self) -> Color32 {
+        let [r, g, b, a] = self.background_rgba();
+        Color32::from_rgba_unmultiplied(r, g, b, a)
+    }
+}
+
+/// The colors of the overlays drawn over the layers, e.g. crosshairs, outlines, markers and shape numbers, so they
+/// remain visible over dark and light backgrounds.
+///
+/// Use [`OverlayColors::from_visuals`] to follow the dark or light mode of the host application, or
+/// [`OverlayColors::for_theme`] when drawing over the background of a [`ColorTheme`].  The default is for dark
+/// backgrounds.
+#[cfg(feature = "egui")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlayColors {
+    /// The crosshair at the gerber origin.
+    pub origin: Color32,
+    /// The crosshair at the center of the viewport.
+    pub center: Color32,
+    /// Outlines, e.g. of the transformed layers.
+    pub outline: Color32,
+    /// Axis aligned bounding boxes.
+    pub bounding_box: Color32,
+    pub marker: Color32,
+    /// The circles and labels of markers.
+    pub marker_secondary: Color32,
+    pub datum: Color32,
+    pub shape_numbers: Color32,
+    pub vertex_numbers: Color32,
+    /// Behind labels, so they can be read over the layers.
+    pub label_background: Color32,
+}
+
+#[cfg(feature = "egui")]
+impl Default for OverlayColors {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+#[cfg(feature = "egui")]
+impl OverlayColors {
+    /// Bright colors, for dark backgrounds.
+    pub fn dark() -> Self {
+        Self {
+            origin: Color32::BLUE,
+            center: Color32::LIGHT_GRAY,
+            outline: Color32::GREEN,
+            bounding_box: Color32::RED,
+            marker: Color32::ORANGE,
+            marker_secondary: Color32::YELLOW,
+            datum: Color32::GREEN,
+            shape_numbers: Color32::GREEN,
+            vertex_numbers: Color32::RED,
+            label_background: Color32::from_black_alpha(200),
+        }
+    }
+
+    /// Darker colors, for light backgrounds.
+    pub fn light() -> Self {
+        Self {
+            origin: Color32::from_rgb(0, 0, 180),
+            center: Color32::from_gray(100),
+            outline: Color32::from_rgb(0, 120, 0),
+            bounding_box: Color32::from_rgb(190, 0, 0),
+            marker: Color32::from_rgb(200, 90, 0),
+            marker_secondary: Color32::from_rgb(130, 100, 0),
+            datum: Color32::from_rgb(0, 120, 0),
+            shape_numbers: Color32::from_rgb(0, 120, 0),
+            vertex_numbers: Color32::from_rgb(190, 0, 0),
+            label_background: Color32::from_white_alpha(200),
+        }
+    }
+
+    /// Follows the dark or light mode of egui, e.g. `OverlayColors::from_visuals(ui.visuals())`.
+    pub fn from_visuals(visuals: &egui::Visuals) -> Self {
+        match visuals.dark_mode {
+            true => Self::dark(),
+            false => Self::light(),
+        }
+    }
+
+    /// For drawing over the background color, by its luminance.
+    pub fn for_background(background: Color32) -> Self {
+        let luminance =
+            0.2126 * background.r() as f32 + 0.7152 * background.g() as f32 + 0.0722 * background.b() as f32;
+        match luminance < 128.0 {
+            true => Self::dark(),
+            false => Self::light(),
+        }
+    }
+
+    /// For drawing over the background of the theme, see [`ColorTheme::background_color`].
+    pub fn for_theme(theme: ColorTheme) -> Self {
+        Self::for_background(theme.background_color())
+    }
+}
+
+#[cfg(test)]
+mod color_theme_tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(ColorTheme::Classic)]
+    #[case(ColorTheme::KiCad)]
+    #[case(ColorTheme::HighContrast)]
+    #[case(ColorTheme::ColorblindSafe)]
+    fn test_copper_layers_are_distinguishable(#[case] theme: ColorTheme) {
+        // given
+        let copper = [LayerType::TopCopper, LayerType::InnerCopper(1), LayerType::BottomCopper]
+            .map(|layer_type| theme.rgba(layer_type));
+
+        // expect
+        assert_ne!(copper[0], copper[1]);
+        assert_ne!(copper[0], copper[2]);
+        assert_ne!(copper[1], copper[2]);
+        assert!(copper
+            .iter()
+            .all(|color| color[3] == 255));
+        assert!(!copper.contains(&theme.background_rgba()));
+    }
+
+    #[cfg(feature = "egui")]
+    #[rstest]
+    #[case(Color32::BLACK, OverlayColors::dark())]
+    #[case(Color32::from_rgb(0, 16, 35), OverlayColors::dark())]
+    #[case(Color32::WHITE, OverlayColors::light())]
+    #[case(Color32::from_gray(200), OverlayColors::light())]
+    fn test_overlay_colors_for_background(#[case] background: Color32, #[case] expected: OverlayColors) {
+        assert_eq!(OverlayColors::for_background(background), expected);
+    }
+}
+
+#[cfg(test)]
+mod blend_mode_tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(BlendMode::Normal, [0.5, 0.0, 0.0, 0.5], [0.0, 0.5, 1.0, 1.0], [0.5, 0.25, 0.5, 1.0])]
+    #[case(BlendMode::Multiply, [1.0, 0.5, 0.0, 1.0], [0.5, 0.5, 0.5, 1.0], [0.5, 0.25, 0.0, 1.0])]
+    #[case(BlendMode::Screen, [1.0, 0.5, 0.0, 1.0], [0.5, 0.5, 0.5, 1.0], [1.0, 0.75, 0.5, 1.0])]
+    #[case(BlendMode::Additive, [0.75, 0.5, 0.0, 1.0], [0.5, 0.25, 0.5, 1.0], [1.0, 0.75, 0.5, 1.0])]
+    fn test_blend(
+        #[case] mode: BlendMode,
+        #[case] source: [f32; 4],
+        #[case] destination: [f32; 4],
+        #[case] expected: [f32; 4],
+    ) {
+        assert_eq!(mode.blend(source, destination), expected);
+    }
+
+    #[rstest]
+    #[case(BlendMode::Normal)]
+    #[case(BlendMode::Multiply)]
+    #[case(BlendMode::Screen)]
+    #[case(BlendMode::Additive)]
+    fn test_blend_transparent_source(#[case] mode: BlendMode) {
+        // expect
+        let destination = [0.25, 0.5, 0.75, 1.0];
+        assert_eq!(mode.blend([0.0, 0.0, 0.0, 0.0], destination), destination);
+    }
+}
+
 #[cfg(all(test, feature = "egui"))]
 mod exposure_tests {
     use super::*;
@@ -0,0 +1,465 @@
+use egui::{
+    pos2, vec2, Align2, Color32, ColorImage, CursorIcon, FontId, Pos2, Rect, Response, Sense, Stroke, TextureHandle,
+    TextureOptions, Ui, Vec2,
+};
+use log::warn;
+use nalgebra::Point2;
+
+use crate::geometry::{BoundingBox, GerberTransform};
+use crate::{
+    draw_crosshair, render_difference, DifferenceColors, GerberLayer, GerberRenderer, RasterOptions,
+    RenderConfiguration, Resolution, UiState, ViewState, DEFAULT_ANIMATION_DURATION,
+};
+
+/// How the two sides of a [`ComparisonWidget`] are arranged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ComparisonLayout {
+    /// Two viewports next to each other, the `before` layers on the left.
+    #[default]
+    SideBySide,
+    /// One viewport, the `before` layers are shown left of a draggable divider and the `after` layers right of it.
+    Divider,
+    /// One viewport, showing the geometry present in only one of the sides, in the added and removed colors of
+    /// [`ComparisonState::difference_colors`], see [`render_difference`].
+    Difference,
+}
+
+/// The state of a [`ComparisonWidget`] that is kept between frames, e.g. in your app state.
+pub struct ComparisonState {
+    /// The view shared by both sides, in the coordinates of the left viewport.
+    pub view: ViewState,
+    pub ui_state: UiState,
+    pub renderer: GerberRenderer,
+    pub layout: ComparisonLayout,
+    /// The position of the divider, from `0.0`, the left edge of the viewport, to `1.0`, the right edge, `0.5` by
+    /// default, see [`ComparisonLayout::Divider`].
+    pub divider: f32,
+    /// The colors of [`ComparisonLayout::Difference`], call [`ComparisonState::invalidate_difference`] after changing
+    /// them.
+    pub difference_colors: DifferenceColors,
+    /// The size of the longest side of the difference image, in pixels, `2048` by default.
+    pub difference_resolution: u32,
+    needs_fit: bool,
+    difference: Option<CachedDifference>,
+}
+
+struct CachedDifference {
+    /// The ids of the `before` and `after` layers.
+    key: (Vec<u64>, Vec<u64>),
+    texture: TextureHandle,
+    /// The area of the layers covered by the texture, in gerber coordinates, after the transform.
+    bounds: BoundingBox,
+    added_pixels: usize,
+    removed_pixels: usize,
+}
+
+impl Default for ComparisonState {
+    fn default() -> Self {
+        Self {
+            view: ViewState::default(),
+            ui_state: UiState::default(),
+            renderer: GerberRenderer::default(),
+            layout: ComparisonLayout::default(),
+            divider: 0.5,
+            difference_colors: DifferenceColors::default(),
+            difference_resolution: 2048,
+            needs_fit: true,
+            difference: None,
+        }
+    }
+}
+
+impl ComparisonState {
+    /// Fits the layers to the viewport the next time the widget is shown, e.g. after loading other revisions.
+    pub fn request_fit(&mut self) {
+        self.needs_fit = true;
+    }
+
+    /// Drops the difference image, so it is rendered again the next time the widget is shown, e.g. after changing the
+    /// colors, resolution or transform.  It is rendered again automatically when other layers are compared.
+    pub fn invalidate_difference(&mut self) {
+        self.difference = None;
+    }
+
+    /// The number of pixels of the difference image that were added and removed, `None` until the difference has been
+    /// shown, see [`ComparisonLayout::Difference`].
+    pub fn difference_pixels(&self) -> Option<(usize, usize)> {
+        self.difference
+            .as_ref()
+            .map(|difference| (difference.added_pixels, difference.removed_pixels))
+    }
+
+    fn difference_texture(
+        &mut self,
+        ui: &Ui,
+        before: &[&GerberLayer],
+        after: &[&GerberLayer],
+        bounds: &BoundingBox,
+        transform: &GerberTransform,
+    ) -> Option<&CachedDifference> {
+        let key = (
+            before
+                .iter()
+                .map(|layer| layer.id())
+                .collect::<Vec<_>>(),
+            after
+                .iter()
+                .map(|layer| layer.id())
+                .collect::<Vec<_>>(),
+        );
+        if !matches!(&self.difference, Some(cached) if cached.key == key) {
+            self.difference = None;
+            if bounds.is_empty() || bounds.width() <= 0.0 || bounds.height() <= 0.0 {
+                return None;
+            }
+
+            // an image with the aspect ratio of the layers, so it is covered exactly
+            let aspect_ratio = bounds.width() / bounds.height();
+            let resolution = self.difference_resolution as f64;
+            let (width, height) = match aspect_ratio >= 1.0 {
+                true => (resolution, resolution / aspect_ratio),
+                false => (resolution * aspect_ratio, resolution),
+            };
+            let options = RasterOptions {
+                resolution: Resolution::Fit {
+                    width: (width.round() as u32).max(1),
+                    height: (height.round() as u32).max(1),
+                },
+                transform: *transform,
+                anti_aliasing: false,
+                ..RasterOptions::default()
+            };
+
+            let difference = render_difference(before, after, &options, &self.difference_colors)
+                .inspect_err(|error| warn!("Unable to render difference. error: {error}"))
+                .ok()?;
+            let image = &difference.image;
+            let texture = ui.ctx().load_texture(
+                "comparison_difference",
+                ColorImage::from_rgba_unmultiplied([image.width as usize, image.height as usize], &image.pixels),
+                TextureOptions::NEAREST,
+            );
+            self.difference = Some(CachedDifference {
+                key,
+                texture,
+                bounds: difference.bounds,
+                added_pixels: difference.added_pixels,
+                removed_pixels: difference.removed_pixels,
+            });
+        }
+
+        self.difference.as_ref()
+    }
+}
+
+/// What happened in a [`ComparisonWidget`] this frame.
+pub struct ComparisonResponse {
+    /// The union of the responses of the viewports.
+    pub response: Response,
+    /// The cursor position in gerber coordinates, on either side, `None` if the cursor is not over a viewport.
+    pub cursor_gerber_coords: Option<Point2<f64>>,
+}
+
+/// Compares two sets of layers, e.g. the fab outputs of a release against the previous release, side by side, with a
+/// draggable divider, or as the difference of the two, see [`ComparisonLayout`].
+///
+/// Both sides share one view, panning and zooming either side moves both, so the same part of the board is always
+/// shown.  Create one each frame, using the [`ComparisonState`] kept in your app state, e.g.
+/// ```ignore
+/// ComparisonWidget::new(&mut self.comparison_state)
+///     .before(&self.previous_top_copper, Color32::from_rgb(184, 115, 51))
+///     .after(&self.top_copper, Color32::from_rgb(184, 115, 51))
+///     .labels("Rev A", "Rev B")
+///     .linked_cursor(Some(Color32::YELLOW))
+///     .show(ui);
+/// ```
+/// The widget fills the available space.  The layers are fitted to the viewport the first time the widget is shown,
+/// after [`ComparisonState::request_fit`], and when a viewport is double-clicked.
+pub struct ComparisonWidget<'a> {
+    state: &'a mut ComparisonState,
+    before: Vec<(&'a GerberLayer, Color32)>,
+    after: Vec<(&'a GerberLayer, Color32)>,
+    labels: Option<(String, String)>,
+    configuration: RenderConfiguration,
+    transform: GerberTransform,
+    fit_zoom_factor: f32,
+    linked_cursor: Option<Color32>,
+    divider_color: Option<Color32>,
+}
+
+impl<'a> ComparisonWidget<'a> {
+    pub fn new(state: &'a mut ComparisonState) -> Self {
+        Self {
+            state,
+            before: vec![],
+            after: vec![],
+            labels: None,
+            configuration: RenderConfiguration::default(),
+            transform: GerberTransform::default(),
+            fit_zoom_factor: 1.0,
+            linked_cursor: None,
+            divider_color: None,
+        }
+    }
+
+    /// Adds a layer to the left side, layers are painted in the order they are added.
+    pub fn before(mut self, layer: &'a GerberLayer, color: Color32) -> Self {
+        self.before.push((layer, color));
+        self
+    }
+
+    /// Adds a layer to the right side, layers are painted in the order they are added.
+    pub fn after(mut self, layer: &'a GerberLayer, color: Color32) -> Self {
+        self.after.push((layer, color));
+        self
+    }
+
+    /// Names of the sides, e.g. the revisions, shown in the top corners.
+    pub fn labels(mut self, before: impl Into<String>, after: impl Into<String>) -> Self {
+        self.labels = Some((before.into(), after.into()));
+        self
+    }
+
+    /// The configuration used for all the layers.
+    pub fn configuration(mut self, configuration: RenderConfiguration) -> Self {
+        self.configuration = configuration;
+        self
+    }
+
+    /// The transform used for all the layers of both sides.
+    pub fn transform(mut self, transform: GerberTransform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// The zoom after fitting the layers to the viewport, e.g. `0.5` for 50%, `1.0` by default.
+    pub fn fit_zoom_factor(mut self, fit_zoom_factor: f32) -> Self {
+        self.fit_zoom_factor = fit_zoom_factor;
+        self
+    }
+
+    /// Draws a crosshair at the cursor position on both sides, so the same position can be compared.
+    pub fn linked_cursor(mut self, color: Option<Color32>) -> Self {
+        self.linked_cursor = color;
+        self
+    }
+
+    /// The color of the divider, the selection stroke color of the visuals by default.
+    pub fn divider_color(mut self, color: Color32) -> Self {
+        self.divider_color = Some(color);
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> ComparisonResponse {
+        let Self {
+            state,
+            before,
+            after,
+            labels,
+            configuration,
+            transform,
+            fit_zoom_factor,
+            linked_cursor,
+            divider_color,
+        } = self;
+
+        let viewport = ui.available_rect_before_wrap();
+
+        let mut bounds = BoundingBox::default();
+        for (layer, _) in before.iter().chain(&after) {
+            let vertices = layer
+                .bounding_box()
+                .transform_vertices(&transform);
+            bounds.expand(&BoundingBox::from_points(&vertices));
+        }
+
+        // the viewports that handle input, with the offset of their view from the shared view
+        let inputs: Vec<(Response, Vec2)>;
+        // where each side is painted, with the offset of its view from the shared view
+        let panes: Vec<(Rect, Vec2)>;
+        let mut divider = None;
+        match state.layout {
+            ComparisonLayout::SideBySide => {
+                let gap = ui.spacing().item_spacing.x;
+                let width = (viewport.width() - gap) / 2.0;
+                let left = Rect::from_min_size(viewport.min, vec2(width, viewport.height()));
+                let right = left.translate(vec2(width + gap, 0.0));
+                let offset = right.min - left.min;
+
+                inputs = vec![
+                    (ui.allocate_rect(left, Sense::click_and_drag()), Vec2::ZERO),
+                    (ui.allocate_rect(right, Sense::click_and_drag()), offset),
+                ];
+                panes = vec![(left, Vec2::ZERO), (right, offset)];
+            }
+            ComparisonLayout::Divider => {
+                inputs = vec![(ui.allocate_rect(viewport, Sense::click_and_drag()), Vec2::ZERO)];
+
+                // the handle is added after the viewport, so it gets the drags
+                let x = viewport.left() + viewport.width() * state.divider;
+                let handle = ui.interact(
+                    Rect::from_center_size(pos2(x, viewport.center().y), vec2(8.0, viewport.height())),
+                    ui.id().with("comparison_divider"),
+                    Sense::drag(),
+                );
+                if let (true, Some(pointer)) = (handle.dragged(), handle.interact_pointer_pos()) {
+                    state.divider = ((pointer.x - viewport.left()) / viewport.width()).clamp(0.0, 1.0);
+                }
+                if handle.hovered() || handle.dragged() {
+                    ui.ctx()
+                        .set_cursor_icon(CursorIcon::ResizeHorizontal);
+                }
+
+                let x = viewport.left() + viewport.width() * state.divider;
+                let mut left = viewport;
+                left.max.x = x;
+                let mut right = viewport;
+                right.min.x = x;
+                panes = vec![(left, Vec2::ZERO), (right, Vec2::ZERO)];
+                divider = Some(x);
+            }
+            ComparisonLayout::Difference => {
+                inputs = vec![(ui.allocate_rect(viewport, Sense::click_and_drag()), Vec2::ZERO)];
+                panes = vec![];
+            }
+        }
+
+        let ui_state = &mut state.ui_state;
+        let fit_viewport = inputs[0].0.rect;
+        if state.needs_fit && !bounds.is_empty() {
+            state
+                .view
+                .fit_view(fit_viewport, &bounds, fit_zoom_factor);
+            state.needs_fit = false;
+        } else if inputs
+            .iter()
+            .any(|(response, _)| response.double_clicked())
+            && !bounds.is_empty()
+        {
+            ui_state.animate_fit_view(
+                &mut state.view,
+                &fit_viewport,
+                &bounds,
+                fit_zoom_factor,
+                DEFAULT_ANIMATION_DURATION,
+                ui,
+            );
+        }
+        ui_state.update_animation(&mut state.view, ui);
+        for (response, offset) in &inputs {
+            let mut view = offset_view(state.view, *offset);
+            ui_state.update_cursor_position(&view, response, ui);
+            ui_state.handle_panning(&mut view, response, ui);
+            ui_state.handle_zooming(&mut view, response, ui);
+            state.view = offset_view(view, -*offset);
+        }
+        if !inputs
+            .iter()
+            .any(|(response, _)| response.hovered())
+        {
+            ui_state.cursor_gerber_coords = None;
+        }
+        let cursor_gerber_coords = ui_state.cursor_gerber_coords;
+
+        let label_color = ui.visuals().text_color();
+        for (index, ((clip, offset), layers)) in panes
+            .into_iter()
+            .zip([&before, &after])
+            .enumerate()
+        {
+            let painter = ui.painter().with_clip_rect(clip);
+            let view = offset_view(state.view, offset);
+            for (layer, color) in layers {
+                state
+                    .renderer
+                    .paint_layer(&painter, view, layer, *color, &configuration, &transform);
+            }
+
+            if let (Some(color), Some(position)) = (linked_cursor, cursor_gerber_coords) {
+                draw_crosshair(&painter, view.gerber_to_screen_coords(position), color);
+            }
+            if let Some((before_label, after_label)) = &labels {
+                let (position, anchor, text) = match index {
+                    0 => (clip.left_top() + vec2(4.0, 4.0), Align2::LEFT_TOP, before_label),
+                    _ => (clip.right_top() + vec2(-4.0, 4.0), Align2::RIGHT_TOP, after_label),
+                };
+                painter.text(position, anchor, text, FontId::proportional(14.0), label_color);
+            }
+        }
+
+        if state.layout == ComparisonLayout::Difference {
+            let before_layers = before
+                .iter()
+                .map(|(layer, _)| *layer)
+                .collect::<Vec<_>>();
+            let after_layers = after
+                .iter()
+                .map(|(layer, _)| *layer)
+                .collect::<Vec<_>>();
+            let view = state.view;
+            let colors = state.difference_colors;
+            let painter = ui.painter().with_clip_rect(viewport);
+            if let Some(difference) = state.difference_texture(ui, &before_layers, &after_layers, &bounds, &transform) {
+                let image_rect = Rect::from_two_pos(
+                    view.gerber_to_screen_coords(Point2::new(difference.bounds.min.x, difference.bounds.max.y)),
+                    view.gerber_to_screen_coords(Point2::new(difference.bounds.max.x, difference.bounds.min.y)),
+                );
+                painter.image(
+                    difference.texture.id(),
+                    image_rect,
+                    Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                    Color32::WHITE,
+                );
+            }
+
+            if let (Some(color), Some(position)) = (linked_cursor, cursor_gerber_coords) {
+                draw_crosshair(&painter, view.gerber_to_screen_coords(position), color);
+            }
+            // a legend, in the colors of the removed and added geometry
+            if let Some((before_label, after_label)) = &labels {
+                let to_color32 = |[red, green, blue, _]: [u8; 4]| Color32::from_rgb(red, green, blue);
+                painter.text(
+                    viewport.left_top() + vec2(4.0, 4.0),
+                    Align2::LEFT_TOP,
+                    format!("- {before_label}"),
+                    FontId::proportional(14.0),
+                    to_color32(colors.removed),
+                );
+                painter.text(
+                    viewport.right_top() + vec2(-4.0, 4.0),
+                    Align2::RIGHT_TOP,
+                    format!("+ {after_label}"),
+                    FontId::proportional(14.0),
+                    to_color32(colors.added),
+                );
+            }
+        }
+
+        if let Some(x) = divider {
+            let color = divider_color.unwrap_or(ui.visuals().selection.stroke.color);
+            let painter = ui.painter().with_clip_rect(viewport);
+            painter.vline(x, viewport.y_range(), Stroke::new(2.0, color));
+            painter.circle_filled(pos2(x, viewport.center().y), 5.0, color);
+        }
+
+        let response = inputs
+            .into_iter()
+            .map(|(response, _)| response)
+            .reduce(|a, b| a.union(b))
+            .expect("at least one viewport");
+
+        ComparisonResponse {
+            response,
+            cursor_gerber_coords,
+        }
+    }
+}
+
+/// The shared view, moved by the offset of a viewport from the left viewport.
+fn offset_view(view: ViewState, offset: Vec2) -> ViewState {
+    ViewState {
+        translation: view.translation + offset,
+        ..view
+    }
+}
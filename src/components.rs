@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use nalgebra::Point2;
+
+use crate::attributes::{Attribute, PrimitiveAttributes};
+use crate::geometry::BoundingBox;
+use crate::GerberLayer;
+
+/// Object attribute for the component reference designator, e.g. `.C,R1`
+pub const COMPONENT_ATTRIBUTE: &str = ".C";
+/// Object attribute for a component pin, e.g. `.P,R1,1` or `.P,U1,5,VCC`
+pub const PIN_ATTRIBUTE: &str = ".P";
+
+/// A component, built from the X2/X3 component attributes (`.C`, `.CRot`, `.CVal`, etc.) and pin attributes (`.P`).
+///
+/// Typically found in assembly drawings and copper layers exported with component attributes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    /// Reference designator, e.g. `R1`
+    pub reference: String,
+    /// The component reference point, from the flash with the `ComponentMain` aperture function if present, otherwise
+    /// the center of all the primitives of the component.
+    pub position: Option<Point2<f64>>,
+    /// Rotation in degrees, positive = counter-clockwise, from `.CRot`
+    pub rotation: Option<f64>,
+    /// From `.CVal`
+    pub value: Option<String>,
+    /// From `.CFtp`
+    pub footprint: Option<String>,
+    /// From `.CMfr`
+    pub manufacturer: Option<String>,
+    /// From `.CMPN`
+    pub manufacturer_part_number: Option<String>,
+    /// From `.CMnt`, e.g. `TH`, `SMD`, `Pressfit`, `Fiducial`, `Other`
+    pub mount_type: Option<String>,
+    /// From `.CHgt`
+    pub height: Option<f64>,
+    pub pins: Vec<ComponentPin>,
+    /// Indices of the primitives which have the component attribute.
+    pub primitives: Vec<usize>,
+}
+
+impl Component {
+    fn new(reference: &str) -> Self {
+        Self {
+            reference: reference.to_string(),
+            position: None,
+            rotation: None,
+            value: None,
+            footprint: None,
+            manufacturer: None,
+            manufacturer_part_number: None,
+            mount_type: None,
+            height: None,
+            pins: vec![],
+            primitives: vec![],
+        }
+    }
+
+    pub fn pin(&self, number: &str) -> Option<&ComponentPin> {
+        self.pins
+            .iter()
+            .find(|pin| pin.number == number)
+    }
+
+    fn apply_characteristics(&mut self, attributes: &PrimitiveAttributes) {
+        fn string_value(attributes: &PrimitiveAttributes, name: &str) -> Option<String> {
+            attributes
+                .object
+                .get(name)
+                .and_then(Attribute::value)
+                .map(str::to_string)
+        }
+        fn f64_value(attributes: &PrimitiveAttributes, name: &str) -> Option<f64> {
+            attributes
+                .object
+                .get(name)
+                .and_then(Attribute::value)
+                .and_then(|value| value.parse::<f64>().ok())
+        }
+
+        self.rotation = self
+            .rotation
+            .or_else(|| f64_value(attributes, ".CRot"));
+        self.height = self
+            .height
+            .or_else(|| f64_value(attributes, ".CHgt"));
+        self.value = self
+            .value
+            .take()
+            .or_else(|| string_value(attributes, ".CVal"));
+        self.footprint = self
+            .footprint
+            .take()
+            .or_else(|| string_value(attributes, ".CFtp"));
+        self.manufacturer = self
+            .manufacturer
+            .take()
+            .or_else(|| string_value(attributes, ".CMfr"));
+        self.manufacturer_part_number = self
+            .manufacturer_part_number
+            .take()
+            .or_else(|| string_value(attributes, ".CMPN"));
+        self.mount_type = self
+            .mount_type
+            .take()
+            .or_else(|| string_value(attributes, ".CMnt"));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentPin {
+    /// Pin number, e.g. `1`, `A3`
+    pub number: String,
+    /// Optional pin function, e.g. `VCC`, `CLK`
+    pub function: Option<String>,
+    /// The center of the primitives of the pin.
+    pub position: Point2<f64>,
+    /// Indices of the primitives which have the pin attribute.
+    pub primitives: Vec<usize>,
+}
+
+impl GerberLayer {
+    /// Returns the components, in order of first use, from the `.C` and `.P` object attributes.
+    ///
+    /// Returns an empty list for files without component attributes.
+    pub fn components(&self) -> Vec<Component> {
+        struct PinBuilder {
+            function: Option<String>,
+            bbox: BoundingBox,
+            primitives: Vec<usize>,
+        }
+
+        struct ComponentBuilder {
+            component: Component,
+            main_bbox: BoundingBox,
+            bbox: BoundingBox,
+            pins: Vec<(String, PinBuilder)>,
+        }
+
+        let mut builders: Vec<ComponentBuilder> = vec![];
+        let mut lookup: HashMap<String, usize> = HashMap::new();
+
+        let mut builder_for = |reference: &str, builders: &mut Vec<ComponentBuilder>| -> usize {
+            *lookup
+                .entry(reference.to_string())
+                .or_insert_with(|| {
+                    builders.push(ComponentBuilder {
+                        component: Component::new(reference),
+                        main_bbox: BoundingBox::default(),
+                        bbox: BoundingBox::default(),
+                        pins: vec![],
+                    });
+                    builders.len() - 1
+                })
+        };
+
+        for (index, primitive) in self
+            .gerber_primitives()
+            .iter()
+            .enumerate()
+        {
+            let Some(attributes) = self.primitive_attributes(index) else {
+                continue;
+            };
+
+            if let Some(pin) = attributes.object.get(PIN_ATTRIBUTE) {
+                let [reference, number, rest @ ..] = pin.values.as_slice() else {
+                    continue;
+                };
+                let builder_index = builder_for(reference, &mut builders);
+                let builder = &mut builders[builder_index];
+
+                let pin_index = match builder
+                    .pins
+                    .iter()
+                    .position(|(candidate, _)| candidate == number)
+                {
+                    Some(pin_index) => pin_index,
+                    None => {
+                        builder
+                            .pins
+                            .push((number.clone(), PinBuilder {
+                                function: rest
+                                    .first()
+                                    .filter(|function| !function.is_empty())
+                                    .cloned(),
+                                bbox: BoundingBox::default(),
+                                primitives: vec![],
+                            }));
+                        builder.pins.len() - 1
+                    }
+                };
+                let pin_builder = &mut builder.pins[pin_index].1;
+                pin_builder
+                    .bbox
+                    .expand(&primitive.bounding_box());
+                pin_builder.primitives.push(index);
+            } else if let Some(reference) = attributes
+                .object
+                .get(COMPONENT_ATTRIBUTE)
+                .and_then(Attribute::value)
+            {
+                let builder_index = builder_for(reference, &mut builders);
+                let builder = &mut builders[builder_index];
+
+                let bbox = primitive.bounding_box();
+                let is_main = attributes
+                    .aperture
+                    .get(".AperFunction")
+                    .and_then(Attribute::value)
+                    == Some("ComponentMain");
+                if is_main {
+                    builder.main_bbox.expand(&bbox);
+                }
+                builder.bbox.expand(&bbox);
+                builder
+                    .component
+                    .apply_characteristics(attributes);
+                builder.component.primitives.push(index);
+            }
+        }
+
+        builders
+            .into_iter()
+            .map(|builder| {
+                let ComponentBuilder {
+                    mut component,
+                    main_bbox,
+                    bbox,
+                    pins,
+                } = builder;
+
+                component.position = [main_bbox, bbox]
+                    .into_iter()
+                    .find(|bbox| !bbox.is_empty())
+                    .map(|bbox| bbox.center());
+
+                component.pins = pins
+                    .into_iter()
+                    .map(|(number, pin)| ComponentPin {
+                        number,
+                        function: pin.function,
+                        position: pin.bbox.center(),
+                        primitives: pin.primitives,
+                    })
+                    .collect();
+
+                component
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod tests {
+    use std::io::BufReader;
+
+    use gerber_parser::parse;
+    use nalgebra::Point2;
+
+    use crate::GerberLayer;
+
+    #[test]
+    fn test_components_and_pins() {
+        // given
+        let source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%TF.FileFunction,Component,L1,Top*%
+%TA.AperFunction,ComponentMain*%
+%ADD10C,0.300*%
+%TA.AperFunction,ComponentPin*%
+%ADD11C,0.100*%
+%TD*%
+D10*
+%TO.C,R1*%
+%TO.CRot,90*%
+%TO.CVal,10K*%
+X5000000Y5000000D03*
+%TD*%
+D11*
+%TO.P,R1,1*%
+X4000000Y5000000D03*
+%TO.P,R1,2,OUT*%
+X6000000Y5000000D03*
+%TD*%
+M02*
+"#;
+        let reader = BufReader::new(source.as_bytes());
+        let layer = GerberLayer::new(parse(reader).unwrap().into_commands());
+
+        // when
+        let components = layer.components();
+
+        // then
+        assert_eq!(components.len(), 1);
+        let component = &components[0];
+        assert_eq!(component.reference, "R1");
+        assert_eq!(component.position, Some(Point2::new(5.0, 5.0)));
+        assert_eq!(component.rotation, Some(90.0));
+        assert_eq!(component.value.as_deref(), Some("10K"));
+
+        // and
+        assert_eq!(component.pins.len(), 2);
+        assert_eq!(
+            component
+                .pin("1")
+                .map(|pin| pin.position),
+            Some(Point2::new(4.0, 5.0))
+        );
+        assert_eq!(
+            component
+                .pin("2")
+                .and_then(|pin| pin.function.as_deref()),
+            Some("OUT")
+        );
+    }
+}
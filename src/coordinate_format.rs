@@ -0,0 +1,166 @@
+use gerber_types::{Command, CoordinateFormat, Coordinates, DCode, ExtendedCode, FunctionCode, Operation};
+use thiserror::Error;
+
+use crate::GerberLayer;
+
+/// Problems with the coordinate format (FS) of a file, command indices are 0-based, line numbers are 1-based.
+///
+/// These problems don't prevent a layer from being built, but typically result in wildly wrong geometry, e.g. a board
+/// that is 1000 times too large.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CoordinateFormatDiagnostic {
+    #[error("No coordinate format (FS) specified before the first coordinate. index: {0}")]
+    MissingFormat(usize),
+    #[error("Coordinate format (FS) specified more than once. index: {0}")]
+    MultipleFormats(usize),
+    #[error("Coordinate exceeds the declared integer digits. index: {index}, value: {value}, integer_digits: {integer_digits}")]
+    CoordinateOutOfRange {
+        index: usize,
+        value: f64,
+        integer_digits: u8,
+    },
+    #[error("Trailing zero omission is deprecated. line: {0}")]
+    TrailingZeroOmission(usize),
+    #[error("Coordinate format (FS) zero omission differs from a previous format. line: {0}")]
+    MixedZeroOmission(usize),
+}
+
+/// Checks the coordinate format of parsed commands.
+///
+/// The zero omission is not available after parsing, use [`validate_coordinate_format_source`] to check it.
+pub fn validate_coordinate_format(commands: &[Command]) -> Vec<CoordinateFormatDiagnostic> {
+    let mut diagnostics = vec![];
+    let mut format: Option<CoordinateFormat> = None;
+    let mut missing_reported = false;
+
+    for (index, command) in commands.iter().enumerate() {
+        match command {
+            Command::ExtendedCode(ExtendedCode::CoordinateFormat(new_format)) => {
+                if format.is_some() {
+                    diagnostics.push(CoordinateFormatDiagnostic::MultipleFormats(index));
+                }
+                format = Some(*new_format);
+            }
+            Command::FunctionCode(FunctionCode::DCode(DCode::Operation(operation))) => {
+                let coordinates = match operation {
+                    Operation::Move(coordinates) | Operation::Flash(coordinates) => coordinates,
+                    Operation::Interpolate(coordinates, _) => coordinates,
+                };
+                if is_empty(coordinates) {
+                    continue;
+                }
+
+                let Some(format) = format else {
+                    if !missing_reported {
+                        missing_reported = true;
+                        diagnostics.push(CoordinateFormatDiagnostic::MissingFormat(index));
+                    }
+                    continue;
+                };
+
+                let limit = 10_f64.powi(format.integer as i32);
+                for value in [coordinates.x, coordinates.y]
+                    .into_iter()
+                    .flatten()
+                    .map(f64::from)
+                {
+                    if value.abs() >= limit {
+                        diagnostics.push(CoordinateFormatDiagnostic::CoordinateOutOfRange {
+                            index,
+                            value,
+                            integer_digits: format.integer,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    diagnostics
+}
+
+/// Checks the zero omission of the coordinate format (FS) statements of gerber source.
+///
+/// Gerber spec 2024.05 - 4.2.2 - only leading zero omission (`L`) is allowed, trailing zero omission (`T`) was
+/// deprecated and files that mix them, e.g. after concatenation, are interpreted incorrectly.
+pub fn validate_coordinate_format_source(source: &str) -> Vec<CoordinateFormatDiagnostic> {
+    let mut diagnostics = vec![];
+    let mut first_omission: Option<char> = None;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        for (position, _) in line.match_indices("%FS") {
+            let Some(omission) = line[position + 3..].chars().next() else {
+                continue;
+            };
+
+            if omission == 'T' {
+                diagnostics.push(CoordinateFormatDiagnostic::TrailingZeroOmission(line_number));
+            }
+            match first_omission {
+                None => first_omission = Some(omission),
+                Some(first) if first != omission => {
+                    diagnostics.push(CoordinateFormatDiagnostic::MixedZeroOmission(line_number));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    diagnostics
+}
+
+fn is_empty(coordinates: &Coordinates) -> bool {
+    coordinates.x.is_none() && coordinates.y.is_none()
+}
+
+impl GerberLayer {
+    /// See [`validate_coordinate_format`].
+    pub fn coordinate_format_diagnostics(&self) -> Vec<CoordinateFormatDiagnostic> {
+        validate_coordinate_format(self.commands())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::testing::flash;
+
+    fn format() -> Command {
+        Command::ExtendedCode(ExtendedCode::CoordinateFormat(CoordinateFormat::new(2, 4)))
+    }
+
+    #[rstest]
+    #[case(vec![format(), flash(1.0, 2.0)], vec![])]
+    #[case(vec![flash(1.0, 2.0), flash(3.0, 4.0)], vec![CoordinateFormatDiagnostic::MissingFormat(0)])]
+    #[case(vec![format(), flash(1.0, 2.0), format()], vec![CoordinateFormatDiagnostic::MultipleFormats(2)])]
+    #[case(vec![format(), flash(150.0, 2.0)], vec![CoordinateFormatDiagnostic::CoordinateOutOfRange {
+        index: 1,
+        value: 150.0,
+        integer_digits: 2,
+    }])]
+    fn test_validate_coordinate_format(
+        #[case] commands: Vec<Command>,
+        #[case] expected: Vec<CoordinateFormatDiagnostic>,
+    ) {
+        assert_eq!(validate_coordinate_format(&commands), expected);
+    }
+
+    #[test]
+    fn test_validate_coordinate_format_source() {
+        // given
+        let source = "%FSLAX26Y26*%\n%MOMM*%\nX0Y0D03*\n%FSTAX26Y26*%\nM02*\n";
+
+        // when
+        let diagnostics = validate_coordinate_format_source(source);
+
+        // then
+        assert_eq!(diagnostics, vec![
+            CoordinateFormatDiagnostic::TrailingZeroOmission(4),
+            CoordinateFormatDiagnostic::MixedZeroOmission(4),
+        ]);
+    }
+}
@@ -0,0 +1,200 @@
+use nalgebra::Point2;
+
+use crate::geometry::BoundingBox;
+use crate::raster::{premultiply, Canvas, ImageGeometry};
+use crate::{BlendMode, GerberLayer, RasterError, RasterImage, RasterOptions};
+
+/// The colors of [`render_difference`], straight (not premultiplied) RGBA.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DifferenceColors {
+    /// Geometry only in the `after` layers.
+    pub added: [u8; 4],
+    /// Geometry only in the `before` layers.
+    pub removed: [u8; 4],
+    /// Geometry in both, use a transparent color to only show the changes.
+    pub unchanged: [u8; 4],
+}
+
+impl Default for DifferenceColors {
+    fn default() -> Self {
+        Self {
+            added: [0, 200, 0, 255],
+            removed: [220, 0, 0, 255],
+            unchanged: [128, 128, 128, 96],
+        }
+    }
+}
+
+/// The result of [`render_difference`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerDifference {
+    pub image: RasterImage,
+    /// The area covered by the image, in gerber coordinates, after the transform.
+    pub bounds: BoundingBox,
+    /// The number of pixels covered by the `after` layers only.
+    pub added_pixels: usize,
+    /// The number of pixels covered by the `before` layers only.
+    pub removed_pixels: usize,
+}
+
+impl LayerDifference {
+    /// `true` if no pixels were added or removed, at the resolution of the image.
+    pub fn is_unchanged(&self) -> bool {
+        self.added_pixels == 0 && self.removed_pixels == 0
+    }
+}
+
+/// Renders the geometry present in only one of two sets of layers in distinct colors, e.g. to diff the gerbers of a
+/// release against the previous release.
+///
+/// Both sets are rasterized over the same area, the union of their bounding boxes, and compared pixel by pixel, like an
+/// XOR.  A pixel is covered by a set when at least half of it is covered, so disable [`RasterOptions::anti_aliasing`]
+/// for exact pixel coverage.  Clear primitives erase the dark primitives drawn before them, as in
+/// [`GerberLayer::render_to_image`].
+///
+/// [`RasterOptions::color`] and [`RasterOptions::negative`] are ignored.  All the layers are assumed to be in the same
+/// units.
+pub fn render_difference(
+    before: &[&GerberLayer],
+    after: &[&GerberLayer],
+    options: &RasterOptions,
+    colors: &DifferenceColors,
+) -> Result<LayerDifference, RasterError> {
+    let layers = before
+        .iter()
+        .chain(after)
+        .filter(|layer| !layer.is_empty())
+        .collect::<Vec<_>>();
+
+    let first = layers
+        .first()
+        .ok_or(RasterError::Empty)?;
+    let mut bounds = BoundingBox::default();
+    for layer in &layers {
+        let vertices = layer
+            .bounding_box()
+            .transform_vertices(&options.transform);
+        bounds.expand(&BoundingBox::from_points(&vertices));
+    }
+
+    let geometry = ImageGeometry::new(&bounds, first.units(), options.resolution)?;
+    let mut canvas = Canvas::new(&bounds, first.units(), options)?;
+
+    // the union of the coverage of the layers of a set
+    let coverage = |layers: &[&GerberLayer]| -> Vec<f32> {
+        let mut coverage = vec![0.0; geometry.width * geometry.height];
+        for layer in layers
+            .iter()
+            .filter(|layer| !layer.is_empty())
+        {
+            let mut mask = canvas.mask(
+                &options
+                    .transform
+                    .resolve(layer.bounding_box()),
+            );
+            mask.draw_layer(layer, None);
+            for (total, layer_coverage) in coverage
+                .iter_mut()
+                .zip(mask.into_coverage())
+            {
+                *total = total.max(layer_coverage);
+            }
+        }
+        coverage
+    };
+    let before_coverage = coverage(before);
+    let after_coverage = coverage(after);
+
+    let mut added_pixels = 0;
+    let mut removed_pixels = 0;
+    for ((pixel, before), after) in canvas
+        .pixels
+        .iter_mut()
+        .zip(before_coverage)
+        .zip(after_coverage)
+    {
+        let color = match (before >= 0.5, after >= 0.5) {
+            (false, false) => continue,
+            (true, false) => {
+                removed_pixels += 1;
+                colors.removed
+            }
+            (false, true) => {
+                added_pixels += 1;
+                colors.added
+            }
+            (true, true) => colors.unchanged,
+        };
+        // the background shows through a translucent color
+        *pixel = BlendMode::Normal.blend(premultiply(color, 1.0), *pixel);
+    }
+
+    // the image is centered in the bounds for a `Resolution::Fit` with another aspect ratio
+    let pixels_per_unit = geometry.pixels_per_unit;
+    let min_x = -geometry.to_pixels[(0, 2)] / pixels_per_unit;
+    let max_y = geometry.to_pixels[(1, 2)] / pixels_per_unit;
+    let image_bounds = BoundingBox::from_points(&[
+        Point2::new(min_x, max_y - geometry.height as f64 / pixels_per_unit),
+        Point2::new(min_x + geometry.width as f64 / pixels_per_unit, max_y),
+    ]);
+
+    Ok(LayerDifference {
+        image: canvas.into_image(),
+        bounds: image_bounds,
+        added_pixels,
+        removed_pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Rectangular, Unit};
+
+    use super::*;
+    use crate::testing::flash_layer;
+    use crate::{Resolution, MILLIMETERS_PER_INCH};
+
+    /// A layer with a 4x4mm square flashed at (x, 5) mm.
+    fn square_layer(x: f64) -> GerberLayer {
+        flash_layer(
+            Unit::Millimeters,
+            Aperture::Rectangle(Rectangular::new(4.0, 4.0)),
+            x,
+            5.0,
+        )
+    }
+
+    #[test]
+    fn test_render_difference() {
+        // given
+        // the square moved 2mm to the right
+        let before = square_layer(3.0);
+        let after = square_layer(5.0);
+        let options = RasterOptions {
+            // 1 pixel per mm
+            resolution: Resolution::Dpi(MILLIMETERS_PER_INCH),
+            ..RasterOptions::default()
+        };
+        let colors = DifferenceColors::default();
+
+        // when
+        let difference = render_difference(&[&before], &[&after], &options, &colors).unwrap();
+
+        // then
+        let image = &difference.image;
+        assert_eq!((image.width, image.height), (6, 4));
+        assert_eq!(image.pixel(0, 1), colors.removed);
+        assert_eq!(image.pixel(3, 1), colors.unchanged);
+        assert_eq!(image.pixel(5, 1), colors.added);
+        assert_eq!((difference.added_pixels, difference.removed_pixels), (8, 8));
+        assert_eq!(
+            (difference.bounds.min, difference.bounds.max),
+            (Point2::new(1.0, 3.0), Point2::new(7.0, 7.0))
+        );
+
+        // and
+        let unchanged = render_difference(&[&before], &[&before], &options, &colors).unwrap();
+        assert!(unchanged.is_unchanged());
+    }
+}
@@ -1,4 +1,13 @@
-use egui::{Color32, Painter, Pos2, Shape, Stroke};
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use egui::emath::Rot2;
+use egui::epaint::TextShape;
+use egui::{Align2, Color32, FontId, Painter, Pos2, Rect, Shape, Stroke, Vec2};
+use gerber_types::Unit;
+use nalgebra::Point2;
+
+use crate::geometry::BoundingBox;
+use crate::{CoordinateReadout, DrillSymbol, DrillTool, Measurement, OverlayColors, ViewState, MILLIMETERS_PER_INCH};
 
 pub fn draw_crosshair(painter: &Painter, position: Pos2, color: Color32) {
     // Calculate viewport bounds to extend lines across entire view
@@ -43,3 +52,828 @@ pub fn draw_marker(painter: &Painter, position: Pos2, color1: Color32, color2: C
 
     painter.circle(position, radius * 0.25, Color32::TRANSPARENT, Stroke::new(1.0, color2));
 }
+
+/// Draws a drill map symbol, `size` is the width and height of the symbol.
+pub fn draw_drill_symbol(painter: &Painter, center: Pos2, size: f32, symbol: DrillSymbol, color: Color32) {
+    let stroke = Stroke::new(1.0, color);
+    let half = size / 2.0;
+
+    let regular_polygon = |sides: usize, start_angle: f32| -> Vec<Pos2> {
+        (0..sides)
+            .map(|i| {
+                let angle = start_angle + TAU * i as f32 / sides as f32;
+                center + Vec2::angled(angle) * half
+            })
+            .collect()
+    };
+
+    match symbol {
+        DrillSymbol::Cross => {
+            painter.line_segment(
+                [center + Vec2::new(-half, -half), center + Vec2::new(half, half)],
+                stroke,
+            );
+            painter.line_segment(
+                [center + Vec2::new(-half, half), center + Vec2::new(half, -half)],
+                stroke,
+            );
+        }
+        DrillSymbol::Plus => {
+            painter.line_segment([center - Vec2::X * half, center + Vec2::X * half], stroke);
+            painter.line_segment([center - Vec2::Y * half, center + Vec2::Y * half], stroke);
+        }
+        DrillSymbol::Square => draw_outline(painter, regular_polygon(4, TAU / 8.0), color),
+        DrillSymbol::Diamond => draw_outline(painter, regular_polygon(4, 0.0), color),
+        // screen space y is down, so start at the bottom to point the triangle up
+        DrillSymbol::Triangle => draw_outline(painter, regular_polygon(3, -TAU / 4.0), color),
+        DrillSymbol::Hexagon => draw_outline(painter, regular_polygon(6, 0.0), color),
+        DrillSymbol::Circle => {
+            painter.circle_stroke(center, half, stroke);
+        }
+        DrillSymbol::Star => {
+            for vertex in regular_polygon(5, -TAU / 4.0) {
+                painter.line_segment([center, vertex], stroke);
+            }
+        }
+    }
+}
+
+/// Draws a drill legend, one row per tool, starting at `top_left`, in screen coordinates.
+///
+/// Each row has the tool's symbol, as assigned by [`DrillSymbol::for_index`], the tool number, diameter, plating and
+/// hit count, e.g. `T10  0.800  PTH  42`.
+pub fn draw_drill_legend(painter: &Painter, top_left: Pos2, tools: &[DrillTool], color: Color32) {
+    const ROW_HEIGHT: f32 = 16.0;
+    const SYMBOL_SIZE: f32 = 10.0;
+
+    let font_id = FontId::monospace(12.0);
+
+    for (index, tool) in tools.iter().enumerate() {
+        let row_center_y = top_left.y + ROW_HEIGHT * (index as f32 + 0.5);
+
+        draw_drill_symbol(
+            painter,
+            Pos2::new(top_left.x + SYMBOL_SIZE / 2.0, row_center_y),
+            SYMBOL_SIZE,
+            DrillSymbol::for_index(index),
+            color,
+        );
+
+        let plating = match tool.plated {
+            Some(true) => "PTH",
+            Some(false) => "NPTH",
+            None => "",
+        };
+        let mut text = format!(
+            "T{:<3} {:>7.3} {:<4} {:>5}",
+            tool.number, tool.diameter, plating, tool.hits
+        );
+        if tool.slots > 0 {
+            text.push_str(&format!(" ({} slots)", tool.slots));
+        }
+
+        painter.text(
+            Pos2::new(top_left.x + SYMBOL_SIZE * 2.0, row_center_y),
+            Align2::LEFT_CENTER,
+            text,
+            font_id.clone(),
+            color,
+        );
+    }
+}
+
+/// The unit of a grid, of rulers and of the cursor readout, see [`GridSettings`], [`RulerSettings`] and
+/// [`crate::CoordinateReadout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridUnit {
+    Millimeters,
+    /// Thousandths of an inch.
+    Mils,
+    Inches,
+}
+
+impl GridUnit {
+    /// The size of one grid unit in the units of the layers.
+    pub(crate) fn layer_units_per_unit(&self, layer_units: Unit) -> f64 {
+        match (self, layer_units) {
+            (GridUnit::Millimeters, Unit::Millimeters) => 1.0,
+            (GridUnit::Millimeters, Unit::Inches) => 1.0 / MILLIMETERS_PER_INCH,
+            (GridUnit::Mils, Unit::Millimeters) => MILLIMETERS_PER_INCH / 1000.0,
+            (GridUnit::Mils, Unit::Inches) => 1.0 / 1000.0,
+            (GridUnit::Inches, Unit::Millimeters) => MILLIMETERS_PER_INCH,
+            (GridUnit::Inches, Unit::Inches) => 1.0,
+        }
+    }
+
+    /// The symbol, e.g. `mm`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            GridUnit::Millimeters => "mm",
+            GridUnit::Mils => "mil",
+            GridUnit::Inches => "in",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridStyle {
+    Lines,
+    /// A dot at each intersection, less distracting than lines.
+    Dots,
+}
+
+/// How a grid is drawn, see [`draw_grid`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridSettings {
+    /// The distance between the minor lines, in the `unit`.
+    pub spacing: f64,
+    pub unit: GridUnit,
+    /// The units of the gerber coordinates, i.e. of the layers, see [`crate::GerberLayer::units`].
+    pub layer_units: Unit,
+    pub style: GridStyle,
+    /// Every `subdivisions`th line is a major line.  When zoomed out so that the minor lines would be closer than
+    /// `min_screen_spacing` the spacing is multiplied by `subdivisions`, until they are far enough apart.
+    pub subdivisions: u32,
+    /// In screen points.
+    pub min_screen_spacing: f32,
+    pub color: Color32,
+    pub major_color: Color32,
+    /// The color of the lines through the gerber origin, `None` to draw them like the other major lines.
+    pub axis_color: Option<Color32>,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            spacing: 1.0,
+            unit: GridUnit::Millimeters,
+            layer_units: Unit::Millimeters,
+            style: GridStyle::Lines,
+            subdivisions: 10,
+            min_screen_spacing: 8.0,
+            color: Color32::from_gray(40),
+            major_color: Color32::from_gray(70),
+            axis_color: Some(Color32::from_gray(120)),
+        }
+    }
+}
+
+impl GridSettings {
+    /// The distance between the minor lines that are drawn at the given scale, in gerber units, see
+    /// [`ViewState::scale`].
+    pub fn adapted_spacing(&self, scale: f32) -> f64 {
+        let mut spacing = self.spacing
+            * self
+                .unit
+                .layer_units_per_unit(self.layer_units);
+        let factor = self.subdivisions.max(2) as f64;
+        let min_spacing = self.min_screen_spacing.max(1.0) as f64 / scale as f64;
+        if !spacing.is_finite() || spacing <= 0.0 || !min_spacing.is_finite() {
+            return spacing;
+        }
+        while spacing < min_spacing {
+            spacing *= factor;
+        }
+        spacing
+    }
+}
+
+/// Draws a grid, in gerber coordinates, over the viewport, the grid adapts to the zoom, see [`GridSettings`].
+///
+/// Draw the grid before the layers, so it is behind them.
+pub fn draw_grid(painter: &Painter, viewport: Rect, view_state: &ViewState, settings: &GridSettings) {
+    let spacing = settings.adapted_spacing(view_state.scale);
+    if !spacing.is_finite() || spacing <= 0.0 {
+        return;
+    }
+
+    // screen y is down, gerber y is up
+    let top_left = view_state.screen_to_gerber_coords(viewport.left_top());
+    let bottom_right = view_state.screen_to_gerber_coords(viewport.right_bottom());
+    let indices = |min: f64, max: f64| (min / spacing).ceil() as i64..=(max / spacing).floor() as i64;
+    let columns = indices(top_left.x, bottom_right.x);
+    let rows = indices(bottom_right.y, top_left.y);
+
+    let subdivisions = settings.subdivisions.max(2) as i64;
+    // axis lines are drawn over major lines, which are drawn over minor lines
+    let importance = |index: i64| match index {
+        0 if settings.axis_color.is_some() => 2,
+        _ if index % subdivisions == 0 => 1,
+        _ => 0,
+    };
+    let color = |index: i64| match importance(index) {
+        2 => settings
+            .axis_color
+            .unwrap_or(settings.major_color),
+        1 => settings.major_color,
+        _ => settings.color,
+    };
+    let to_screen =
+        |x: i64, y: i64| view_state.gerber_to_screen_coords(Point2::new(x as f64 * spacing, y as f64 * spacing));
+
+    match settings.style {
+        GridStyle::Lines => {
+            for x in columns {
+                let screen_x = to_screen(x, 0).x;
+                painter.line_segment(
+                    [Pos2::new(screen_x, viewport.min.y), Pos2::new(screen_x, viewport.max.y)],
+                    Stroke::new(1.0, color(x)),
+                );
+            }
+            for y in rows {
+                let screen_y = to_screen(0, y).y;
+                painter.line_segment(
+                    [Pos2::new(viewport.min.x, screen_y), Pos2::new(viewport.max.x, screen_y)],
+                    Stroke::new(1.0, color(y)),
+                );
+            }
+        }
+        GridStyle::Dots => {
+            let shapes = columns
+                .flat_map(|x| {
+                    rows.clone().map(move |y| {
+                        // the dot takes the color of the most important line through it
+                        let index = match importance(x) >= importance(y) {
+                            true => x,
+                            false => y,
+                        };
+                        Shape::circle_filled(to_screen(x, y), 1.0, color(index))
+                    })
+                })
+                .collect::<Vec<_>>();
+            painter.extend(shapes);
+        }
+    }
+}
+
+/// How the rulers are drawn, see [`draw_rulers`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RulerSettings {
+    /// The unit of the labels.
+    pub unit: GridUnit,
+    /// The units of the gerber coordinates, i.e. of the layers, see [`crate::GerberLayer::units`].
+    pub layer_units: Unit,
+    /// The height of the top ruler and the width of the left ruler, in screen points.
+    pub size: f32,
+    /// The minimum distance between labelled ticks, in screen points, the ticks use a 1, 2, 5 sequence.
+    pub min_label_spacing: f32,
+    pub background: Color32,
+    pub color: Color32,
+    pub font_size: f32,
+    /// The color of the marker for the cursor position, `None` for no marker.
+    pub cursor_color: Option<Color32>,
+}
+
+impl Default for RulerSettings {
+    fn default() -> Self {
+        Self {
+            unit: GridUnit::Millimeters,
+            layer_units: Unit::Millimeters,
+            size: 20.0,
+            min_label_spacing: 60.0,
+            background: Color32::from_black_alpha(200),
+            color: Color32::from_gray(180),
+            font_size: 10.0,
+            cursor_color: Some(Color32::YELLOW),
+        }
+    }
+}
+
+impl RulerSettings {
+    /// The distance between labelled ticks, in the `unit`, and the number of minor ticks per labelled tick, at the
+    /// given scale, see [`ViewState::scale`].
+    pub fn tick_spacing(&self, scale: f32) -> (f64, u32) {
+        let units_per_point = 1.0
+            / (scale as f64
+                * self
+                    .unit
+                    .layer_units_per_unit(self.layer_units));
+        let min_spacing = self.min_label_spacing.max(1.0) as f64 * units_per_point;
+        if !min_spacing.is_finite() || min_spacing <= 0.0 {
+            return (1.0, 5);
+        }
+
+        let magnitude = 10_f64.powf(min_spacing.log10().floor());
+        [(1.0, 5), (2.0, 4), (5.0, 5), (10.0, 5)]
+            .into_iter()
+            .map(|(step, subdivisions)| (step * magnitude, subdivisions))
+            .find(|(spacing, _)| *spacing >= min_spacing)
+            .unwrap_or((10.0 * magnitude, 5))
+    }
+}
+
+/// Draws rulers along the top and left edges of the viewport, with ticks and labels in gerber coordinates that adapt
+/// to the zoom, and a marker for the `cursor` position, in gerber coordinates, see [`crate::UiState`].
+///
+/// Draw the rulers after the layers, so they are on top.
+pub fn draw_rulers(
+    painter: &Painter,
+    viewport: Rect,
+    view_state: &ViewState,
+    settings: &RulerSettings,
+    cursor: Option<Point2<f64>>,
+) {
+    let size = settings.size;
+    let top = Rect::from_min_max(viewport.min, Pos2::new(viewport.max.x, viewport.min.y + size));
+    let left = Rect::from_min_max(viewport.min, Pos2::new(viewport.min.x + size, viewport.max.y));
+    painter.rect_filled(top, 0.0, settings.background);
+    painter.rect_filled(left.with_min_y(top.max.y), 0.0, settings.background);
+
+    let (label_spacing, subdivisions) = settings.tick_spacing(view_state.scale);
+    let scale = settings
+        .unit
+        .layer_units_per_unit(settings.layer_units);
+    let spacing = label_spacing * scale / subdivisions as f64;
+    let decimals = (-label_spacing.log10().floor()).max(0.0) as usize;
+
+    let stroke = Stroke::new(1.0, settings.color);
+    let font_id = FontId::proportional(settings.font_size);
+    let top_left = view_state.screen_to_gerber_coords(viewport.left_top());
+    let bottom_right = view_state.screen_to_gerber_coords(viewport.right_bottom());
+    let indices = |min: f64, max: f64| (min / spacing).ceil() as i64..=(max / spacing).floor() as i64;
+    let subdivisions = subdivisions as i64;
+    let label = |index: i64| format!("{:.*}", decimals, (index / subdivisions) as f64 * label_spacing);
+
+    for x in indices(top_left.x, bottom_right.x) {
+        let screen_x = view_state
+            .gerber_to_screen_coords(Point2::new(x as f64 * spacing, 0.0))
+            .x;
+        if screen_x < left.max.x {
+            continue;
+        }
+        let major = x % subdivisions == 0;
+        let length = if major { size * 0.6 } else { size * 0.25 };
+        painter.line_segment(
+            [Pos2::new(screen_x, top.max.y - length), Pos2::new(screen_x, top.max.y)],
+            stroke,
+        );
+        if major {
+            painter.text(
+                Pos2::new(screen_x + 2.0, top.min.y + 1.0),
+                Align2::LEFT_TOP,
+                label(x),
+                font_id.clone(),
+                settings.color,
+            );
+        }
+    }
+
+    for y in indices(bottom_right.y, top_left.y) {
+        let screen_y = view_state
+            .gerber_to_screen_coords(Point2::new(0.0, y as f64 * spacing))
+            .y;
+        if screen_y < top.max.y {
+            continue;
+        }
+        let major = y % subdivisions == 0;
+        let length = if major { size * 0.6 } else { size * 0.25 };
+        painter.line_segment(
+            [
+                Pos2::new(left.max.x - length, screen_y),
+                Pos2::new(left.max.x, screen_y),
+            ],
+            stroke,
+        );
+        if major {
+            // rotated, reading upwards from the tick
+            let galley = painter.layout_no_wrap(label(y), font_id.clone(), settings.color);
+            painter.add(
+                TextShape::new(Pos2::new(left.min.x + 1.0, screen_y - 2.0), galley, settings.color)
+                    .with_angle(-FRAC_PI_2),
+            );
+        }
+    }
+
+    if let (Some(color), Some(cursor)) = (settings.cursor_color, cursor) {
+        let position = view_state.gerber_to_screen_coords(cursor);
+        if position.x >= left.max.x {
+            painter.line_segment(
+                [Pos2::new(position.x, top.min.y), Pos2::new(position.x, top.max.y)],
+                Stroke::new(1.0, color),
+            );
+        }
+        if position.y >= top.max.y {
+            painter.line_segment(
+                [Pos2::new(left.min.x, position.y), Pos2::new(left.max.x, position.y)],
+                Stroke::new(1.0, color),
+            );
+        }
+    }
+}
+
+/// How the cursor guides are drawn, see [`draw_cursor_guides`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorGuideSettings {
+    pub color: Color32,
+    pub font_size: f32,
+    /// Behind the labels, so they can be read over the layers.
+    pub label_background: Color32,
+}
+
+impl Default for CursorGuideSettings {
+    fn default() -> Self {
+        Self {
+            color: Color32::from_rgba_unmultiplied(255, 255, 0, 160),
+            font_size: 11.0,
+            label_background: Color32::from_black_alpha(200),
+        }
+    }
+}
+
+/// Draws horizontal and vertical guide lines through the `cursor`, in gerber coordinates, across the whole viewport,
+/// e.g. for visually aligning features across the board.
+///
+/// With a `readout` the coordinates of the cursor are labelled, the x coordinate at the bottom of the vertical line and
+/// the y coordinate at the right of the horizontal line, away from rulers, see [`draw_rulers`].  Draw the guides after
+/// the layers, so they are on top.
+pub fn draw_cursor_guides(
+    painter: &Painter,
+    viewport: Rect,
+    view_state: &ViewState,
+    settings: &CursorGuideSettings,
+    cursor: Option<Point2<f64>>,
+    readout: Option<&CoordinateReadout>,
+) {
+    let Some(cursor) = cursor else {
+        return;
+    };
+    let position = view_state.gerber_to_screen_coords(cursor);
+    if !viewport.contains(position) {
+        return;
+    }
+
+    let stroke = Stroke::new(1.0, settings.color);
+    painter.line_segment(
+        [
+            Pos2::new(viewport.min.x, position.y),
+            Pos2::new(viewport.max.x, position.y),
+        ],
+        stroke,
+    );
+    painter.line_segment(
+        [
+            Pos2::new(position.x, viewport.min.y),
+            Pos2::new(position.x, viewport.max.y),
+        ],
+        stroke,
+    );
+
+    let Some(readout) = readout else {
+        return;
+    };
+    let (x, y) = readout.format_axes(cursor);
+    let font_id = FontId::proportional(settings.font_size);
+    let label = |anchor: Pos2, align: Align2, text: String| {
+        let galley = painter.layout_no_wrap(text, font_id.clone(), settings.color);
+        let mut rect = align.anchor_size(anchor, galley.size());
+        // keep the label in the viewport, e.g. when the cursor is near the edge
+        rect = rect.translate(Vec2::new(
+            (viewport.min.x - rect.min.x).max(0.0) + (viewport.max.x - rect.max.x).min(0.0),
+            (viewport.min.y - rect.min.y).max(0.0) + (viewport.max.y - rect.max.y).min(0.0),
+        ));
+        painter.rect_filled(rect.expand(2.0), 2.0, settings.label_background);
+        painter.galley(rect.min, galley, settings.color);
+    };
+    label(
+        Pos2::new(position.x + 4.0, viewport.max.y - 4.0),
+        Align2::LEFT_BOTTOM,
+        x,
+    );
+    label(
+        Pos2::new(viewport.max.x - 4.0, position.y - 4.0),
+        Align2::RIGHT_BOTTOM,
+        y,
+    );
+}
+
+/// How dimensions are drawn, see [`draw_dimension`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimensionSettings {
+    pub color: Color32,
+    pub font_size: f32,
+    /// Behind the labels, so they can be read over the layers.
+    pub label_background: Color32,
+    /// The distance from the measured feature to the dimension line, in screen points.
+    pub offset: f32,
+    /// The gap between the feature and the start of the leader lines, in screen points.
+    pub leader_gap: f32,
+    /// The length of the arrowheads, in screen points.
+    pub arrow_size: f32,
+}
+
+impl Default for DimensionSettings {
+    fn default() -> Self {
+        Self {
+            color: Color32::from_rgb(0, 200, 255),
+            font_size: 12.0,
+            label_background: Color32::from_black_alpha(200),
+            offset: 24.0,
+            leader_gap: 4.0,
+            arrow_size: 8.0,
+        }
+    }
+}
+
+/// Draws a dimension line between two points, in gerber coordinates, labelled with the distance between them formatted
+/// using the `readout`, see [`CoordinateReadout::format_length`].
+///
+/// The dimension line is parallel to the points, moved `settings.offset` screen points to the right of the direction
+/// from `start` to `end`, as seen on screen, use a negative offset for the other side.  Leader lines connect the points
+/// to the ends of the dimension line, which has arrowheads at both ends.  The label is drawn along the line, so it
+/// reads from left to right, or upwards.
+pub fn draw_dimension(
+    painter: &Painter,
+    view_state: &ViewState,
+    settings: &DimensionSettings,
+    start: Point2<f64>,
+    end: Point2<f64>,
+    readout: &CoordinateReadout,
+) {
+    let start_screen = view_state.gerber_to_screen_coords(start);
+    let end_screen = view_state.gerber_to_screen_coords(end);
+    let Some(direction) = Some(end_screen - start_screen)
+        .filter(|direction| direction.length() > 0.0)
+        .map(Vec2::normalized)
+    else {
+        return;
+    };
+    // screen space y is down, so this is to the right of the direction
+    let normal = Vec2::new(-direction.y, direction.x);
+
+    let stroke = Stroke::new(1.0, settings.color);
+    let line_start = start_screen + normal * settings.offset;
+    let line_end = end_screen + normal * settings.offset;
+    // the leader lines start a gap away from the feature and extend a little past the dimension line
+    let gap = normal * settings.offset.signum() * settings.leader_gap;
+    for (from, to) in [(start_screen, line_start), (end_screen, line_end)] {
+        painter.line_segment([from + gap, to + gap], stroke);
+    }
+    painter.line_segment([line_start, line_end], stroke);
+
+    draw_arrowhead(painter, line_start, -direction, settings.arrow_size, settings.color);
+    draw_arrowhead(painter, line_end, direction, settings.arrow_size, settings.color);
+
+    let text = readout.format_length((end - start).norm());
+    let galley = painter.layout_no_wrap(text, FontId::proportional(settings.font_size), settings.color);
+    // keep the text readable, i.e. not upside down
+    let direction = match direction.x < 0.0 || (direction.x == 0.0 && direction.y > 0.0) {
+        true => -direction,
+        false => direction,
+    };
+    let angle = direction.angle();
+    let rotate = |v: Vec2| Rot2::from_angle(angle) * v;
+
+    let size = galley.size() + Vec2::splat(4.0);
+    let center = line_start + (line_end - line_start) / 2.0;
+    let corners = [
+        Vec2::new(-size.x, -size.y),
+        Vec2::new(size.x, -size.y),
+        Vec2::new(size.x, size.y),
+        Vec2::new(-size.x, size.y),
+    ]
+    .map(|corner| center + rotate(corner / 2.0))
+    .to_vec();
+    painter.add(Shape::convex_polygon(corners, settings.label_background, Stroke::NONE));
+    painter.add(TextShape::new(center - rotate(galley.size() / 2.0), galley, settings.color).with_angle(angle));
+}
+
+/// Draws the width of the bounding box, in gerber coordinates, below it, and its height to the right of it, e.g. for
+/// the board outline, see [`crate::LayerSet::board_bounding_box`], or a selected feature, see [`draw_dimension`].
+pub fn draw_bounding_box_dimensions(
+    painter: &Painter,
+    view_state: &ViewState,
+    settings: &DimensionSettings,
+    bbox: &BoundingBox,
+    readout: &CoordinateReadout,
+) {
+    if bbox.is_empty() {
+        return;
+    }
+    let bottom_right = Point2::new(bbox.max.x, bbox.min.y);
+    // counter-clockwise in gerber coordinates, so the dimensions are outside the bounding box
+    draw_dimension(painter, view_state, settings, bbox.min, bottom_right, readout);
+    draw_dimension(painter, view_state, settings, bottom_right, bbox.max, readout);
+}
+
+/// How measurements are drawn, see [`draw_measurement`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeasurementSettings {
+    pub color: Color32,
+    pub font_size: f32,
+    /// Behind the label, so it can be read over the layers.
+    pub label_background: Color32,
+    /// The length of the arrowheads, in screen points.
+    pub arrow_size: f32,
+}
+
+impl Default for MeasurementSettings {
+    fn default() -> Self {
+        Self {
+            color: Color32::from_rgb(255, 128, 0),
+            font_size: 12.0,
+            label_background: Color32::from_black_alpha(200),
+            arrow_size: 8.0,
+        }
+    }
+}
+
+/// Draws a measurement as a line with arrowheads at both ends, and labels it with the length, the x and y distances
+/// and the angle, formatted using the `readout`, see [`Measurement::format`], e.g. while dragging, see
+/// [`crate::UiState::handle_measuring`].
+pub fn draw_measurement(
+    painter: &Painter,
+    view_state: &ViewState,
+    settings: &MeasurementSettings,
+    measurement: &Measurement,
+    readout: &CoordinateReadout,
+) {
+    let start = view_state.gerber_to_screen_coords(measurement.start);
+    let end = view_state.gerber_to_screen_coords(measurement.end);
+    let stroke = Stroke::new(1.5, settings.color);
+    painter.line_segment([start, end], stroke);
+    if let Some(direction) = Some(end - start)
+        .filter(|direction| direction.length() > settings.arrow_size * 2.0)
+        .map(Vec2::normalized)
+    {
+        draw_arrowhead(painter, start, -direction, settings.arrow_size, settings.color);
+        draw_arrowhead(painter, end, direction, settings.arrow_size, settings.color);
+    }
+
+    let galley = painter.layout_no_wrap(
+        measurement.format(readout),
+        FontId::proportional(settings.font_size),
+        settings.color,
+    );
+    // next to the end, i.e. the cursor while dragging, but inside the viewport
+    let viewport = painter.clip_rect();
+    let mut rect = Align2::LEFT_TOP.anchor_size(end + Vec2::new(12.0, 12.0), galley.size());
+    rect = rect.translate(Vec2::new(
+        (viewport.max.x - rect.max.x).min(0.0),
+        (viewport.max.y - rect.max.y).min(0.0),
+    ));
+    painter.rect_filled(rect.expand(2.0), 2.0, settings.label_background);
+    painter.galley(rect.min, galley, settings.color);
+}
+
+/// A filled arrowhead with the tip at `tip`, pointing in the `direction`, a unit vector, in screen coordinates.
+fn draw_arrowhead(painter: &Painter, tip: Pos2, direction: Vec2, size: f32, color: Color32) {
+    let base = tip - direction * size;
+    let half_width = Vec2::new(-direction.y, direction.x) * size / 3.0;
+    painter.add(Shape::convex_polygon(
+        vec![tip, base + half_width, base - half_width],
+        color,
+        Stroke::NONE,
+    ));
+}
+
+/// The shape of a marker, see [`MarkerStyle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerShape {
+    /// Lines across the whole viewport, the size is not used, see [`draw_crosshair`].
+    Crosshair,
+    Cross,
+    /// A cross with a small circle in the middle, see [`draw_marker`].
+    CrossCircle,
+    Circle,
+    Diamond,
+}
+
+/// The size of a marker, e.g. the radius of a circle or the half-length of the lines of a cross.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarkerSize {
+    /// In screen points, the marker stays the same size when zooming.
+    Screen(f32),
+    /// In gerber units, the marker is scaled with the layers.
+    Board(f64),
+}
+
+impl MarkerSize {
+    /// The size in screen points, for the `scale`, e.g. [`ViewState::scale`].
+    pub fn to_screen(&self, scale: f32) -> f32 {
+        match self {
+            MarkerSize::Screen(size) => *size,
+            MarkerSize::Board(size) => *size as f32 * scale,
+        }
+    }
+}
+
+/// How a marker is drawn, e.g. the origin, the center of the viewport or a datum, see [`draw_styled_marker`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkerStyle {
+    pub shape: MarkerShape,
+    pub size: MarkerSize,
+    pub color: Color32,
+    /// The color of the circle of [`MarkerShape::CrossCircle`], and of the label.
+    pub secondary_color: Color32,
+    /// Drawn next to the marker, e.g. `Origin`, `None` for no label.
+    pub label: Option<String>,
+    pub font_size: f32,
+}
+
+impl Default for MarkerStyle {
+    /// For dark backgrounds, see [`MarkerStyle::new`].
+    fn default() -> Self {
+        let colors = OverlayColors::dark();
+        Self {
+            shape: MarkerShape::CrossCircle,
+            size: MarkerSize::Screen(10.0),
+            color: colors.marker,
+            secondary_color: colors.marker_secondary,
+            label: None,
+            font_size: 11.0,
+        }
+    }
+}
+
+impl MarkerStyle {
+    /// A crosshair, for the gerber origin, 0,0, e.g. `MarkerStyle::origin(&OverlayColors::from_visuals(ui.visuals()))`.
+    pub fn origin(colors: &OverlayColors) -> Self {
+        Self {
+            shape: MarkerShape::Crosshair,
+            color: colors.origin,
+            secondary_color: colors.origin,
+            ..Self::new(colors)
+        }
+    }
+
+    /// A crosshair, for the center of the viewport.
+    pub fn center(colors: &OverlayColors) -> Self {
+        Self {
+            shape: MarkerShape::Crosshair,
+            color: colors.center,
+            secondary_color: colors.center,
+            ..Self::new(colors)
+        }
+    }
+
+    /// A labelled diamond, for [`ViewState::datum`].
+    pub fn datum(colors: &OverlayColors) -> Self {
+        Self {
+            shape: MarkerShape::Diamond,
+            size: MarkerSize::Screen(8.0),
+            color: colors.datum,
+            secondary_color: colors.datum,
+            label: Some("Datum".to_string()),
+            ..Self::new(colors)
+        }
+    }
+
+    /// A cross with a circle, in the marker colors.
+    pub fn new(colors: &OverlayColors) -> Self {
+        Self {
+            color: colors.marker,
+            secondary_color: colors.marker_secondary,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+}
+
+/// Draws a marker at the `position`, in gerber coordinates, e.g. the origin, a datum or an offset, instead of
+/// [`draw_crosshair`] and [`draw_marker`] with hard-coded colors and sizes.  The label is drawn to the bottom right of
+/// the marker.
+pub fn draw_styled_marker(painter: &Painter, view_state: &ViewState, position: Point2<f64>, style: &MarkerStyle) {
+    let center = view_state.gerber_to_screen_coords(position);
+    let size = style.size.to_screen(view_state.scale);
+    let stroke = Stroke::new(1.0, style.color);
+
+    match style.shape {
+        MarkerShape::Crosshair => draw_crosshair(painter, center, style.color),
+        MarkerShape::Cross => {
+            painter.line_segment([center - Vec2::X * size, center + Vec2::X * size], stroke);
+            painter.line_segment([center - Vec2::Y * size, center + Vec2::Y * size], stroke);
+        }
+        MarkerShape::CrossCircle => draw_marker(painter, center, style.color, style.secondary_color, size),
+        MarkerShape::Circle => {
+            painter.circle_stroke(center, size, stroke);
+        }
+        MarkerShape::Diamond => draw_outline(
+            painter,
+            vec![
+                center - Vec2::Y * size,
+                center + Vec2::X * size,
+                center + Vec2::Y * size,
+                center - Vec2::X * size,
+            ],
+            style.color,
+        ),
+    }
+
+    if let Some(label) = &style.label {
+        let offset = match style.shape {
+            MarkerShape::Crosshair => 4.0,
+            _ => size * std::f32::consts::FRAC_1_SQRT_2 + 2.0,
+        };
+        painter.text(
+            center + Vec2::splat(offset),
+            Align2::LEFT_TOP,
+            label,
+            FontId::proportional(style.font_size),
+            style.secondary_color,
+        );
+    }
+}
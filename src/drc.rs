@@ -0,0 +1,509 @@
+use std::cell::OnceCell;
+
+use gerber_types::Unit;
+use nalgebra::{Point2, Vector2};
+use thiserror::Error;
+
+use crate::geometry::BoundingBox;
+use crate::layer::{
+    CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, RectangleGerberPrimitive,
+};
+use crate::Exposure;
+
+/// The design rules checked by [`DrcRuleSet::check`], in mm, `None` disables a rule.
+///
+/// With the `drc-rules` feature rule sets can be loaded from TOML or JSON files, e.g. the capabilities of a PCB
+/// manufacturer, see [`DrcRuleSet::load`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct DrcRuleSet {
+    /// The name shown to users, e.g. the manufacturer and process, empty by default.
+    pub name: String,
+    /// The minimum gap between copper primitives that don't touch and are not connected, i.e. on the same net or, with
+    /// the `geo` feature, part of the same island of copper, see `GerberLayer::islands`.
+    pub min_spacing: Option<f64>,
+    /// The minimum width of traces, i.e. lines and arcs.
+    pub min_width: Option<f64>,
+    /// The minimum width of the copper around a hole, measured at the narrowest point.
+    pub min_annular_ring: Option<f64>,
+    /// The minimum diameter of a drilled hole.
+    pub min_drill: Option<f64>,
+}
+
+/// A design rule violation, layers are indices into the layers passed to [`DrcRuleSet::check`].
+///
+/// Measurements are in mm, locations are in the units of the layer.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum DrcViolation {
+    #[error("Spacing less than {minimum}mm. layer: {layer}, primitives: {primitives:?}, spacing: {spacing}mm")]
+    Spacing {
+        layer: usize,
+        primitives: (usize, usize),
+        spacing: f64,
+        minimum: f64,
+        /// The closest points of the two primitives.
+        points: (Point2<f64>, Point2<f64>),
+    },
+    #[error("Trace width less than {minimum}mm. layer: {layer}, primitive: {primitive}, width: {width}mm")]
+    Width {
+        layer: usize,
+        primitive: usize,
+        width: f64,
+        minimum: f64,
+        location: Point2<f64>,
+    },
+    #[error("Annular ring less than {minimum}mm. layer: {layer}, primitive: {primitive}, drill_layer: {drill_layer}, ring: {ring}mm")]
+    AnnularRing {
+        /// The copper layer.
+        layer: usize,
+        /// The pad.
+        primitive: usize,
+        drill_layer: usize,
+        ring: f64,
+        minimum: f64,
+        /// The center of the hole, in the units of the drill layer.
+        location: Point2<f64>,
+    },
+    #[error("Drill smaller than {minimum}mm. layer: {layer}, tool: {tool}, diameter: {diameter}mm")]
+    Drill {
+        layer: usize,
+        tool: i32,
+        diameter: f64,
+        minimum: f64,
+        location: Point2<f64>,
+    },
+}
+
+impl DrcViolation {
+    /// A location for markers, e.g. the middle of the gap for spacing violations.
+    pub fn location(&self) -> Point2<f64> {
+        match self {
+            DrcViolation::Spacing {
+                points: (from, to), ..
+            } => Point2::from((from.coords + to.coords) / 2.0),
+            DrcViolation::Width {
+                location, ..
+            }
+            | DrcViolation::AnnularRing {
+                location, ..
+            }
+            | DrcViolation::Drill {
+                location, ..
+            } => *location,
+        }
+    }
+}
+
+impl DrcRuleSet {
+    /// Checks the layers against the rules.
+    ///
+    /// Layers with a drill `.FileFunction` are drill layers, see [`GerberLayer::drill_plating`], the others are checked
+    /// as copper layers.  Annular rings are checked for every plated hole that is inside a circular or rectangular pad
+    /// on a copper layer.  Layers without units are ignored.
+    pub fn check(&self, layers: &[&GerberLayer]) -> Vec<DrcViolation> {
+        let mut violations = vec![];
+
+        let (drill_layers, copper_layers): (Vec<_>, Vec<_>) = layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.units().is_some())
+            .partition(|(_, layer)| layer.drill_plating().is_some());
+
+        for (index, layer) in &copper_layers {
+            if let Some(minimum) = self.min_spacing {
+                check_spacing(*index, layer, minimum, &mut violations);
+            }
+            if let Some(minimum) = self.min_width {
+                check_width(*index, layer, minimum, &mut violations);
+            }
+        }
+
+        for (drill_index, drill_layer) in &drill_layers {
+            if let Some(minimum) = self.min_drill {
+                check_drill(*drill_index, drill_layer, minimum, &mut violations);
+            }
+            if let Some(minimum) = self.min_annular_ring {
+                if drill_layer.drill_plating() == Some(true) {
+                    for (index, layer) in &copper_layers {
+                        check_annular_ring(*index, layer, *drill_index, drill_layer, minimum, &mut violations);
+                    }
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+fn mm_scale(layer: &GerberLayer) -> f64 {
+    layer
+        .scale_to(Unit::Millimeters)
+        .unwrap_or(1.0)
+}
+
+fn check_spacing(layer_index: usize, layer: &GerberLayer, minimum: f64, violations: &mut Vec<DrcViolation>) {
+    let to_mm = mm_scale(layer);
+    let margin = minimum / to_mm;
+    let is_copper = |index: usize| layer.gerber_primitives()[index].exposure() == Exposure::Add;
+    // only computed when there are primitives to compare, merging the copper is expensive
+    let islands = OnceCell::new();
+    let on_same_island = |a: usize, b: usize| {
+        let islands = islands.get_or_init(|| primitive_islands(layer));
+        let island = |index: usize| islands.get(index).copied().flatten();
+        matches!((island(a), island(b)), (Some(a), Some(b)) if a == b)
+    };
+
+    for (index, primitive) in layer
+        .gerber_primitives()
+        .iter()
+        .enumerate()
+    {
+        if !is_copper(index) {
+            continue;
+        }
+        let bbox = primitive.bounding_box();
+
+        let mut candidates = layer
+            .primitive_offsets(index)
+            .into_iter()
+            .flat_map(|offset| {
+                let region = BoundingBox {
+                    min: bbox.min + offset,
+                    max: bbox.max + offset,
+                };
+                layer.query_region(&region.inflate(margin))
+            })
+            .filter(|other| *other > index && is_copper(*other) && !on_same_net(layer, index, *other))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        for other in candidates {
+            let Some(clearance) = layer.clearance(index, other) else {
+                continue;
+            };
+            let spacing = clearance.distance * to_mm;
+            // touching primitives are connected, e.g. a pad and its trace, and so are primitives connected via other
+            // primitives, e.g. the two legs of a hairpin trace
+            if spacing > 0.0 && spacing < minimum && !on_same_island(index, other) {
+                violations.push(DrcViolation::Spacing {
+                    layer: layer_index,
+                    primitives: (index, other),
+                    spacing,
+                    minimum,
+                    points: (clearance.from, clearance.to),
+                });
+            }
+        }
+    }
+}
+
+fn on_same_net(layer: &GerberLayer, a: usize, b: usize) -> bool {
+    let net = |index: usize| {
+        layer
+            .primitive_attributes(index)
+            .and_then(|attributes| attributes.net())
+    };
+    matches!((net(a), net(b)), (Some(a), Some(b)) if !a.is_empty() && a == b)
+}
+
+/// The island of each primitive, see [`Islands::primitive_islands`](crate::Islands::primitive_islands).
+#[cfg(feature = "geo")]
+fn primitive_islands(layer: &GerberLayer) -> Vec<Option<usize>> {
+    layer.islands().primitive_islands
+}
+
+/// Without `geo` only the net attributes are used to find connected primitives.
+#[cfg(not(feature = "geo"))]
+fn primitive_islands(_layer: &GerberLayer) -> Vec<Option<usize>> {
+    vec![]
+}
+
+fn check_width(layer_index: usize, layer: &GerberLayer, minimum: f64, violations: &mut Vec<DrcViolation>) {
+    let to_mm = mm_scale(layer);
+
+    for (index, primitive) in layer
+        .gerber_primitives()
+        .iter()
+        .enumerate()
+    {
+        let (width, location) = match primitive {
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                exposure: Exposure::Add,
+            }) => (*width, Point2::from((start.coords + end.coords) / 2.0)),
+            GerberPrimitive::Arc(arc) if arc.exposure == Exposure::Add => {
+                let angle = arc.start_angle + arc.sweep_angle / 2.0;
+                (
+                    arc.width,
+                    arc.center + Vector2::new(angle.cos(), angle.sin()) * arc.radius,
+                )
+            }
+            _ => continue,
+        };
+
+        let width = width * to_mm;
+        if width < minimum {
+            for offset in layer.primitive_offsets(index) {
+                violations.push(DrcViolation::Width {
+                    layer: layer_index,
+                    primitive: index,
+                    width,
+                    minimum,
+                    location: location + offset,
+                });
+            }
+        }
+    }
+}
+
+fn check_drill(layer_index: usize, layer: &GerberLayer, minimum: f64, violations: &mut Vec<DrcViolation>) {
+    let to_mm = mm_scale(layer);
+
+    for tool in layer.drill_tools() {
+        let diameter = tool.diameter * to_mm;
+        if diameter >= minimum {
+            continue;
+        }
+        for position in tool.positions {
+            violations.push(DrcViolation::Drill {
+                layer: layer_index,
+                tool: tool.number,
+                diameter,
+                minimum,
+                location: position,
+            });
+        }
+    }
+}
+
+fn check_annular_ring(
+    layer_index: usize,
+    layer: &GerberLayer,
+    drill_layer_index: usize,
+    drill_layer: &GerberLayer,
+    minimum: f64,
+    violations: &mut Vec<DrcViolation>,
+) {
+    let to_mm = mm_scale(layer);
+    // drill layer units to copper layer units
+    let drill_scale = mm_scale(drill_layer) / to_mm;
+
+    for tool in drill_layer.drill_tools() {
+        let hole_radius = tool.diameter * drill_scale / 2.0;
+
+        for position in &tool.positions {
+            let center = Point2::from(position.coords * drill_scale);
+
+            // the pad with the widest ring, when pads overlap
+            let pad = layer
+                .hit_test(center, 0.0)
+                .into_iter()
+                .filter_map(|index| {
+                    layer
+                        .primitive_offsets(index)
+                        .into_iter()
+                        .filter_map(|offset| {
+                            annular_ring(&layer.gerber_primitives()[index], center - offset, hole_radius)
+                        })
+                        .max_by(f64::total_cmp)
+                        .map(|ring| (index, ring))
+                })
+                .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            if let Some((primitive, ring)) = pad {
+                let ring = ring * to_mm;
+                if ring < minimum {
+                    violations.push(DrcViolation::AnnularRing {
+                        layer: layer_index,
+                        primitive,
+                        drill_layer: drill_layer_index,
+                        ring,
+                        minimum,
+                        location: *position,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// The narrowest copper between the hole and the edge of the pad, negative if the hole breaks out of the pad.
+///
+/// Returns `None` if the primitive isn't a pad containing the center of the hole.
+fn annular_ring(primitive: &GerberPrimitive, center: Point2<f64>, hole_radius: f64) -> Option<f64> {
+    let distance_to_edge = match primitive {
+        GerberPrimitive::Circle(CircleGerberPrimitive {
+            center: pad_center,
+            diameter,
+            exposure: Exposure::Add,
+        }) => diameter / 2.0 - (center - *pad_center).norm(),
+        GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+            origin,
+            width,
+            height,
+            exposure: Exposure::Add,
+        }) => (center.x - origin.x)
+            .min(origin.x + width - center.x)
+            .min(center.y - origin.y)
+            .min(origin.y + height - center.y),
+        _ => return None,
+    };
+
+    match distance_to_edge >= 0.0 {
+        true => Some(distance_to_edge - hole_radius),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "parser")]
+    use std::io::BufReader;
+
+    #[cfg(feature = "parser")]
+    use gerber_parser::parse;
+    use gerber_types::{Aperture, Circle};
+
+    use super::*;
+    use crate::testing::{aperture, header, interpolate, move_to};
+
+    #[test]
+    fn test_spacing_and_width() {
+        // given
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(0.1))));
+        commands.extend([
+            // two parallel traces, 0.1mm apart
+            move_to(0.0, 0.0),
+            interpolate(10.0, 0.0),
+            move_to(0.0, 0.2),
+            interpolate(10.0, 0.2),
+        ]);
+        let layer = GerberLayer::new(commands);
+        let rules = DrcRuleSet {
+            min_spacing: Some(0.15),
+            min_width: Some(0.15),
+            ..DrcRuleSet::default()
+        };
+
+        // when
+        let violations = rules.check(&[&layer]);
+
+        // then
+        assert_eq!(violations.len(), 3);
+        let DrcViolation::Spacing {
+            primitives,
+            spacing,
+            ..
+        } = &violations[0]
+        else {
+            panic!("expected a spacing violation, got {:?}", violations[0]);
+        };
+        assert_eq!(*primitives, (0, 1));
+        assert!((spacing - 0.1).abs() < 1e-9);
+        assert!(matches!(violations[1], DrcViolation::Width {
+            primitive: 0,
+            ..
+        }));
+        assert!(matches!(violations[2], DrcViolation::Width {
+            primitive: 1,
+            ..
+        }));
+    }
+
+    #[test]
+    #[cfg(feature = "geo")]
+    fn test_spacing_of_connected_primitives() {
+        // given
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(0.1))));
+        commands.extend([
+            // a hairpin trace without net attributes, the legs are 0.1mm apart
+            move_to(0.0, 0.0),
+            interpolate(10.0, 0.0),
+            interpolate(10.0, 0.2),
+            interpolate(0.0, 0.2),
+        ]);
+        let layer = GerberLayer::new(commands);
+        let rules = DrcRuleSet {
+            min_spacing: Some(0.15),
+            ..DrcRuleSet::default()
+        };
+
+        // when
+        let violations = rules.check(&[&layer]);
+
+        // then
+        assert_eq!(violations, vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "parser")]
+    fn test_annular_ring_and_drill() {
+        // given
+        let copper_source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%TF.FileFunction,Copper,L1,Top*%
+%ADD10R,1.000X1.000*%
+D10*
+X0Y0D03*
+X5000000Y0D03*
+M02*
+"#;
+        // the second hole is off-center and breaks out of the pad
+        let drill_source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%TF.FileFunction,Plated,1,2,PTH*%
+%ADD20C,0.500*%
+D20*
+X0Y0D03*
+X5300000Y0D03*
+M02*
+"#;
+        let layer = |source: &str| {
+            let reader = BufReader::new(source.as_bytes());
+            GerberLayer::new(parse(reader).unwrap().into_commands())
+        };
+        let copper = layer(copper_source);
+        let drill = layer(drill_source);
+
+        let rules = DrcRuleSet {
+            min_annular_ring: Some(0.15),
+            min_drill: Some(0.6),
+            ..DrcRuleSet::default()
+        };
+
+        // when
+        let violations = rules.check(&[&copper, &drill]);
+
+        // then
+        assert_eq!(violations.len(), 3);
+        assert!(matches!(violations[0], DrcViolation::Drill {
+            layer: 1,
+            tool: 20,
+            ..
+        }));
+        assert!(matches!(violations[1], DrcViolation::Drill {
+            layer: 1,
+            tool: 20,
+            ..
+        }));
+        let DrcViolation::AnnularRing {
+            layer,
+            primitive,
+            drill_layer,
+            ring,
+            ..
+        } = &violations[2]
+        else {
+            panic!("expected an annular ring violation, got {:?}", violations[2]);
+        };
+        assert_eq!((*layer, *primitive, *drill_layer), (0, 1, 1));
+        assert!((ring - -0.05).abs() < 1e-9);
+    }
+}
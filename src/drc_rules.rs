@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::DrcRuleSet;
+
+#[derive(Error, Debug)]
+pub enum DrcRulesError {
+    #[error("IO error. path: {path:?}, cause: {cause}")]
+    Io { path: PathBuf, cause: std::io::Error },
+    #[error("Unknown rule file format, expected a '.toml' or '.json' file. path: {0:?}")]
+    UnknownFormat(PathBuf),
+    #[error("TOML error. cause: {0}")]
+    TomlDe(#[from] toml::de::Error),
+    #[error("TOML error. cause: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("JSON error. cause: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The format of a rule file, see [`DrcRuleSet::load`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrcRuleFormat {
+    Toml,
+    Json,
+}
+
+impl DrcRuleFormat {
+    /// The format of a file, from its extension, case-insensitive.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path
+            .as_ref()
+            .extension()?
+            .to_str()?
+            .to_lowercase();
+        match extension.as_str() {
+            "toml" => Some(DrcRuleFormat::Toml),
+            "json" => Some(DrcRuleFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl DrcRuleSet {
+    /// Parses a rule set, rules that are missing are disabled, e.g.
+    /// ```toml
+    /// name = "Example 2-layer"
+    /// min_spacing = 0.15
+    /// min_width = 0.15
+    /// min_annular_ring = 0.13
+    /// min_drill = 0.3
+    /// ```
+    /// The values are in mm.
+    pub fn parse(content: &str, format: DrcRuleFormat) -> Result<Self, DrcRulesError> {
+        Ok(match format {
+            DrcRuleFormat::Toml => toml::from_str(content)?,
+            DrcRuleFormat::Json => serde_json::from_str(content)?,
+        })
+    }
+
+    /// Formats the rule set, disabled rules are omitted from TOML and are `null` in JSON.
+    pub fn format(&self, format: DrcRuleFormat) -> Result<String, DrcRulesError> {
+        Ok(match format {
+            DrcRuleFormat::Toml => toml::to_string_pretty(self)?,
+            DrcRuleFormat::Json => serde_json::to_string_pretty(self)?,
+        })
+    }
+
+    /// Loads a rule set from a `.toml` or `.json` file, see [`DrcRuleSet::parse`].
+    ///
+    /// If the file doesn't name the rule set, the file name without the extension is used.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DrcRulesError> {
+        let path = path.as_ref();
+        let format = DrcRuleFormat::from_path(path).ok_or_else(|| DrcRulesError::UnknownFormat(path.to_path_buf()))?;
+        let content = std::fs::read_to_string(path).map_err(|cause| DrcRulesError::Io {
+            path: path.to_path_buf(),
+            cause,
+        })?;
+
+        let mut rule_set = Self::parse(&content, format)?;
+        if rule_set.name.is_empty() {
+            if let Some(stem) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+            {
+                rule_set.name = stem.to_string();
+            }
+        }
+
+        Ok(rule_set)
+    }
+}
+
+/// Loads all the `.toml` and `.json` rule files in a directory (non-recursive), sorted by file name, e.g. the rules of
+/// the manufacturers that users can choose from.  Other files are ignored.
+pub fn load_rule_sets(path: impl AsRef<Path>) -> Result<Vec<DrcRuleSet>, DrcRulesError> {
+    let path = path.as_ref();
+    let mut file_paths = std::fs::read_dir(path)
+        .map_err(|cause| DrcRulesError::Io {
+            path: path.to_path_buf(),
+            cause,
+        })?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && DrcRuleFormat::from_path(path).is_some())
+        .collect::<Vec<_>>();
+    // read_dir order is platform dependent
+    file_paths.sort();
+
+    file_paths
+        .iter()
+        .map(DrcRuleSet::load)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(DrcRuleFormat::Toml, "name = \"Example\"\nmin_spacing = 0.15\nmin_drill = 0.3\n")]
+    #[case(
+        DrcRuleFormat::Json,
+        r#"{ "name": "Example", "min_spacing": 0.15, "min_drill": 0.3 }"#
+    )]
+    fn test_parse(#[case] format: DrcRuleFormat, #[case] content: &str) {
+        // when
+        let rule_set = DrcRuleSet::parse(content, format).unwrap();
+
+        // then
+        assert_eq!(rule_set, DrcRuleSet {
+            name: "Example".to_string(),
+            min_spacing: Some(0.15),
+            min_drill: Some(0.3),
+            ..DrcRuleSet::default()
+        });
+    }
+
+    #[rstest]
+    #[case(DrcRuleFormat::Toml)]
+    #[case(DrcRuleFormat::Json)]
+    fn test_round_trip(#[case] format: DrcRuleFormat) {
+        // given
+        let rule_set = DrcRuleSet {
+            name: "Example".to_string(),
+            min_width: Some(0.127),
+            min_annular_ring: Some(0.13),
+            ..DrcRuleSet::default()
+        };
+
+        // when
+        let content = rule_set.format(format).unwrap();
+
+        // then
+        assert_eq!(DrcRuleSet::parse(&content, format).unwrap(), rule_set);
+    }
+
+    #[rstest]
+    #[case("rules/fab.toml", Some(DrcRuleFormat::Toml))]
+    #[case("FAB.JSON", Some(DrcRuleFormat::Json))]
+    #[case("fab.yaml", None)]
+    #[case("fab", None)]
+    fn test_format_from_path(#[case] path: &str, #[case] expected: Option<DrcRuleFormat>) {
+        // expect
+        assert_eq!(DrcRuleFormat::from_path(path), expected);
+    }
+}
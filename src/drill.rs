@@ -0,0 +1,185 @@
+use gerber_types::{Aperture, ApertureDefinition};
+use nalgebra::Point2;
+
+use crate::attributes::Attribute;
+use crate::layer::GerberPrimitive;
+use crate::GerberLayer;
+
+/// An entry in the tool table of a drill layer.
+///
+/// Drill layers are gerber files with a `.FileFunction` of `Plated` or `NonPlated`, each tool is a circular aperture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrillTool {
+    /// The D-code of the aperture
+    pub number: i32,
+    /// In file units
+    pub diameter: f64,
+    /// From the `.FileFunction` file attribute, `None` if unknown.
+    pub plated: Option<bool>,
+    /// The number of flashes (D03).
+    pub hits: usize,
+    /// The number of draws (D01), i.e. routed slots.
+    pub slots: usize,
+    /// The centers of the flashed holes.
+    pub positions: Vec<Point2<f64>>,
+}
+
+/// The symbols used for drill maps and legends, assigned to tools in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrillSymbol {
+    Cross,
+    Square,
+    Triangle,
+    Diamond,
+    Circle,
+    Plus,
+    Hexagon,
+    Star,
+}
+
+impl DrillSymbol {
+    pub const ALL: [DrillSymbol; 8] = [
+        DrillSymbol::Cross,
+        DrillSymbol::Square,
+        DrillSymbol::Triangle,
+        DrillSymbol::Diamond,
+        DrillSymbol::Circle,
+        DrillSymbol::Plus,
+        DrillSymbol::Hexagon,
+        DrillSymbol::Star,
+    ];
+
+    /// Symbols are re-used when there are more tools than symbols.
+    pub fn for_index(index: usize) -> Self {
+        Self::ALL[index % Self::ALL.len()]
+    }
+}
+
+impl GerberLayer {
+    /// Returns the plating from the `.FileFunction` file attribute, e.g. `%TF.FileFunction,Plated,1,2,PTH*%`.
+    ///
+    /// Returns `None` for non-drill layers.
+    pub fn drill_plating(&self) -> Option<bool> {
+        match self
+            .file_attributes()
+            .get(".FileFunction")
+            .and_then(Attribute::value)
+        {
+            Some("Plated") => Some(true),
+            Some("NonPlated") => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns the tool table, one entry for each used circular aperture, ordered by tool number.
+    ///
+    /// Any layer can be treated as a drill layer, however `plated` will only be set for layers with a drill
+    /// `.FileFunction`.
+    pub fn drill_tools(&self) -> Vec<DrillTool> {
+        let plated = self.drill_plating();
+
+        let mut tools: Vec<DrillTool> = self
+            .aperture_definitions()
+            .filter_map(|definition| match definition {
+                ApertureDefinition {
+                    code,
+                    aperture: Aperture::Circle(circle),
+                } => {
+                    let usage = self.aperture_usage(*code);
+                    Some(DrillTool {
+                        number: *code,
+                        diameter: circle.diameter,
+                        plated,
+                        hits: usage.flashes,
+                        slots: usage.draws,
+                        positions: vec![],
+                    })
+                }
+                _ => None,
+            })
+            .filter(|tool| tool.hits > 0 || tool.slots > 0)
+            .collect();
+
+        tools.sort_by_key(|tool| tool.number);
+
+        for (index, primitive) in self
+            .gerber_primitives()
+            .iter()
+            .enumerate()
+        {
+            let GerberPrimitive::Circle(circle) = primitive else {
+                continue;
+            };
+            let Some(code) = self
+                .primitive_info(index)
+                .and_then(|info| info.aperture)
+            else {
+                continue;
+            };
+            if let Ok(tool_index) = tools.binary_search_by_key(&code, |tool| tool.number) {
+                for offset in self.primitive_offsets(index) {
+                    tools[tool_index]
+                        .positions
+                        .push(circle.center + offset);
+                }
+            }
+        }
+
+        tools
+    }
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod tests {
+    use std::io::BufReader;
+
+    use gerber_parser::parse;
+    use nalgebra::Point2;
+
+    use super::*;
+
+    #[test]
+    fn test_drill_tools() {
+        // given
+        let source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%TF.FileFunction,Plated,1,2,PTH*%
+%ADD10C,0.800*%
+%ADD11C,1.000*%
+%ADD12C,3.000*%
+D11*
+X1000000Y1000000D03*
+X2000000Y1000000D03*
+D10*
+X3000000Y1000000D03*
+X3000000Y2000000D01*
+M02*
+"#;
+        let reader = BufReader::new(source.as_bytes());
+        let layer = GerberLayer::new(parse(reader).unwrap().into_commands());
+
+        // when
+        let tools = layer.drill_tools();
+
+        // then
+        assert_eq!(tools, vec![
+            DrillTool {
+                number: 10,
+                diameter: 0.8,
+                plated: Some(true),
+                hits: 1,
+                slots: 1,
+                positions: vec![Point2::new(3.0, 1.0)],
+            },
+            DrillTool {
+                number: 11,
+                diameter: 1.0,
+                plated: Some(true),
+                hits: 2,
+                slots: 0,
+                positions: vec![Point2::new(1.0, 1.0), Point2::new(2.0, 1.0)],
+            },
+        ]);
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt::Display;
+
+use gerber_types::Unit;
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::split_contours;
+use crate::layer::{
+    ArcGerberPrimitive, CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive,
+    PolygonGerberPrimitive, RectangleGerberPrimitive,
+};
+use crate::{unit_conversion_factor, Exposure};
+
+/// Options for [`GerberLayer::export_dxf`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DxfOptions {
+    /// The units of the DXF coordinates, `None` uses the units of the layer.
+    pub unit: Option<Unit>,
+    /// The name of the DXF layer the entities are placed on.
+    pub layer_name: String,
+}
+
+impl Default for DxfOptions {
+    fn default() -> Self {
+        Self {
+            unit: Some(Unit::Millimeters),
+            layer_name: "0".to_string(),
+        }
+    }
+}
+
+impl GerberLayer {
+    /// Exports the geometry of the layer as DXF entities, e.g. to import a board outline or a mechanical layer into an
+    /// MCAD tool.
+    ///
+    /// Lines and arcs are exported as `LINE` and `ARC` entities along their center-lines, the width is not exported.
+    /// Circles are exported as `CIRCLE` entities, rectangles, regions and macro primitives as closed `POLYLINE`
+    /// entities, one per contour.  Step-and-repeat blocks are expanded.  Clear primitives are not exported.
+    ///
+    /// The output is an ASCII DXF (R12) with only the header and entities sections, which most CAD tools accept.
+    pub fn export_dxf(&self, options: &DxfOptions) -> String {
+        let unit = options.unit.or(self.units());
+        let scale = match (self.units(), unit) {
+            (Some(from), Some(to)) => unit_conversion_factor(from, to),
+            _ => 1.0,
+        };
+
+        let mut writer = DxfWriter {
+            output: String::new(),
+            layer_name: &options.layer_name,
+            scale,
+        };
+
+        // AutoCAD $INSUNITS, 0 = unitless, 1 = inches, 4 = millimeters
+        let insunits = match unit {
+            Some(Unit::Inches) => 1,
+            Some(Unit::Millimeters) => 4,
+            None => 0,
+        };
+        writer.pair(0, "SECTION");
+        writer.pair(2, "HEADER");
+        writer.pair(9, "$ACADVER");
+        writer.pair(1, "AC1009");
+        writer.pair(9, "$INSUNITS");
+        writer.pair(70, insunits);
+        writer.pair(0, "ENDSEC");
+
+        writer.pair(0, "SECTION");
+        writer.pair(2, "ENTITIES");
+        for (index, primitive) in self
+            .gerber_primitives()
+            .iter()
+            .enumerate()
+        {
+            if primitive.exposure() == Exposure::CutOut {
+                continue;
+            }
+            for offset in self.primitive_offsets(index) {
+                writer.primitive(primitive, offset);
+            }
+        }
+        writer.pair(0, "ENDSEC");
+        writer.pair(0, "EOF");
+
+        writer.output
+    }
+}
+
+struct DxfWriter<'a> {
+    output: String,
+    layer_name: &'a str,
+    /// Layer units to DXF units.
+    scale: f64,
+}
+
+impl DxfWriter<'_> {
+    /// Writes a group code and its value, each on its own line.
+    fn pair(&mut self, code: u16, value: impl Display) {
+        self.output
+            .push_str(&format!("{}\n{}\n", code, value));
+    }
+
+    /// Starts an entity, on the layer.
+    fn entity(&mut self, name: &str) {
+        self.pair(0, name);
+        self.pair(8, self.layer_name.to_string());
+    }
+
+    /// Writes a point, `code` is the group code of x, y and z use the following group codes.
+    fn point(&mut self, code: u16, point: Point2<f64>) {
+        self.pair(code, point.x * self.scale);
+        self.pair(code + 10, point.y * self.scale);
+        self.pair(code + 20, 0.0);
+    }
+
+    fn primitive(&mut self, primitive: &GerberPrimitive, offset: Vector2<f64>) {
+        match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => self.circle(*center + offset, diameter / 2.0),
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let origin = *origin + offset;
+                let corners = [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)]
+                    .map(|(x, y)| origin + Vector2::new(x, y));
+                self.polyline(&corners);
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                ..
+            }) => {
+                self.entity("LINE");
+                self.point(10, *start + offset);
+                self.point(11, *end + offset);
+            }
+            GerberPrimitive::Arc(arc) => {
+                if arc.is_full_circle() {
+                    self.circle(arc.center + offset, arc.radius);
+                } else {
+                    let (start_angle, end_angle) = arc_angles(arc);
+                    self.entity("ARC");
+                    self.point(10, arc.center + offset);
+                    self.pair(40, arc.radius * self.scale);
+                    self.pair(50, start_angle);
+                    self.pair(51, end_angle);
+                }
+            }
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let center = *center + offset;
+                for contour in split_contours(&geometry.relative_vertices) {
+                    let contour = contour
+                        .into_iter()
+                        .map(|vertex| center + vertex.coords)
+                        .collect::<Vec<_>>();
+                    self.polyline(&contour);
+                }
+            }
+        }
+    }
+
+    fn circle(&mut self, center: Point2<f64>, radius: f64) {
+        self.entity("CIRCLE");
+        self.point(10, center);
+        self.pair(40, radius * self.scale);
+    }
+
+    /// A closed polyline.
+    fn polyline(&mut self, vertices: &[Point2<f64>]) {
+        self.entity("POLYLINE");
+        // vertices follow
+        self.pair(66, 1);
+        self.point(10, Point2::origin());
+        // closed
+        self.pair(70, 1);
+        for vertex in vertices {
+            self.entity("VERTEX");
+            self.point(10, *vertex);
+        }
+        self.entity("SEQEND");
+    }
+}
+
+/// The start and end angles, in degrees from 0 to 360, of a DXF arc, which always goes counter-clockwise.
+fn arc_angles(arc: &ArcGerberPrimitive) -> (f64, f64) {
+    let (start, end) = match arc.sweep_angle >= 0.0 {
+        true => (arc.start_angle, arc.start_angle + arc.sweep_angle),
+        false => (arc.start_angle + arc.sweep_angle, arc.start_angle),
+    };
+    let degrees = |angle: f64| angle.to_degrees().rem_euclid(360.0);
+    (degrees(start), degrees(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    use gerber_types::{Aperture, Circle, Command, FunctionCode, GCode, InterpolationMode};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::testing::{aperture, header, interpolate, move_to};
+
+    #[test]
+    fn test_export_dxf() {
+        // given
+        let mut commands = header(Unit::Inches);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(0.01))));
+        commands.extend([
+            Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(InterpolationMode::Linear))),
+            move_to(0.0, 0.0),
+            interpolate(1.0, 0.0),
+        ]);
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let dxf = layer.export_dxf(&DxfOptions::default());
+
+        // then
+        // converted to millimeters
+        assert!(dxf.contains("$INSUNITS\n70\n4\n"));
+        assert!(dxf.contains("0\nLINE\n8\n0\n10\n0\n20\n0\n30\n0\n11\n25.4\n21\n0\n31\n0\n"));
+        assert!(dxf.ends_with("0\nENDSEC\n0\nEOF\n"));
+    }
+
+    #[rstest]
+    // counter-clockwise, from 0 to 90 degrees
+    #[case(0.0, FRAC_PI_2, (0.0, 90.0))]
+    // clockwise, from 90 to 0 degrees, is the same arc
+    #[case(FRAC_PI_2, -FRAC_PI_2, (0.0, 90.0))]
+    // crossing 0 degrees
+    #[case(-FRAC_PI_2, PI, (270.0, 90.0))]
+    fn test_arc_angles(#[case] start_angle: f64, #[case] sweep_angle: f64, #[case] expected: (f64, f64)) {
+        // given
+        let arc = ArcGerberPrimitive {
+            center: Point2::new(0.0, 0.0),
+            radius: 1.0,
+            width: 0.1,
+            start_angle,
+            sweep_angle,
+            exposure: Exposure::Add,
+            tolerance: None,
+        };
+
+        // when
+        let (start, end) = arc_angles(&arc);
+
+        // then
+        assert!((start - expected.0).abs() < 1e-9);
+        assert!((end - expected.1).abs() < 1e-9);
+    }
+}
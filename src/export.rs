@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use gerber_types::{
+    Aperture, ApertureDefinition, Circle, Command, CoordinateFormat, CoordinateNumber, CoordinateOffset, Coordinates,
+    DCode, ExtendedCode, FunctionCode, GCode, InterpolationMode, MCode, Operation, Polarity, QuadrantMode,
+};
+use log::warn;
+use nalgebra::{Matrix3, Point2, Vector2};
+
+use crate::layer::{
+    ArcGerberPrimitive, CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive,
+    PolygonGerberPrimitive, RectangleGerberPrimitive,
+};
+use crate::{Exposure, GerberTransform};
+
+/// The coordinate format used for exported commands, the maximum precision allowed by the spec.
+fn export_format() -> CoordinateFormat {
+    CoordinateFormat::new(4, 6)
+}
+
+/// The first aperture code used for exported apertures, codes below 10 are reserved.
+const FIRST_APERTURE_CODE: i32 = 10;
+
+impl GerberLayer {
+    /// Exports the layer as a new command stream, with the transform baked into the coordinates and apertures, e.g. to
+    /// relocate a design to 0,0 or to panelize it.
+    ///
+    /// The commands are generated from the primitives of the layer, so the output is flattened; flashes and draws use
+    /// circular apertures, and everything else, e.g. rectangles, macro apertures and regions, is exported as regions.
+    /// Step-and-repeat blocks are expanded.  The file attributes are preserved, aperture and object attributes are not.
+    ///
+    /// Use [`gerber_types::GerberCode::serialize`] to generate the gerber source.
+    pub fn export_commands(&self, transform: &GerberTransform) -> Vec<Command> {
+        let mut exporter = Exporter::new(&transform.resolve(self.bounding_box()));
+
+        let primitives = self.gerber_primitives();
+        for (index, offset) in self.primitives_in_draw_order() {
+            exporter.export_primitive(&primitives[index], offset);
+        }
+
+        let mut commands = vec![Command::ExtendedCode(ExtendedCode::CoordinateFormat(export_format()))];
+        if let Some(unit) = self.units() {
+            commands.push(Command::ExtendedCode(ExtendedCode::Unit(unit)));
+        }
+        commands.extend(
+            self.commands()
+                .iter()
+                .filter(|command| matches!(command, Command::ExtendedCode(ExtendedCode::FileAttribute(_))))
+                .cloned(),
+        );
+        commands.extend(exporter.aperture_definitions());
+        commands.push(Command::FunctionCode(FunctionCode::GCode(GCode::QuadrantMode(
+            QuadrantMode::Multi,
+        ))));
+        commands.extend(exporter.commands);
+        commands.push(Command::FunctionCode(FunctionCode::MCode(MCode::EndOfFile)));
+
+        commands
+    }
+}
+
+struct Exporter {
+    matrix: Matrix3<f64>,
+    scale: f64,
+    /// `true` if the transform mirrors the image, which reverses the direction of arcs
+    mirrored: bool,
+    /// diameter (as bits) -> aperture code, in order of use
+    apertures: HashMap<u64, i32>,
+    commands: Vec<Command>,
+    polarity: Option<Polarity>,
+    interpolation_mode: Option<InterpolationMode>,
+    aperture: Option<i32>,
+}
+
+impl Exporter {
+    fn new(transform: &GerberTransform) -> Self {
+        let matrix = transform.to_matrix();
+        let determinant = matrix[(0, 0)] * matrix[(1, 1)] - matrix[(0, 1)] * matrix[(1, 0)];
+
+        Self {
+            matrix,
+            scale: transform.scale,
+            mirrored: determinant < 0.0,
+            apertures: HashMap::default(),
+            commands: vec![],
+            polarity: None,
+            interpolation_mode: None,
+            aperture: None,
+        }
+    }
+
+    fn export_primitive(&mut self, primitive: &GerberPrimitive, offset: Vector2<f64>) {
+        self.set_polarity(primitive.exposure());
+
+        match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => {
+                self.select_aperture(*diameter);
+                let center = self.transform(*center + offset);
+                self.push_operation(Operation::Flash(coordinates(center)));
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => {
+                self.select_aperture(*width);
+                self.set_interpolation_mode(InterpolationMode::Linear);
+                let (start, end) = (self.transform(*start + offset), self.transform(*end + offset));
+                self.push_operation(Operation::Move(coordinates(start)));
+                self.push_operation(Operation::Interpolate(coordinates(end), None));
+            }
+            GerberPrimitive::Arc(arc) => {
+                self.select_aperture(arc.width);
+                self.export_arc(arc, offset);
+            }
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let vertices = [
+                    Vector2::new(0.0, 0.0),
+                    Vector2::new(*width, 0.0),
+                    Vector2::new(*width, *height),
+                    Vector2::new(0.0, *height),
+                ]
+                .map(|corner| *origin + corner + offset);
+                self.export_region(&vertices);
+            }
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let vertices = geometry
+                    .relative_vertices
+                    .iter()
+                    .map(|vertex| *center + vertex.coords + offset)
+                    .collect::<Vec<_>>();
+                self.export_region(&vertices);
+            }
+        }
+    }
+
+    fn export_arc(&mut self, arc: &ArcGerberPrimitive, offset: Vector2<f64>) {
+        let center = arc.center + offset;
+        let point_at = |angle: f64| center + Vector2::new(angle.cos(), angle.sin()) * arc.radius;
+
+        let start = point_at(arc.start_angle);
+        let (end, clockwise) = match arc.is_full_circle() {
+            // the start and end coincide, the direction of a full circle is irrelevant
+            true => (start, false),
+            false => (point_at(arc.start_angle + arc.sweep_angle), arc.sweep_angle < 0.0),
+        };
+
+        let (center, start, end) = (self.transform(center), self.transform(start), self.transform(end));
+        let mode = match clockwise ^ self.mirrored {
+            true => InterpolationMode::ClockwiseCircular,
+            false => InterpolationMode::CounterclockwiseCircular,
+        };
+        self.set_interpolation_mode(mode);
+
+        self.push_operation(Operation::Move(coordinates(start)));
+        let center_offset = center - start;
+        self.push_operation(Operation::Interpolate(
+            coordinates(end),
+            Some(CoordinateOffset {
+                x: coordinate_number(center_offset.x),
+                y: coordinate_number(center_offset.y),
+                format: export_format(),
+            }),
+        ));
+    }
+
+    fn export_region(&mut self, vertices: &[Point2<f64>]) {
+        let Some(first) = vertices.first() else {
+            return;
+        };
+
+        self.set_interpolation_mode(InterpolationMode::Linear);
+        self.commands
+            .push(Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(true))));
+        self.push_operation(Operation::Move(coordinates(self.transform(*first))));
+        for vertex in vertices[1..].iter().chain(Some(first)) {
+            self.push_operation(Operation::Interpolate(coordinates(self.transform(*vertex)), None));
+        }
+        self.commands
+            .push(Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(false))));
+    }
+
+    fn transform(&self, point: Point2<f64>) -> Point2<f64> {
+        self.matrix.transform_point(&point)
+    }
+
+    fn select_aperture(&mut self, diameter: f64) {
+        let next_code = FIRST_APERTURE_CODE + self.apertures.len() as i32;
+        let code = *self
+            .apertures
+            .entry((diameter * self.scale).to_bits())
+            .or_insert(next_code);
+
+        if self.aperture != Some(code) {
+            self.aperture = Some(code);
+            self.commands
+                .push(Command::FunctionCode(FunctionCode::DCode(DCode::SelectAperture(code))));
+        }
+    }
+
+    fn set_polarity(&mut self, exposure: Exposure) {
+        let polarity = match exposure {
+            Exposure::Add => Polarity::Dark,
+            Exposure::CutOut => Polarity::Clear,
+        };
+        if self.polarity != Some(polarity) {
+            self.polarity = Some(polarity);
+            self.commands
+                .push(Command::ExtendedCode(ExtendedCode::LoadPolarity(polarity)));
+        }
+    }
+
+    fn set_interpolation_mode(&mut self, mode: InterpolationMode) {
+        if self.interpolation_mode != Some(mode) {
+            self.interpolation_mode = Some(mode);
+            self.commands
+                .push(Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(
+                    mode,
+                ))));
+        }
+    }
+
+    fn push_operation(&mut self, operation: Operation) {
+        self.commands
+            .push(Command::FunctionCode(FunctionCode::DCode(DCode::Operation(operation))));
+    }
+
+    fn aperture_definitions(&self) -> Vec<Command> {
+        let mut apertures = self
+            .apertures
+            .iter()
+            .map(|(diameter, code)| (*code, f64::from_bits(*diameter)))
+            .collect::<Vec<_>>();
+        apertures.sort_by_key(|(code, _)| *code);
+
+        apertures
+            .into_iter()
+            .map(|(code, diameter)| {
+                Command::ExtendedCode(ExtendedCode::ApertureDefinition(ApertureDefinition::new(
+                    code,
+                    Aperture::Circle(Circle::new(diameter)),
+                )))
+            })
+            .collect()
+    }
+}
+
+fn coordinates(point: Point2<f64>) -> Coordinates {
+    Coordinates {
+        x: coordinate_number(point.x),
+        y: coordinate_number(point.y),
+        format: export_format(),
+    }
+}
+
+fn coordinate_number(value: f64) -> Option<CoordinateNumber> {
+    CoordinateNumber::try_from(value)
+        .inspect_err(|error| warn!("Invalid coordinate. value: {}, error: {:?}", value, error))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{StepAndRepeat, Unit};
+
+    use super::*;
+    use crate::testing::{aperture, flash, flash_layer, header};
+    use crate::Mirroring;
+
+    #[test]
+    fn test_export_commands_bakes_transform_into_coordinates_and_apertures() {
+        // given
+        let layer = flash_layer(Unit::Millimeters, Aperture::Circle(Circle::new(0.5)), 2.0, 1.0);
+        let transform = GerberTransform {
+            mirroring: Mirroring {
+                x: true,
+                y: false,
+            },
+            offset: Vector2::new(10.0, 0.0),
+            scale: 2.0,
+            ..GerberTransform::default()
+        };
+
+        // when
+        let exported = GerberLayer::new(layer.export_commands(&transform));
+
+        // then
+        let circles = exported
+            .gerber_primitives()
+            .iter()
+            .map(|primitive| match primitive {
+                GerberPrimitive::Circle(CircleGerberPrimitive {
+                    center,
+                    diameter,
+                    ..
+                }) => (*center, *diameter),
+                _ => panic!("expected a circle"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(circles, vec![(Point2::new(6.0, 2.0), 1.0)]);
+    }
+
+    #[test]
+    fn test_export_commands_expands_step_and_repeat_one_copy_at_a_time() {
+        // given
+        // a dark and a clear flash, repeated twice
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(1.0))));
+        commands.extend([
+            Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Open {
+                repeat_x: 2,
+                repeat_y: 1,
+                distance_x: 10.0,
+                distance_y: 0.0,
+            })),
+            flash(0.0, 0.0),
+            Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)),
+            flash(0.0, 0.0),
+            Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Close)),
+        ]);
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let exported = GerberLayer::new(layer.export_commands(&GerberTransform::default()));
+
+        // then
+        // the clear flash of the first copy must not clear the second copy
+        let exposures = exported
+            .gerber_primitives()
+            .iter()
+            .map(GerberPrimitive::exposure)
+            .collect::<Vec<_>>();
+        assert_eq!(exposures, vec![
+            Exposure::Add,
+            Exposure::CutOut,
+            Exposure::Add,
+            Exposure::CutOut
+        ]);
+    }
+}
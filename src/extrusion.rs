@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use gerber_types::Unit;
+use nalgebra::{Point2, Point3, Vector2, Vector3};
+use thiserror::Error;
+
+use crate::geometry::{join_contours, tessellate_polygon, GerberTransform};
+use crate::layer::GerberLayer;
+use crate::layer_mesh::Triangles;
+use crate::{unit_conversion_factor, Exposure, LayerSet, LayerType};
+
+/// Options for [`LayerSet::extrude`], the thicknesses are in millimeters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtrusionOptions {
+    pub board_thickness: f64,
+    /// Extrudes the top and bottom copper layers on the faces of the board, e.g. to check the clearance of connectors
+    /// and large pads, otherwise only the board is extruded.
+    pub copper: bool,
+    pub copper_thickness: f64,
+    /// The maximum gap between the lines and arcs of the outline layer, see [`GerberLayer::board_outline`].
+    pub outline_tolerance: f64,
+}
+
+impl Default for ExtrusionOptions {
+    fn default() -> Self {
+        Self {
+            board_thickness: 1.6,
+            copper: false,
+            // 1oz copper
+            copper_thickness: 0.035,
+            outline_tolerance: 0.01,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ExtrusionError {
+    #[error("No board outline, the outline layer is missing or has no closed contours.")]
+    NoOutline,
+}
+
+/// A triangle mesh of a board, in millimeters, with z up, the bottom of the board is at z = 0, see
+/// [`LayerSet::extrude`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BoardModel {
+    pub vertices: Vec<Point3<f64>>,
+    /// Counter-clockwise when viewed from outside the model.
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl LayerSet {
+    /// Extrudes the board outline, and optionally the outer copper layers, into a 3D model, e.g. for quick mockups and
+    /// enclosure fit checks, export it using [`BoardModel::to_stl`] or [`BoardModel::to_obj`].
+    ///
+    /// The first contour of the outline is the board edge, the other contours are cut-outs, see
+    /// [`GerberLayer::board_outline`].  Copper is placed on top of the board and below it, each dark primitive is
+    /// extruded separately, so overlapping primitives overlap in the model, clear primitives are ignored.  The
+    /// transform of each layer is applied, the opacity is not.  Layers without units are assumed to be in millimeters.
+    pub fn extrude(&self, options: &ExtrusionOptions) -> Result<BoardModel, ExtrusionError> {
+        let outline_info = self
+            .get(LayerType::Outline)
+            .ok_or(ExtrusionError::NoOutline)?;
+        let outline_layer = &outline_info.layer;
+        let outline = outline_layer.board_outline(options.outline_tolerance);
+        let (board, cut_outs) = outline
+            .contours
+            .split_first()
+            .ok_or(ExtrusionError::NoOutline)?;
+
+        let mut model = BoardModel::default();
+
+        let scale = millimeters_per_unit(outline_layer);
+        let transform = outline_info.resolved_transform();
+        let mirrored = is_mirrored(&transform);
+        let scaled = |contour: &Vec<Point2<f64>>| {
+            let mut contour = contour
+                .iter()
+                .map(|point| {
+                    Point2::from(
+                        transform
+                            .apply_to_position(*point)
+                            .coords
+                            * scale,
+                    )
+                })
+                .collect::<Vec<_>>();
+            // mirroring makes the contours clockwise
+            if mirrored {
+                contour.reverse();
+            }
+            contour
+        };
+        let board = scaled(board);
+        let cut_outs = cut_outs
+            .iter()
+            .map(scaled)
+            .collect::<Vec<_>>();
+
+        // the contours are counter-clockwise, the walls of the cut-outs face into the cut-outs
+        let tessellation = tessellate_polygon(&join_contours(board.clone(), cut_outs.clone()));
+        let vertices = tessellation
+            .vertices
+            .iter()
+            .map(|[x, y]| Point2::new(*x as f64, *y as f64))
+            .collect::<Vec<_>>();
+        let triangles = tessellation
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect::<Vec<_>>();
+        let mut boundary = vec![board];
+        boundary.extend(cut_outs.into_iter().map(|mut contour| {
+            contour.reverse();
+            contour
+        }));
+        model.add_prism(
+            &vertices,
+            &triangles,
+            &contour_edges(&boundary),
+            0.0,
+            options.board_thickness,
+        );
+
+        if options.copper {
+            let top = options.board_thickness;
+            for (layer_type, bottom) in [
+                (LayerType::TopCopper, top),
+                (LayerType::BottomCopper, -options.copper_thickness),
+            ] {
+                for info in self
+                    .iter()
+                    .filter(|info| info.layer_type == layer_type)
+                {
+                    model.add_layer(
+                        &info.layer,
+                        &info.resolved_transform(),
+                        bottom,
+                        bottom + options.copper_thickness,
+                    );
+                }
+            }
+        }
+
+        Ok(model)
+    }
+}
+
+impl BoardModel {
+    /// An ASCII STL file, STL has no units, most tools assume millimeters.
+    pub fn to_stl(&self, name: &str) -> String {
+        let mut output = String::new();
+        writeln!(output, "solid {name}").unwrap();
+        for triangle in &self.triangles {
+            let [a, b, c] = triangle.map(|index| self.vertices[index as usize]);
+            let normal = (b - a)
+                .cross(&(c - a))
+                .try_normalize(0.0)
+                .unwrap_or_else(Vector3::zeros);
+            writeln!(output, "facet normal {} {} {}", normal.x, normal.y, normal.z).unwrap();
+            writeln!(output, "outer loop").unwrap();
+            for vertex in [a, b, c] {
+                writeln!(output, "vertex {} {} {}", vertex.x, vertex.y, vertex.z).unwrap();
+            }
+            writeln!(output, "endloop").unwrap();
+            writeln!(output, "endfacet").unwrap();
+        }
+        writeln!(output, "endsolid {name}").unwrap();
+        output
+    }
+
+    /// A Wavefront OBJ file, with only vertices and faces.
+    pub fn to_obj(&self) -> String {
+        let mut output = String::new();
+        for vertex in &self.vertices {
+            writeln!(output, "v {} {} {}", vertex.x, vertex.y, vertex.z).unwrap();
+        }
+        for [a, b, c] in &self.triangles {
+            // OBJ indices are 1-based
+            writeln!(output, "f {} {} {}", a + 1, b + 1, c + 1).unwrap();
+        }
+        output
+    }
+
+    /// Extrudes each dark primitive of the layer, step-and-repeat blocks are expanded, the `transform` must be resolved.
+    fn add_layer(&mut self, layer: &GerberLayer, transform: &GerberTransform, bottom: f64, top: f64) {
+        let scale = millimeters_per_unit(layer);
+        for (index, primitive) in layer
+            .gerber_primitives()
+            .iter()
+            .enumerate()
+        {
+            if primitive.exposure() == Exposure::CutOut {
+                continue;
+            }
+            for offset in layer.primitive_offsets(index) {
+                let mut primitive_triangles = Triangles::default();
+                primitive_triangles.add_primitive(primitive, offset, Exposure::Add);
+
+                let vertices = primitive_triangles
+                    .positions
+                    .iter()
+                    .map(|position| {
+                        Point2::from(
+                            transform
+                                .apply_to_position(*position)
+                                .coords
+                                * scale,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let triangles = primitive_triangles
+                    .indices
+                    .chunks_exact(3)
+                    .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                    .collect::<Vec<_>>();
+                let boundary = boundary_edges(&vertices, &triangles);
+                self.add_prism(&vertices, &triangles, &boundary, bottom, top);
+            }
+        }
+    }
+
+    /// Adds the triangles as the top and bottom faces, and a wall along each edge of the boundary, the edges must have
+    /// the outside of the prism on their right, i.e. counter-clockwise for outer contours.
+    fn add_prism(
+        &mut self,
+        vertices: &[Point2<f64>],
+        triangles: &[[u32; 3]],
+        boundary: &[(Point2<f64>, Point2<f64>)],
+        bottom: f64,
+        top: f64,
+    ) {
+        let base = self.vertices.len() as u32;
+        let count = vertices.len() as u32;
+        for z in [bottom, top] {
+            self.vertices.extend(
+                vertices
+                    .iter()
+                    .map(|vertex| Point3::new(vertex.x, vertex.y, z)),
+            );
+        }
+        for &[a, b, c] in triangles {
+            let (pa, pb, pc) = (vertices[a as usize], vertices[b as usize], vertices[c as usize]);
+            let counter_clockwise = (pb - pa).perp(&(pc - pa)) > 0.0;
+            let [a, b, c] = match counter_clockwise {
+                true => [a, b, c],
+                false => [a, c, b],
+            };
+            // the bottom faces down
+            self.triangles
+                .push([base + a, base + c, base + b]);
+            self.triangles
+                .push([base + count + a, base + count + b, base + count + c]);
+        }
+
+        for (start, end) in boundary {
+            let wall = self.vertices.len() as u32;
+            self.vertices.extend([
+                Point3::new(start.x, start.y, bottom),
+                Point3::new(end.x, end.y, bottom),
+                Point3::new(end.x, end.y, top),
+                Point3::new(start.x, start.y, top),
+            ]);
+            self.triangles
+                .extend([[wall, wall + 1, wall + 2], [wall, wall + 2, wall + 3]]);
+        }
+    }
+}
+
+/// Mirroring along one axis reverses the winding of the contours.
+fn is_mirrored(transform: &GerberTransform) -> bool {
+    transform.to_matrix().determinant() < 0.0
+}
+
+fn millimeters_per_unit(layer: &GerberLayer) -> f64 {
+    layer
+        .units()
+        .map_or(1.0, |unit| unit_conversion_factor(unit, Unit::Millimeters))
+}
+
+/// The edges of the contours, each contour is closed.
+fn contour_edges(contours: &[Vec<Point2<f64>>]) -> Vec<(Point2<f64>, Point2<f64>)> {
+    contours
+        .iter()
+        .flat_map(|contour| {
+            contour
+                .iter()
+                .zip(contour.iter().cycle().skip(1))
+                .map(|(start, end)| (*start, *end))
+        })
+        .collect()
+}
+
+/// The edges that belong to a single triangle, with the triangle on their left.
+fn boundary_edges(vertices: &[Point2<f64>], triangles: &[[u32; 3]]) -> Vec<(Point2<f64>, Point2<f64>)> {
+    let mut edges = HashMap::<(u32, u32), (u32, u32)>::new();
+    for &[a, b, c] in triangles {
+        let (pa, pb, pc) = (vertices[a as usize], vertices[b as usize], vertices[c as usize]);
+        let [a, b, c] = match (pb - pa).perp(&(pc - pa)) > 0.0 {
+            true => [a, b, c],
+            false => [a, c, b],
+        };
+        for (start, end) in [(a, b), (b, c), (c, a)] {
+            let key = (start.min(end), start.max(end));
+            if edges.remove(&key).is_none() {
+                edges.insert(key, (start, end));
+            }
+        }
+    }
+
+    edges
+        .into_values()
+        .filter(|(start, end)| {
+            let direction: Vector2<f64> = vertices[*end as usize] - vertices[*start as usize];
+            direction.norm() > 0.0
+        })
+        .map(|(start, end)| (vertices[start as usize], vertices[end as usize]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Command, FunctionCode, GCode, InterpolationMode};
+
+    use super::*;
+    use crate::testing::{aperture, header, interpolate, move_to};
+
+    /// A 10x5mm board outline.
+    fn outline_layer() -> GerberLayer {
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(0.1))));
+        commands.extend([
+            Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(InterpolationMode::Linear))),
+            move_to(0.0, 0.0),
+        ]);
+        for (x, y) in [(10.0, 0.0), (10.0, 5.0), (0.0, 5.0), (0.0, 0.0)] {
+            commands.push(interpolate(x, y));
+        }
+        GerberLayer::new(commands)
+    }
+
+    #[test]
+    fn test_extrude() {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::Outline, "outline", outline_layer());
+
+        // when
+        let model = layer_set
+            .extrude(&ExtrusionOptions::default())
+            .unwrap();
+
+        // then
+        // two triangles on the top and the bottom, and two per wall
+        assert_eq!(model.triangles.len(), 2 + 2 + 4 * 2);
+        assert!(model
+            .vertices
+            .iter()
+            .all(|vertex| vertex.z == 0.0 || vertex.z == 1.6));
+
+        // and
+        let stl = model.to_stl("board");
+        assert!(stl.starts_with("solid board\n"));
+        assert_eq!(stl.matches("endfacet").count(), 12);
+        assert!(stl.ends_with("endsolid board\n"));
+
+        // and
+        let obj = model.to_obj();
+        assert_eq!(
+            obj.lines()
+                .filter(|line| line.starts_with("v "))
+                .count(),
+            model.vertices.len()
+        );
+        assert_eq!(
+            obj.lines()
+                .filter(|line| line.starts_with("f "))
+                .count(),
+            12
+        );
+    }
+
+    #[test]
+    fn test_extrude_with_layer_transform() {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::Outline, "outline", outline_layer());
+        let outline = layer_set
+            .get_mut(LayerType::Outline)
+            .unwrap();
+        outline.transform.offset = Vector2::new(20.0, 0.0);
+        outline.transform.mirroring.x = true;
+
+        // when
+        let model = layer_set
+            .extrude(&ExtrusionOptions::default())
+            .unwrap();
+
+        // then
+        assert!(model
+            .vertices
+            .iter()
+            .all(|vertex| vertex.x >= 9.9 && vertex.x <= 20.1));
+        // the walls face out, even though the outline is mirrored
+        let center = Vector3::new(15.0, 2.5, 0.0);
+        for triangle in &model.triangles {
+            let [a, b, c] = triangle.map(|index| model.vertices[index as usize]);
+            if a.z == b.z && b.z == c.z {
+                continue;
+            }
+            let normal = (b - a).cross(&(c - a));
+            let outwards = (a.coords - center).component_mul(&Vector3::new(1.0, 1.0, 0.0));
+            assert!(normal.dot(&outwards) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_extrude_without_outline() {
+        // expect
+        assert_eq!(
+            LayerSet::default().extrude(&ExtrusionOptions::default()),
+            Err(ExtrusionError::NoOutline)
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use geo::{unary_union, Coord, LineString, MultiPolygon, Polygon, Rect};
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::BoundingBox;
+use crate::layer::{GerberLayer, GerberPrimitive, LineGerberPrimitive};
+use crate::outline::BoardOutline;
+use crate::PrimitiveView;
+
+impl From<&BoundingBox> for Rect<f64> {
+    fn from(value: &BoundingBox) -> Self {
+        Rect::new(coord(value.min), coord(value.max))
+    }
+}
+
+impl From<Rect<f64>> for BoundingBox {
+    fn from(value: Rect<f64>) -> Self {
+        BoundingBox {
+            min: Point2::new(value.min().x, value.min().y),
+            max: Point2::new(value.max().x, value.max().y),
+        }
+    }
+}
+
+impl From<PrimitiveView<'_>> for MultiPolygon<f64> {
+    fn from(value: PrimitiveView<'_>) -> Self {
+        value.to_multi_polygon()
+    }
+}
+
+impl PrimitiveView<'_> {
+    /// The outline of the primitive as non-overlapping polygons, ignoring the exposure.
+    ///
+    /// Circles and arcs are approximated, see [`crate::CIRCLE_SEGMENTS`].
+    pub fn to_multi_polygon(&self) -> MultiPolygon<f64> {
+        self.primitive().to_multi_polygon()
+    }
+
+    /// The center-line of lines and arcs, the width is ignored, `None` for other primitives.
+    ///
+    /// Arcs are approximated by line segments, see [`crate::ArcView::points`].
+    pub fn to_line_string(&self) -> Option<LineString<f64>> {
+        self.primitive().to_line_string()
+    }
+}
+
+impl GerberPrimitive {
+    /// The outline of the primitive as non-overlapping polygons, ignoring the exposure.
+    ///
+    /// Circles and arcs are approximated, see [`crate::CIRCLE_SEGMENTS`].
+    pub(crate) fn to_multi_polygon(&self) -> MultiPolygon<f64> {
+        unary_union(&self.to_polygons(Vector2::new(0.0, 0.0)))
+    }
+
+    /// The center-line of lines and arcs, the width is ignored, `None` for other primitives.
+    ///
+    /// Arcs are approximated by line segments, see [`crate::ArcGerberPrimitive::generate_points`].
+    pub(crate) fn to_line_string(&self) -> Option<LineString<f64>> {
+        match self {
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                ..
+            }) => Some(LineString::new(vec![coord(*start), coord(*end)])),
+            GerberPrimitive::Arc(arc) => Some(
+                arc.generate_points()
+                    .into_iter()
+                    .map(|point| coord(arc.center + point.coords))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl GerberLayer {
+    /// The outline of the primitive at `index`, including each copy of step-and-repeat primitives, as non-overlapping
+    /// polygons, ignoring the exposure.
+    ///
+    /// Returns an empty `MultiPolygon` if the index is out of range.  See [`GerberLayer::merged_polygons`] for the
+    /// polygons of the whole layer.
+    pub fn primitive_polygons(&self, index: usize) -> MultiPolygon<f64> {
+        let Some(primitive) = self.gerber_primitives().get(index) else {
+            return MultiPolygon::new(vec![]);
+        };
+
+        let polygons = self
+            .primitive_offsets(index)
+            .into_iter()
+            .flat_map(|offset| primitive.to_polygons(offset))
+            .collect::<Vec<_>>();
+
+        unary_union(&polygons)
+    }
+}
+
+impl BoardOutline {
+    /// The board as a polygon, the first (largest) contour is the exterior and the other contours are holes.
+    ///
+    /// Returns `None` if there are no contours.
+    pub fn to_polygon(&self) -> Option<Polygon<f64>> {
+        let mut contours = self.contours.iter().map(|contour| {
+            contour
+                .iter()
+                .copied()
+                .map(coord)
+                .collect::<LineString<f64>>()
+        });
+        let exterior = contours.next()?;
+        Some(Polygon::new(exterior, contours.collect()))
+    }
+}
+
+fn coord(point: Point2<f64>) -> Coord<f64> {
+    Coord {
+        x: point.x,
+        y: point.y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::ArcGerberPrimitive;
+    use crate::Exposure;
+
+    #[test]
+    fn test_bounding_box_round_trip() {
+        // given
+        let bbox = BoundingBox {
+            min: Point2::new(-1.0, 2.0),
+            max: Point2::new(3.0, 4.0),
+        };
+
+        // when
+        let rect = Rect::from(&bbox);
+
+        // then
+        assert_eq!(rect.width(), 4.0);
+        assert_eq!(rect.height(), 2.0);
+        assert_eq!(BoundingBox::from(rect), bbox);
+    }
+
+    #[test]
+    fn test_arc_line_string() {
+        // given
+        let arc = GerberPrimitive::Arc(ArcGerberPrimitive {
+            center: Point2::new(10.0, 0.0),
+            radius: 1.0,
+            width: 0.1,
+            start_angle: 0.0,
+            sweep_angle: std::f64::consts::PI,
+            exposure: Exposure::Add,
+            tolerance: None,
+        });
+
+        // when
+        let line_string = arc.to_line_string().unwrap();
+
+        // then
+        let first = line_string.0.first().unwrap();
+        let last = line_string.0.last().unwrap();
+        assert!((first.x - 11.0).abs() < 1e-9 && first.y.abs() < 1e-9);
+        assert!((last.x - 9.0).abs() < 1e-9 && last.y.abs() < 1e-9);
+    }
+}
@@ -1,11 +1,15 @@
 mod bounding_box;
+mod clipping;
 mod mesh;
 mod mirroring;
+mod repair;
 mod shapes;
 mod transform;
 
 pub use bounding_box::*;
+pub use clipping::*;
 pub use mesh::*;
 pub use mirroring::*;
+pub use repair::*;
 pub use shapes::*;
 pub use transform::*;
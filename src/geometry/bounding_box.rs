@@ -11,7 +11,10 @@ pub struct BoundingBox {
 
 impl BoundingBox {
     /// Use to generate an outline of the bbox
+    ///
+    /// A [`GerberTransform::anchor`] is resolved against this bounding box.
     pub fn transform_vertices(&self, transform: &GerberTransform) -> Vec<Point2<f64>> {
+        let transform = transform.resolve(self);
         self.vertices()
             .into_iter()
             .map(|v| transform.apply_to_position(v))
@@ -44,6 +47,18 @@ impl BoundingBox {
         self.eq(&BoundingBox::default())
     }
 
+    /// Returns a new bounding box with the coordinates multiplied by `factor`, e.g. for unit conversion.
+    pub fn scale(&self, factor: f64) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+
+        Self {
+            min: self.min * factor,
+            max: self.max * factor,
+        }
+    }
+
     pub fn width(&self) -> f64 {
         self.max.x - self.min.x
     }
@@ -51,7 +66,52 @@ impl BoundingBox {
         self.max.y - self.min.y
     }
 
+    /// Zero for an empty bounding box.
+    pub fn area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        self.width() * self.height()
+    }
+
+    /// True if the bounding boxes overlap or touch, false if either is empty.
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x && self.min.y <= other.max.y && self.max.y >= other.min.y
+    }
+
+    /// True if the point is inside or on the edge of the bounding box.
+    pub fn contains_point(&self, point: Point2<f64>) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x && point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    /// True if `other` is entirely inside, or on the edge of, the bounding box.
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        other.min.x >= self.min.x && other.min.y >= self.min.y && other.max.x <= self.max.x && other.max.y <= self.max.y
+    }
+
+    /// Returns a new bounding box that contains both bounding boxes, see [`BoundingBox::expand`].
+    pub fn union(&self, other: &BoundingBox) -> Self {
+        let mut result = self.clone();
+        result.expand(other);
+        result
+    }
+
+    /// Returns a new bounding box with each edge moved outwards by `margin`, or inwards for a negative `margin`.
+    pub fn inflate(&self, margin: f64) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+
+        let margin = Vector2::new(margin, margin);
+        Self {
+            min: self.min - margin,
+            max: self.max + margin,
+        }
+    }
+
+    /// A [`GerberTransform::anchor`] is resolved against this bounding box.
     pub fn apply_transform(&self, transform: &GerberTransform) -> Self {
+        let transform = transform.resolve(self);
         // Step 1: Transform each corner of the original bbox
         let transformed_bbox_vertices: Vec<_> = self
             .vertices()
@@ -160,6 +220,51 @@ mod bbox_tests {
         assert_eq!(input.is_empty(), expected);
     }
 
+    fn bbox(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> BoundingBox {
+        BoundingBox {
+            min: Point2::new(min_x, min_y),
+            max: Point2::new(max_x, max_y),
+        }
+    }
+
+    #[rstest]
+    #[case(bbox(5.0, 5.0, 15.0, 15.0), true)]
+    // touching
+    #[case(bbox(10.0, 0.0, 20.0, 10.0), true)]
+    #[case(bbox(11.0, 0.0, 20.0, 10.0), false)]
+    #[case(BoundingBox::default(), false)]
+    fn test_intersects(#[case] other: BoundingBox, #[case] expected: bool) {
+        assert_eq!(bbox(0.0, 0.0, 10.0, 10.0).intersects(&other), expected);
+    }
+
+    #[rstest]
+    #[case(bbox(1.0, 1.0, 9.0, 9.0), true)]
+    #[case(bbox(0.0, 0.0, 10.0, 10.0), true)]
+    #[case(bbox(5.0, 5.0, 15.0, 15.0), false)]
+    fn test_contains_box(#[case] other: BoundingBox, #[case] expected: bool) {
+        assert_eq!(bbox(0.0, 0.0, 10.0, 10.0).contains_box(&other), expected);
+    }
+
+    #[test]
+    fn test_union_inflate_and_area() {
+        // given
+        let a = bbox(0.0, 0.0, 1.0, 1.0);
+        let b = bbox(2.0, 3.0, 4.0, 5.0);
+
+        // when
+        let union = a.union(&b);
+
+        // then
+        assert_eq!(union, bbox(0.0, 0.0, 4.0, 5.0));
+        assert_eq!(union.area(), 20.0);
+        assert_eq!(a.inflate(1.0), bbox(-1.0, -1.0, 2.0, 2.0));
+        assert!(a.contains_point(Point2::new(0.5, 1.0)));
+        assert_eq!(BoundingBox::default().area(), 0.0);
+        assert!(BoundingBox::default()
+            .inflate(1.0)
+            .is_empty());
+    }
+
     #[test]
     pub fn test_apply_rotation_90_degrees_zero_offset() {
         let bbox = BoundingBox {
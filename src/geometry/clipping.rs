@@ -0,0 +1,125 @@
+use nalgebra::Point2;
+
+use super::mesh::signed_area;
+
+/// Pieces of a difference with a smaller area than this are discarded.
+const AREA_EPSILON: f64 = 1e-12;
+
+/// Subtracts the convex polygon `hole` from the convex polygon `polygon`.
+///
+/// Returns the difference as convex pieces, one for each edge of the hole that the polygon extends beyond, an empty
+/// list if the hole covers the polygon.  The winding of the polygons does not matter.
+pub(crate) fn convex_difference(polygon: &[Point2<f64>], hole: &[Point2<f64>]) -> Vec<Vec<Point2<f64>>> {
+    // the inside of a counter-clockwise hole is to the left of each edge
+    let mut hole = hole.to_vec();
+    if signed_area(&hole) < 0.0 {
+        hole.reverse();
+    }
+
+    let mut pieces = vec![];
+    let mut remaining = polygon.to_vec();
+    for (index, start) in hole.iter().enumerate() {
+        let end = hole[(index + 1) % hole.len()];
+
+        let outside = clip_to_half_plane(&remaining, *start, end, false);
+        if signed_area(&outside).abs() > AREA_EPSILON {
+            pieces.push(outside);
+        }
+        remaining = clip_to_half_plane(&remaining, *start, end, true);
+        if remaining.len() < 3 {
+            break;
+        }
+    }
+
+    pieces
+}
+
+/// Clips a convex polygon to the half-plane to the left (`left` = `true`) or to the right of the line through `start`
+/// and `end`, see Sutherland–Hodgman.
+fn clip_to_half_plane(polygon: &[Point2<f64>], start: Point2<f64>, end: Point2<f64>, left: bool) -> Vec<Point2<f64>> {
+    let direction = end - start;
+    let side = |point: &Point2<f64>| {
+        let offset = point - start;
+        let cross = direction.x * offset.y - direction.y * offset.x;
+        if left {
+            cross
+        } else {
+            -cross
+        }
+    };
+
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (index, current) in polygon.iter().enumerate() {
+        let next = polygon[(index + 1) % polygon.len()];
+        let (current_side, next_side) = (side(current), side(&next));
+
+        if current_side >= 0.0 {
+            clipped.push(*current);
+        }
+        if (current_side >= 0.0) != (next_side >= 0.0) {
+            let t = current_side / (current_side - next_side);
+            clipped.push(current + (next - current) * t);
+        }
+    }
+
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(min: f64, max: f64) -> Vec<Point2<f64>> {
+        vec![
+            Point2::new(min, min),
+            Point2::new(max, min),
+            Point2::new(max, max),
+            Point2::new(min, max),
+        ]
+    }
+
+    fn area(pieces: &[Vec<Point2<f64>>]) -> f64 {
+        pieces
+            .iter()
+            .map(|piece| signed_area(piece).abs())
+            .sum()
+    }
+
+    #[test]
+    fn test_convex_difference_with_a_hole_inside() {
+        // when
+        let pieces = convex_difference(&square(0.0, 4.0), &square(1.0, 2.0));
+
+        // then
+        assert_eq!(pieces.len(), 4);
+        assert!((area(&pieces) - 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_convex_difference_with_a_partially_overlapping_hole() {
+        // when
+        let pieces = convex_difference(&square(0.0, 4.0), &square(2.0, 6.0));
+
+        // then
+        assert!((area(&pieces) - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_convex_difference_with_a_covering_hole() {
+        // expect
+        assert!(convex_difference(&square(1.0, 2.0), &square(0.0, 4.0)).is_empty());
+    }
+
+    #[test]
+    fn test_convex_difference_with_a_clockwise_hole() {
+        // given
+        let mut hole = square(1.0, 2.0);
+        hole.reverse();
+
+        // when
+        let pieces = convex_difference(&square(0.0, 4.0), &hole);
+
+        // then
+        assert!((area(&pieces) - 15.0).abs() < 1e-9);
+    }
+}
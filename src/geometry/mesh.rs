@@ -1,19 +1,90 @@
+use std::collections::HashMap;
+
+use log::{error, warn};
 use nalgebra::Point2;
 
-#[derive(Debug, Clone)]
+use crate::hit_test::distance_to_segment;
+
+#[derive(Debug, Clone, Default)]
 pub struct PolygonMesh {
     pub vertices: Vec<[f32; 2]>,
     pub indices: Vec<u32>,
 }
 
+/// Vertices closer than this are considered to be the same vertex when detecting cut-ins.
+const CONTOUR_EPSILON: f64 = 1e-6;
+
+/// Controls the tessellation of concave polygons, e.g. regions, see [`crate::LayerOptions::tessellation`].
+///
+/// Large copper pours can have hundreds of thousands of vertices, these options trade fidelity for speed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TessellationOptions {
+    /// Vertices that are closer than this to the simplified outline are removed before tessellating, in the units of
+    /// the layer.  Only the mesh is simplified, the vertices of the polygon are unchanged.  `None` keeps every vertex.
+    pub tolerance: Option<f64>,
+    /// Polygons with more vertices than this, after simplification, are not tessellated, see `fallback`.
+    pub max_vertices: Option<usize>,
+    /// What to draw when a polygon is not tessellated.
+    pub fallback: TessellationFallback,
+}
+
+/// What to draw for a polygon that has too many vertices, or could not be tessellated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TessellationFallback {
+    /// The outline of the polygon is drawn, the polygon has no tessellation.
+    #[default]
+    Outline,
+    /// Nothing is drawn, the polygon has an empty tessellation.
+    Skip,
+}
+
+/// Tessellates a polygon, cut-ins are supported, see [`split_contours`].
 pub fn tessellate_polygon(vertices: &[Point2<f64>]) -> PolygonMesh {
+    tessellate_contours(split_contours(vertices))
+        .inspect_err(|error| error!("Unable to tessellate polygon. error: {:?}", error))
+        .unwrap_or_default()
+}
+
+/// Like [`tessellate_polygon`], but using the options.
+///
+/// Returns `None` if the polygon has more than [`TessellationOptions::max_vertices`] or could not be tessellated, the
+/// [`TessellationOptions::fallback`] is not applied.
+pub fn try_tessellate_polygon(vertices: &[Point2<f64>], options: &TessellationOptions) -> Option<PolygonMesh> {
+    let mut contours = split_contours(vertices);
+    if let Some(tolerance) = options.tolerance {
+        contours = contours
+            .into_iter()
+            .map(|contour| simplify_contour(&contour, tolerance))
+            .filter(|contour| contour.len() >= 3)
+            .collect();
+    }
+
+    let vertex_count = contours
+        .iter()
+        .map(Vec::len)
+        .sum::<usize>();
+    if options
+        .max_vertices
+        .is_some_and(|max_vertices| vertex_count > max_vertices)
+    {
+        warn!("Polygon not tessellated, too many vertices. vertices: {}", vertex_count);
+        return None;
+    }
+
+    tessellate_contours(contours)
+        .inspect_err(|error| warn!("Unable to tessellate polygon. error: {:?}", error))
+        .ok()
+}
+
+fn tessellate_contours(contours: Vec<Vec<Point2<f64>>>) -> Result<PolygonMesh, lyon::tessellation::TessellationError> {
     use lyon::path::Path;
     use lyon::tessellation::{BuffersBuilder, FillOptions, FillRule, FillTessellator, VertexBuffers};
 
     let mut path_builder = Path::builder();
-    if let Some(first) = vertices.first() {
+    for contour in contours {
+        let Some(first) = contour.first() else { continue };
         path_builder.begin(lyon::math::Point::new(first.x as f32, first.y as f32));
-        for pos in &vertices[1..] {
+        for pos in &contour[1..] {
             path_builder.line_to(lyon::math::Point::new(pos.x as f32, pos.y as f32));
         }
         path_builder.close();
@@ -23,18 +94,339 @@ pub fn tessellate_polygon(vertices: &[Point2<f64>]) -> PolygonMesh {
     let mut geometry = VertexBuffers::new();
     let mut tessellator = FillTessellator::new();
 
-    tessellator
-        .tessellate_path(
-            &path,
-            &FillOptions::default().with_fill_rule(FillRule::EvenOdd),
-            &mut BuffersBuilder::new(&mut geometry, |vertex: lyon::tessellation::FillVertex| {
-                [vertex.position().x, vertex.position().y]
-            }),
-        )
-        .unwrap();
+    tessellator.tessellate_path(
+        &path,
+        &FillOptions::default().with_fill_rule(FillRule::EvenOdd),
+        &mut BuffersBuilder::new(&mut geometry, |vertex: lyon::tessellation::FillVertex| {
+            [vertex.position().x, vertex.position().y]
+        }),
+    )?;
 
-    PolygonMesh {
+    Ok(PolygonMesh {
         vertices: geometry.vertices,
         indices: geometry.indices,
+    })
+}
+
+/// Simplifies a polygon using the Ramer-Douglas-Peucker algorithm, vertices that are closer than `tolerance` to the
+/// simplified outline are removed.
+///
+/// Cut-ins are supported, see [`split_contours`], each contour is simplified separately and holes that collapse are
+/// removed.  The polygon is returned unchanged if the outer contour would collapse.
+pub fn simplify_polygon(vertices: &[Point2<f64>], tolerance: f64) -> Vec<Point2<f64>> {
+    let mut contours = split_contours(vertices)
+        .into_iter()
+        .map(|contour| simplify_contour(&contour, tolerance));
+
+    let Some(outer) = contours.next() else {
+        return vec![];
+    };
+    if outer.len() < 3 {
+        return vertices.to_vec();
+    }
+
+    join_contours(
+        outer,
+        contours
+            .filter(|contour| contour.len() >= 3)
+            .collect(),
+    )
+}
+
+/// The reverse of [`split_contours`], each hole is connected to the first vertex of the outer contour by a cut-in.
+pub(crate) fn join_contours(outer: Vec<Point2<f64>>, holes: Vec<Vec<Point2<f64>>>) -> Vec<Point2<f64>> {
+    let start = outer[0];
+    let mut vertices = outer;
+    for hole in holes {
+        // the cut-in is the pair of edges between `start` and the start of the hole, the edge back to `start` is added
+        // by the next hole, or by closing the polygon.
+        let hole_start = hole[0];
+        vertices.push(start);
+        vertices.extend(hole);
+        vertices.push(hole_start);
+    }
+    vertices
+}
+
+/// Simplifies a closed contour using the Ramer-Douglas-Peucker algorithm.
+fn simplify_contour(contour: &[Point2<f64>], tolerance: f64) -> Vec<Point2<f64>> {
+    let Some(first) = contour.first() else {
+        return vec![];
+    };
+    // close the contour, so the last edge is simplified too
+    let points = contour
+        .iter()
+        .chain(std::iter::once(first))
+        .copied()
+        .collect::<Vec<_>>();
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let farthest = (start + 1..end)
+            .map(|index| (index, distance_to_segment(points[index], points[start], points[end])))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((index, distance)) = farthest {
+            if distance > tolerance {
+                keep[index] = true;
+                ranges.push((start, index));
+                ranges.push((index, end));
+            }
+        }
+    }
+
+    let mut simplified = points
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(point))
+        .collect::<Vec<_>>();
+    simplified.pop();
+    simplified
+}
+
+/// Splits a closed polygon that uses cut-ins into its contours, the first contour is the outer contour.
+///
+/// Gerber spec 2024.05 - 4.10.2 - holes in regions are created using cut-ins, a cut-in is a pair of coincident edges
+/// traversed in opposite directions that connect the outer contour to the contour of the hole, e.g.:
+///
+/// ```plaintext
+///   ┌─────────────┐
+///   │   ┌───┐     │
+///   │   │   │     │
+///   │   └─┬─┘     │
+///   │     │       │
+///   └─────┴───────┘
+/// ```
+///
+/// The contours are found by walking the vertices, whenever a vertex is revisited the vertices since the previous visit
+/// form a closed contour.  The cut-in edges themselves form degenerate contours which are discarded.
+///
+/// Returns a single contour, the original vertices, when there are no cut-ins.
+pub fn split_contours(vertices: &[Point2<f64>]) -> Vec<Vec<Point2<f64>>> {
+    fn key(point: &Point2<f64>) -> (i64, i64) {
+        (
+            (point.x / CONTOUR_EPSILON).round() as i64,
+            (point.y / CONTOUR_EPSILON).round() as i64,
+        )
+    }
+
+    let mut contours: Vec<Vec<Point2<f64>>> = vec![];
+    let mut stack: Vec<Point2<f64>> = Vec::with_capacity(vertices.len());
+    // key -> index in `stack`
+    let mut visited: HashMap<(i64, i64), usize> = HashMap::with_capacity(vertices.len());
+
+    for vertex in vertices {
+        let vertex_key = key(vertex);
+        match visited.get(&vertex_key) {
+            Some(&start) => {
+                // the vertex at `start` remains on the stack, it's part of the enclosing contour
+                let contour = stack.split_off(start + 1);
+                for removed in &contour {
+                    visited.remove(&key(removed));
+                }
+                let mut contour_with_start = Vec::with_capacity(contour.len() + 1);
+                contour_with_start.push(stack[start]);
+                contour_with_start.extend(contour);
+                contours.push(contour_with_start);
+            }
+            None => {
+                visited.insert(vertex_key, stack.len());
+                stack.push(*vertex);
+            }
+        }
+    }
+    if !stack.is_empty() {
+        contours.push(stack);
+    }
+
+    // discard the degenerate contours formed by the cut-in edges
+    contours.retain(|contour| contour.len() >= 3);
+
+    if contours.len() <= 1 {
+        return vec![vertices.to_vec()];
+    }
+
+    // the outer contour has the largest area, the remaining contours are holes
+    let outer_index = contours
+        .iter()
+        .enumerate()
+        .map(|(index, contour)| (index, signed_area(contour).abs()))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+        .unwrap();
+    contours.swap(0, outer_index);
+
+    contours
+}
+
+pub(crate) fn signed_area(vertices: &[Point2<f64>]) -> f64 {
+    let mut sum = 0.0;
+    for i in 0..vertices.len() {
+        let j = (i + 1) % vertices.len();
+        sum += vertices[i].x * vertices[j].y - vertices[j].x * vertices[i].y;
+    }
+    sum / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_contours_without_cut_ins() {
+        // given
+        let vertices = vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(0.0, 1.0),
+        ];
+
+        // when
+        let contours = split_contours(&vertices);
+
+        // then
+        assert_eq!(contours, vec![vertices]);
+    }
+
+    #[test]
+    fn test_split_contours_with_cut_in() {
+        // given
+        // a 10x10 square with a 2x2 hole, the cut-in is from (5,0) to (5,4)
+        let vertices = vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(5.0, 0.0),
+            Point2::new(5.0, 4.0),
+            Point2::new(4.0, 4.0),
+            Point2::new(4.0, 6.0),
+            Point2::new(6.0, 6.0),
+            Point2::new(6.0, 4.0),
+            Point2::new(5.0, 4.0),
+            Point2::new(5.0, 0.0),
+            Point2::new(10.0, 0.0),
+            Point2::new(10.0, 10.0),
+            Point2::new(0.0, 10.0),
+        ];
+
+        // when
+        let contours = split_contours(&vertices);
+
+        // then
+        assert_eq!(contours, vec![
+            vec![
+                Point2::new(0.0, 0.0),
+                Point2::new(5.0, 0.0),
+                Point2::new(10.0, 0.0),
+                Point2::new(10.0, 10.0),
+                Point2::new(0.0, 10.0),
+            ],
+            vec![
+                Point2::new(5.0, 4.0),
+                Point2::new(4.0, 4.0),
+                Point2::new(4.0, 6.0),
+                Point2::new(6.0, 6.0),
+                Point2::new(6.0, 4.0),
+            ],
+        ]);
+    }
+
+    #[test]
+    fn test_simplify_polygon_with_cut_in() {
+        // given
+        // a 10x10 square, with many collinear vertices along the bottom edge, and a 2x2 hole
+        let mut outer = (0..=100)
+            .map(|index| Point2::new(index as f64 / 10.0, 0.0))
+            .collect::<Vec<_>>();
+        outer.extend([Point2::new(10.0, 10.0), Point2::new(0.0, 10.0)]);
+        let hole = vec![
+            Point2::new(4.0, 4.0),
+            Point2::new(6.0, 4.0),
+            Point2::new(6.0, 6.0),
+            Point2::new(4.0, 6.0),
+        ];
+        let vertices = join_contours(outer, vec![hole.clone()]);
+
+        // when
+        let simplified = simplify_polygon(&vertices, 0.01);
+
+        // then
+        assert_eq!(split_contours(&simplified), vec![
+            vec![
+                Point2::new(0.0, 0.0),
+                Point2::new(10.0, 0.0),
+                Point2::new(10.0, 10.0),
+                Point2::new(0.0, 10.0),
+            ],
+            hole,
+        ]);
+    }
+
+    #[test]
+    fn test_try_tessellate_polygon_with_options() {
+        // given
+        // a square, with many collinear vertices along the bottom edge
+        let mut vertices = (0..=100)
+            .map(|index| Point2::new(index as f64 / 10.0, 0.0))
+            .collect::<Vec<_>>();
+        vertices.extend([Point2::new(10.0, 10.0), Point2::new(0.0, 10.0)]);
+
+        let options = TessellationOptions {
+            tolerance: Some(0.001),
+            max_vertices: Some(4),
+            ..TessellationOptions::default()
+        };
+
+        // when
+        let mesh = try_tessellate_polygon(&vertices, &options).unwrap();
+
+        // then
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices.len(), 6);
+
+        // and without simplification there are too many vertices
+        let options = TessellationOptions {
+            max_vertices: Some(4),
+            ..TessellationOptions::default()
+        };
+        assert!(try_tessellate_polygon(&vertices, &options).is_none());
+    }
+
+    #[test]
+    fn test_tessellate_polygon_with_cut_in_excludes_hole() {
+        // given
+        let vertices = vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(5.0, 0.0),
+            Point2::new(5.0, 4.0),
+            Point2::new(4.0, 4.0),
+            Point2::new(4.0, 6.0),
+            Point2::new(6.0, 6.0),
+            Point2::new(6.0, 4.0),
+            Point2::new(5.0, 4.0),
+            Point2::new(5.0, 0.0),
+            Point2::new(10.0, 0.0),
+            Point2::new(10.0, 10.0),
+            Point2::new(0.0, 10.0),
+        ];
+
+        // when
+        let mesh = tessellate_polygon(&vertices);
+
+        // then
+        let area: f64 = mesh
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| {
+                    let [x, y] = mesh.vertices[triangle[i] as usize];
+                    Point2::new(x as f64, y as f64)
+                });
+                signed_area(&[a, b, c]).abs()
+            })
+            .sum();
+        assert!((area - 96.0).abs() < 1e-3);
     }
 }
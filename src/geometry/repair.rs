@@ -0,0 +1,220 @@
+use nalgebra::Point2;
+
+use super::mesh::{join_contours, signed_area, split_contours};
+
+/// Polygons with an area smaller than this are considered to have no area.
+const ZERO_AREA_EPSILON: f64 = 1e-12;
+
+/// Intersections closer than this to the end of an edge, as a fraction of the edge, are ignored, they are either shared
+/// vertices or touching contours, not crossings.
+const INTERSECTION_EPSILON: f64 = 1e-9;
+
+/// The result of [`repair_polygon`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolygonRepair {
+    Valid,
+    /// The polygon has no area, e.g. a contour that doubles back on itself.
+    ZeroArea,
+    /// The polygon is self-intersecting, and was split into simple polygons, the polygons may contain cut-ins.
+    Split(Vec<Vec<Point2<f64>>>),
+}
+
+/// Checks a polygon, that may contain cut-ins, see [`split_contours`], for self-intersecting contours and for zero area.
+///
+/// Self-intersecting contours are split at the intersections into simple contours, each outer contour becomes a
+/// polygon, and each hole is added to the polygon that contains it.  Holes outside all the outer contours are dropped.
+/// Contours that cross other contours are not detected.
+pub fn repair_polygon(vertices: &[Point2<f64>]) -> PolygonRepair {
+    let mut contours = split_contours(vertices).into_iter();
+    let Some(outer) = contours.next() else {
+        return PolygonRepair::ZeroArea;
+    };
+    let holes = contours.collect::<Vec<_>>();
+
+    let is_self_intersecting = std::iter::once(&outer)
+        .chain(&holes)
+        .any(|contour| !find_intersections(contour).is_empty());
+
+    if !is_self_intersecting {
+        return match signed_area(&outer).abs() < ZERO_AREA_EPSILON {
+            true => PolygonRepair::ZeroArea,
+            false => PolygonRepair::Valid,
+        };
+    }
+
+    let outer_contours = split_at_intersections(&outer);
+    let mut holes_of_contour = vec![vec![]; outer_contours.len()];
+    for hole in holes
+        .iter()
+        .flat_map(|hole| split_at_intersections(hole))
+    {
+        if let Some(index) = outer_contours
+            .iter()
+            .position(|contour| contains(contour, hole[0]))
+        {
+            holes_of_contour[index].push(hole);
+        }
+    }
+
+    let polygons = outer_contours
+        .into_iter()
+        .zip(holes_of_contour)
+        .map(|(contour, holes)| join_contours(contour, holes))
+        .collect::<Vec<_>>();
+
+    match polygons.is_empty() {
+        true => PolygonRepair::ZeroArea,
+        false => PolygonRepair::Split(polygons),
+    }
+}
+
+/// The points where edges of the closed contour cross each other, as (edge index, position along the edge, point).
+///
+/// Each crossing is returned twice, once for each edge, with the same point.  The edges are swept in order of their
+/// minimum x coordinate so that only edges with overlapping x ranges are compared.
+fn find_intersections(contour: &[Point2<f64>]) -> Vec<(usize, f64, Point2<f64>)> {
+    let count = contour.len();
+    if count < 4 {
+        return vec![];
+    }
+    let edge = |index: usize| (contour[index], contour[(index + 1) % count]);
+
+    let mut order = (0..count).collect::<Vec<_>>();
+    order.sort_by(|a, b| {
+        let (a_start, a_end) = edge(*a);
+        let (b_start, b_end) = edge(*b);
+        a_start
+            .x
+            .min(a_end.x)
+            .total_cmp(&b_start.x.min(b_end.x))
+    });
+
+    let mut intersections = vec![];
+    let mut active: Vec<usize> = vec![];
+    for index in order {
+        let (start, end) = edge(index);
+        let min_x = start.x.min(end.x);
+        active.retain(|other| {
+            let (other_start, other_end) = edge(*other);
+            other_start.x.max(other_end.x) >= min_x
+        });
+
+        for other in &active {
+            // adjacent edges share a vertex
+            let distance = index.abs_diff(*other);
+            if distance == 1 || distance == count - 1 {
+                continue;
+            }
+
+            let (other_start, other_end) = edge(*other);
+            if let Some((t, u)) = crossing(start, end, other_start, other_end) {
+                let point = start + (end - start) * t;
+                intersections.push((index, t, point));
+                intersections.push((*other, u, point));
+            }
+        }
+        active.push(index);
+    }
+
+    intersections
+}
+
+/// The positions along both segments where they cross, `None` if they don't cross or are parallel.
+fn crossing(a_start: Point2<f64>, a_end: Point2<f64>, b_start: Point2<f64>, b_end: Point2<f64>) -> Option<(f64, f64)> {
+    let a = a_end - a_start;
+    let b = b_end - b_start;
+    let denominator = a.perp(&b);
+    if denominator == 0.0 {
+        return None;
+    }
+
+    let between = b_start - a_start;
+    let t = between.perp(&b) / denominator;
+    let u = between.perp(&a) / denominator;
+    let range = INTERSECTION_EPSILON..=1.0 - INTERSECTION_EPSILON;
+    (range.contains(&t) && range.contains(&u)).then_some((t, u))
+}
+
+/// Inserts the intersections into the contour, and splits it into simple contours, contours without area are dropped.
+fn split_at_intersections(contour: &[Point2<f64>]) -> Vec<Vec<Point2<f64>>> {
+    let mut intersections = find_intersections(contour);
+    if intersections.is_empty() {
+        return vec![contour.to_vec()];
+    }
+    intersections.sort_by(|(a_index, a_t, _), (b_index, b_t, _)| {
+        a_index
+            .cmp(b_index)
+            .then(a_t.total_cmp(b_t))
+    });
+
+    let mut vertices = Vec::with_capacity(contour.len() + intersections.len());
+    let mut intersections = intersections.into_iter().peekable();
+    for (index, vertex) in contour.iter().enumerate() {
+        vertices.push(*vertex);
+        while let Some((_, _, point)) = intersections.next_if(|(edge, _, _)| *edge == index) {
+            vertices.push(point);
+        }
+    }
+
+    // each intersection is now a revisited vertex, so splitting the contours splits the contour at the intersections.
+    split_contours(&vertices)
+        .into_iter()
+        .filter(|contour| signed_area(contour).abs() >= ZERO_AREA_EPSILON)
+        .collect()
+}
+
+/// Even-odd point in polygon test.
+fn contains(contour: &[Point2<f64>], point: Point2<f64>) -> bool {
+    let mut inside = false;
+    for (index, start) in contour.iter().enumerate() {
+        let end = contour[(index + 1) % contour.len()];
+        if (start.y > point.y) != (end.y > point.y)
+            && point.x < start.x + (point.y - start.y) * (end.x - start.x) / (end.y - start.y)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coordinates: &[(f64, f64)]) -> Vec<Point2<f64>> {
+        coordinates
+            .iter()
+            .map(|(x, y)| Point2::new(*x, *y))
+            .collect()
+    }
+
+    #[test]
+    fn test_repair_bow_tie() {
+        // given
+        let vertices = points(&[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)]);
+
+        // when
+        let repair = repair_polygon(&vertices);
+
+        // then
+        let PolygonRepair::Split(polygons) = repair else {
+            panic!("expected a split, got {:?}", repair);
+        };
+        assert_eq!(polygons.len(), 2);
+        for polygon in &polygons {
+            assert_eq!(polygon.len(), 3);
+            assert!((signed_area(polygon).abs() - 1.0).abs() < 1e-9);
+            assert!(polygon.contains(&Point2::new(1.0, 1.0)));
+        }
+    }
+
+    #[test]
+    fn test_repair_valid_and_zero_area() {
+        // expect
+        let square = points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        assert_eq!(repair_polygon(&square), PolygonRepair::Valid);
+
+        let line = points(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]);
+        assert_eq!(repair_polygon(&line), PolygonRepair::ZeroArea);
+    }
+}
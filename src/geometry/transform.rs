@@ -3,6 +3,7 @@ use std::f64::consts::PI;
 use egui::{Pos2, Vec2};
 use nalgebra::{Matrix3, Point2, Vector2, Vector3};
 
+use crate::geometry::bounding_box::BoundingBox;
 use crate::geometry::mirroring::Mirroring;
 
 /// Gerber-specific transform.
@@ -11,6 +12,8 @@ use crate::geometry::mirroring::Mirroring;
 /// * Origin is subtracted from coordinates so that rotation and mirroring occurs around the origin.
 /// * After mirroring, rotation and scaling, Origin is then added to relocate the coordinates
 /// * Finally an offset is added
+///
+/// Instead of computing the origin by hand, an [`TransformAnchor`] can be used, see [`GerberTransform::resolve`].
 #[derive(Debug, Copy, Clone)]
 pub struct GerberTransform {
     /// rotation in radians, positive = counter-clockwise
@@ -22,6 +25,21 @@ pub struct GerberTransform {
     pub offset: Vector2<f64>,
     // scale factor, 0.5 = 50%, 1.0 = 100%
     pub scale: f64,
+    /// when set, replaces `origin` when the transform is resolved, see [`GerberTransform::resolve`].
+    pub anchor: Option<TransformAnchor>,
+}
+
+/// The point that rotation and mirroring occurs around.
+///
+/// `Origin` and `Custom` points are in gerber coordinates after the offset has been applied, i.e. where the design
+/// ends up, so they do not need adjusting when the offset changes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TransformAnchor {
+    /// The center of the bounding box of the untransformed design.
+    BboxCenter,
+    /// 0,0
+    Origin,
+    Custom(Point2<f64>),
 }
 
 impl Default for GerberTransform {
@@ -32,11 +50,34 @@ impl Default for GerberTransform {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         }
     }
 }
 
 impl GerberTransform {
+    /// Returns a copy of the transform with the `origin` computed from the `anchor`, if any, and the anchor cleared.
+    ///
+    /// `bbox` is the bounding box of the untransformed design, e.g. [`crate::GerberLayer::bounding_box`].
+    ///
+    /// The renderer and [`BoundingBox::apply_transform`] resolve the transform, anything that uses the `origin`
+    /// directly, e.g. [`GerberTransform::to_matrix`] or [`GerberTransform::apply_to_position`], requires a resolved
+    /// transform.
+    pub fn resolve(&self, bbox: &BoundingBox) -> Self {
+        let origin = match self.anchor {
+            None => return *self,
+            Some(TransformAnchor::BboxCenter) => bbox.center().coords,
+            Some(TransformAnchor::Origin) => -self.offset,
+            Some(TransformAnchor::Custom(point)) => point.coords - self.offset,
+        };
+
+        Self {
+            origin,
+            anchor: None,
+            ..*self
+        }
+    }
+
     /// Apply the transform to a logical `Point2` (Gerber-space)
     #[inline]
     pub fn apply_to_position(&self, pos: Point2<f64>) -> Point2<f64> {
@@ -176,6 +217,7 @@ impl GerberTransform {
             origin: Vector2::new(0.0, 0.0),
             offset,
             scale,
+            anchor: None,
         }
     }
 
@@ -279,6 +321,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         let matrix = identity.to_matrix();
@@ -316,6 +359,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         let matrix = rotation_90.to_matrix();
@@ -345,6 +389,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(10.0, 20.0),
             scale: 1.0,
+            anchor: None,
         };
 
         let matrix = offset_transform.to_matrix();
@@ -383,6 +428,7 @@ mod transform_tests {
             origin: Vector2::new(-5.0, 0.0), // Rotate around box1's position
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         let transform2 = GerberTransform {
@@ -391,6 +437,7 @@ mod transform_tests {
             origin: Vector2::new(5.0, 0.0), // Rotate around box2's position
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         // Step 2: Create a 90-degree rotation transform around (0, 0) for both boxes
@@ -400,6 +447,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0), // Rotate around the origin
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         // Apply transforms to get reference results
@@ -470,6 +518,7 @@ mod transform_tests {
             origin: Vector2::new(-5.0, -5.0), // Rotate around box1's position
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         let transform2 = GerberTransform {
@@ -478,6 +527,7 @@ mod transform_tests {
             origin: Vector2::new(-5.0, 5.0), // Rotate around box2's position
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         // Step 2: Create a 90-degree rotation transform around (0, 0) for both boxes
@@ -487,6 +537,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0), // Rotate around the origin
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         // Apply transforms sequentially to get reference results
@@ -636,6 +687,7 @@ mod transform_tests {
             origin: Vector2::new(3.0, 4.0),
             offset: Vector2::new(1.0, 2.0),
             scale: 1.5,
+            anchor: None,
         };
 
         let transform2 = GerberTransform {
@@ -644,6 +696,7 @@ mod transform_tests {
             origin: Vector2::new(-2.0, 5.0),
             offset: Vector2::new(3.0, -1.0),
             scale: 0.8,
+            anchor: None,
         };
 
         // Test points
@@ -681,6 +734,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         // Create a rotation transform
@@ -690,6 +744,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         // Test point
@@ -720,6 +775,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(0.0, 0.0),
             scale: 2.0,
+            anchor: None,
         };
 
         // Create transform with offset
@@ -729,6 +785,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(10.0, 20.0),
             scale: 1.0,
+            anchor: None,
         };
 
         // Test point
@@ -759,6 +816,7 @@ mod transform_tests {
             origin: Vector2::new(-5.0, 0.0),
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         let transform2 = GerberTransform {
@@ -767,6 +825,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         let transform3 = GerberTransform {
@@ -775,6 +834,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0),
             offset: Vector2::new(10.0, 10.0),
             scale: 2.0,
+            anchor: None,
         };
 
         // Test point
@@ -795,6 +855,50 @@ mod transform_tests {
         assert!((final_combined.y - final_reference.y).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_resolve_anchor() {
+        // given
+        let bbox = BoundingBox {
+            min: Point2::new(0.0, 0.0),
+            max: Point2::new(10.0, 4.0),
+        };
+        let transform = GerberTransform {
+            rotation: PI,
+            offset: Vector2::new(-5.0, -10.0),
+            ..GerberTransform::default()
+        };
+
+        // expect
+        let cases = [
+            (None, Vector2::new(0.0, 0.0)),
+            (Some(TransformAnchor::BboxCenter), Vector2::new(5.0, 2.0)),
+            (Some(TransformAnchor::Origin), Vector2::new(5.0, 10.0)),
+            (
+                Some(TransformAnchor::Custom(Point2::new(1.0, 2.0))),
+                Vector2::new(6.0, 12.0),
+            ),
+        ];
+        for (anchor, expected_origin) in cases {
+            let resolved = GerberTransform {
+                anchor,
+                ..transform
+            }
+            .resolve(&bbox);
+            assert_eq!(resolved.origin, expected_origin);
+            assert_eq!(resolved.anchor, None);
+        }
+
+        // and
+        // rotating around the center of the bbox keeps the bbox in place, before the offset
+        let resolved = GerberTransform {
+            anchor: Some(TransformAnchor::BboxCenter),
+            ..transform
+        }
+        .resolve(&bbox);
+        let corner = resolved.apply_to_position(Point2::new(0.0, 0.0));
+        assert!((corner - Point2::new(5.0, -6.0)).norm() < 1e-6);
+    }
+
     #[test]
     fn test_rotation_around_different_centers() {
         // This test specifically targets the original issue
@@ -812,6 +916,7 @@ mod transform_tests {
             origin: Vector2::new(-5.0, 0.0), // Rotate around box1's position
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         let box2_local_rot = GerberTransform {
@@ -820,6 +925,7 @@ mod transform_tests {
             origin: Vector2::new(5.0, 0.0), // Rotate around box2's position
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         // Global rotation (90°) around (0, 0)
@@ -829,6 +935,7 @@ mod transform_tests {
             origin: Vector2::new(0.0, 0.0), // Rotate around the origin
             offset: Vector2::new(0.0, 0.0),
             scale: 1.0,
+            anchor: None,
         };
 
         // Apply transforms sequentially
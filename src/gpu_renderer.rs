@@ -0,0 +1,662 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+
+use egui::{Color32, PaintCallbackInfo, Painter};
+use egui_wgpu::wgpu;
+use egui_wgpu::wgpu::util::DeviceExt;
+use egui_wgpu::{CallbackResources, CallbackTrait, RenderState, ScreenDescriptor};
+use nalgebra::Matrix3;
+
+use crate::geometry::GerberTransform;
+use crate::layer::GerberLayer;
+use crate::layer_mesh::Triangles;
+use crate::{BlendMode, Exposure, NegativeArea, ViewState};
+
+const SHADER: &str = r#"
+struct Uniforms {
+    transform: mat4x4<f32>,
+    color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) exposure: f32,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, @location(1) exposure: f32) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = uniforms.transform * vec4<f32>(position, 0.0, 1.0);
+    out.exposure = exposure;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // clear (cut-out) primitives erase what was drawn before them, the blending is disabled
+    return select(vec4<f32>(0.0, 0.0, 0.0, 0.0), uniforms.color, in.exposure > 0.5);
+}
+"#;
+
+/// Copies the offscreen texture of a layer to the egui render target, which blends it over the other layers.
+const COMPOSITE_SHADER: &str = r#"
+@group(0) @binding(0)
+var layer_texture: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// a single triangle that covers the viewport
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(layer_texture));
+    let texel = min(vec2<i32>(in.uv * vec2<f32>(size)), size - 1);
+    return textureLoad(layer_texture, texel, 0);
+}
+"#;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuVertex {
+    position: [f32; 2],
+    /// 1.0 for dark (add) primitives, 0.0 for clear (cut-out) primitives.
+    exposure: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    /// Gerber coordinates to clip space, column-major.
+    transform: [[f32; 4]; 4],
+    /// Premultiplied, gamma-space, like egui vertex colors.
+    color: [f32; 4],
+}
+
+/// The triangles of a layer, in gerber coordinates, built once and uploaded to the GPU the first time it's painted, see
+/// [`GpuRenderer::paint_layer`].
+///
+/// Step-and-repeat blocks are expanded.  The color and transform are not part of the mesh, so changing them, or the
+/// view, does not require rebuilding it.  Polygons without a tessellation, see
+/// [`crate::TessellationFallback::Outline`], are not drawn.
+#[derive(Debug)]
+pub struct GpuLayerMesh {
+    id: u64,
+    vertices: Vec<GpuVertex>,
+    indices: Vec<u32>,
+}
+
+impl GpuLayerMesh {
+    pub fn new(layer: &GerberLayer) -> Self {
+        Self::build(layer, None)
+    }
+
+    /// Builds the mesh of a negative layer, e.g. a soldermask layer, the area is filled and the primitives are drawn
+    /// with their exposure inverted, so openings show the layers below.
+    pub fn new_negative(layer: &GerberLayer, area: &NegativeArea) -> Self {
+        Self::build(layer, Some(area))
+    }
+
+    fn build(layer: &GerberLayer, negative: Option<&NegativeArea>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let triangles = Triangles::new(layer, negative);
+        let vertices = triangles
+            .positions
+            .iter()
+            .zip(&triangles.exposures)
+            .map(|(position, exposure)| GpuVertex {
+                position: [position.x as f32, position.y as f32],
+                exposure: match exposure {
+                    Exposure::Add => 1.0,
+                    Exposure::CutOut => 0.0,
+                },
+            })
+            .collect();
+
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            vertices,
+            indices: triangles.indices,
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+/// Renders layers on the GPU using wgpu, as an alternative to [`crate::GerberRenderer`] for large boards.
+///
+/// The geometry of each layer is uploaded once, see [`GpuLayerMesh`], after that each frame only updates the transform,
+/// so the cost of a frame no longer depends on the number of vertices on the CPU.
+///
+/// Each layer is first rendered into an offscreen texture, where clear (cut-out) primitives erase the dark primitives
+/// drawn before them, the texture is then blended over the layers below, so negative planes and macro cut-outs are
+/// see-through.  The texture is blended using a [`BlendMode`].
+///
+/// Call [`GpuRenderer::register`] once, e.g. when creating the app, then [`GpuRenderer::paint_layer`] each frame.
+/// The shape numbering, vertex numbering, unique shape colors, net highlighting, wireframe mode, minimum stroke width
+/// and draw order of [`crate::RenderConfiguration`] are not supported.
+#[derive(Default)]
+pub struct GpuRenderer {}
+
+impl GpuRenderer {
+    /// Creates the pipeline, using the format of the egui render target, e.g. from `eframe::CreationContext`.
+    pub fn register(render_state: &RenderState) {
+        let device = &render_state.device;
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("gerber_layer"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gerber_layer"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("gerber_layer"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("gerber_layer"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: size_of::<GpuVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: render_state.target_format,
+                    // replaces the color, so clear primitives erase the alpha
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let composite_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("gerber_layer_composite"),
+            source: wgpu::ShaderSource::Wgsl(COMPOSITE_SHADER.into()),
+        });
+
+        let composite_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gerber_layer_composite"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float {
+                        filterable: false,
+                    },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let composite_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("gerber_layer_composite"),
+            bind_group_layouts: &[&composite_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let composite_pipelines = BlendMode::ALL
+            .into_iter()
+            .map(|blend_mode| {
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("gerber_layer_composite"),
+                    layout: Some(&composite_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &composite_shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &composite_shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: render_state.target_format,
+                            blend: Some(blend_state(blend_mode)),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                });
+                (blend_mode, pipeline)
+            })
+            .collect();
+
+        render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(GpuResources {
+                pipeline,
+                bind_group_layout,
+                composite_pipelines,
+                composite_bind_group_layout,
+                target_format: render_state.target_format,
+                meshes: HashMap::new(),
+                callbacks: vec![],
+                prepared_callbacks: 0,
+                frame_prepared: false,
+            });
+    }
+
+    /// Paints the layer, the mesh is uploaded the first time it's painted and released when it's dropped.
+    ///
+    /// Each paint has its own transform and offscreen texture, so a mesh can be painted more than once per frame, e.g.
+    /// in a minimap or in several viewports.
+    ///
+    /// `opacity`, from 0.0 to 1.0, is applied in addition to the alpha of the `color`, e.g. for a layer transparency
+    /// slider.  Since the layer is rendered offscreen first, overlapping primitives have the same opacity.
+    #[allow(clippy::too_many_arguments)]
+    pub fn paint_layer(
+        &self,
+        painter: &Painter,
+        view: ViewState,
+        layer: &GerberLayer,
+        mesh: &Arc<GpuLayerMesh>,
+        color: Color32,
+        opacity: f32,
+        blend_mode: BlendMode,
+        transform: &GerberTransform,
+    ) {
+        let rect = painter.clip_rect();
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
+
+        let gerber = transform
+            .resolve(layer.bounding_box())
+            .to_matrix();
+
+        // same as the egui renderer, y is flipped, see `Matrix3Pos2Ext::transform_pos2`
+        let (scale, translation) = (view.scale as f64, view.translation);
+        let screen = Matrix3::new(
+            scale,
+            0.0,
+            translation.x as f64,
+            0.0,
+            -scale,
+            translation.y as f64,
+            0.0,
+            0.0,
+            1.0,
+        );
+
+        // the viewport of the callback is the clip rect
+        let (width, height) = (rect.width() as f64, rect.height() as f64);
+        let clip = Matrix3::new(
+            2.0 / width,
+            0.0,
+            -1.0 - 2.0 * rect.min.x as f64 / width,
+            0.0,
+            -2.0 / height,
+            1.0 + 2.0 * rect.min.y as f64 / height,
+            0.0,
+            0.0,
+            1.0,
+        );
+
+        let m = clip * screen * gerber;
+        let uniforms = Uniforms {
+            transform: [
+                [m[(0, 0)] as f32, m[(1, 0)] as f32, 0.0, 0.0],
+                [m[(0, 1)] as f32, m[(1, 1)] as f32, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [m[(0, 2)] as f32, m[(1, 2)] as f32, 0.0, 1.0],
+            ],
+            color: color
+                .gamma_multiply(opacity.clamp(0.0, 1.0))
+                .to_normalized_gamma_f32(),
+        };
+
+        let pixels_per_point = painter.pixels_per_point();
+        let size = [
+            ((rect.width() * pixels_per_point).round() as u32).max(1),
+            ((rect.height() * pixels_per_point).round() as u32).max(1),
+        ];
+
+        painter.add(egui_wgpu::Callback::new_paint_callback(rect, LayerCallback {
+            mesh: mesh.clone(),
+            uniforms,
+            size,
+            blend_mode,
+            slot: AtomicUsize::new(usize::MAX),
+        }));
+    }
+}
+
+/// The blending of the premultiplied layer texture with the egui render target, the alpha is always blended normally.
+///
+/// Same as [`BlendMode::blend`].
+fn blend_state(blend_mode: BlendMode) -> wgpu::BlendState {
+    let (src_factor, dst_factor) = match blend_mode {
+        BlendMode::Normal => (wgpu::BlendFactor::One, wgpu::BlendFactor::OneMinusSrcAlpha),
+        BlendMode::Multiply => (wgpu::BlendFactor::Dst, wgpu::BlendFactor::OneMinusSrcAlpha),
+        BlendMode::Screen => (wgpu::BlendFactor::OneMinusDst, wgpu::BlendFactor::One),
+        BlendMode::Additive => (wgpu::BlendFactor::One, wgpu::BlendFactor::One),
+    };
+
+    wgpu::BlendState {
+        color: wgpu::BlendComponent {
+            src_factor,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        },
+        alpha: wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        },
+    }
+}
+
+struct GpuResources {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    composite_pipelines: HashMap<BlendMode, wgpu::RenderPipeline>,
+    composite_bind_group_layout: wgpu::BindGroupLayout,
+    /// Also used for the offscreen textures, so they can be copied without conversion.
+    target_format: wgpu::TextureFormat,
+    /// keyed by `GpuLayerMesh::id`, shared by all the paints of the mesh.
+    meshes: HashMap<u64, GpuMesh>,
+    /// One for each paint callback of a frame, in the order they are prepared, reused by the callbacks of the next
+    /// frame, see [`LayerCallback::slot`].
+    callbacks: Vec<CallbackSlot>,
+    /// The number of callbacks prepared in the current frame.
+    prepared_callbacks: usize,
+    /// Set when all the callbacks of a frame are prepared, the next prepared callback starts a new frame.
+    frame_prepared: bool,
+}
+
+struct GpuMesh {
+    /// Used to release the buffers when the mesh is dropped.
+    mesh: Weak<GpuLayerMesh>,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+}
+
+/// The uniforms and offscreen texture of a single paint of a mesh.
+struct CallbackSlot {
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// Created when the slot is first used, and re-created when the size of the viewport changes.
+    target: Option<LayerTarget>,
+}
+
+/// The offscreen texture the layer is rendered into before it's composited.
+struct LayerTarget {
+    /// In physical pixels.
+    size: [u32; 2],
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl LayerTarget {
+    fn new(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        size: [u32; 2],
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("gerber_layer_target"),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gerber_layer_composite"),
+            layout: bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+
+        Self {
+            size,
+            view,
+            bind_group,
+        }
+    }
+}
+
+struct LayerCallback {
+    mesh: Arc<GpuLayerMesh>,
+    uniforms: Uniforms,
+    /// The size of the viewport, in physical pixels.
+    size: [u32; 2],
+    blend_mode: BlendMode,
+    /// The index of the [`CallbackSlot`], assigned when the callback is prepared.
+    slot: AtomicUsize,
+}
+
+impl CallbackTrait for LayerCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let Some(resources) = callback_resources.get_mut::<GpuResources>() else {
+            log::error!("GpuRenderer::register was not called");
+            return vec![];
+        };
+
+        if resources.frame_prepared {
+            resources
+                .meshes
+                .retain(|_, mesh| mesh.mesh.strong_count() > 0);
+            resources.prepared_callbacks = 0;
+            resources.frame_prepared = false;
+        }
+
+        let GpuResources {
+            pipeline,
+            bind_group_layout,
+            composite_bind_group_layout,
+            target_format,
+            meshes,
+            callbacks,
+            prepared_callbacks,
+            ..
+        } = resources;
+        let mesh = meshes
+            .entry(self.mesh.id)
+            .or_insert_with(|| {
+                let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("gerber_layer_vertices"),
+                    contents: bytemuck::cast_slice(&self.mesh.vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                });
+                let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("gerber_layer_indices"),
+                    contents: bytemuck::cast_slice(&self.mesh.indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                });
+
+                GpuMesh {
+                    mesh: Arc::downgrade(&self.mesh),
+                    vertex_buffer,
+                    index_buffer,
+                    index_count: self.mesh.indices.len() as u32,
+                }
+            });
+
+        let slot_index = *prepared_callbacks;
+        *prepared_callbacks += 1;
+        self.slot
+            .store(slot_index, Ordering::Relaxed);
+        if slot_index == callbacks.len() {
+            let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("gerber_layer_uniforms"),
+                contents: bytemuck::bytes_of(&self.uniforms),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("gerber_layer"),
+                layout: bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }],
+            });
+            callbacks.push(CallbackSlot {
+                uniform_buffer,
+                bind_group,
+                target: None,
+            });
+        }
+        let slot = &mut callbacks[slot_index];
+
+        queue.write_buffer(&slot.uniform_buffer, 0, bytemuck::bytes_of(&self.uniforms));
+
+        if slot
+            .target
+            .as_ref()
+            .is_none_or(|target| target.size != self.size)
+        {
+            slot.target = Some(LayerTarget::new(
+                device,
+                composite_bind_group_layout,
+                *target_format,
+                self.size,
+            ));
+        }
+        let Some(target) = &slot.target else {
+            return vec![];
+        };
+
+        // the primitives are drawn in order, so clear primitives only erase the dark primitives before them
+        let mut render_pass = egui_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("gerber_layer"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if mesh.index_count > 0 {
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &slot.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.index_count, 0, 0..1);
+        }
+
+        vec![]
+    }
+
+    fn finish_prepare(
+        &self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        // called for each callback once all of them are prepared, the slots of callbacks that are no longer painted are
+        // released by the first call
+        if let Some(resources) = callback_resources.get_mut::<GpuResources>() {
+            if !resources.frame_prepared {
+                resources
+                    .callbacks
+                    .truncate(resources.prepared_callbacks);
+                resources.frame_prepared = true;
+            }
+        }
+
+        vec![]
+    }
+
+    fn paint(
+        &self,
+        _info: PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'static>,
+        callback_resources: &CallbackResources,
+    ) {
+        let Some(resources) = callback_resources.get::<GpuResources>() else {
+            return;
+        };
+        let Some(target) = resources
+            .callbacks
+            .get(self.slot.load(Ordering::Relaxed))
+            .and_then(|slot| slot.target.as_ref())
+        else {
+            return;
+        };
+
+        render_pass.set_pipeline(&resources.composite_pipelines[&self.blend_mode]);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
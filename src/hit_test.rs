@@ -0,0 +1,498 @@
+use std::f64::consts::TAU;
+use std::fmt::{Display, Formatter};
+
+use gerber_types::Unit;
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::{split_contours, BoundingBox};
+use crate::layer::{
+    ArcGerberPrimitive, CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive,
+    PolygonGerberPrimitive, RectangleGerberPrimitive,
+};
+use crate::Exposure;
+
+/// A copy of a primitive under a point, see [`GerberLayer::hovered_primitive`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrimitiveHit {
+    pub index: usize,
+    /// The offset of the step-and-repeat copy that was hit, zero for the primitive itself.
+    pub offset: Vector2<f64>,
+}
+
+/// The type and size of a primitive, in the units of the layer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PrimitiveShape {
+    Circle {
+        diameter: f64,
+    },
+    Rectangle {
+        width: f64,
+        height: f64,
+    },
+    Line {
+        width: f64,
+        length: f64,
+    },
+    /// The sweep angle is in radians.
+    Arc {
+        width: f64,
+        radius: f64,
+        sweep_angle: f64,
+    },
+    /// Regions and outline, polygon and thermal macro primitives, the size is the size of the bounding box.
+    Polygon {
+        width: f64,
+        height: f64,
+    },
+}
+
+/// Information about a primitive, e.g. for showing a tooltip for the primitive under the cursor, see
+/// [`GerberLayer::primitive_summary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrimitiveSummary {
+    pub index: usize,
+    pub shape: PrimitiveShape,
+    pub exposure: Exposure,
+    /// The D-code of the aperture used to create the primitive, `None` for regions.
+    pub aperture: Option<i32>,
+    /// The nets from the `.N` object attribute, empty if unknown.
+    pub nets: Vec<String>,
+    pub units: Option<Unit>,
+}
+
+impl Display for PrimitiveSummary {
+    /// One line for each of the shape, the aperture and the nets, e.g. `Line, width: 0.200 mm, length: 5.000 mm`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let units = match self.units {
+            Some(Unit::Millimeters) => " mm",
+            Some(Unit::Inches) => " in",
+            None => "",
+        };
+        let size = |name: &str, value: f64| format!("{}: {:.3}{}", name, value, units);
+
+        match self.shape {
+            PrimitiveShape::Circle {
+                diameter,
+            } => write!(f, "Circle, {}", size("diameter", diameter))?,
+            PrimitiveShape::Rectangle {
+                width,
+                height,
+            } => write!(f, "Rectangle, {}, {}", size("width", width), size("height", height))?,
+            PrimitiveShape::Line {
+                width,
+                length,
+            } => write!(f, "Line, {}, {}", size("width", width), size("length", length))?,
+            PrimitiveShape::Arc {
+                width,
+                radius,
+                sweep_angle,
+            } => write!(
+                f,
+                "Arc, {}, {}, sweep: {:.1}°",
+                size("width", width),
+                size("radius", radius),
+                sweep_angle.to_degrees()
+            )?,
+            PrimitiveShape::Polygon {
+                width,
+                height,
+            } => write!(f, "Polygon, {}, {}", size("width", width), size("height", height))?,
+        }
+        if self.exposure == Exposure::CutOut {
+            write!(f, " (clear)")?;
+        }
+
+        match self.aperture {
+            Some(aperture) => write!(f, "\nD{}", aperture)?,
+            None => write!(f, "\nRegion")?,
+        }
+
+        if !self.nets.is_empty() {
+            write!(f, "\nNet: {}", self.nets.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl GerberLayer {
+    /// Returns the indices of the primitives that contain the point, or are within `tolerance` of it, in drawing order.
+    ///
+    /// The point is in gerber coordinates.  Lines and arcs are tested using their stroke width, polygons are tested
+    /// using the even-odd rule, so points in the holes of regions created with cut-ins are not hits.  Primitives with
+    /// any exposure are returned, and each copy of a step-and-repeat primitive is tested.
+    ///
+    /// Only the candidates found using the spatial index are tested, see [`GerberLayer::query_region`].
+    pub fn hit_test(&self, point: Point2<f64>, tolerance: f64) -> Vec<usize> {
+        self.query_region(&square_around(point, tolerance))
+            .into_iter()
+            .filter(|index| {
+                let primitive = &self.gerber_primitives()[*index];
+                self.primitive_offsets(*index)
+                    .iter()
+                    .any(|offset| primitive.distance_to(point - *offset) <= tolerance)
+            })
+            .collect()
+    }
+
+    /// Returns the index of the primitive nearest to the point, and the distance to its edge, e.g. for snapping.
+    ///
+    /// The distance is zero if the point is inside the primitive.  See [`GerberLayer::hit_test`] for how primitives are
+    /// measured.  Returns `None` if the layer is empty.
+    pub fn nearest_primitive(&self, point: Point2<f64>) -> Option<(usize, f64)> {
+        let bbox = self.try_bounding_box()?;
+
+        // every primitive is within this distance
+        let max_radius = bbox
+            .vertices()
+            .iter()
+            .map(|corner| (*corner - point).norm())
+            .fold(0.0, f64::max);
+        let mut radius = match bbox.width().max(bbox.height()) / 64.0 {
+            radius if radius > 0.0 => radius.min(max_radius),
+            _ => max_radius,
+        };
+
+        // grow the search region until there are candidates
+        let nearest = loop {
+            let candidates = self.query_region(&square_around(point, radius));
+            if !candidates.is_empty() || radius >= max_radius {
+                break self.nearest_of(candidates, point)?;
+            }
+            radius *= 2.0;
+        };
+
+        // primitives outside the search region may be closer than the nearest candidate
+        self.nearest_of(self.query_region(&square_around(point, nearest.1)), point)
+    }
+
+    /// Returns the top-most primitive that contains the point, or is within `tolerance` of it, e.g. for highlighting the
+    /// primitive under the cursor.
+    ///
+    /// Primitives are drawn in order, so the top-most primitive is the last hit returned by [`GerberLayer::hit_test`],
+    /// which may be a clear primitive.  Of the step-and-repeat copies of the primitive, the last copy hit is returned.
+    pub fn hovered_primitive(&self, point: Point2<f64>, tolerance: f64) -> Option<PrimitiveHit> {
+        let index = *self.hit_test(point, tolerance).last()?;
+        let primitive = &self.gerber_primitives()[index];
+        self.primitive_offsets(index)
+            .into_iter()
+            .rev()
+            .find(|offset| primitive.distance_to(point - *offset) <= tolerance)
+            .map(|offset| PrimitiveHit {
+                index,
+                offset,
+            })
+    }
+
+    /// Returns the type, size, D-code and nets of the primitive at `index`, e.g. for a tooltip.
+    pub fn primitive_summary(&self, index: usize) -> Option<PrimitiveSummary> {
+        let primitive = self.gerber_primitives().get(index)?;
+        let shape = match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                diameter, ..
+            }) => PrimitiveShape::Circle {
+                diameter: *diameter,
+            },
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                width,
+                height,
+                ..
+            }) => PrimitiveShape::Rectangle {
+                width: *width,
+                height: *height,
+            },
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => PrimitiveShape::Line {
+                width: *width,
+                length: (end - start).norm(),
+            },
+            GerberPrimitive::Arc(arc) => PrimitiveShape::Arc {
+                width: arc.width,
+                radius: arc.radius,
+                sweep_angle: arc.sweep_angle,
+            },
+            GerberPrimitive::Polygon(_) => {
+                let bbox = primitive.bounding_box();
+                PrimitiveShape::Polygon {
+                    width: bbox.width(),
+                    height: bbox.height(),
+                }
+            }
+        };
+
+        Some(PrimitiveSummary {
+            index,
+            shape,
+            exposure: primitive.exposure(),
+            aperture: self
+                .primitive_info(index)
+                .and_then(|info| info.aperture),
+            nets: self
+                .primitive_attributes(index)
+                .map(|attributes| {
+                    attributes
+                        .nets()
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            units: self.units(),
+        })
+    }
+
+    fn nearest_of(&self, candidates: Vec<usize>, point: Point2<f64>) -> Option<(usize, f64)> {
+        candidates
+            .into_iter()
+            .map(|index| {
+                let primitive = &self.gerber_primitives()[index];
+                let distance = self
+                    .primitive_offsets(index)
+                    .iter()
+                    .map(|offset| primitive.distance_to(point - *offset))
+                    .fold(f64::MAX, f64::min);
+                (index, distance)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+}
+
+pub(crate) fn square_around(point: Point2<f64>, half_size: f64) -> BoundingBox {
+    BoundingBox {
+        min: point,
+        max: point,
+    }
+    .inflate(half_size)
+}
+
+impl GerberPrimitive {
+    /// The distance from the point to the edge of the primitive, zero if the point is inside the primitive.
+    pub(crate) fn distance_to(&self, point: Point2<f64>) -> f64 {
+        let distance = match self {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => (point - *center).norm() - diameter / 2.0,
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let dx = (origin.x - point.x).max(point.x - (origin.x + width));
+                let dy = (origin.y - point.y).max(point.y - (origin.y + height));
+                Vector2::new(dx.max(0.0), dy.max(0.0)).norm()
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => distance_to_segment(point, *start, *end) - width / 2.0,
+            GerberPrimitive::Arc(arc) => distance_to_arc(point, arc) - arc.width / 2.0,
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let relative = Point2::from(point - *center);
+                distance_to_polygon(relative, &geometry.relative_vertices)
+            }
+        };
+
+        distance.max(0.0)
+    }
+}
+
+pub(crate) fn distance_to_segment(point: Point2<f64>, start: Point2<f64>, end: Point2<f64>) -> f64 {
+    let segment = end - start;
+    let length_squared = segment.norm_squared();
+    if length_squared == 0.0 {
+        return (point - start).norm();
+    }
+
+    let t = ((point - start).dot(&segment) / length_squared).clamp(0.0, 1.0);
+    (point - (start + segment * t)).norm()
+}
+
+/// The distance from the point to the center-line of the arc.
+fn distance_to_arc(point: Point2<f64>, arc: &ArcGerberPrimitive) -> f64 {
+    let relative = point - arc.center;
+    let distance_to_circle = (relative.norm() - arc.radius).abs();
+    if arc.is_full_circle() {
+        return distance_to_circle;
+    }
+
+    // the angle of the point, measured from the start of the arc in the direction of the sweep
+    let angle = relative.y.atan2(relative.x);
+    let along_sweep = match arc.sweep_angle >= 0.0 {
+        true => (angle - arc.start_angle).rem_euclid(TAU),
+        false => (arc.start_angle - angle).rem_euclid(TAU),
+    };
+    if along_sweep <= arc.sweep_angle.abs() {
+        return distance_to_circle;
+    }
+
+    let point_at = |angle: f64| arc.center + Vector2::new(angle.cos(), angle.sin()) * arc.radius;
+    let start = point_at(arc.start_angle);
+    let end = point_at(arc.start_angle + arc.sweep_angle);
+    (point - start)
+        .norm()
+        .min((point - end).norm())
+}
+
+/// Zero if the point is inside the polygon, using the even-odd rule on the contours of the polygon.
+fn distance_to_polygon(point: Point2<f64>, vertices: &[Point2<f64>]) -> f64 {
+    let contours = split_contours(vertices);
+
+    let mut inside = false;
+    let mut distance = f64::MAX;
+    for contour in &contours {
+        for (index, start) in contour.iter().enumerate() {
+            let end = contour[(index + 1) % contour.len()];
+            distance = distance.min(distance_to_segment(point, *start, end));
+
+            if (start.y > point.y) != (end.y > point.y)
+                && point.x < start.x + (point.y - start.y) * (end.x - start.x) / (end.y - start.y)
+            {
+                inside = !inside;
+            }
+        }
+    }
+
+    match inside {
+        true => 0.0,
+        false => distance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use gerber_types::{Aperture, Circle, Unit};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::layer::GerberPolygon;
+    use crate::testing::{aperture, flash, header};
+
+    #[rstest]
+    #[case(Point2::new(0.5, 0.0), 0.0)]
+    #[case(Point2::new(2.0, 0.0), 1.0)]
+    fn test_circle_distance(#[case] point: Point2<f64>, #[case] expected: f64) {
+        // given
+        let primitive = GerberPrimitive::Circle(CircleGerberPrimitive {
+            center: Point2::new(0.0, 0.0),
+            diameter: 2.0,
+            exposure: Exposure::Add,
+        });
+
+        // expect
+        assert_eq!(primitive.distance_to(point), expected);
+    }
+
+    #[rstest]
+    // on the arc
+    #[case(Point2::new(0.0, 1.0), 0.0)]
+    // outside the sweep, closest to the start
+    #[case(Point2::new(1.0, -1.0), 1.0)]
+    fn test_arc_distance(#[case] point: Point2<f64>, #[case] expected: f64) {
+        // given
+        // quarter circle, from 0 to 90 degrees, counter-clockwise
+        let primitive = GerberPrimitive::Arc(ArcGerberPrimitive {
+            center: Point2::new(0.0, 0.0),
+            radius: 1.0,
+            width: 0.0,
+            start_angle: 0.0,
+            sweep_angle: PI / 2.0,
+            exposure: Exposure::Add,
+            tolerance: None,
+        });
+
+        // expect
+        assert!((primitive.distance_to(point) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_nearest_primitive() {
+        // given
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(2.0))));
+        commands.extend([(0.0, 0.0), (50.0, 0.0), (10.0, 10.0)].map(|(x, y)| flash(x, y)));
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let nearest = layer.nearest_primitive(Point2::new(10.0, 5.0));
+
+        // then
+        let (index, distance) = nearest.unwrap();
+        assert_eq!(index, 2);
+        assert!((distance - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_hovered_primitive() {
+        // given
+        // two overlapping flashes, the second is drawn on top of the first
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(2.0))));
+        commands.extend([(0.0, 0.0), (1.0, 0.0)].map(|(x, y)| flash(x, y)));
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let hit = layer.hovered_primitive(Point2::new(0.5, 0.0), 0.0);
+
+        // then
+        assert_eq!(
+            hit,
+            Some(PrimitiveHit {
+                index: 1,
+                offset: Vector2::new(0.0, 0.0),
+            })
+        );
+
+        // and
+        let summary = layer.primitive_summary(1).unwrap();
+        assert_eq!(summary.shape, PrimitiveShape::Circle {
+            diameter: 2.0
+        });
+        assert_eq!(summary.aperture, Some(10));
+        assert_eq!(summary.to_string(), "Circle, diameter: 2.000 mm\nD10");
+    }
+
+    #[rstest]
+    #[case(Point2::new(1.0, 1.0), true)]
+    // in the hole
+    #[case(Point2::new(5.0, 5.0), false)]
+    fn test_polygon_with_hole_hit(#[case] point: Point2<f64>, #[case] expected: bool) {
+        // given
+        // a 10x10 square with a 2x2 hole, the cut-in is from (5,0) to (5,4)
+        let vertices = vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(5.0, 0.0),
+            Point2::new(5.0, 4.0),
+            Point2::new(4.0, 4.0),
+            Point2::new(4.0, 6.0),
+            Point2::new(6.0, 6.0),
+            Point2::new(6.0, 4.0),
+            Point2::new(5.0, 4.0),
+            Point2::new(5.0, 0.0),
+            Point2::new(10.0, 0.0),
+            Point2::new(10.0, 10.0),
+            Point2::new(0.0, 10.0),
+        ];
+        let primitive = GerberPrimitive::new_polygon(GerberPolygon {
+            center: Point2::new(0.0, 0.0),
+            vertices,
+            exposure: Exposure::Add,
+        });
+
+        // expect
+        assert_eq!(primitive.distance_to(point) == 0.0, expected);
+    }
+}
@@ -0,0 +1,224 @@
+use std::f64::consts::PI;
+
+use gerber_types::{Command, ExtendedCode, ImageMirroring};
+use nalgebra::{Point2, Vector2};
+
+use crate::layer::{
+    ArcGerberPrimitive, CircleGerberPrimitive, GerberPolygon, GerberPrimitive, LineGerberPrimitive,
+    PolygonGerberPrimitive, RectangleGerberPrimitive,
+};
+
+/// The image transform from the legacy Mirror Image (MI), Scale Factor (SF) and Offset (OF) commands.
+///
+/// These commands were deprecated in 2012, but are still found in files from older CAM software. They apply to the
+/// whole image, i.e. to all the primitives regardless of where the commands are in the file.
+///
+/// The transform is applied in the order; scale, mirror, offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ImageTransform {
+    pub mirror_a: bool,
+    pub mirror_b: bool,
+    pub scale: Vector2<f64>,
+    pub offset: Vector2<f64>,
+}
+
+impl Default for ImageTransform {
+    fn default() -> Self {
+        Self {
+            mirror_a: false,
+            mirror_b: false,
+            scale: Vector2::new(1.0, 1.0),
+            offset: Vector2::new(0.0, 0.0),
+        }
+    }
+}
+
+impl ImageTransform {
+    /// If a command occurs more than once, the last one is used.
+    pub(crate) fn from_commands(commands: &[Command]) -> Self {
+        let mut transform = Self::default();
+        for command in commands {
+            match command {
+                Command::ExtendedCode(ExtendedCode::MirrorImage(mirroring)) => {
+                    (transform.mirror_a, transform.mirror_b) = match mirroring {
+                        ImageMirroring::None => (false, false),
+                        ImageMirroring::A => (true, false),
+                        ImageMirroring::B => (false, true),
+                        ImageMirroring::AB => (true, true),
+                    };
+                }
+                Command::ExtendedCode(ExtendedCode::ScaleImage(scaling)) => {
+                    transform.scale = Vector2::new(scaling.a, scaling.b);
+                }
+                Command::ExtendedCode(ExtendedCode::OffsetImage(offset)) => {
+                    transform.offset = Vector2::new(offset.a, offset.b);
+                }
+                _ => {}
+            }
+        }
+        transform
+    }
+
+    pub(crate) fn is_identity(&self) -> bool {
+        self.eq(&Self::default())
+    }
+
+    /// Applies the scale and mirroring, but not the offset, e.g. for relative positions.
+    pub(crate) fn apply_to_vector(&self, vector: Vector2<f64>) -> Vector2<f64> {
+        Vector2::new(
+            vector.x * self.scale.x * self.mirror_sign_a(),
+            vector.y * self.scale.y * self.mirror_sign_b(),
+        )
+    }
+
+    pub(crate) fn apply_to_point(&self, point: Point2<f64>) -> Point2<f64> {
+        Point2::from(self.apply_to_vector(point.coords)) + self.offset
+    }
+
+    pub(crate) fn apply(&self, primitive: &mut GerberPrimitive) {
+        // sizes, e.g. diameters and widths, can't be scaled non-uniformly, use the geometric mean of the scale factors.
+        let size_factor = (self.scale.x * self.scale.y)
+            .abs()
+            .sqrt();
+
+        match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => {
+                *center = self.apply_to_point(*center);
+                *diameter *= size_factor;
+            }
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                // the origin is the minimum corner, which may no-longer be the minimum corner after mirroring.
+                let a = self.apply_to_point(*origin);
+                let b = self.apply_to_point(*origin + Vector2::new(*width, *height));
+                *origin = Point2::new(a.x.min(b.x), a.y.min(b.y));
+                *width = (b.x - a.x).abs();
+                *height = (b.y - a.y).abs();
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => {
+                *start = self.apply_to_point(*start);
+                *end = self.apply_to_point(*end);
+                *width *= size_factor;
+            }
+            GerberPrimitive::Arc(ArcGerberPrimitive {
+                center,
+                radius,
+                width,
+                start_angle,
+                sweep_angle,
+                ..
+            }) => {
+                *center = self.apply_to_point(*center);
+                *radius *= size_factor;
+                *width *= size_factor;
+                if self.mirror_a {
+                    *start_angle = PI - *start_angle;
+                    *sweep_angle = -*sweep_angle;
+                }
+                if self.mirror_b {
+                    *start_angle = -*start_angle;
+                    *sweep_angle = -*sweep_angle;
+                }
+            }
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                exposure,
+                geometry,
+            }) => {
+                let vertices = geometry
+                    .relative_vertices
+                    .iter()
+                    .map(|vertex| Point2::from(self.apply_to_vector(vertex.coords)))
+                    .collect();
+
+                // rebuilt, so that the winding, convexity and tessellation are updated
+                *primitive = GerberPrimitive::new_polygon(GerberPolygon {
+                    center: self.apply_to_point(*center),
+                    vertices,
+                    exposure: *exposure,
+                });
+            }
+        }
+    }
+
+    fn mirror_sign_a(&self) -> f64 {
+        match self.mirror_a {
+            true => -1.0,
+            false => 1.0,
+        }
+    }
+
+    fn mirror_sign_b(&self) -> f64 {
+        match self.mirror_b {
+            true => -1.0,
+            false => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{ImageOffset, ImageScaling};
+
+    use super::*;
+
+    #[test]
+    fn test_from_commands() {
+        // given
+        let commands = vec![
+            Command::ExtendedCode(ExtendedCode::MirrorImage(ImageMirroring::A)),
+            Command::ExtendedCode(ExtendedCode::ScaleImage(ImageScaling {
+                a: 2.0,
+                b: 2.0,
+            })),
+            Command::ExtendedCode(ExtendedCode::OffsetImage(ImageOffset {
+                a: 1.0,
+                b: 0.5,
+            })),
+        ];
+
+        // when
+        let transform = ImageTransform::from_commands(&commands);
+
+        // then
+        assert_eq!(transform.apply_to_point(Point2::new(1.0, 1.0)), Point2::new(-1.0, 2.5));
+    }
+
+    #[test]
+    fn test_mirrored_rectangle_origin_is_minimum_corner() {
+        // given
+        let transform = ImageTransform {
+            mirror_a: true,
+            ..ImageTransform::default()
+        };
+        let mut primitive = GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+            origin: Point2::new(1.0, 1.0),
+            width: 2.0,
+            height: 1.0,
+            exposure: crate::types::Exposure::Add,
+        });
+
+        // when
+        transform.apply(&mut primitive);
+
+        // then
+        let GerberPrimitive::Rectangle(rectangle) = primitive else {
+            panic!("expected a rectangle");
+        };
+        assert_eq!(rectangle.origin, Point2::new(-3.0, 1.0));
+        assert_eq!((rectangle.width, rectangle.height), (2.0, 1.0));
+    }
+}
@@ -0,0 +1,145 @@
+use egui::{CollapsingHeader, Grid, Response, Ui};
+use gerber_types::Unit;
+
+use crate::{Attributes, Exposure, GerberLayer, PrimitiveHit, PrimitiveShape};
+
+/// Shows the details of a primitive, e.g. the primitive selected by clicking, see
+/// [`GerberLayer::hovered_primitive`]: its type and dimensions, position, aperture D-code, nets, attributes and the
+/// index of the command that created it.
+///
+/// Create one each frame, e.g.
+/// ```ignore
+/// ObjectInspector::new(&layer, self.selected).show(ui);
+/// ```
+pub struct ObjectInspector<'a> {
+    layer: &'a GerberLayer,
+    hit: Option<PrimitiveHit>,
+}
+
+impl<'a> ObjectInspector<'a> {
+    /// With `None`, or an index that's not a primitive of the layer, a placeholder is shown.
+    pub fn new(layer: &'a GerberLayer, hit: Option<PrimitiveHit>) -> Self {
+        Self {
+            layer,
+            hit,
+        }
+    }
+
+    pub fn show(self, ui: &mut Ui) -> Response {
+        let Self {
+            layer,
+            hit,
+        } = self;
+
+        let Some((hit, summary)) = hit.and_then(|hit| Some((hit, layer.primitive_summary(hit.index)?))) else {
+            return ui.weak("Nothing selected");
+        };
+
+        let units = match summary.units {
+            Some(Unit::Millimeters) => " mm",
+            Some(Unit::Inches) => " in",
+            None => "",
+        };
+        let length = |value: f64| format!("{:.3}{}", value, units);
+
+        let (shape, dimensions) = match summary.shape {
+            PrimitiveShape::Circle {
+                diameter,
+            } => ("Circle", vec![("Diameter", length(diameter))]),
+            PrimitiveShape::Rectangle {
+                width,
+                height,
+            } => ("Rectangle", vec![("Width", length(width)), ("Height", length(height))]),
+            PrimitiveShape::Line {
+                width,
+                length: line_length,
+            } => ("Line", vec![("Width", length(width)), ("Length", length(line_length))]),
+            PrimitiveShape::Arc {
+                width,
+                radius,
+                sweep_angle,
+            } => ("Arc", vec![
+                ("Width", length(width)),
+                ("Radius", length(radius)),
+                ("Sweep", format!("{:.1}°", sweep_angle.to_degrees())),
+            ]),
+            PrimitiveShape::Polygon {
+                width,
+                height,
+            } => ("Polygon", vec![("Width", length(width)), ("Height", length(height))]),
+        };
+        let center = layer.gerber_primitives()[hit.index]
+            .bounding_box()
+            .center()
+            + hit.offset;
+
+        let response = Grid::new(ui.id().with("object_inspector"))
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let mut row = |name: &str, value: String| {
+                    ui.label(name);
+                    ui.label(value);
+                    ui.end_row();
+                };
+
+                row("Primitive", format!("#{}", hit.index));
+                row("Type", shape.to_string());
+                for (name, value) in dimensions {
+                    row(name, value);
+                }
+                row("Center", format!("{}, {}", length(center.x), length(center.y)));
+                if hit.offset.x != 0.0 || hit.offset.y != 0.0 {
+                    row(
+                        "Repeat offset",
+                        format!("{}, {}", length(hit.offset.x), length(hit.offset.y)),
+                    );
+                }
+                row(
+                    "Polarity",
+                    match summary.exposure {
+                        Exposure::Add => "Dark",
+                        Exposure::CutOut => "Clear",
+                    }
+                    .to_string(),
+                );
+                row(
+                    "Aperture",
+                    summary
+                        .aperture
+                        .map(|code| format!("D{}", code))
+                        .unwrap_or_else(|| "Region".to_string()),
+                );
+                if let Some(info) = layer.primitive_info(hit.index) {
+                    row("Command", format!("#{}", info.source_index));
+                }
+                if !summary.nets.is_empty() {
+                    row("Net", summary.nets.join(", "));
+                }
+            })
+            .response;
+
+        if let Some(attributes) = layer
+            .primitive_attributes(hit.index)
+            .filter(|attributes| !attributes.is_empty())
+        {
+            CollapsingHeader::new("Attributes")
+                .id_salt(
+                    ui.id()
+                        .with("object_inspector_attributes"),
+                )
+                .show(ui, |ui| {
+                    show_attributes(ui, "Object", &attributes.object);
+                    show_attributes(ui, "Aperture", &attributes.aperture);
+                });
+        }
+
+        response
+    }
+}
+
+fn show_attributes(ui: &mut Ui, kind: &str, attributes: &Attributes) {
+    for attribute in attributes.iter() {
+        ui.label(format!("{} {}: {}", kind, attribute.name, attribute.values.join(", ")));
+    }
+}
@@ -0,0 +1,711 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use gerber_types::Unit;
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::{split_contours, GerberTransform};
+use crate::layer::{
+    ArcGerberPrimitive, CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive,
+    PolygonGerberPrimitive, RectangleGerberPrimitive,
+};
+use crate::{unit_conversion_factor, BoardSide, Exposure, LayerSet, LayerType};
+
+/// Options for [`LayerSet::export_ipc2581`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ipc2581Options {
+    /// The name of the step, i.e. the board.
+    pub name: String,
+    /// The units of the file.
+    pub units: Unit,
+    /// The thickness of the board, in the units of the file, for the stackup.
+    pub board_thickness: f64,
+    /// The thickness of each copper layer, in the units of the file, for the stackup.
+    pub copper_thickness: f64,
+    /// The maximum gap between the lines and arcs of the outline layer, see [`GerberLayer::board_outline`].
+    pub outline_tolerance: f64,
+}
+
+impl Default for Ipc2581Options {
+    fn default() -> Self {
+        Self {
+            name: "board".to_string(),
+            units: Unit::Millimeters,
+            board_thickness: 1.6,
+            copper_thickness: 0.035,
+            outline_tolerance: 0.01,
+        }
+    }
+}
+
+impl LayerSet {
+    /// Exports the layers as a minimal IPC-2581 (revision C) file, e.g. to hand a board assembled from gerbers to a CAM
+    /// system.
+    ///
+    /// The file contains:
+    /// * A layer for each layer of the set, with its function and side, from the [`LayerType`].
+    /// * A stackup of the copper layers, top to bottom, with dielectric layers between them that share the rest of the
+    ///   board thickness, and the soldermask, silkscreen and paste layers of each side.
+    /// * The profile of the board, the contours of the outline layer, or the bounding box of the layers when there is
+    ///   no outline layer, or the outline has no closed contours.
+    /// * The holes of the drill layers, from their tool tables, see [`GerberLayer::drill_tools`], slots are not exported.
+    /// * The features of the other layers, flashed circles and rectangles are pads, using the dictionary of standard
+    ///   primitives, lines and arcs are exported along their center-lines with their width, regions and macro
+    ///   primitives as contours.  Step-and-repeat blocks are expanded, clear primitives are in negative sets.
+    ///
+    /// The transform of each layer is applied, see [`crate::LayerInfo::transform`], rotated rectangular pads get an
+    /// `Xform`, the opacity is not used.  Layers without units are assumed to be in the units of the file.
+    pub fn export_ipc2581(&self, options: &Ipc2581Options) -> String {
+        let units = match options.units {
+            Unit::Millimeters => "MILLIMETER",
+            Unit::Inches => "INCH",
+        };
+        let names = unique_names(self);
+
+        let mut dictionary = Dictionary::default();
+        let mut layer_features = String::new();
+        for (info, name) in self.iter().zip(&names) {
+            let mut writer = FeatureWriter {
+                output: String::new(),
+                dictionary: &mut dictionary,
+                scale: scale_of(&info.layer, options.units),
+                transform: info.resolved_transform(),
+            };
+            match info.layer_type {
+                LayerType::Drill => writer.holes(&info.layer),
+                LayerType::Outline => continue,
+                _ => writer.features(&info.layer),
+            }
+            if !writer.output.is_empty() {
+                let _ = writeln!(layer_features, "        <LayerFeature layerRef=\"{}\">", escape(name));
+                layer_features.push_str(&writer.output);
+                layer_features.push_str("        </LayerFeature>\n");
+            }
+        }
+
+        let layers = self.iter().collect::<Vec<_>>();
+        let stack = stack_order(self);
+        let copper_count = stack
+            .iter()
+            .filter(|index| is_copper(layers[**index].layer_type))
+            .count();
+        let dielectric_thickness = match copper_count > 1 {
+            true => ((options.board_thickness - options.copper_thickness * copper_count as f64)
+                / (copper_count - 1) as f64)
+                .max(0.0),
+            false => options.board_thickness,
+        };
+
+        let mut output = String::new();
+        output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str("<IPC-2581 revision=\"C\" xmlns=\"http://webstds.ipc.org/2581\">\n");
+
+        output.push_str("  <Content roleRef=\"Owner\">\n");
+        output.push_str("    <FunctionMode mode=\"FABRICATION\"/>\n");
+        let _ = writeln!(output, "    <StepRef name=\"{}\"/>", escape(&options.name));
+        for name in &names {
+            let _ = writeln!(output, "    <LayerRef name=\"{}\"/>", escape(name));
+        }
+        for index in 1..copper_count {
+            let _ = writeln!(output, "    <LayerRef name=\"dielectric_{index}\"/>");
+        }
+        let _ = writeln!(output, "    <DictionaryStandard units=\"{units}\">");
+        output.push_str(&dictionary.entries);
+        output.push_str("    </DictionaryStandard>\n");
+        output.push_str("  </Content>\n");
+
+        output.push_str("  <LogisticHeader>\n");
+        output.push_str("    <Role id=\"Owner\" roleFunction=\"SENDER\"/>\n");
+        output.push_str("    <Enterprise id=\"Enterprise\" code=\"NONE\"/>\n");
+        output.push_str("    <Person name=\"Owner\" enterpriseRef=\"Enterprise\" roleRef=\"Owner\"/>\n");
+        output.push_str("  </LogisticHeader>\n");
+
+        let _ = writeln!(output, "  <Ecad name=\"{}\">", escape(&options.name));
+        let _ = writeln!(output, "    <CadHeader units=\"{units}\"/>");
+        output.push_str("    <CadData>\n");
+        for (info, name) in self.iter().zip(&names) {
+            let (function, side) = layer_function(info.layer_type);
+            let _ = writeln!(
+                output,
+                "      <Layer name=\"{}\" layerFunction=\"{function}\" side=\"{side}\" polarity=\"POSITIVE\"/>",
+                escape(name)
+            );
+        }
+        for index in 1..copper_count {
+            let _ = writeln!(
+                output,
+                "      <Layer name=\"dielectric_{index}\" layerFunction=\"DIELCORE\" side=\"INTERNAL\" polarity=\"POSITIVE\"/>"
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "      <Stackup name=\"stackup\" overallThickness=\"{}\" whereMeasured=\"METAL\" tolPlus=\"0\" tolMinus=\"0\">",
+            number(options.board_thickness)
+        );
+        let _ = writeln!(
+            output,
+            "        <StackupGroup name=\"group\" thickness=\"{}\" tolPlus=\"0\" tolMinus=\"0\">",
+            number(options.board_thickness)
+        );
+        let mut sequence = 0;
+        let mut coppers = 0;
+        for index in stack {
+            let copper = is_copper(layers[index].layer_type);
+            let thickness = match copper {
+                true => options.copper_thickness,
+                false => 0.0,
+            };
+            sequence += 1;
+            let _ = writeln!(
+                output,
+                "          <StackupLayer layerOrGroupRef=\"{}\" thickness=\"{}\" tolPlus=\"0\" tolMinus=\"0\" sequence=\"{sequence}\"/>",
+                escape(&names[index]),
+                number(thickness)
+            );
+            if copper {
+                coppers += 1;
+                if coppers < copper_count {
+                    sequence += 1;
+                    let _ = writeln!(
+                        output,
+                        "          <StackupLayer layerOrGroupRef=\"dielectric_{coppers}\" thickness=\"{}\" tolPlus=\"0\" tolMinus=\"0\" sequence=\"{sequence}\"/>",
+                        number(dielectric_thickness)
+                    );
+                }
+            }
+        }
+        output.push_str("        </StackupGroup>\n");
+        output.push_str("      </Stackup>\n");
+
+        let _ = writeln!(output, "      <Step name=\"{}\">", escape(&options.name));
+        output.push_str("        <Datum x=\"0\" y=\"0\"/>\n");
+        output.push_str(&self.profile(options));
+        output.push_str(&layer_features);
+        output.push_str("      </Step>\n");
+        output.push_str("    </CadData>\n");
+        output.push_str("  </Ecad>\n");
+        output.push_str("</IPC-2581>\n");
+
+        output
+    }
+
+    /// The contours of the outline layer, or the bounding box of the layers.
+    fn profile(&self, options: &Ipc2581Options) -> String {
+        let (mut contours, mut scale) = match self.get(LayerType::Outline) {
+            Some(info) => {
+                let transform = info.resolved_transform();
+                let contours = info
+                    .layer
+                    .board_outline(options.outline_tolerance)
+                    .contours
+                    .into_iter()
+                    .map(|contour| {
+                        contour
+                            .into_iter()
+                            .map(|point| transform.apply_to_position(point))
+                            .collect()
+                    })
+                    .collect();
+                (contours, scale_of(&info.layer, options.units))
+            }
+            None => (vec![], 1.0),
+        };
+        if contours.is_empty() {
+            // after the transform of each layer
+            let bounds = self.bounding_box();
+            if bounds.is_empty() {
+                return String::new();
+            }
+            contours = vec![vec![
+                bounds.min,
+                Point2::new(bounds.max.x, bounds.min.y),
+                bounds.max,
+                Point2::new(bounds.min.x, bounds.max.y),
+            ]];
+            scale = self
+                .iter()
+                .find(|info| !info.layer.is_empty())
+                .map_or(1.0, |info| scale_of(&info.layer, options.units));
+        }
+
+        let mut output = String::new();
+        output.push_str("        <Profile>\n");
+        for (index, contour) in contours.iter().enumerate() {
+            let element = match index {
+                0 => "Polygon",
+                _ => "Cutout",
+            };
+            write_polygon(&mut output, element, contour, scale, "          ");
+        }
+        output.push_str("        </Profile>\n");
+
+        output
+    }
+}
+
+/// The standard primitives used by the pads, e.g. `<Circle diameter="0.5"/>`, each with a unique id.
+#[derive(Default)]
+struct Dictionary {
+    entries: String,
+    /// The XML of each primitive -> its id.
+    ids: HashMap<String, String>,
+}
+
+impl Dictionary {
+    /// Returns the id of the primitive, adding it if needed.
+    fn id(&mut self, primitive: String) -> String {
+        if let Some(id) = self.ids.get(&primitive) {
+            return id.clone();
+        }
+        let id = format!("PRIMITIVE_{}", self.ids.len() + 1);
+        let _ = writeln!(
+            self.entries,
+            "      <EntryStandard id=\"{id}\">{primitive}</EntryStandard>"
+        );
+        self.ids.insert(primitive, id.clone());
+        id
+    }
+}
+
+struct FeatureWriter<'a> {
+    output: String,
+    dictionary: &'a mut Dictionary,
+    /// Layer units to file units.
+    scale: f64,
+    /// The transform of the layer, resolved.
+    transform: GerberTransform,
+}
+
+impl FeatureWriter<'_> {
+    const INDENT: &'static str = "          ";
+
+    /// A position in the layer, in file units, after the transform of the layer.
+    fn position(&self, point: Point2<f64>) -> Point2<f64> {
+        Point2::from(
+            self.transform
+                .apply_to_position(point)
+                .coords
+                * self.scale,
+        )
+    }
+
+    /// A size in the layer, e.g. a width, in file units, after the transform of the layer.
+    fn length(&self, value: f64) -> f64 {
+        value * self.transform.scale.abs() * self.scale
+    }
+
+    fn features(&mut self, layer: &GerberLayer) {
+        for (index, primitive) in layer
+            .gerber_primitives()
+            .iter()
+            .enumerate()
+        {
+            for offset in layer.primitive_offsets(index) {
+                self.primitive(primitive, offset);
+            }
+        }
+    }
+
+    fn holes(&mut self, layer: &GerberLayer) {
+        for tool in layer.drill_tools() {
+            let plating = match tool.plated {
+                Some(false) => "NONPLATED",
+                _ => "PLATED",
+            };
+            for (index, position) in tool.positions.iter().enumerate() {
+                let position = self.position(*position);
+                let _ = writeln!(
+                    self.output,
+                    "{}<Set><Hole name=\"T{}_{}\" diameter=\"{}\" platingStatus=\"{plating}\" plusTol=\"0\" minusTol=\"0\" x=\"{}\" y=\"{}\"/></Set>",
+                    Self::INDENT,
+                    tool.number,
+                    index + 1,
+                    number(self.length(tool.diameter)),
+                    number(position.x),
+                    number(position.y),
+                );
+            }
+        }
+    }
+
+    /// Starts a set, clear primitives are in negative sets.
+    fn set(&mut self, exposure: Exposure) {
+        let polarity = match exposure {
+            Exposure::Add => "",
+            Exposure::CutOut => " polarity=\"NEGATIVE\"",
+        };
+        let _ = writeln!(self.output, "{}<Set{polarity}>", Self::INDENT);
+    }
+
+    fn end_set(&mut self) {
+        let _ = writeln!(self.output, "{}</Set>", Self::INDENT);
+    }
+
+    /// A pad, rotated by `rotation` degrees, counter-clockwise.
+    fn pad(&mut self, center: Point2<f64>, primitive: String, rotation: f64) {
+        let id = self.dictionary.id(primitive);
+        let center = self.position(center);
+        let xform = match rotation == 0.0 {
+            true => String::new(),
+            false => format!("<Xform rotation=\"{}\"/>", number(rotation)),
+        };
+        let _ = writeln!(
+            self.output,
+            "{}  <Pad>{xform}<Location x=\"{}\" y=\"{}\"/><StandardPrimitiveRef id=\"{id}\"/></Pad>",
+            Self::INDENT,
+            number(center.x),
+            number(center.y),
+        );
+    }
+
+    fn primitive(&mut self, primitive: &GerberPrimitive, offset: Vector2<f64>) {
+        self.set(primitive.exposure());
+        match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => {
+                let circle = format!("<Circle diameter=\"{}\"/>", number(self.length(*diameter)));
+                self.pad(*center + offset, circle, 0.0);
+            }
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let rectangle = format!(
+                    "<RectCenter width=\"{}\" height=\"{}\"/>",
+                    number(self.length(*width)),
+                    number(self.length(*height))
+                );
+                // mirroring and half turns do not change a centered rectangle
+                let rotation = (self.transform.rotation.to_degrees() as f64).rem_euclid(180.0);
+                self.pad(
+                    *origin + offset + Vector2::new(width / 2.0, height / 2.0),
+                    rectangle,
+                    rotation,
+                );
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => {
+                let (start, end) = (self.position(*start + offset), self.position(*end + offset));
+                let _ = writeln!(
+                    self.output,
+                    "{}  <Features><Line startX=\"{}\" startY=\"{}\" endX=\"{}\" endY=\"{}\"><LineDesc lineWidth=\"{}\" lineEnd=\"ROUND\"/></Line></Features>",
+                    Self::INDENT,
+                    number(start.x),
+                    number(start.y),
+                    number(end.x),
+                    number(end.y),
+                    number(self.length(*width)),
+                );
+            }
+            GerberPrimitive::Arc(arc) => self.arc(arc, offset),
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let center = *center + offset;
+                let mut contours = split_contours(&geometry.relative_vertices)
+                    .into_iter()
+                    .map(|contour| {
+                        contour
+                            .into_iter()
+                            .map(|vertex| self.position(center + vertex.coords))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                // the largest contour is the outside, the others are holes
+                contours.sort_by(|a, b| area(b).total_cmp(&area(a)));
+
+                let indent = format!("{}    ", Self::INDENT);
+                let _ = writeln!(self.output, "{}  <Features><Contour>", Self::INDENT);
+                for (index, contour) in contours.iter().enumerate() {
+                    let element = match index {
+                        0 => "Polygon",
+                        _ => "Cutout",
+                    };
+                    write_polygon(&mut self.output, element, contour, 1.0, &indent);
+                }
+                let _ = writeln!(self.output, "{}  </Contour></Features>", Self::INDENT);
+            }
+        }
+        self.end_set();
+    }
+
+    /// A polyline along the center-line of the arc, full circles are two half circles.
+    fn arc(&mut self, arc: &ArcGerberPrimitive, offset: Vector2<f64>) {
+        let center = arc.center + offset;
+        let point = |angle: f64| center + Vector2::new(angle.cos(), angle.sin()) * arc.radius;
+        let sweep = match arc.is_full_circle() {
+            true => std::f64::consts::TAU,
+            false => arc.sweep_angle,
+        };
+        // mirroring reverses the direction of the arc
+        let mirrored = self.transform.to_matrix().determinant() < 0.0;
+        let clockwise = (sweep < 0.0) != mirrored;
+
+        let start = self.position(point(arc.start_angle));
+        let mut ends = vec![point(arc.start_angle + sweep)];
+        if arc.is_full_circle() {
+            ends.insert(0, point(arc.start_angle + sweep / 2.0));
+        }
+        let center = self.position(center);
+
+        let _ = write!(
+            self.output,
+            "{}  <Features><Polyline><PolyBegin x=\"{}\" y=\"{}\"/>",
+            Self::INDENT,
+            number(start.x),
+            number(start.y)
+        );
+        for end in ends {
+            let end = self.position(end);
+            let _ = write!(
+                self.output,
+                "<PolyStepCurve x=\"{}\" y=\"{}\" centerX=\"{}\" centerY=\"{}\" clockwise=\"{clockwise}\"/>",
+                number(end.x),
+                number(end.y),
+                number(center.x),
+                number(center.y),
+            );
+        }
+        let _ = writeln!(
+            self.output,
+            "<LineDesc lineWidth=\"{}\" lineEnd=\"ROUND\"/></Polyline></Features>",
+            number(self.length(arc.width))
+        );
+    }
+}
+
+/// Writes a closed polygon, e.g. `<Polygon>` or `<Cutout>`, the first vertex is repeated at the end.
+fn write_polygon(output: &mut String, element: &str, vertices: &[Point2<f64>], scale: f64, indent: &str) {
+    let Some(first) = vertices.first() else {
+        return;
+    };
+    let _ = write!(
+        output,
+        "{indent}<{element}><PolyBegin x=\"{}\" y=\"{}\"/>",
+        number(first.x * scale),
+        number(first.y * scale)
+    );
+    for vertex in vertices.iter().skip(1).chain([first]) {
+        let _ = write!(
+            output,
+            "<PolyStepSegment x=\"{}\" y=\"{}\"/>",
+            number(vertex.x * scale),
+            number(vertex.y * scale)
+        );
+    }
+    let _ = writeln!(output, "</{element}>");
+}
+
+/// The absolute area of a contour, using the shoelace formula.
+fn area(vertices: &[Point2<f64>]) -> f64 {
+    let sum: f64 = vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum();
+    (sum / 2.0).abs()
+}
+
+fn scale_of(layer: &GerberLayer, units: Unit) -> f64 {
+    layer
+        .units()
+        .map_or(1.0, |layer_units| unit_conversion_factor(layer_units, units))
+}
+
+/// Formats a number with up to 6 decimals, without trailing zeros.
+fn number(value: f64) -> String {
+    let formatted = format!("{value:.6}");
+    let trimmed = formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.');
+    match trimmed {
+        "-0" | "" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The names of the layers, made unique by adding the index of the layer to duplicates.
+fn unique_names(layer_set: &LayerSet) -> Vec<String> {
+    let mut counts = HashMap::new();
+    for info in layer_set.iter() {
+        *counts
+            .entry(info.name.as_str())
+            .or_insert(0) += 1;
+    }
+    layer_set
+        .iter()
+        .enumerate()
+        .map(|(index, info)| match counts[info.name.as_str()] {
+            1 => info.name.clone(),
+            _ => format!("{}_{}", info.name, index + 1),
+        })
+        .collect()
+}
+
+fn is_copper(layer_type: LayerType) -> bool {
+    matches!(
+        layer_type,
+        LayerType::TopCopper | LayerType::InnerCopper(_) | LayerType::InnerPlane(_) | LayerType::BottomCopper
+    )
+}
+
+/// The IPC-2581 layer function and side of a layer.
+fn layer_function(layer_type: LayerType) -> (&'static str, &'static str) {
+    let side = match layer_type.side() {
+        Some(BoardSide::Top) => "TOP",
+        Some(BoardSide::Bottom) => "BOTTOM",
+        None => match layer_type {
+            LayerType::InnerCopper(_) | LayerType::InnerPlane(_) => "INTERNAL",
+            _ => "ALL",
+        },
+    };
+    let function = match layer_type {
+        LayerType::TopCopper | LayerType::InnerCopper(_) | LayerType::BottomCopper => "SIGNAL",
+        LayerType::InnerPlane(_) => "PLANE",
+        LayerType::TopSolderMask | LayerType::BottomSolderMask => "SOLDERMASK",
+        LayerType::TopSilkscreen | LayerType::BottomSilkscreen => "SILKSCREEN",
+        LayerType::TopPaste | LayerType::BottomPaste => "SOLDERPASTE",
+        LayerType::Drill => "DRILL",
+        LayerType::Outline => "BOARD_OUTLINE",
+        LayerType::Other => "DOCUMENT",
+    };
+    (function, side)
+}
+
+/// The indexes of the layers of the stackup, from the top of the board to the bottom.
+fn stack_order(layer_set: &LayerSet) -> Vec<usize> {
+    let position = |layer_type: LayerType| match layer_type {
+        LayerType::TopPaste => Some(0),
+        LayerType::TopSilkscreen => Some(1),
+        LayerType::TopSolderMask => Some(2),
+        LayerType::TopCopper => Some(3),
+        LayerType::InnerCopper(inner) => Some(3 + inner as u64),
+        // below the inner signal layers, see `LayerType::stack_position`
+        LayerType::InnerPlane(inner) => Some(3 + u32::MAX as u64 + inner as u64),
+        LayerType::BottomCopper => Some(u64::MAX - 3),
+        LayerType::BottomSolderMask => Some(u64::MAX - 2),
+        LayerType::BottomSilkscreen => Some(u64::MAX - 1),
+        LayerType::BottomPaste => Some(u64::MAX),
+        LayerType::Drill | LayerType::Outline | LayerType::Other => None,
+    };
+
+    let mut layers = layer_set
+        .iter()
+        .enumerate()
+        .filter_map(|(index, info)| position(info.layer_type).map(|position| (position, index)))
+        .collect::<Vec<_>>();
+    layers.sort_by_key(|(position, _)| *position);
+    layers
+        .into_iter()
+        .map(|(_, index)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Rectangular};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::testing::flash_layer;
+
+    #[test]
+    fn test_export_ipc2581() {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(
+            LayerType::TopCopper,
+            "top & front",
+            flash_layer(Unit::Inches, Aperture::Circle(Circle::new(0.1)), 1.0, 1.0),
+        );
+        layer_set.add(
+            LayerType::BottomCopper,
+            "bottom",
+            flash_layer(Unit::Inches, Aperture::Rectangle(Rectangular::new(0.2, 0.1)), 1.0, 1.0),
+        );
+
+        // when
+        let xml = layer_set.export_ipc2581(&Ipc2581Options::default());
+
+        // then
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<IPC-2581 revision=\"C\""));
+        assert!(xml.contains("<Layer name=\"top &amp; front\" layerFunction=\"SIGNAL\" side=\"TOP\""));
+        assert!(xml.contains("<Layer name=\"dielectric_1\" layerFunction=\"DIELCORE\" side=\"INTERNAL\""));
+        // converted to millimeters
+        assert!(xml.contains("<EntryStandard id=\"PRIMITIVE_1\"><Circle diameter=\"2.54\"/></EntryStandard>"));
+        assert!(xml.contains(
+            "<EntryStandard id=\"PRIMITIVE_2\"><RectCenter width=\"5.08\" height=\"2.54\"/></EntryStandard>"
+        ));
+        assert!(xml.contains("<Pad><Location x=\"25.4\" y=\"25.4\"/><StandardPrimitiveRef id=\"PRIMITIVE_1\"/></Pad>"));
+        // the stackup is top to bottom, the dielectric is the rest of the board thickness
+        assert!(xml.contains(
+            "<StackupLayer layerOrGroupRef=\"top &amp; front\" thickness=\"0.035\" tolPlus=\"0\" tolMinus=\"0\" sequence=\"1\"/>"
+        ));
+        assert!(xml.contains(
+            "<StackupLayer layerOrGroupRef=\"dielectric_1\" thickness=\"1.53\" tolPlus=\"0\" tolMinus=\"0\" sequence=\"2\"/>"
+        ));
+        // without an outline layer the profile is the bounding box
+        assert!(xml.contains("<Profile>"));
+        assert!(xml.ends_with("</IPC-2581>\n"));
+    }
+
+    #[test]
+    fn test_export_ipc2581_with_layer_transform() {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(
+            LayerType::TopCopper,
+            "top",
+            flash_layer(
+                Unit::Millimeters,
+                Aperture::Rectangle(Rectangular::new(2.0, 1.0)),
+                1.0,
+                1.0,
+            ),
+        );
+        let top = layer_set
+            .get_mut(LayerType::TopCopper)
+            .unwrap();
+        top.transform.rotation = std::f32::consts::FRAC_PI_2;
+        top.transform.offset = Vector2::new(10.0, 0.0);
+
+        // when
+        let xml = layer_set.export_ipc2581(&Ipc2581Options::default());
+
+        // then
+        // rotated around the origin, then moved
+        assert!(xml.contains(
+            "<Pad><Xform rotation=\"90\"/><Location x=\"9\" y=\"1\"/><StandardPrimitiveRef id=\"PRIMITIVE_1\"/></Pad>"
+        ));
+    }
+
+    #[rstest]
+    #[case(1.5, "1.5")]
+    #[case(2.0, "2")]
+    #[case(-0.0000001, "0")]
+    #[case(0.1234567, "0.123457")]
+    fn test_number(#[case] value: f64, #[case] expected: &str) {
+        // expect
+        assert_eq!(number(value), expected);
+    }
+}
@@ -0,0 +1,138 @@
+use geo::{Area, BoundingRect, Intersects, Polygon};
+use nalgebra::Point2;
+
+use crate::geometry::BoundingBox;
+use crate::layer::GerberLayer;
+use crate::Exposure;
+
+/// A connected area of copper, i.e. one of the merged polygons of a layer, see [`GerberLayer::islands`].
+#[derive(Debug, Clone)]
+pub struct Island {
+    pub polygon: Polygon<f64>,
+    /// The area of the island, excluding holes, in the units of the layer, squared.
+    pub area: f64,
+    pub bounding_box: BoundingBox,
+    /// The indices of the additive primitives that form the island, in drawing order.
+    pub primitives: Vec<usize>,
+}
+
+/// The connected components of a layer, see [`GerberLayer::islands`].
+#[derive(Debug, Clone, Default)]
+pub struct Islands {
+    pub islands: Vec<Island>,
+    /// The index of the island of each primitive, one entry per primitive.
+    ///
+    /// `None` for cut-out primitives and primitives that were entirely removed by cut-outs.  A step-and-repeat primitive,
+    /// or a primitive that was split by a cut-out, can be part of many islands, this is the first one.
+    pub primitive_islands: Vec<Option<usize>>,
+}
+
+impl Islands {
+    pub fn island_of(&self, primitive_index: usize) -> Option<&Island> {
+        self.primitive_islands
+            .get(primitive_index)
+            .copied()
+            .flatten()
+            .map(|index| &self.islands[index])
+    }
+}
+
+impl GerberLayer {
+    /// Groups touching primitives into islands of connected copper, e.g. for highlighting the copper of a net or for
+    /// finding dead copper, i.e. islands that are not connected to anything.
+    ///
+    /// The islands are the merged polygons of the layer, see [`GerberLayer::merged_polygons`], so primitives that are
+    /// separated by a cut-out are in different islands.
+    pub fn islands(&self) -> Islands {
+        let islands = self
+            .merged_polygons()
+            .into_iter()
+            .map(|polygon| {
+                let bounding_box = polygon
+                    .bounding_rect()
+                    .map(|rect| BoundingBox {
+                        min: Point2::new(rect.min().x, rect.min().y),
+                        max: Point2::new(rect.max().x, rect.max().y),
+                    })
+                    .unwrap_or_default();
+
+                Island {
+                    area: polygon.unsigned_area(),
+                    polygon,
+                    bounding_box,
+                    primitives: vec![],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut result = Islands {
+            primitive_islands: vec![None; self.gerber_primitives().len()],
+            islands,
+        };
+
+        for (index, primitive) in self
+            .gerber_primitives()
+            .iter()
+            .enumerate()
+        {
+            if primitive.exposure() != Exposure::Add {
+                continue;
+            }
+
+            for offset in self.primitive_offsets(index) {
+                let polygons = primitive.to_polygons(offset);
+
+                for (island_index, island) in result.islands.iter_mut().enumerate() {
+                    if island.primitives.last() == Some(&index)
+                        || !polygons
+                            .iter()
+                            .any(|polygon| island.polygon.intersects(polygon))
+                    {
+                        continue;
+                    }
+                    island.primitives.push(index);
+                    result.primitive_islands[index].get_or_insert(island_index);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Rectangular, Unit};
+
+    use super::*;
+    use crate::testing::{aperture, flash, header};
+
+    #[test]
+    fn test_islands() {
+        // given
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Rectangle(Rectangular::new(2.0, 2.0))));
+        commands.extend([
+            // two overlapping squares
+            flash(0.0, 0.0),
+            flash(1.0, 0.0),
+            // an isolated square
+            flash(10.0, 0.0),
+        ]);
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let islands = layer.islands();
+
+        // then
+        assert_eq!(islands.islands.len(), 2);
+        assert_eq!(islands.primitive_islands[0], islands.primitive_islands[1]);
+        assert_ne!(islands.primitive_islands[0], islands.primitive_islands[2]);
+
+        let island = islands.island_of(0).unwrap();
+        assert_eq!(island.primitives, vec![0, 1]);
+        assert!((island.area - 6.0).abs() < 1e-9);
+        assert_eq!(island.bounding_box.min, Point2::new(-1.0, -1.0));
+        assert_eq!(island.bounding_box.max, Point2::new(2.0, 1.0));
+    }
+}
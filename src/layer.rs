@@ -1,47 +1,538 @@
 use std::collections::{HashMap, HashSet};
 use std::ops::{Add, Range};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 
-use gerber_types::{ApertureBlock, Circle, InterpolationMode, QuadrantMode, StepAndRepeat};
+use gerber_types::{ApertureBlock, Circle, InterpolationMode, Polarity, QuadrantMode, StepAndRepeat};
 use log::{debug, error, info, trace, warn};
 use nalgebra::{Point2, Vector2};
+use thiserror::Error;
 
+use super::attributes::{collect_file_attributes, AttributeCommand, Attributes, PrimitiveAttributes};
 use super::expressions::{
     evaluate_expression, macro_boolean_to_bool, macro_decimal_pair_to_f64, macro_decimal_to_f64, macro_integer_to_u32,
     ExpressionEvaluationError, MacroContext,
 };
 use super::gerber_types::{
     Aperture, ApertureDefinition, ApertureMacro, Command, Coordinates, DCode, ExtendedCode, FunctionCode, GCode,
-    MacroContent, MacroDecimal, Operation, VariableDefinition,
+    MacroContent, MacroDecimal, Operation, Unit, VariableDefinition,
 };
 use super::spacial::deduplicate::DedupEpsilon;
 use super::{geometry, gerber_types, ToVector};
 use crate::geometry::BoundingBox;
-use crate::geometry::PolygonMesh;
+use crate::geometry::{PolygonMesh, PolygonRepair, TessellationFallback, TessellationOptions};
+use crate::image_transform::ImageTransform;
+use crate::primitive_view::PrimitiveView;
+use crate::spatial_index::SpatialIndex;
 use crate::types::{Exposure, Winding};
+use crate::units::unit_conversion_factor;
 
 /// FUTURE if the rendering is always real-time, then caching the points at the time the primitives are created would have
 ///        a performance benefit. e.g. `GerberArcPrimitive::generate_points` and similar methods.
 
 #[derive(Clone, Debug)]
 pub struct GerberLayer {
-    /// Storing the commands, soon we'll want to tag the primitives with the `Command` used to build them.
-    #[allow(unused)]
+    /// Unique for each built layer, see [`GerberLayer::id`].
+    id: u64,
+    /// The primitives are tagged with the index of the `Command` used to build them, see `PrimitiveInfo::source_index`.
     commands: Vec<Command>,
     gerber_primitives: Vec<GerberPrimitive>,
+    /// One entry per primitive.
+    primitive_info: Vec<PrimitiveInfo>,
+    aperture_usage: HashMap<i32, ApertureUsage>,
+    /// The evaluated primitives of each macro aperture, relative to the flash position.
+    macro_apertures: HashMap<i32, Vec<GerberPrimitive>>,
+    instances: Vec<PrimitiveInstances>,
+    file_attributes: Attributes,
+    units: Option<Unit>,
     bounding_box: BoundingBox,
+    spatial_index: SpatialIndex,
+    region_diagnostics: Vec<RegionDiagnostic>,
+}
+
+/// Information about how a primitive was created.
+#[derive(Debug, Clone)]
+pub struct PrimitiveInfo {
+    /// The D-code of the aperture used to create the primitive, `None` for regions.
+    pub aperture: Option<i32>,
+    /// Primitives created with the same attributes share the same instance.
+    pub attributes: Arc<PrimitiveAttributes>,
+    /// The index of the command that created the primitive.
+    ///
+    /// For regions, this is the command that started the contour, i.e. the G36 or the D02 within the region.
+    /// For primitives created by flashing a block aperture, this is the command within the block.
+    pub source_index: usize,
+    /// The indices of all the primitives created by the same command, e.g. the primitives of a macro flash.
+    pub command_primitives: Range<usize>,
+}
+
+/// Copies of a range of primitives, created by step-and-repeat (SR) blocks.
+///
+/// Instead of duplicating the primitives for each repeat, the primitives of the first repeat are stored once, along with
+/// the offsets of the remaining repeats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrimitiveInstances {
+    /// The indices of the primitives created by the block.
+    pub primitives: Range<usize>,
+    /// The offsets of each copy, excluding the zero offset of the primitives themselves.
+    pub offsets: Vec<Vector2<f64>>,
+}
+
+/// How many times an aperture was used.
+///
+/// For block apertures only the flashes of the block itself are counted, not the flashes and draws of the apertures
+/// used within the block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ApertureUsage {
+    /// D03 operations
+    pub flashes: usize,
+    /// D01 operations, outside of regions
+    pub draws: usize,
+}
+
+/// Progress of a layer build, see [`GerberLayer::build_in_background`] and [`GerberLayer::build_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+impl BuildProgress {
+    /// 0-100
+    pub fn percent(&self) -> u8 {
+        match self.total {
+            0 => 100,
+            total => (self.processed.min(total) * 100 / total) as u8,
+        }
+    }
+}
+
+/// A layer being built on a worker thread, see [`GerberLayer::build_in_background`].
+///
+/// Poll it from the UI, e.g. once per frame, to show the progress and to pick up the layer once it has been built.
+pub struct LayerBuild {
+    progress: Arc<Mutex<BuildProgress>>,
+    handle: Option<JoinHandle<GerberLayer>>,
+}
+
+impl LayerBuild {
+    /// The latest progress reported by the build, does not block.
+    pub fn progress(&self) -> BuildProgress {
+        *self.progress.lock().unwrap()
+    }
+
+    /// `true` once the layer has been built, or taken.
+    pub fn is_finished(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_none_or(JoinHandle::is_finished)
+    }
+
+    /// The layer, once it has been built, does not block.
+    ///
+    /// Returns `None` while the layer is being built and after the layer has been taken.  A panic of the build is
+    /// resumed on the calling thread.
+    pub fn try_take(&mut self) -> Option<GerberLayer> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        self.handle
+            .take()
+            .map(Self::join_handle)
+    }
+
+    /// Blocks until the layer has been built.
+    ///
+    /// Panics if the layer was already taken by [`LayerBuild::try_take`].
+    pub fn wait(mut self) -> GerberLayer {
+        let handle = self
+            .handle
+            .take()
+            .expect("layer already taken");
+        Self::join_handle(handle)
+    }
+
+    fn join_handle(handle: JoinHandle<GerberLayer>) -> GerberLayer {
+        handle
+            .join()
+            .unwrap_or_else(|cause| std::panic::resume_unwind(cause))
+    }
+}
+
+/// Options used when building a layer, see [`GerberLayer::new_with_options`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayerOptions {
+    /// The maximum distance between an arc and the line segments used to approximate it, in the units of the layer.
+    ///
+    /// Large arcs use more segments, small arcs fewer.  The approximation is used for rendering and for the bounding
+    /// boxes.  `None` uses a fixed number of segments for every arc.
+    pub arc_tolerance: Option<f64>,
+    /// Vertices of regions and macro polygons that are closer than this to the simplified outline are removed when
+    /// the layer is built, in the units of the layer, e.g. to reduce the vertex counts of imported ground pours.
+    ///
+    /// Unlike [`TessellationOptions::tolerance`] the polygons themselves are simplified, so exports, hit testing and
+    /// analysis use the simplified polygons too.  See [`crate::simplify_polygon`].  `None` keeps every vertex.
+    pub polygon_tolerance: Option<f64>,
+    /// Controls the tessellation of concave polygons, e.g. regions.
+    pub tessellation: TessellationOptions,
+}
+
+struct ProgressTracker {
+    total: usize,
+    furthest: usize,
+    last_percent: Option<u8>,
+}
+
+impl ProgressTracker {
+    fn new(total: usize) -> Self {
+        Self {
+            total,
+            furthest: 0,
+            last_percent: None,
+        }
+    }
+
+    fn update(&mut self, index: usize, callback: &mut dyn FnMut(BuildProgress)) {
+        self.furthest = self.furthest.max(index.min(self.total));
+        let progress = BuildProgress {
+            processed: self.furthest,
+            total: self.total,
+        };
+        let percent = progress.percent();
+        if self.last_percent != Some(percent) {
+            self.last_percent = Some(percent);
+            callback(progress);
+        }
+    }
+}
+
+/// Fatal conditions detected by [`GerberLayer::try_new`], command indices are 0-based.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum GerberLayerError {
+    #[error("No units (MO) specified")]
+    MissingUnits,
+    #[error("Region (G36) not closed. index: {0}")]
+    UnclosedRegion(usize),
+    #[error("Region end (G37) without matching start. index: {0}")]
+    UnexpectedRegionEnd(usize),
+    #[error("Aperture block not closed. code: {code}, index: {index}")]
+    UnclosedApertureBlock { code: i32, index: usize },
+    #[error("Aperture block close without matching open. index: {0}")]
+    UnexpectedApertureBlockClose(usize),
+    #[error("Step and repeat not closed. index: {0}")]
+    UnclosedStepAndRepeat(usize),
+    #[error("Step and repeat close without matching open. index: {0}")]
+    UnexpectedStepAndRepeatClose(usize),
+}
+
+/// Invalid regions detected, and repaired, when a layer is built, command indices are 0-based.
+///
+/// The index is the index of the command that started the contour, see [`PrimitiveInfo::source_index`].
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RegionDiagnostic {
+    #[error("Region has no area, it was removed. index: {0}")]
+    ZeroArea(usize),
+    #[error(
+        "Region contour is self-intersecting, it was split into simple polygons. index: {index}, polygons: {polygons}"
+    )]
+    SelfIntersecting { index: usize, polygons: usize },
 }
 
 impl GerberLayer {
+    /// Like [`GerberLayer::new`], but returns an error instead of building an empty or incorrect layer when the
+    /// commands have no units or have unbalanced region, aperture block or step-and-repeat commands.
+    pub fn try_new(commands: Vec<Command>) -> Result<Self, GerberLayerError> {
+        Self::validate(&commands)?;
+        Ok(Self::new(commands))
+    }
+
+    /// Lenient, errors in the commands are logged and ignored where possible.
     pub fn new(commands: Vec<Command>) -> Self {
-        let gerber_primitives = GerberLayer::build_primitives(&commands);
-        let bounding_box = GerberLayer::calculate_bounding_box(&gerber_primitives);
+        Self::build_with_progress(commands, |_| {})
+    }
+
+    /// Like [`GerberLayer::new`], but with options, e.g. to control the approximation of arcs.
+    pub fn new_with_options(commands: Vec<Command>, options: LayerOptions) -> Self {
+        Self::build(commands, &options, &mut |_| {})
+    }
+
+    /// Builds the layer on a worker thread and returns immediately, so that the UI thread is not blocked while large
+    /// files are built.
+    ///
+    /// `on_progress` is called on the worker thread as the commands are processed, at most once per percent, e.g. to
+    /// request a repaint of the UI.  The latest progress, and the layer once it has been built, are available from the
+    /// returned [`LayerBuild`].
+    pub fn build_in_background(
+        commands: Vec<Command>,
+        options: LayerOptions,
+        mut on_progress: impl FnMut(BuildProgress) + Send + 'static,
+    ) -> LayerBuild {
+        let progress = Arc::new(Mutex::new(BuildProgress {
+            processed: 0,
+            total: commands.len(),
+        }));
+        let handle = std::thread::spawn({
+            let progress = progress.clone();
+            move || {
+                Self::build(commands, &options, &mut |update| {
+                    *progress.lock().unwrap() = update;
+                    on_progress(update);
+                })
+            }
+        });
+
+        LayerBuild {
+            progress,
+            handle: Some(handle),
+        }
+    }
+
+    /// Like [`GerberLayer::new`], but calls `progress` as the commands are processed, at most once per percent.
+    ///
+    /// Blocks until the layer has been built, the callback is called on the calling thread.  To keep the UI responsive
+    /// while large files are built use [`GerberLayer::build_in_background`] instead.
+    pub fn build_with_progress(commands: Vec<Command>, mut progress: impl FnMut(BuildProgress)) -> Self {
+        Self::build(commands, &LayerOptions::default(), &mut progress)
+    }
+
+    fn build(commands: Vec<Command>, options: &LayerOptions, progress: &mut dyn FnMut(BuildProgress)) -> Self {
+        let BuildOutput {
+            primitives: mut gerber_primitives,
+            primitive_info,
+            aperture_usage,
+            mut macro_apertures,
+            instances,
+            region_diagnostics,
+        } = GerberLayer::build_primitives(&commands, options, progress);
+
+        if let Some(tolerance) = options.arc_tolerance {
+            let arcs = gerber_primitives
+                .iter_mut()
+                .chain(macro_apertures.values_mut().flatten())
+                .filter_map(|primitive| match primitive {
+                    GerberPrimitive::Arc(arc) => Some(arc),
+                    _ => None,
+                });
+            for arc in arcs {
+                arc.tolerance = Some(tolerance);
+            }
+        }
+
+        let file_attributes = collect_file_attributes(&commands);
+        let units = commands
+            .iter()
+            .find_map(|command| match command {
+                Command::ExtendedCode(ExtendedCode::Unit(unit)) => Some(*unit),
+                _ => None,
+            });
+        let bounding_box = GerberLayer::calculate_bounding_box(&gerber_primitives, &instances);
+        let spatial_index = GerberLayer::build_spatial_index(&gerber_primitives, &instances);
+
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
 
         Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
             commands,
             gerber_primitives,
+            primitive_info,
+            aperture_usage,
+            macro_apertures,
+            instances,
+            file_attributes,
+            units,
             bounding_box,
+            spatial_index,
+            region_diagnostics,
+        }
+    }
+
+    fn validate(commands: &[Command]) -> Result<(), GerberLayerError> {
+        let mut has_units = false;
+        let mut region_start: Option<usize> = None;
+        let mut aperture_block_stack: Vec<(i32, usize)> = Vec::new();
+        let mut step_repeat_start: Option<usize> = None;
+
+        for (index, command) in commands.iter().enumerate() {
+            match command {
+                Command::ExtendedCode(ExtendedCode::Unit(_)) => has_units = true,
+                Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(true))) => {
+                    if let Some(start) = region_start {
+                        return Err(GerberLayerError::UnclosedRegion(start));
+                    }
+                    region_start = Some(index);
+                }
+                Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(false))) => {
+                    if region_start.take().is_none() {
+                        return Err(GerberLayerError::UnexpectedRegionEnd(index));
+                    }
+                }
+                Command::ExtendedCode(ExtendedCode::ApertureBlock(ApertureBlock::Open {
+                    code,
+                })) => aperture_block_stack.push((*code, index)),
+                Command::ExtendedCode(ExtendedCode::ApertureBlock(ApertureBlock::Close)) => {
+                    if aperture_block_stack.pop().is_none() {
+                        return Err(GerberLayerError::UnexpectedApertureBlockClose(index));
+                    }
+                }
+                // step and repeat blocks cannot be nested
+                Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Open {
+                    ..
+                })) => {
+                    if let Some(start) = step_repeat_start {
+                        return Err(GerberLayerError::UnclosedStepAndRepeat(start));
+                    }
+                    step_repeat_start = Some(index);
+                }
+                Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Close)) => {
+                    if step_repeat_start.take().is_none() {
+                        return Err(GerberLayerError::UnexpectedStepAndRepeatClose(index));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(start) = region_start {
+            return Err(GerberLayerError::UnclosedRegion(start));
+        }
+        if let Some((code, index)) = aperture_block_stack.pop() {
+            return Err(GerberLayerError::UnclosedApertureBlock {
+                code,
+                index,
+            });
+        }
+        if let Some(start) = step_repeat_start {
+            return Err(GerberLayerError::UnclosedStepAndRepeat(start));
+        }
+        if !has_units {
+            return Err(GerberLayerError::MissingUnits);
+        }
+
+        Ok(())
+    }
+
+    /// The file attributes (TF), e.g. `.FileFunction`, `.Part`, `.GenerationSoftware`.
+    pub fn file_attributes(&self) -> &Attributes {
+        &self.file_attributes
+    }
+
+    /// The aperture and attributes used to create the primitive at `index`.
+    pub fn primitive_info(&self, index: usize) -> Option<&PrimitiveInfo> {
+        self.primitive_info.get(index)
+    }
+
+    /// The command that created the primitive at `index`, see [`PrimitiveInfo::source_index`].
+    pub fn command_for_primitive(&self, index: usize) -> Option<&Command> {
+        self.primitive_info
+            .get(index)
+            .and_then(|info| self.commands.get(info.source_index))
+    }
+
+    /// The step-and-repeat instances, see [`PrimitiveInstances`].
+    pub fn instances(&self) -> &[PrimitiveInstances] {
+        &self.instances
+    }
+
+    /// Returns the offsets of each copy of the primitive, the first offset is always zero, i.e. the primitive itself.
+    pub fn primitive_offsets(&self, index: usize) -> Vec<Vector2<f64>> {
+        let mut offsets = vec![Vector2::new(0.0, 0.0)];
+        for instance in &self.instances {
+            if instance.primitives.contains(&index) {
+                offsets.extend(instance.offsets.iter().copied());
+            }
         }
+        offsets
+    }
+
+    /// Returns the index and offset of each primitive, including each copy of a step-and-repeat primitive, in the
+    /// order they are drawn.
+    ///
+    /// The copies of a step-and-repeat block are drawn immediately after the last primitive of the block, one copy at
+    /// a time, this preserves the order of dark and clear primitives within each copy.
+    pub fn primitives_in_draw_order(&self) -> impl Iterator<Item = (usize, Vector2<f64>)> + '_ {
+        let mut instances = self.instances.iter().peekable();
+        (0..self.gerber_primitives.len()).flat_map(move |index| {
+            let copies = std::iter::from_fn(|| instances.next_if(|instance| instance.primitives.end == index + 1))
+                .flat_map(|instance| {
+                    instance
+                        .offsets
+                        .iter()
+                        .flat_map(move |offset| {
+                            instance
+                                .primitives
+                                .clone()
+                                .map(move |instance_index| (instance_index, *offset))
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            std::iter::once((index, Vector2::new(0.0, 0.0))).chain(copies)
+        })
+    }
+
+    /// The commands the layer was built from.
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// The aperture (TA) and object (TO) attributes of the primitive at `index`.
+    pub fn primitive_attributes(&self, index: usize) -> Option<&PrimitiveAttributes> {
+        self.primitive_info
+            .get(index)
+            .map(|info| info.attributes.as_ref())
+    }
+
+    /// Returns the usage of the aperture, unused or unknown apertures have a usage of zero.
+    pub fn aperture_usage(&self, code: i32) -> ApertureUsage {
+        self.aperture_usage
+            .get(&code)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the primitives that a flash of a macro aperture produces, relative to the flash position, with all the
+    /// variables and expressions of the macro evaluated using the arguments of the aperture definition.
+    ///
+    /// Returns `None` if the aperture is not defined or is not a macro aperture.
+    pub fn macro_aperture_primitives(&self, code: i32) -> Option<impl ExactSizeIterator<Item = PrimitiveView<'_>>> {
+        self.macro_apertures
+            .get(&code)
+            .map(|primitives| {
+                primitives
+                    .iter()
+                    .map(PrimitiveView::new)
+            })
+    }
+
+    /// Returns the standard and macro aperture definitions, in the order they were defined.
+    pub fn aperture_definitions(&self) -> impl Iterator<Item = &ApertureDefinition> {
+        self.commands
+            .iter()
+            .filter_map(|command| match command {
+                Command::ExtendedCode(ExtendedCode::ApertureDefinition(definition)) => Some(definition),
+                _ => None,
+            })
+    }
+
+    /// Returns the indices of the primitives that are on the given net, as specified by the `.N` object attribute.
+    pub fn primitives_for_net(&self, name: &str) -> Vec<usize> {
+        self.primitive_info
+            .iter()
+            .enumerate()
+            .filter(|(_, info)| info.attributes.is_on_net(name))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Returns the unique net names used by the primitives, in order of first use.
+    pub fn nets(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.primitive_info
+            .iter()
+            .flat_map(|info| info.attributes.nets())
+            .filter(|net| seen.insert(*net))
+            .collect()
     }
 
     /// It's possible to have a gerber file with no primitives
@@ -49,10 +540,37 @@ impl GerberLayer {
         self.bounding_box.is_empty()
     }
 
+    /// Unique for each built layer, clones share the id since they have the same primitives.
+    ///
+    /// Used to key caches, e.g. the meshes cached by the renderer, a rebuilt layer, e.g. after reloading, has a new id.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
     pub fn bounding_box(&self) -> &BoundingBox {
         &self.bounding_box
     }
 
+    /// The units from the MO command, `None` if the file didn't specify the units.
+    ///
+    /// All coordinates and sizes of the layer, including the bounding box, are in these units.
+    pub fn units(&self) -> Option<Unit> {
+        self.units
+    }
+
+    /// Returns the bounding box converted to the given units, `None` if the layer's units are unknown.
+    pub fn bounding_box_in(&self, unit: Unit) -> Option<BoundingBox> {
+        let factor = self.scale_to(unit)?;
+        Some(self.bounding_box.scale(factor))
+    }
+
+    /// Returns the factor to convert values in the layer's units to the given units, `None` if the layer's units are
+    /// unknown.
+    pub fn scale_to(&self, unit: Unit) -> Option<f64> {
+        self.units
+            .map(|units| unit_conversion_factor(units, unit))
+    }
+
     /// Return the bounding box if the gerber file resulted in primitives which need drawing.
     pub fn try_bounding_box(&self) -> Option<&BoundingBox> {
         match self.is_empty() {
@@ -61,15 +579,48 @@ impl GerberLayer {
         }
     }
 
-    pub(crate) fn primitives(&self) -> &[GerberPrimitive] {
+    /// The invalid regions that were repaired or removed when the layer was built.
+    pub fn region_diagnostics(&self) -> &[RegionDiagnostic] {
+        &self.region_diagnostics
+    }
+
+    /// The spatial index of the primitives, built when the layer is built.
+    pub fn spatial_index(&self) -> &SpatialIndex {
+        &self.spatial_index
+    }
+
+    /// Returns the indices of the primitives whose bounding box intersects the region, see [`SpatialIndex`].
+    pub fn query_region(&self, region: &BoundingBox) -> Vec<usize> {
+        self.spatial_index.query_region(region)
+    }
+
+    /// Returns the indices of the primitives whose bounding box contains the point, see [`SpatialIndex`].
+    pub fn query_point(&self, point: Point2<f64>) -> Vec<usize> {
+        self.spatial_index.query_point(point)
+    }
+
+    /// The primitives of the layer, in drawing order, see [`GerberLayer::primitives`] for the public view.
+    pub(crate) fn gerber_primitives(&self) -> &[GerberPrimitive] {
         &self.gerber_primitives
     }
 }
 
-trait WithBoundingBox {
+pub(crate) trait WithBoundingBox {
     fn bounding_box(&self) -> BoundingBox;
 }
 
+impl WithBoundingBox for GerberPrimitive {
+    fn bounding_box(&self) -> BoundingBox {
+        match self {
+            GerberPrimitive::Circle(primitive) => primitive.bounding_box(),
+            GerberPrimitive::Arc(primitive) => primitive.bounding_box(),
+            GerberPrimitive::Rectangle(primitive) => primitive.bounding_box(),
+            GerberPrimitive::Line(primitive) => primitive.bounding_box(),
+            GerberPrimitive::Polygon(primitive) => primitive.bounding_box(),
+        }
+    }
+}
+
 impl WithBoundingBox for CircleGerberPrimitive {
     fn bounding_box(&self) -> BoundingBox {
         let Self {
@@ -196,18 +747,87 @@ impl GerberLayer {
         *current_pos = Point2::new(x, y);
     }
 
-    fn calculate_bounding_box(primitives: &Vec<GerberPrimitive>) -> BoundingBox {
+    /// Selects the center of a single quadrant (G74) arc.
+    ///
+    /// Gerber spec 2024.05 - 8.1.10 - in single quadrant mode the offsets are unsigned, there are 4 candidate centers,
+    /// the center is the candidate for which the arc, in the given direction, is not more than 90 degrees and for which
+    /// the start and end radii are closest.
+    ///
+    /// Returns `(center, radius, start_angle, sweep_angle)`, angles in radians, the sweep is negative for clockwise arcs.
+    fn single_quadrant_arc(
+        start: Point2<f64>,
+        end: Point2<f64>,
+        offset: Vector2<f64>,
+        clockwise: bool,
+    ) -> (Point2<f64>, f64, f64, f64) {
+        use std::f64::consts::{FRAC_PI_2, TAU};
+
+        const ANGLE_EPSILON: f64 = 1e-9;
+
+        let (offset_i, offset_j) = (offset.x.abs(), offset.y.abs());
+
+        let candidates = [(1.0, 1.0), (-1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)].map(|(sign_i, sign_j)| {
+            let center = start + Vector2::new(sign_i * offset_i, sign_j * offset_j);
+            let start_angle = (start.y - center.y).atan2(start.x - center.x);
+            let end_angle = (end.y - center.y).atan2(end.x - center.x);
+
+            let mut sweep_angle = end_angle - start_angle;
+            if clockwise && sweep_angle > 0.0 {
+                sweep_angle -= TAU;
+            } else if !clockwise && sweep_angle < 0.0 {
+                sweep_angle += TAU;
+            }
+            // a sweep of ~360 degrees is a zero length arc
+            if (sweep_angle.abs() - TAU).abs() < ANGLE_EPSILON {
+                sweep_angle = 0.0;
+            }
+
+            let radius = (start - center).norm();
+            let radius_deviation = ((end - center).norm() - radius).abs();
+
+            (center, radius, start_angle, sweep_angle, radius_deviation)
+        });
+
+        let by_deviation =
+            |a: &&(Point2<f64>, f64, f64, f64, f64), b: &&(Point2<f64>, f64, f64, f64, f64)| a.4.total_cmp(&b.4);
+
+        let best = candidates
+            .iter()
+            .filter(|(_, _, _, sweep_angle, _)| sweep_angle.abs() <= FRAC_PI_2 + ANGLE_EPSILON)
+            .min_by(by_deviation)
+            .unwrap_or_else(|| {
+                warn!(
+                    "No single quadrant arc candidate of 90 degrees or less. start: {:?}, end: {:?}, offset: {:?}",
+                    start, end, offset
+                );
+                candidates
+                    .iter()
+                    .min_by(by_deviation)
+                    .unwrap()
+            });
+
+        let (center, radius, start_angle, sweep_angle, _) = *best;
+        (center, radius, start_angle, sweep_angle)
+    }
+
+    fn calculate_bounding_box(primitives: &Vec<GerberPrimitive>, instances: &[PrimitiveInstances]) -> BoundingBox {
         let mut bbox = BoundingBox::default();
 
         for primitive in primitives {
-            let primitive_bbox = match primitive {
-                GerberPrimitive::Circle(primitive) => primitive.bounding_box(),
-                GerberPrimitive::Arc(primitive) => primitive.bounding_box(),
-                GerberPrimitive::Rectangle(primitive) => primitive.bounding_box(),
-                GerberPrimitive::Line(primitive) => primitive.bounding_box(),
-                GerberPrimitive::Polygon(primitive) => primitive.bounding_box(),
-            };
-            bbox.expand(&primitive_bbox);
+            bbox.expand(&primitive.bounding_box());
+        }
+
+        for instance in instances {
+            let mut instance_bbox = BoundingBox::default();
+            for primitive in &primitives[instance.primitives.clone()] {
+                instance_bbox.expand(&primitive.bounding_box());
+            }
+            for offset in &instance.offsets {
+                bbox.expand(&BoundingBox {
+                    min: instance_bbox.min + *offset,
+                    max: instance_bbox.max + *offset,
+                });
+            }
         }
 
         trace!("layer bbox: {:?}", bbox);
@@ -215,19 +835,150 @@ impl GerberLayer {
         bbox
     }
 
-    fn build_primitives(commands: &[Command]) -> Vec<GerberPrimitive> {
+    fn build_spatial_index(primitives: &[GerberPrimitive], instances: &[PrimitiveInstances]) -> SpatialIndex {
+        let mut entries = primitives
+            .iter()
+            .enumerate()
+            .map(|(index, primitive)| (index, primitive.bounding_box()))
+            .collect::<Vec<_>>();
+
+        for instance in instances {
+            for index in instance.primitives.clone() {
+                let bbox = primitives[index].bounding_box();
+                for offset in &instance.offsets {
+                    entries.push((index, BoundingBox {
+                        min: bbox.min + *offset,
+                        max: bbox.max + *offset,
+                    }));
+                }
+            }
+        }
+
+        SpatialIndex::new(entries)
+    }
+
+    /// Tessellates the concave polygons that were created without a tessellation, in parallel when the `rayon` feature
+    /// is enabled.
+    ///
+    /// Polygons created by flashing a macro aperture share their geometry, each unique geometry is tessellated once.
+    fn tessellate_polygons(primitives: &mut [GerberPrimitive], options: &TessellationOptions) {
+        #[cfg(feature = "rayon")]
+        use rayon::prelude::*;
+
+        // keyed by pointer, the pointers remain valid while `pending` holds a reference to each geometry.
+        let mut seen: HashSet<usize> = HashSet::new();
+        let pending: Vec<Arc<PolygonGeometry>> = primitives
+            .iter()
+            .filter_map(|primitive| match primitive {
+                GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                    geometry, ..
+                }) if !geometry.is_convex && geometry.tessellation.is_none() => Some(geometry),
+                _ => None,
+            })
+            .filter(|geometry| seen.insert(Arc::as_ptr(geometry) as usize))
+            .cloned()
+            .collect();
+
+        if pending.is_empty() {
+            return;
+        }
+
+        #[cfg(feature = "rayon")]
+        let pending_iter = pending.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let pending_iter = pending.iter();
+
+        let tessellated: HashMap<usize, Arc<PolygonGeometry>> = pending_iter
+            .map(|geometry| {
+                let tessellation = match geometry::try_tessellate_polygon(&geometry.relative_vertices, options) {
+                    Some(tessellation) => Some(tessellation),
+                    None => match options.fallback {
+                        TessellationFallback::Outline => None,
+                        TessellationFallback::Skip => Some(PolygonMesh::default()),
+                    },
+                };
+                (
+                    Arc::as_ptr(geometry) as usize,
+                    Arc::new(PolygonGeometry {
+                        relative_vertices: geometry.relative_vertices.clone(),
+                        tessellation,
+                        is_convex: geometry.is_convex,
+                    }),
+                )
+            })
+            .collect();
+
+        for primitive in primitives.iter_mut() {
+            if let GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                geometry, ..
+            }) = primitive
+            {
+                if let Some(replacement) = tessellated.get(&(Arc::as_ptr(geometry) as usize)) {
+                    *geometry = replacement.clone();
+                }
+            }
+        }
+
+        debug!("tessellated polygons: {}", pending.len());
+    }
+
+    /// Simplifies the vertices of the polygons, see [`LayerOptions::polygon_tolerance`].
+    ///
+    /// Polygons created by flashing a macro aperture share their geometry, each unique geometry is simplified once.
+    fn simplify_polygons(primitives: &mut [GerberPrimitive], tolerance: f64) {
+        // keyed by pointer, the pointers remain valid while `simplified` holds a reference to each original geometry.
+        let mut simplified: HashMap<usize, (Arc<PolygonGeometry>, Arc<PolygonGeometry>)> = HashMap::new();
+        let mut removed = 0;
+
+        for primitive in primitives.iter_mut() {
+            let GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                geometry, ..
+            }) = primitive
+            else {
+                continue;
+            };
+
+            let (_, replacement) = simplified
+                .entry(Arc::as_ptr(geometry) as usize)
+                .or_insert_with(|| {
+                    let relative_vertices = geometry::simplify_polygon(&geometry.relative_vertices, tolerance);
+                    removed += geometry
+                        .relative_vertices
+                        .len()
+                        .saturating_sub(relative_vertices.len());
+                    let replacement = Arc::new(PolygonGeometry {
+                        relative_vertices,
+                        tessellation: None,
+                        is_convex: geometry.is_convex,
+                    });
+                    (geometry.clone(), replacement)
+                });
+            *geometry = replacement.clone();
+        }
+
+        debug!(
+            "simplified polygons: {}, removed vertices: {}",
+            simplified.len(),
+            removed
+        );
+    }
+
+    fn build_primitives(
+        commands: &[Command],
+        options: &LayerOptions,
+        progress: &mut dyn FnMut(BuildProgress),
+    ) -> BuildOutput {
         #[derive(Debug)]
         struct StepRepeatState {
             initial_position: Point2<f64>,
-            start_index: usize,
+            /// index of the first primitive created by the block
+            first_primitive: usize,
+            aperture_usage_at_open: HashMap<i32, ApertureUsage>,
 
             repeat_x: u32,
             repeat_y: u32,
             distance_x: f64,
             distance_y: f64,
-
-            x_index: u32,
-            y_index: u32,
         }
 
         let mut macro_definitions: HashMap<String, &ApertureMacro> = HashMap::default();
@@ -254,6 +1005,12 @@ impl GerberLayer {
         }
 
         let mut apertures: HashMap<i32, LocalApertureKind> = HashMap::default();
+        let mut pending_macros: Vec<(i32, &ApertureMacro, &Option<Vec<MacroDecimal>>)> = Vec::new();
+
+        // Gerber spec 2024.05 - 5.3 - "the aperture attributes in the attribute dictionary are attached to the
+        // aperture when it is defined"
+        let mut aperture_attribute_dictionary = Attributes::default();
+        let mut aperture_attributes: HashMap<i32, Attributes> = HashMap::default();
 
         // entries are pushed onto the stack as AB 'open' commands are found
         // popped off the stack and stored in the aperture definitions when a corresponding AB 'close' command is encountered.
@@ -267,6 +1024,22 @@ impl GerberLayer {
         }
 
         for (index, command) in commands.iter().enumerate() {
+            match AttributeCommand::from_command(command) {
+                Some(AttributeCommand::Aperture(attribute)) => aperture_attribute_dictionary.set(attribute),
+                Some(AttributeCommand::Delete(Some(name))) => {
+                    aperture_attribute_dictionary.remove(&name);
+                }
+                Some(AttributeCommand::Delete(None)) => aperture_attribute_dictionary.clear(),
+                _ => {}
+            }
+
+            if let Command::ExtendedCode(ExtendedCode::ApertureDefinition(ApertureDefinition {
+                code, ..
+            })) = command
+            {
+                aperture_attributes.insert(*code, aperture_attribute_dictionary.clone());
+            }
+
             match command {
                 Command::ExtendedCode(ExtendedCode::ApertureBlock(ApertureBlock::Open {
                     code,
@@ -304,393 +1077,105 @@ impl GerberLayer {
                         // Handle macro-based apertures
 
                         if let Some(macro_def) = macro_definitions.get(macro_name) {
-                            //
-                            // build a unique name based on the macro name and args
-                            //
-                            let macro_name_and_args = match args {
-                                None => macro_name,
-                                Some(args) => {
-                                    let args_str = args
-                                        .iter()
-                                        .map(|arg| {
-                                            let meh = match arg {
-                                                MacroDecimal::Value(value) => value.to_string(),
-                                                MacroDecimal::Variable(variable) => format!("${}", variable),
-                                                MacroDecimal::Expression(expression) => expression.clone(),
-                                            };
+                            // evaluated once all the apertures are known, in parallel with the `rayon` feature
+                            apertures.insert(*code, LocalApertureKind::Standard(ApertureKind::Macro(vec![])));
+                            pending_macros.push((*code, *macro_def, args));
+                        } else {
+                            error!(
+                                "Aperture definition references unknown macro. macro_name: {}",
+                                macro_name
+                            );
+                        }
+                    }
+                    _ => {
+                        apertures.insert(
+                            *code,
+                            LocalApertureKind::Standard(ApertureKind::Standard(aperture.clone())),
+                        );
+                    }
+                },
+                _ => {}
+            }
+        }
+        // macro apertures are independent of each other, the evaluation of macros with many primitives, e.g. outlines
+        // with many vertices, dominates the load time of some layers.
+        #[cfg(feature = "rayon")]
+        let evaluated_macros: Vec<(i32, Vec<GerberPrimitive>)> = {
+            use rayon::prelude::*;
+            pending_macros
+                .par_iter()
+                .map(|(code, macro_def, args)| (*code, evaluate_macro(macro_def, args)))
+                .collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let evaluated_macros: Vec<(i32, Vec<GerberPrimitive>)> = pending_macros
+            .iter()
+            .map(|(code, macro_def, args)| (*code, evaluate_macro(macro_def, args)))
+            .collect();
 
-                                            meh
-                                        })
-                                        .collect::<Vec<_>>()
-                                        .join("X");
+        // in definition order, so the last definition of a code wins, as when evaluated sequentially
+        for (code, primitives) in evaluated_macros {
+            if let Some(LocalApertureKind::Standard(ApertureKind::Macro(existing))) = apertures.get_mut(&code) {
+                *existing = primitives;
+            }
+        }
 
-                                    &format!("{}_{}", macro_name, args_str)
-                                }
-                            };
-                            debug!("macro_name_and_args: {}", macro_name_and_args);
-
-                            let mut macro_context = MacroContext::default();
-
-                            //
-                            // populate the macro_context from the args.
-                            //
-                            if let Some(args) = args {
-                                for (index, arg) in args.iter().enumerate() {
-                                    let arg_number = (index + 1) as u32;
-
-                                    match arg {
-                                        MacroDecimal::Value(value) => {
-                                            macro_context
-                                                .put(arg_number, *value)
-                                                .inspect_err(|error| {
-                                                    error!("Error setting variable {}: {}", arg_number, error);
-                                                })
-                                                .ok();
-                                        }
-                                        MacroDecimal::Variable(variable) => {
-                                            macro_context
-                                                .put(arg_number, macro_context.get(variable))
-                                                .inspect_err(|error| {
-                                                    error!("Error setting variable {}: {}", arg_number, error);
-                                                })
-                                                .ok();
-                                        }
-                                        MacroDecimal::Expression(expression) => {
-                                            evaluate_expression(&expression, &macro_context)
-                                                .map(|value| {
-                                                    macro_context
-                                                        .put(arg_number, value)
-                                                        .inspect_err(|error| {
-                                                            error!("Error setting variable {}: {}", arg_number, error);
-                                                        })
-                                                        .ok();
-                                                })
-                                                .inspect_err(|error| {
-                                                    error!("Error evaluating expression {}: {}", expression, error);
-                                                })
-                                                .ok();
-                                        }
-                                    }
-                                }
-                            }
-
-                            trace!("initial macro_context: {:?}", macro_context);
-
-                            let mut primitive_defs = vec![];
-
-                            for content in &macro_def.content {
-                                trace!("macro_content: {:?}", content);
-
-                                fn process_content(
-                                    content: &MacroContent,
-                                    macro_context: &mut MacroContext,
-                                ) -> Result<Option<GerberPrimitive>, ExpressionEvaluationError>
-                                {
-                                    match content {
-                                        MacroContent::Circle(circle) => {
-                                            let diameter = macro_decimal_to_f64(&circle.diameter, macro_context)?;
-                                            let (center_x, center_y) =
-                                                macro_decimal_pair_to_f64(&circle.center, macro_context)?;
-
-                                            // Get rotation angle and convert to radians
-                                            let rotation_radians = if let Some(angle) = &circle.angle {
-                                                macro_decimal_to_f64(angle, macro_context)? * std::f64::consts::PI
-                                                    / 180.0
-                                            } else {
-                                                0.0
-                                            };
-
-                                            // Apply rotation to center coordinates around macro origin (0,0)
-                                            let (sin_theta, cos_theta) = rotation_radians.sin_cos();
-                                            let rotated_x = center_x * cos_theta - center_y * sin_theta;
-                                            let rotated_y = center_x * sin_theta + center_y * cos_theta;
-
-                                            Ok(Some(GerberPrimitive::Circle(CircleGerberPrimitive {
-                                                center: Point2::new(rotated_x, rotated_y),
-                                                diameter,
-                                                exposure: macro_boolean_to_bool(&circle.exposure, macro_context)?
-                                                    .into(),
-                                            })))
-                                        }
-                                        MacroContent::VectorLine(vector_line) => {
-                                            // Get parameters
-                                            let (start_x, start_y) =
-                                                macro_decimal_pair_to_f64(&vector_line.start, macro_context)?;
-                                            let (end_x, end_y) =
-                                                macro_decimal_pair_to_f64(&vector_line.end, macro_context)?;
-                                            let width = macro_decimal_to_f64(&vector_line.width, macro_context)?;
-                                            let rotation_angle =
-                                                macro_decimal_to_f64(&vector_line.angle, macro_context)?;
-                                            let rotation_radians = rotation_angle.to_radians();
-                                            let (sin_theta, cos_theta) = rotation_radians.sin_cos();
-
-                                            // Rotate start and end points
-                                            let rotated_start_x = start_x * cos_theta - start_y * sin_theta;
-                                            let rotated_start_y = start_x * sin_theta + start_y * cos_theta;
-                                            let rotated_end_x = end_x * cos_theta - end_y * sin_theta;
-                                            let rotated_end_y = end_x * sin_theta + end_y * cos_theta;
-
-                                            // Calculate direction vector
-                                            let dx = rotated_end_x - rotated_start_x;
-                                            let dy = rotated_end_y - rotated_start_y;
-                                            let length = (dx * dx + dy * dy).sqrt();
-
-                                            if length == 0.0 {
-                                                return Ok(None);
-                                            }
-
-                                            // Calculate perpendicular direction
-                                            let ux = dx / length;
-                                            let uy = dy / length;
-                                            let perp_x = -uy;
-                                            let perp_y = ux;
-
-                                            // Calculate width offsets
-                                            let half_width = width / 2.0;
-                                            let hw_perp_x = perp_x * half_width;
-                                            let hw_perp_y = perp_y * half_width;
-
-                                            // Calculate corners in absolute coordinates
-                                            let corners = [
-                                                (rotated_start_x - hw_perp_x, rotated_start_y - hw_perp_y),
-                                                (rotated_start_x + hw_perp_x, rotated_start_y + hw_perp_y),
-                                                (rotated_end_x + hw_perp_x, rotated_end_y + hw_perp_y),
-                                                (rotated_end_x - hw_perp_x, rotated_end_y - hw_perp_y),
-                                            ];
-
-                                            // Calculate center point
-                                            let center_x = (rotated_start_x + rotated_end_x) / 2.0;
-                                            let center_y = (rotated_start_y + rotated_end_y) / 2.0;
-
-                                            // Convert to relative vertices
-                                            let vertices = corners
-                                                .iter()
-                                                .map(|&(x, y)| Point2::new(x - center_x, y - center_y))
-                                                .collect();
-
-                                            Ok(Some(GerberPrimitive::new_polygon(GerberPolygon {
-                                                center: Point2::new(center_x, center_y),
-                                                vertices,
-                                                exposure: macro_boolean_to_bool(&vector_line.exposure, macro_context)?
-                                                    .into(),
-                                            })))
-                                        }
-                                        MacroContent::CenterLine(center_line) => {
-                                            // Get parameters
-                                            let (center_x, center_y) =
-                                                macro_decimal_pair_to_f64(&center_line.center, macro_context)?;
-                                            let (length, width) =
-                                                macro_decimal_pair_to_f64(&center_line.dimensions, macro_context)?;
-                                            let rotation_angle =
-                                                macro_decimal_to_f64(&center_line.angle, macro_context)?;
-                                            let rotation_radians = rotation_angle.to_radians();
-                                            let (sin_theta, cos_theta) = rotation_radians.sin_cos();
-
-                                            // Calculate half dimensions
-                                            let half_length = length / 2.0;
-                                            let half_width = width / 2.0;
-
-                                            // Define unrotated vertices relative to center
-                                            let unrotated_vertices = [
-                                                Point2::new(half_length, half_width),
-                                                Point2::new(-half_length, half_width),
-                                                Point2::new(-half_length, -half_width),
-                                                Point2::new(half_length, -half_width),
-                                            ];
-
-                                            // Rotate each vertex relative to the center
-                                            let vertices = unrotated_vertices
-                                                .iter()
-                                                .map(|pos| {
-                                                    let x = pos.x * cos_theta - pos.y * sin_theta;
-                                                    let y = pos.x * sin_theta + pos.y * cos_theta;
-                                                    Point2::new(x, y)
-                                                })
-                                                .collect();
-
-                                            Ok(Some(GerberPrimitive::new_polygon(GerberPolygon {
-                                                center: Point2::new(center_x, center_y),
-                                                vertices,
-                                                exposure: macro_boolean_to_bool(&center_line.exposure, macro_context)?
-                                                    .into(),
-                                            })))
-                                        }
-                                        MacroContent::Outline(outline) => {
-                                            // Need at least 3 points to form a polygon
-                                            if outline.points.len() < 3 {
-                                                warn!("Outline with less than 3 points. outline: {:?}", outline);
-                                                return Ok(None);
-                                            }
-
-                                            // Get vertices - points are already relative to (0,0)
-                                            let mut vertices: Vec<Point2<f64>> = outline
-                                                .points
-                                                .iter()
-                                                .filter_map(|point| {
-                                                    macro_decimal_pair_to_f64(point, macro_context)
-                                                        .map(|(x, y)| Point2::new(x, y))
-                                                        .inspect_err(|err| {
-                                                            error!("Error building vertex: {}", err);
-                                                        })
-                                                        .ok()
-                                                })
-                                                .collect::<Vec<_>>();
-
-                                            // Get rotation angle and convert to radians
-                                            let rotation_degrees = macro_decimal_to_f64(&outline.angle, macro_context)?;
-                                            let rotation_radians = rotation_degrees * std::f64::consts::PI / 180.0;
-
-                                            // If there's rotation, apply it to all vertices around (0,0)
-                                            if rotation_radians != 0.0 {
-                                                let (sin_theta, cos_theta) = rotation_radians.sin_cos();
-                                                vertices = vertices
-                                                    .into_iter()
-                                                    .map(|position| {
-                                                        let rotated_x = position.x * cos_theta - position.y * sin_theta;
-                                                        let rotated_y = position.x * sin_theta + position.y * cos_theta;
-                                                        Point2::new(rotated_x, rotated_y)
-                                                    })
-                                                    .collect();
-                                            }
-
-                                            Ok(Some(GerberPrimitive::new_polygon(GerberPolygon {
-                                                center: Point2::new(0.0, 0.0), // The flash operation will move this to final position
-                                                vertices,
-                                                exposure: macro_boolean_to_bool(&outline.exposure, macro_context)?
-                                                    .into(),
-                                            })))
-                                        }
-                                        MacroContent::Polygon(polygon) => {
-                                            let center = macro_decimal_pair_to_f64(&polygon.center, macro_context)?;
-
-                                            let vertices_count =
-                                                macro_integer_to_u32(&polygon.vertices, macro_context)? as usize;
-                                            let diameter = macro_decimal_to_f64(&polygon.diameter, macro_context)?;
-                                            let rotation_degrees = macro_decimal_to_f64(&polygon.angle, macro_context)?;
-                                            let rotation_radians = rotation_degrees * std::f64::consts::PI / 180.0;
-
-                                            // First generate vertices around (0,0)
-                                            let radius = diameter / 2.0;
-                                            let mut vertices = Vec::with_capacity(vertices_count);
-                                            for i in 0..vertices_count {
-                                                let angle =
-                                                    (2.0 * std::f64::consts::PI * i as f64) / vertices_count as f64;
-                                                let x = radius * angle.cos();
-                                                let y = radius * angle.sin();
-
-                                                // Apply rotation around macro origin (0,0)
-                                                let (sin_theta, cos_theta) = rotation_radians.sin_cos();
-                                                let rotated_x = x * cos_theta - y * sin_theta;
-                                                let rotated_y = x * sin_theta + y * cos_theta;
-
-                                                vertices.push(Point2::new(rotated_x, rotated_y));
-                                            }
-
-                                            // Rotate center point around macro origin
-                                            let (sin_theta, cos_theta) = rotation_radians.sin_cos();
-                                            let rotated_center_x = center.0 * cos_theta - center.1 * sin_theta;
-                                            let rotated_center_y = center.0 * sin_theta + center.1 * cos_theta;
-
-                                            Ok(Some(GerberPrimitive::new_polygon(GerberPolygon {
-                                                center: Point2::new(rotated_center_x, rotated_center_y),
-                                                vertices,
-                                                exposure: macro_boolean_to_bool(&polygon.exposure, macro_context)?
-                                                    .into(),
-                                            })))
-                                        }
-                                        MacroContent::Moire(_) => {
-                                            error!("Moire not supported");
-                                            Ok(None)
-                                        }
-                                        MacroContent::Thermal(_) => {
-                                            error!("Moire not supported");
-                                            Ok(None)
-                                        }
-                                        MacroContent::VariableDefinition(VariableDefinition {
-                                            number,
-                                            expression,
-                                        }) => {
-                                            let result = evaluate_expression(&expression, macro_context);
-                                            match result {
-                                                Ok(value) => {
-                                                    macro_context
-                                                        .put(*number, value)
-                                                        .inspect_err(|error| {
-                                                            error!("Error setting variable {}: {}", number, error);
-                                                        })
-                                                        .ok();
-                                                }
-                                                Err(cause) => {
-                                                    error!("Error evaluating expression {}: {}", expression, cause);
-                                                }
-                                            };
-                                            Ok(None)
-                                        }
-                                        MacroContent::Comment(_) => {
-                                            // Nothing to do
-                                            Ok(None)
-                                        }
-                                    }
-                                }
-
-                                let result = process_content(content, &mut macro_context);
-                                match result {
-                                    Err(cause) => {
-                                        error!("Error processing macro content: {:?}, cause: {}", content, cause);
-                                    }
-                                    Ok(Some(primitive)) => primitive_defs.push(primitive),
-                                    Ok(None) => {}
-                                }
-                            }
-                            trace!("final macro_context: {:?}", macro_context);
-
-                            trace!("primitive_defs: {:?}", primitive_defs);
-
-                            apertures.insert(*code, LocalApertureKind::Standard(ApertureKind::Macro(primitive_defs)));
-                        } else {
-                            error!(
-                                "Aperture definition references unknown macro. macro_name: {}",
-                                macro_name
-                            );
-                        }
-                    }
-                    _ => {
-                        apertures.insert(
-                            *code,
-                            LocalApertureKind::Standard(ApertureKind::Standard(aperture.clone())),
-                        );
-                    }
-                },
-                _ => {}
-            }
-        }
         info!("macros: {:?}", macro_definitions.len());
 
         debug!("aperture codes: {:?}", apertures.keys());
         info!("apertures: {:?}", apertures.len());
 
+        let macro_apertures = apertures
+            .iter()
+            .filter_map(|(code, kind)| match kind {
+                LocalApertureKind::Standard(ApertureKind::Macro(primitives)) => Some((*code, primitives.clone())),
+                _ => None,
+            })
+            .collect();
+
         // Third pass: collect all primitives, handle regions, aperture-block replay and step-repeat blocks
 
         let mut layer_primitives = Vec::new();
         let mut current_pos = Point2::new(0.0, 0.0);
 
         let mut current_aperture = None;
+        let mut current_aperture_code: Option<i32> = None;
+
+        // the info for each primitive, kept in sync with `layer_primitives`
+        let mut primitive_info: Vec<PrimitiveInfo> = Vec::new();
+        let mut aperture_usage: HashMap<i32, ApertureUsage> = HashMap::new();
+        let mut object_attribute_dictionary = Attributes::default();
+        // regions are not created using an aperture, they take the aperture attributes from the dictionary instead
+        let mut aperture_attribute_dictionary = Attributes::default();
+        let mut current_attributes = Arc::new(PrimitiveAttributes::default());
+        let mut attributes_changed = false;
+        // `true` if the `current_attributes` are the attributes of a region
+        let mut region_attributes = false;
+
         let mut interpolation_mode = InterpolationMode::Linear;
         let mut quadrant_mode = QuadrantMode::Single;
 
+        // Gerber spec 2024.05 - 4.9.2 "The default polarity is dark"
+        let mut polarity = Polarity::Dark;
+        // set when replaying a block aperture that was flashed with clear polarity, the objects in the block then
+        // have their polarity toggled.
+        let mut polarity_inverted = false;
+        let mut exposure = Exposure::Add;
+
         // also record aperture selection errors
         let mut aperture_selection_errors: HashSet<i32> = HashSet::new();
 
         // regions are a special case - they are defined by aperture codes
         let mut current_region_vertices: Vec<Point2<f64>> = Vec::new();
         let mut in_region = false;
+        let mut region_start_index = 0;
+        let mut region_diagnostics: Vec<RegionDiagnostic> = Vec::new();
 
         let mut index = 0;
 
         // set to some when the first step-repeat block is encountered
         let mut step_repeat_state: Option<StepRepeatState> = None;
-        // not using an option here to keep the math simple
-        let mut step_repeat_offset: Vector2<f64> = Vector2::new(0.0, 0.0);
+        let mut instances: Vec<PrimitiveInstances> = Vec::new();
 
         #[derive(Debug, Clone)]
         struct ApertureBlockReplayState<'a> {
@@ -700,11 +1185,16 @@ impl GerberLayer {
             initial_offset: Vector2<f64>,
             initial_interpolation_mode: InterpolationMode,
             initial_quadrant_mode: QuadrantMode,
+            initial_polarity: Polarity,
+            initial_polarity_inverted: bool,
         }
 
         let mut aperture_block_replay_stack: Vec<ApertureBlockReplayState> = vec![];
         let mut aperture_block_offset: Vector2<f64> = Vector2::new(0.0, 0.0);
 
+        // the index goes backwards when replaying blocks, so the progress is based on the furthest command reached
+        let mut progress_tracker = ProgressTracker::new(commands.len());
+
         loop {
             trace!("aperture_block_replay_stack: {:?}", aperture_block_replay_stack);
             if let Some(state) = aperture_block_replay_stack.last_mut() {
@@ -721,6 +1211,12 @@ impl GerberLayer {
                     interpolation_mode = state.initial_interpolation_mode;
                     quadrant_mode = state.initial_quadrant_mode;
 
+                    // unlike the rest of the graphics state, the polarity has to be restored, since the polarity
+                    // of the objects in the block is relative to the polarity of the flash.
+                    polarity = state.initial_polarity;
+                    polarity_inverted = state.initial_polarity_inverted;
+                    exposure = Exposure::from_polarity(polarity, polarity_inverted);
+
                     // furthermore, the statement in the spec "Gerber has no stack of graphics states" is misleading,
                     // since we have to reset the current aperture and restore the offset, both of which require
                     // a 'stack of graphic states'.
@@ -728,6 +1224,8 @@ impl GerberLayer {
                     // restore the current aperture to this one, since it may be re-used by the next flash command
                     // before another Dxx code is encountered.
                     current_aperture = apertures.get(&state.block.code);
+                    current_aperture_code = Some(state.block.code);
+                    attributes_changed = true;
 
                     // skip the same command, otherwise we'd repeat forever
                     index = state.initial_index + 1;
@@ -740,16 +1238,39 @@ impl GerberLayer {
             }
 
             trace!(
-                "index: {}, current_position: {}, step_repeat_offset: ({},{}), aperture_block_offset: ({},{})",
+                "index: {}, current_position: {}, aperture_block_offset: ({},{})",
                 index,
                 current_pos,
-                step_repeat_offset.x,
-                step_repeat_offset.y,
                 aperture_block_offset.x,
                 aperture_block_offset.y
             );
             let Some(cmd) = commands.get(index) else { break };
 
+            // set to the index of the command that started the region when a region is completed, regions are not
+            // created using an aperture
+            let mut completed_region_start_index: Option<usize> = None;
+
+            match AttributeCommand::from_command(cmd) {
+                Some(AttributeCommand::Aperture(attribute)) => {
+                    aperture_attribute_dictionary.set(attribute);
+                    attributes_changed = true;
+                }
+                Some(AttributeCommand::Object(attribute)) => {
+                    object_attribute_dictionary.set(attribute);
+                    attributes_changed = true;
+                }
+                Some(AttributeCommand::Delete(Some(name))) => {
+                    attributes_changed |= object_attribute_dictionary.remove(&name);
+                    attributes_changed |= aperture_attribute_dictionary.remove(&name);
+                }
+                Some(AttributeCommand::Delete(None)) => {
+                    object_attribute_dictionary.clear();
+                    aperture_attribute_dictionary.clear();
+                    attributes_changed = true;
+                }
+                _ => {}
+            }
+
             match cmd {
                 Command::ExtendedCode(ExtendedCode::ApertureBlock(ApertureBlock::Open {
                     code,
@@ -799,13 +1320,12 @@ impl GerberLayer {
                         } else {
                             let state = StepRepeatState {
                                 initial_position: current_pos,
+                                first_primitive: layer_primitives.len(),
+                                aperture_usage_at_open: aperture_usage.clone(),
                                 repeat_x: *repeat_x,
                                 repeat_y: *repeat_y,
                                 distance_x: *distance_x,
                                 distance_y: *distance_y,
-                                start_index: index + 1,
-                                x_index: 0,
-                                y_index: 0,
                             };
                             trace!("Step-and-repeat open, state: {:?}", state);
                             step_repeat_state = Some(state);
@@ -816,44 +1336,45 @@ impl GerberLayer {
                     if !aperture_block_replay_stack.is_empty() {
                         trace!("SR (close) during AB replay");
                     } else {
-                        if let Some(state) = &mut step_repeat_state {
-                            let mut complete = false;
-                            state.y_index += 1;
-                            if state.y_index >= state.repeat_y {
-                                state.y_index = 0;
-
-                                state.x_index += 1;
-                                if state.x_index >= state.repeat_x {
-                                    complete = true;
-                                }
+                        if let Some(state) = step_repeat_state.take() {
+                            // Rather than replaying the block for each repeat, the primitives created by the block
+                            // are instanced, see `PrimitiveInstances`.
+                            let offsets = (0..state.repeat_x)
+                                .flat_map(|x_index| (0..state.repeat_y).map(move |y_index| (x_index, y_index)))
+                                // the first repeat is the primitives themselves
+                                .skip(1)
+                                .map(|(x_index, y_index)| {
+                                    Vector2::new(state.distance_x * x_index as f64, state.distance_y * y_index as f64)
+                                })
+                                .collect::<Vec<_>>();
+
+                            let primitives = state.first_primitive..layer_primitives.len();
+                            if !primitives.is_empty() && !offsets.is_empty() {
+                                instances.push(PrimitiveInstances {
+                                    primitives,
+                                    offsets,
+                                });
+                            }
+
+                            // each repeat uses the apertures the same number of times
+                            let repeats = (state.repeat_x * state.repeat_y) as usize;
+                            for (code, usage) in aperture_usage.iter_mut() {
+                                let initial = state
+                                    .aperture_usage_at_open
+                                    .get(code)
+                                    .copied()
+                                    .unwrap_or_default();
+                                usage.flashes = initial.flashes + (usage.flashes - initial.flashes) * repeats;
+                                usage.draws = initial.draws + (usage.draws - initial.draws) * repeats;
                             }
 
                             // The gerber spec says "The current point is undefined after an SR statement."
                             // but let's be consistent by resetting the position to the position when the
-                            // block we started, for commands AFTER the step-repeat and for commands
-                            // in the next step-repeat iteration.
+                            // block we started, for commands AFTER the step-repeat.
                             // We could just not do this, which might be more 'compliant', but inconsistent.
                             current_pos = state.initial_position;
 
-                            if complete {
-                                trace!("Step-and-repeat close");
-                                step_repeat_offset = Vector2::new(0.0, 0.0);
-                                step_repeat_state = None;
-                            } else {
-                                step_repeat_offset = Vector2::new(
-                                    state.distance_x * state.x_index as f64,
-                                    state.distance_y * state.y_index as f64,
-                                );
-
-                                trace!(
-                                    "Step-and-repeat continue, state: {:?}, current_position: {:?}",
-                                    state,
-                                    current_pos
-                                );
-
-                                index = state.start_index;
-                                continue;
-                            }
+                            trace!("Step-and-repeat close, state: {:?}", state);
                         } else {
                             error!("Step repeat close without matching open");
                         }
@@ -865,18 +1386,36 @@ impl GerberLayer {
                 Command::FunctionCode(FunctionCode::GCode(GCode::QuadrantMode(mode))) => {
                     quadrant_mode = *mode;
                 }
+                Command::ExtendedCode(ExtendedCode::LoadPolarity(new_polarity)) => {
+                    // LPD/LPC, applies to all objects created after this command, including regions, flashes and
+                    // the objects created by step-repeat blocks.
+                    polarity = *new_polarity;
+                    exposure = Exposure::from_polarity(polarity, polarity_inverted);
+                    trace!("polarity: {:?}, exposure: {:?}", polarity, exposure);
+                }
                 Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(enabled))) => {
                     if *enabled {
                         // G36 - Begin Region
+                        region_start_index = index;
                         Self::region_begin(&mut current_region_vertices, &mut in_region);
                     } else {
                         // G37 - End Region
-                        Self::region_finalize(&mut layer_primitives, &mut current_region_vertices, &mut in_region);
+                        completed_region_start_index = Some(region_start_index);
+                        Self::region_finalize(
+                            &mut layer_primitives,
+                            &mut current_region_vertices,
+                            &mut in_region,
+                            exposure,
+                            region_start_index,
+                            &mut region_diagnostics,
+                        );
                     }
                 }
 
                 Command::FunctionCode(FunctionCode::DCode(DCode::SelectAperture(code))) => {
                     current_aperture = apertures.get(&code);
+                    current_aperture_code = Some(*code);
+                    attributes_changed = true;
                     if current_aperture.is_none() {
                         aperture_selection_errors.insert(*code);
                     }
@@ -885,7 +1424,7 @@ impl GerberLayer {
                     match operation {
                         Operation::Move(coords) => {
                             let mut end = current_pos;
-                            Self::update_position(&mut end, coords, step_repeat_offset + aperture_block_offset);
+                            Self::update_position(&mut end, coords, aperture_block_offset);
                             if in_region {
                                 // In a region, a move operation starts a new path segment
                                 // However, we may not have any segments yet, i.e. G36 immediately followed by D02
@@ -893,13 +1432,18 @@ impl GerberLayer {
                                     // If we have vertices, close the current segment
                                     current_region_vertices.push(*current_region_vertices.first().unwrap());
 
+                                    completed_region_start_index = Some(region_start_index);
                                     Self::region_finalize(
                                         &mut layer_primitives,
                                         &mut current_region_vertices,
                                         &mut in_region,
+                                        exposure,
+                                        region_start_index,
+                                        &mut region_diagnostics,
                                     );
 
                                     // Now start a new segment
+                                    region_start_index = index;
                                     Self::region_begin(&mut current_region_vertices, &mut in_region);
                                     current_region_vertices.push(end);
                                 }
@@ -908,11 +1452,18 @@ impl GerberLayer {
                         }
                         Operation::Interpolate(coords, offset) => {
                             let mut end = current_pos;
-                            Self::update_position(&mut end, coords, step_repeat_offset + aperture_block_offset);
+                            Self::update_position(&mut end, coords, aperture_block_offset);
                             if in_region {
                                 // Add vertex to the current region
                                 current_region_vertices.push(end);
                             } else {
+                                if let (Some(code), Some(_)) = (current_aperture_code, current_aperture) {
+                                    aperture_usage
+                                        .entry(code)
+                                        .or_default()
+                                        .draws += 1;
+                                }
+
                                 match current_aperture {
                                     // 2024.05 - 2.3 "Graphical objects"
                                     // "The solid circle standard aperture is the only aperture allowed for creating draw or arc objects.
@@ -930,7 +1481,7 @@ impl GerberLayer {
                                                     start: current_pos,
                                                     end,
                                                     width: stroke_width,
-                                                    exposure: Exposure::Add,
+                                                    exposure,
                                                 }));
                                             }
                                             InterpolationMode::ClockwiseCircular
@@ -963,7 +1514,7 @@ impl GerberLayer {
                                                     let end_angle = (end.y - center.y).atan2(end.x - center.x);
 
                                                     // Calculate sweep angle based on interpolation mode
-                                                    let mut sweep_angle = match interpolation_mode {
+                                                    let sweep_angle = match interpolation_mode {
                                                         InterpolationMode::ClockwiseCircular => {
                                                             if end_angle > start_angle {
                                                                 end_angle - start_angle - 2.0 * std::f64::consts::PI
@@ -981,17 +1532,23 @@ impl GerberLayer {
                                                         _ => 0.0, // Should never happen
                                                     };
 
-                                                    // Adjust for single/multi quadrant mode
-                                                    if let QuadrantMode::Single = quadrant_mode {
-                                                        // In single quadrant mode, sweep angle is always <= 90°
-                                                        if sweep_angle.abs() > std::f64::consts::PI / 2.0 {
-                                                            if sweep_angle > 0.0 {
-                                                                sweep_angle = std::f64::consts::PI / 2.0;
-                                                            } else {
-                                                                sweep_angle = -std::f64::consts::PI / 2.0;
-                                                            }
+                                                    // In single quadrant mode the signs of I and J are not
+                                                    // specified, so the center has to be selected from candidates
+                                                    let (center, radius, start_angle, sweep_angle) = match quadrant_mode
+                                                    {
+                                                        QuadrantMode::Single => Self::single_quadrant_arc(
+                                                            current_pos,
+                                                            end,
+                                                            Vector2::new(offset_i, offset_j),
+                                                            matches!(
+                                                                interpolation_mode,
+                                                                InterpolationMode::ClockwiseCircular
+                                                            ),
+                                                        ),
+                                                        QuadrantMode::Multi => {
+                                                            (center, radius, start_angle, sweep_angle)
                                                         }
-                                                    }
+                                                    };
 
                                                     let arc_primitive = ArcGerberPrimitive {
                                                         center,
@@ -999,7 +1556,8 @@ impl GerberLayer {
                                                         width: stroke_width,
                                                         start_angle,
                                                         sweep_angle,
-                                                        exposure: Exposure::Add,
+                                                        exposure,
+                                                        tolerance: None,
                                                     };
 
                                                     if arc_primitive.is_full_circle() {
@@ -1014,7 +1572,7 @@ impl GerberLayer {
                                                             CircleGerberPrimitive {
                                                                 center: start_point + center.to_vector(),
                                                                 diameter: stroke_width,
-                                                                exposure: Exposure::Add,
+                                                                exposure,
                                                             },
                                                         ));
 
@@ -1026,7 +1584,7 @@ impl GerberLayer {
                                                             CircleGerberPrimitive {
                                                                 center: end_point + center.to_vector(),
                                                                 diameter: stroke_width,
-                                                                exposure: Exposure::Add,
+                                                                exposure,
                                                             },
                                                         ));
                                                     }
@@ -1048,17 +1606,23 @@ impl GerberLayer {
                             if in_region {
                                 warn!("Flash operation found within region - ignoring");
                             } else {
-                                Self::update_position(
-                                    &mut current_pos,
-                                    coords,
-                                    step_repeat_offset + aperture_block_offset,
-                                );
+                                Self::update_position(&mut current_pos, coords, aperture_block_offset);
+
+                                if let (Some(code), Some(_)) = (current_aperture_code, current_aperture) {
+                                    aperture_usage
+                                        .entry(code)
+                                        .or_default()
+                                        .flashes += 1;
+                                }
 
                                 if let Some(aperture) = current_aperture {
                                     match aperture {
                                         LocalApertureKind::Standard(ApertureKind::Macro(macro_primitives)) => {
                                             for primitive in macro_primitives {
                                                 let mut primitive = primitive.clone();
+                                                // The exposure-off primitives of the macro have been resolved, see
+                                                // `resolve_exposure_off`, so all the primitives take the polarity
+                                                primitive.set_exposure(exposure);
                                                 // Update the primitive's position based on flash coordinates
                                                 match &mut primitive {
                                                     GerberPrimitive::Polygon(PolygonGerberPrimitive {
@@ -1118,13 +1682,14 @@ impl GerberLayer {
                                                             width,
                                                             start_angle: 0.0,
                                                             sweep_angle: 2.0 * std::f64::consts::PI, // Full circle, clockwise
-                                                            exposure: Exposure::Add,
+                                                            exposure,
+                                                            tolerance: None,
                                                         })
                                                     } else {
                                                         GerberPrimitive::Circle(CircleGerberPrimitive {
                                                             center: current_pos,
                                                             diameter: *diameter,
-                                                            exposure: Exposure::Add,
+                                                            exposure,
                                                         })
                                                     };
 
@@ -1140,7 +1705,7 @@ impl GerberLayer {
                                                             ),
                                                             width: rect.x,
                                                             height: rect.y,
-                                                            exposure: Exposure::Add,
+                                                            exposure,
                                                         },
                                                     ));
                                                 }
@@ -1176,7 +1741,7 @@ impl GerberLayer {
                                                         GerberPolygon {
                                                             center: current_pos,
                                                             vertices,
-                                                            exposure: Exposure::Add,
+                                                            exposure,
                                                         },
                                                     ));
                                                 }
@@ -1213,7 +1778,7 @@ impl GerberLayer {
                                                             ),
                                                             width: rect_width,
                                                             height: rect_height,
-                                                            exposure: Exposure::Add,
+                                                            exposure,
                                                         },
                                                     ));
 
@@ -1224,7 +1789,7 @@ impl GerberLayer {
                                                             CircleGerberPrimitive {
                                                                 center: current_pos + Vector2::new(dx, dy),
                                                                 diameter: circle_radius * 2.0,
-                                                                exposure: Exposure::Add,
+                                                                exposure,
                                                             },
                                                         ));
                                                     }
@@ -1245,9 +1810,17 @@ impl GerberLayer {
                                                 initial_offset: aperture_block_offset,
                                                 initial_interpolation_mode: interpolation_mode,
                                                 initial_quadrant_mode: quadrant_mode,
+                                                initial_polarity: polarity,
+                                                initial_polarity_inverted: polarity_inverted,
                                             };
                                             aperture_block_replay_stack.push(state);
 
+                                            // Gerber spec 2024.05 - 4.11.3 - when a block is flashed with clear
+                                            // polarity, the polarity of the objects in the block is toggled.
+                                            polarity_inverted = matches!(exposure, Exposure::CutOut);
+                                            polarity = Polarity::Dark;
+                                            exposure = Exposure::from_polarity(polarity, polarity_inverted);
+
                                             aperture_block_offset = current_pos.to_vector();
                                             index = block.range.start;
                                             continue;
@@ -1261,8 +1834,38 @@ impl GerberLayer {
                 _ => {}
             }
 
+            if layer_primitives.len() > primitive_info.len() {
+                let is_region = completed_region_start_index.is_some();
+                if attributes_changed || is_region != region_attributes {
+                    current_attributes = Arc::new(PrimitiveAttributes {
+                        aperture: match is_region {
+                            true => aperture_attribute_dictionary.clone(),
+                            false => current_aperture_code
+                                .and_then(|code| aperture_attributes.get(&code))
+                                .cloned()
+                                .unwrap_or_default(),
+                        },
+                        object: object_attribute_dictionary.clone(),
+                    });
+                    attributes_changed = false;
+                    region_attributes = is_region;
+                }
+                let command_primitives = primitive_info.len()..layer_primitives.len();
+                primitive_info.resize(layer_primitives.len(), PrimitiveInfo {
+                    aperture: match completed_region_start_index {
+                        Some(_) => None,
+                        None => current_aperture_code,
+                    },
+                    attributes: current_attributes.clone(),
+                    source_index: completed_region_start_index.unwrap_or(index),
+                    command_primitives,
+                });
+            }
+
             index += 1;
+            progress_tracker.update(index, progress);
         }
+        progress_tracker.update(commands.len(), progress);
 
         if aperture_selection_errors.len() > 0 {
             error!(
@@ -1271,10 +1874,35 @@ impl GerberLayer {
             );
         }
 
+        let image_transform = ImageTransform::from_commands(commands);
+        if !image_transform.is_identity() {
+            info!("Applying legacy image transform. transform: {:?}", image_transform);
+            for primitive in layer_primitives.iter_mut() {
+                image_transform.apply(primitive);
+            }
+            for instance in instances.iter_mut() {
+                for offset in instance.offsets.iter_mut() {
+                    *offset = image_transform.apply_to_vector(*offset);
+                }
+            }
+        }
+
+        if let Some(tolerance) = options.polygon_tolerance {
+            Self::simplify_polygons(&mut layer_primitives, tolerance);
+        }
+        Self::tessellate_polygons(&mut layer_primitives, &options.tessellation);
+
         info!("layer_primitives: {:?}", layer_primitives.len());
         trace!("layer_primitives: {:?}", layer_primitives);
 
-        layer_primitives
+        BuildOutput {
+            primitives: layer_primitives,
+            primitive_info,
+            aperture_usage,
+            macro_apertures,
+            instances,
+            region_diagnostics,
+        }
     }
 
     fn region_begin(current_region_vertices: &mut Vec<Point2<f64>>, in_region: &mut bool) {
@@ -1282,26 +1910,57 @@ impl GerberLayer {
         current_region_vertices.clear();
     }
 
+    /// Self-intersecting regions are split into simple polygons and regions without area are removed, see
+    /// [`RegionDiagnostic`].
     fn region_finalize(
         layer_primitives: &mut Vec<GerberPrimitive>,
         current_region_vertices: &mut Vec<Point2<f64>>,
         in_region: &mut bool,
+        exposure: Exposure,
+        region_start_index: usize,
+        region_diagnostics: &mut Vec<RegionDiagnostic>,
     ) {
-        if *in_region && current_region_vertices.len() >= 3 {
+        if !*in_region || current_region_vertices.len() < 3 {
+            *in_region = false;
+            return;
+        }
+
+        let polygons = match geometry::repair_polygon(current_region_vertices) {
+            PolygonRepair::Valid => vec![std::mem::take(current_region_vertices)],
+            PolygonRepair::ZeroArea => {
+                warn!("Region has no area. index: {}", region_start_index);
+                region_diagnostics.push(RegionDiagnostic::ZeroArea(region_start_index));
+                vec![]
+            }
+            PolygonRepair::Split(polygons) => {
+                warn!(
+                    "Region is self-intersecting. index: {}, polygons: {}",
+                    region_start_index,
+                    polygons.len()
+                );
+                region_diagnostics.push(RegionDiagnostic::SelfIntersecting {
+                    index: region_start_index,
+                    polygons: polygons.len(),
+                });
+                polygons
+            }
+        };
+
+        for vertices in polygons {
             // Find bounding box
-            let min_x = current_region_vertices
+            let min_x = vertices
                 .iter()
                 .map(|position| position.x)
                 .fold(f64::INFINITY, f64::min);
-            let max_x = current_region_vertices
+            let max_x = vertices
                 .iter()
                 .map(|position| position.x)
                 .fold(f64::NEG_INFINITY, f64::max);
-            let min_y = current_region_vertices
+            let min_y = vertices
                 .iter()
                 .map(|position| position.y)
                 .fold(f64::INFINITY, f64::min);
-            let max_y = current_region_vertices
+            let max_y = vertices
                 .iter()
                 .map(|position| position.y)
                 .fold(f64::NEG_INFINITY, f64::max);
@@ -1313,7 +1972,7 @@ impl GerberLayer {
             let center = Vector2::new(center_x, center_y);
 
             // Make vertices relative to center
-            let relative_vertices: Vec<Point2<f64>> = current_region_vertices
+            let relative_vertices: Vec<Point2<f64>> = vertices
                 .iter()
                 .map(|position| *position - center)
                 .collect();
@@ -1321,7 +1980,7 @@ impl GerberLayer {
             let polygon = GerberPrimitive::new_polygon(GerberPolygon {
                 center: Point2::new(center_x, center_y),
                 vertices: relative_vertices,
-                exposure: Exposure::Add,
+                exposure,
             });
             layer_primitives.push(polygon);
         }
@@ -1329,12 +1988,476 @@ impl GerberLayer {
     }
 }
 
+struct BuildOutput {
+    primitives: Vec<GerberPrimitive>,
+    primitive_info: Vec<PrimitiveInfo>,
+    aperture_usage: HashMap<i32, ApertureUsage>,
+    macro_apertures: HashMap<i32, Vec<GerberPrimitive>>,
+    instances: Vec<PrimitiveInstances>,
+    region_diagnostics: Vec<RegionDiagnostic>,
+}
+
+/// Evaluates the variables and expressions of a macro using the arguments of an aperture definition, returns the
+/// primitives of a flash of the aperture, relative to the flash position.
+fn evaluate_macro(macro_def: &ApertureMacro, args: &Option<Vec<MacroDecimal>>) -> Vec<GerberPrimitive> {
+    let macro_name = &macro_def.name;
+    //
+    // build a unique name based on the macro name and args
+    //
+    let macro_name_and_args = match args {
+        None => macro_name,
+        Some(args) => {
+            let args_str = args
+                .iter()
+                .map(|arg| {
+                    let meh = match arg {
+                        MacroDecimal::Value(value) => value.to_string(),
+                        MacroDecimal::Variable(variable) => format!("${}", variable),
+                        MacroDecimal::Expression(expression) => expression.clone(),
+                    };
+
+                    meh
+                })
+                .collect::<Vec<_>>()
+                .join("X");
+
+            &format!("{}_{}", macro_name, args_str)
+        }
+    };
+    debug!("macro_name_and_args: {}", macro_name_and_args);
+
+    let mut macro_context = MacroContext::default();
+
+    //
+    // populate the macro_context from the args.
+    //
+    if let Some(args) = args {
+        for (index, arg) in args.iter().enumerate() {
+            let arg_number = (index + 1) as u32;
+
+            match arg {
+                MacroDecimal::Value(value) => {
+                    macro_context
+                        .put(arg_number, *value)
+                        .inspect_err(|error| {
+                            error!("Error setting variable {}: {}", arg_number, error);
+                        })
+                        .ok();
+                }
+                MacroDecimal::Variable(variable) => {
+                    macro_context
+                        .put(arg_number, macro_context.get(variable))
+                        .inspect_err(|error| {
+                            error!("Error setting variable {}: {}", arg_number, error);
+                        })
+                        .ok();
+                }
+                MacroDecimal::Expression(expression) => {
+                    evaluate_expression(&expression, &macro_context)
+                        .map(|value| {
+                            macro_context
+                                .put(arg_number, value)
+                                .inspect_err(|error| {
+                                    error!("Error setting variable {}: {}", arg_number, error);
+                                })
+                                .ok();
+                        })
+                        .inspect_err(|error| {
+                            error!("Error evaluating expression {}: {}", expression, error);
+                        })
+                        .ok();
+                }
+            }
+        }
+    }
+
+    trace!("initial macro_context: {:?}", macro_context);
+
+    let mut primitive_defs = vec![];
+
+    for content in &macro_def.content {
+        trace!("macro_content: {:?}", content);
+
+        fn process_content(
+            content: &MacroContent,
+            macro_context: &mut MacroContext,
+        ) -> Result<Option<GerberPrimitive>, ExpressionEvaluationError> {
+            match content {
+                MacroContent::Circle(circle) => {
+                    let diameter = macro_decimal_to_f64(&circle.diameter, macro_context)?;
+                    let (center_x, center_y) = macro_decimal_pair_to_f64(&circle.center, macro_context)?;
+
+                    // Get rotation angle and convert to radians
+                    let rotation_radians = if let Some(angle) = &circle.angle {
+                        macro_decimal_to_f64(angle, macro_context)? * std::f64::consts::PI / 180.0
+                    } else {
+                        0.0
+                    };
+
+                    // Apply rotation to center coordinates around macro origin (0,0)
+                    let (sin_theta, cos_theta) = rotation_radians.sin_cos();
+                    let rotated_x = center_x * cos_theta - center_y * sin_theta;
+                    let rotated_y = center_x * sin_theta + center_y * cos_theta;
+
+                    Ok(Some(GerberPrimitive::Circle(CircleGerberPrimitive {
+                        center: Point2::new(rotated_x, rotated_y),
+                        diameter,
+                        exposure: macro_boolean_to_bool(&circle.exposure, macro_context)?.into(),
+                    })))
+                }
+                MacroContent::VectorLine(vector_line) => {
+                    // Get parameters
+                    let (start_x, start_y) = macro_decimal_pair_to_f64(&vector_line.start, macro_context)?;
+                    let (end_x, end_y) = macro_decimal_pair_to_f64(&vector_line.end, macro_context)?;
+                    let width = macro_decimal_to_f64(&vector_line.width, macro_context)?;
+                    let rotation_angle = macro_decimal_to_f64(&vector_line.angle, macro_context)?;
+                    let rotation_radians = rotation_angle.to_radians();
+                    let (sin_theta, cos_theta) = rotation_radians.sin_cos();
+
+                    // Rotate start and end points
+                    let rotated_start_x = start_x * cos_theta - start_y * sin_theta;
+                    let rotated_start_y = start_x * sin_theta + start_y * cos_theta;
+                    let rotated_end_x = end_x * cos_theta - end_y * sin_theta;
+                    let rotated_end_y = end_x * sin_theta + end_y * cos_theta;
+
+                    // Calculate direction vector
+                    let dx = rotated_end_x - rotated_start_x;
+                    let dy = rotated_end_y - rotated_start_y;
+                    let length = (dx * dx + dy * dy).sqrt();
+
+                    if length == 0.0 {
+                        return Ok(None);
+                    }
+
+                    // Calculate perpendicular direction
+                    let ux = dx / length;
+                    let uy = dy / length;
+                    let perp_x = -uy;
+                    let perp_y = ux;
+
+                    // Calculate width offsets
+                    let half_width = width / 2.0;
+                    let hw_perp_x = perp_x * half_width;
+                    let hw_perp_y = perp_y * half_width;
+
+                    // Calculate corners in absolute coordinates
+                    let corners = [
+                        (rotated_start_x - hw_perp_x, rotated_start_y - hw_perp_y),
+                        (rotated_start_x + hw_perp_x, rotated_start_y + hw_perp_y),
+                        (rotated_end_x + hw_perp_x, rotated_end_y + hw_perp_y),
+                        (rotated_end_x - hw_perp_x, rotated_end_y - hw_perp_y),
+                    ];
+
+                    // Calculate center point
+                    let center_x = (rotated_start_x + rotated_end_x) / 2.0;
+                    let center_y = (rotated_start_y + rotated_end_y) / 2.0;
+
+                    // Convert to relative vertices
+                    let vertices = corners
+                        .iter()
+                        .map(|&(x, y)| Point2::new(x - center_x, y - center_y))
+                        .collect();
+
+                    Ok(Some(GerberPrimitive::new_polygon(GerberPolygon {
+                        center: Point2::new(center_x, center_y),
+                        vertices,
+                        exposure: macro_boolean_to_bool(&vector_line.exposure, macro_context)?.into(),
+                    })))
+                }
+                MacroContent::CenterLine(center_line) => {
+                    // Get parameters
+                    let (center_x, center_y) = macro_decimal_pair_to_f64(&center_line.center, macro_context)?;
+                    let (length, width) = macro_decimal_pair_to_f64(&center_line.dimensions, macro_context)?;
+                    let rotation_angle = macro_decimal_to_f64(&center_line.angle, macro_context)?;
+                    let rotation_radians = rotation_angle.to_radians();
+                    let (sin_theta, cos_theta) = rotation_radians.sin_cos();
+
+                    // Calculate half dimensions
+                    let half_length = length / 2.0;
+                    let half_width = width / 2.0;
+
+                    // Define unrotated vertices relative to center
+                    let unrotated_vertices = [
+                        Point2::new(half_length, half_width),
+                        Point2::new(-half_length, half_width),
+                        Point2::new(-half_length, -half_width),
+                        Point2::new(half_length, -half_width),
+                    ];
+
+                    // Rotate each vertex relative to the center
+                    let vertices = unrotated_vertices
+                        .iter()
+                        .map(|pos| {
+                            let x = pos.x * cos_theta - pos.y * sin_theta;
+                            let y = pos.x * sin_theta + pos.y * cos_theta;
+                            Point2::new(x, y)
+                        })
+                        .collect();
+
+                    Ok(Some(GerberPrimitive::new_polygon(GerberPolygon {
+                        center: Point2::new(center_x, center_y),
+                        vertices,
+                        exposure: macro_boolean_to_bool(&center_line.exposure, macro_context)?.into(),
+                    })))
+                }
+                MacroContent::Outline(outline) => {
+                    // Need at least 3 points to form a polygon
+                    if outline.points.len() < 3 {
+                        warn!("Outline with less than 3 points. outline: {:?}", outline);
+                        return Ok(None);
+                    }
+
+                    // Get vertices - points are already relative to (0,0)
+                    let mut vertices: Vec<Point2<f64>> = outline
+                        .points
+                        .iter()
+                        .filter_map(|point| {
+                            macro_decimal_pair_to_f64(point, macro_context)
+                                .map(|(x, y)| Point2::new(x, y))
+                                .inspect_err(|err| {
+                                    error!("Error building vertex: {}", err);
+                                })
+                                .ok()
+                        })
+                        .collect::<Vec<_>>();
+
+                    // Get rotation angle and convert to radians
+                    let rotation_degrees = macro_decimal_to_f64(&outline.angle, macro_context)?;
+                    let rotation_radians = rotation_degrees * std::f64::consts::PI / 180.0;
+
+                    // If there's rotation, apply it to all vertices around (0,0)
+                    if rotation_radians != 0.0 {
+                        let (sin_theta, cos_theta) = rotation_radians.sin_cos();
+                        vertices = vertices
+                            .into_iter()
+                            .map(|position| {
+                                let rotated_x = position.x * cos_theta - position.y * sin_theta;
+                                let rotated_y = position.x * sin_theta + position.y * cos_theta;
+                                Point2::new(rotated_x, rotated_y)
+                            })
+                            .collect();
+                    }
+
+                    Ok(Some(GerberPrimitive::new_polygon(GerberPolygon {
+                        center: Point2::new(0.0, 0.0), // The flash operation will move this to final position
+                        vertices,
+                        exposure: macro_boolean_to_bool(&outline.exposure, macro_context)?.into(),
+                    })))
+                }
+                MacroContent::Polygon(polygon) => {
+                    let center = macro_decimal_pair_to_f64(&polygon.center, macro_context)?;
+
+                    let vertices_count = macro_integer_to_u32(&polygon.vertices, macro_context)? as usize;
+                    let diameter = macro_decimal_to_f64(&polygon.diameter, macro_context)?;
+                    let rotation_degrees = macro_decimal_to_f64(&polygon.angle, macro_context)?;
+                    let rotation_radians = rotation_degrees * std::f64::consts::PI / 180.0;
+
+                    // First generate vertices around (0,0)
+                    let radius = diameter / 2.0;
+                    let mut vertices = Vec::with_capacity(vertices_count);
+                    for i in 0..vertices_count {
+                        let angle = (2.0 * std::f64::consts::PI * i as f64) / vertices_count as f64;
+                        let x = radius * angle.cos();
+                        let y = radius * angle.sin();
+
+                        // Apply rotation around macro origin (0,0)
+                        let (sin_theta, cos_theta) = rotation_radians.sin_cos();
+                        let rotated_x = x * cos_theta - y * sin_theta;
+                        let rotated_y = x * sin_theta + y * cos_theta;
+
+                        vertices.push(Point2::new(rotated_x, rotated_y));
+                    }
+
+                    // Rotate center point around macro origin
+                    let (sin_theta, cos_theta) = rotation_radians.sin_cos();
+                    let rotated_center_x = center.0 * cos_theta - center.1 * sin_theta;
+                    let rotated_center_y = center.0 * sin_theta + center.1 * cos_theta;
+
+                    Ok(Some(GerberPrimitive::new_polygon(GerberPolygon {
+                        center: Point2::new(rotated_center_x, rotated_center_y),
+                        vertices,
+                        exposure: macro_boolean_to_bool(&polygon.exposure, macro_context)?.into(),
+                    })))
+                }
+                MacroContent::Moire(_) => {
+                    error!("Moire not supported");
+                    Ok(None)
+                }
+                MacroContent::Thermal(_) => {
+                    error!("Moire not supported");
+                    Ok(None)
+                }
+                MacroContent::VariableDefinition(VariableDefinition {
+                    number,
+                    expression,
+                }) => {
+                    let result = evaluate_expression(&expression, macro_context);
+                    match result {
+                        Ok(value) => {
+                            macro_context
+                                .put(*number, value)
+                                .inspect_err(|error| {
+                                    error!("Error setting variable {}: {}", number, error);
+                                })
+                                .ok();
+                        }
+                        Err(cause) => {
+                            error!("Error evaluating expression {}: {}", expression, cause);
+                        }
+                    };
+                    Ok(None)
+                }
+                MacroContent::Comment(_) => {
+                    // Nothing to do
+                    Ok(None)
+                }
+            }
+        }
+
+        let result = process_content(content, &mut macro_context);
+        match result {
+            Err(cause) => {
+                error!("Error processing macro content: {:?}, cause: {}", content, cause);
+            }
+            Ok(Some(primitive)) => primitive_defs.push(primitive),
+            Ok(None) => {}
+        }
+    }
+    trace!("final macro_context: {:?}", macro_context);
+
+    trace!("primitive_defs: {:?}", primitive_defs);
+
+    resolve_exposure_off(primitive_defs)
+}
+
+/// The number of segments used to approximate the circles of macros with exposure-off primitives.
+const MACRO_CIRCLE_SEGMENTS: usize = 64;
+
+/// Subtracts each exposure-off primitive of a macro from the dark primitives before it.
+///
+/// Exposure-off primitives erase the primitives of the aperture itself, not the image the aperture is flashed on, so
+/// the result only contains dark primitives, and a flash applies its polarity to all of them; an exposure-off
+/// primitive of a clear (LPC) flash is a hole, through which the image stays visible.  Dark primitives that overlap an
+/// exposure-off primitive are replaced by convex polygons, circles are approximated, see [`MACRO_CIRCLE_SEGMENTS`].
+fn resolve_exposure_off(primitives: Vec<GerberPrimitive>) -> Vec<GerberPrimitive> {
+    if primitives
+        .iter()
+        .all(|primitive| primitive.exposure() == Exposure::Add)
+    {
+        return primitives;
+    }
+
+    let mut resolved: Vec<GerberPrimitive> = Vec::with_capacity(primitives.len());
+    for primitive in primitives {
+        if primitive.exposure() == Exposure::Add {
+            resolved.push(primitive);
+            continue;
+        }
+
+        let bounding_box = primitive.bounding_box();
+        let holes = convex_pieces(&primitive);
+        resolved = resolved
+            .into_iter()
+            .flat_map(|dark| {
+                if !dark
+                    .bounding_box()
+                    .intersects(&bounding_box)
+                {
+                    return vec![dark];
+                }
+
+                let mut pieces = convex_pieces(&dark);
+                for hole in &holes {
+                    pieces = pieces
+                        .iter()
+                        .flat_map(|piece| geometry::convex_difference(piece, hole))
+                        .collect();
+                }
+                pieces
+                    .into_iter()
+                    .map(convex_polygon)
+                    .collect()
+            })
+            .collect();
+    }
+
+    resolved
+}
+
+/// The outline of a macro primitive as convex polygons, macros only create circles and polygons.
+fn convex_pieces(primitive: &GerberPrimitive) -> Vec<Vec<Point2<f64>>> {
+    match primitive {
+        GerberPrimitive::Circle(CircleGerberPrimitive {
+            center,
+            diameter,
+            ..
+        }) => {
+            let radius = diameter / 2.0;
+            let vertices = (0..MACRO_CIRCLE_SEGMENTS)
+                .map(|index| {
+                    let angle = std::f64::consts::TAU * index as f64 / MACRO_CIRCLE_SEGMENTS as f64;
+                    center + Vector2::new(angle.cos(), angle.sin()) * radius
+                })
+                .collect();
+            vec![vertices]
+        }
+        GerberPrimitive::Polygon(PolygonGerberPrimitive {
+            center,
+            geometry,
+            ..
+        }) => {
+            if geometry.is_convex {
+                let vertices = geometry
+                    .relative_vertices
+                    .iter()
+                    .map(|vertex| center + vertex.coords)
+                    .collect();
+                return vec![vertices];
+            }
+
+            // the triangles of a concave polygon
+            let mesh = geometry::tessellate_polygon(&geometry.relative_vertices);
+            mesh.indices
+                .chunks_exact(3)
+                .map(|triangle| {
+                    triangle
+                        .iter()
+                        .map(|index| {
+                            let [x, y] = mesh.vertices[*index as usize];
+                            center + Vector2::new(x as f64, y as f64)
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// A dark polygon primitive from the vertices of a convex polygon.
+fn convex_polygon(vertices: Vec<Point2<f64>>) -> GerberPrimitive {
+    let sum = vertices
+        .iter()
+        .fold(Vector2::new(0.0, 0.0), |sum, vertex| sum + vertex.coords);
+    let center = Point2::from(sum / vertices.len() as f64);
+
+    GerberPrimitive::new_polygon(GerberPolygon {
+        center,
+        vertices: vertices
+            .iter()
+            .map(|vertex| Point2::from(vertex - center))
+            .collect(),
+        exposure: Exposure::Add,
+    })
+}
+
 #[derive(Debug)]
 enum ApertureKind {
     Standard(Aperture),
     Macro(Vec<GerberPrimitive>),
 }
 
+/// A primitive of a layer, in gerber coordinates and the units of the layer.
 #[derive(Debug, Clone)]
 pub(crate) enum GerberPrimitive {
     Circle(CircleGerberPrimitive),
@@ -1344,6 +2467,7 @@ pub(crate) enum GerberPrimitive {
     Polygon(PolygonGerberPrimitive),
 }
 
+/// A flash of a circular aperture.
 #[derive(Debug, Clone)]
 pub(crate) struct CircleGerberPrimitive {
     pub center: Point2<f64>,
@@ -1351,14 +2475,17 @@ pub(crate) struct CircleGerberPrimitive {
     pub exposure: Exposure,
 }
 
+/// An axis-aligned rectangle, e.g. a flash of a rectangular aperture.
 #[derive(Debug, Clone)]
 pub(crate) struct RectangleGerberPrimitive {
+    /// The bottom-left corner.
     pub origin: Point2<f64>,
     pub width: f64,
     pub height: f64,
     pub exposure: Exposure,
 }
 
+/// A draw with a circular aperture, i.e. a line with round ends.
 #[derive(Debug, Clone)]
 pub(crate) struct LineGerberPrimitive {
     pub start: Point2<f64>,
@@ -1367,6 +2494,7 @@ pub(crate) struct LineGerberPrimitive {
     pub exposure: Exposure,
 }
 
+/// A filled polygon, e.g. a region or a macro primitive, see [`PolygonGeometry`].
 #[derive(Debug, Clone)]
 pub(crate) struct PolygonGerberPrimitive {
     pub center: Point2<f64>,
@@ -1374,6 +2502,8 @@ pub(crate) struct PolygonGerberPrimitive {
     pub geometry: Arc<PolygonGeometry>,
 }
 
+/// A circular arc drawn with a circular aperture, the points of the arc are relative to the center, see
+/// [`ArcGerberPrimitive::generate_points`].
 #[derive(Debug, Clone)]
 pub(crate) struct ArcGerberPrimitive {
     pub center: Point2<f64>,
@@ -1382,6 +2512,9 @@ pub(crate) struct ArcGerberPrimitive {
     pub start_angle: f64, // in radians
     pub sweep_angle: f64, // in radians, positive = clockwise
     pub exposure: Exposure,
+    /// The maximum distance between the arc and the line segments used to approximate it, see
+    /// [`LayerOptions::arc_tolerance`], `None` uses a fixed number of segments.
+    pub tolerance: Option<f64>,
 }
 
 impl ArcGerberPrimitive {
@@ -1419,7 +2552,7 @@ impl ArcGerberPrimitive {
         // Check if this is a full circle
         let is_full_circle = self.is_full_circle();
 
-        let steps = if is_full_circle { 33 } else { 32 };
+        let steps = self.steps();
 
         let effective_sweep = if is_full_circle {
             2.0 * std::f64::consts::PI
@@ -1454,8 +2587,42 @@ impl ArcGerberPrimitive {
 
         points
     }
+
+    /// The number of points generated by [`ArcGerberPrimitive::generate_points`], including both ends.
+    fn steps(&self) -> usize {
+        let is_full_circle = self.is_full_circle();
+        let Some(tolerance) = self
+            .tolerance
+            .filter(|tolerance| *tolerance > 0.0)
+        else {
+            return if is_full_circle { 33 } else { 32 };
+        };
+
+        let sweep = match is_full_circle {
+            true => 2.0 * std::f64::consts::PI,
+            false => self.sweep_angle.abs(),
+        };
+
+        // the distance between a segment spanning `angle` and the arc is `radius * (1 - cos(angle / 2))`
+        let max_segment_angle = match tolerance < self.radius {
+            true => 2.0 * (1.0 - tolerance / self.radius).acos(),
+            false => std::f64::consts::PI,
+        };
+        let min_segments = match is_full_circle {
+            true => MIN_CIRCLE_SEGMENTS,
+            false => 1,
+        };
+        let segments = ((sweep / max_segment_angle).ceil() as usize).clamp(min_segments, MAX_ARC_SEGMENTS);
+
+        segments + 1
+    }
 }
 
+/// Limits the number of segments of arcs approximated using [`LayerOptions::arc_tolerance`].
+const MIN_CIRCLE_SEGMENTS: usize = 8;
+const MAX_ARC_SEGMENTS: usize = 4096;
+
+/// The vertices of a polygon, regions with holes have cut-ins, see [`crate::split_contours`].
 #[derive(Debug, Clone)]
 pub struct PolygonGeometry {
     pub relative_vertices: Vec<Point2<f64>>, // Relative to center
@@ -1465,10 +2632,10 @@ pub struct PolygonGeometry {
 
 #[derive(Debug)]
 pub struct GerberPolygon {
-    center: Point2<f64>,
+    pub(crate) center: Point2<f64>,
     /// Relative to center
-    vertices: Vec<Point2<f64>>,
-    exposure: Exposure,
+    pub(crate) vertices: Vec<Point2<f64>>,
+    pub(crate) exposure: Exposure,
 }
 
 impl GerberPolygon {
@@ -1480,7 +2647,53 @@ impl GerberPolygon {
 }
 
 impl GerberPrimitive {
-    fn new_polygon(polygon: GerberPolygon) -> Self {
+    /// Whether the primitive adds (dark) or removes (clear) material.
+    pub(crate) fn exposure(&self) -> Exposure {
+        match self {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                exposure, ..
+            })
+            | GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                exposure, ..
+            })
+            | GerberPrimitive::Line(LineGerberPrimitive {
+                exposure, ..
+            })
+            | GerberPrimitive::Arc(ArcGerberPrimitive {
+                exposure, ..
+            })
+            | GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                exposure, ..
+            }) => *exposure,
+        }
+    }
+
+    /// The bounding box of the primitive, including the width of lines and arcs.
+    pub(crate) fn bounding_box(&self) -> BoundingBox {
+        WithBoundingBox::bounding_box(self)
+    }
+
+    pub(crate) fn set_exposure(&mut self, value: Exposure) {
+        match self {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                exposure, ..
+            })
+            | GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                exposure, ..
+            })
+            | GerberPrimitive::Line(LineGerberPrimitive {
+                exposure, ..
+            })
+            | GerberPrimitive::Arc(ArcGerberPrimitive {
+                exposure, ..
+            })
+            | GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                exposure, ..
+            }) => *exposure = value,
+        }
+    }
+
+    pub(crate) fn new_polygon(polygon: GerberPolygon) -> Self {
         trace!("new_polygon: {:?}", polygon);
         let is_convex = polygon.is_convex();
         let mut relative_vertices = polygon.vertices;
@@ -1495,12 +2708,12 @@ impl GerberPrimitive {
         let epsilon = 1e-6; // 1 nanometer in mm units
         let relative_vertices = relative_vertices.dedup_with_epsilon(epsilon);
 
-        // Precompute tessellation for concave polygons
-        let tessellation = if !is_convex {
-            Some(geometry::tessellate_polygon(&relative_vertices))
-        } else {
-            None
-        };
+        // Polygons with cut-ins, i.e. regions with holes, are never convex, even if the outer contour is.
+        let is_convex = is_convex && geometry::split_contours(&relative_vertices).len() == 1;
+
+        // The tessellation of concave polygons is deferred until all the primitives have been created, so that it can be
+        // done in parallel and using the layer options, see `tessellate_polygons`.
+        let tessellation = None;
 
         let polygon = GerberPrimitive::Polygon(PolygonGerberPrimitive {
             center: polygon.center,
@@ -1703,7 +2916,7 @@ mod circular_plotting_tests {
 
         // When
         let gerber_layer = GerberLayer::new(commands);
-        let primitives = gerber_layer.primitives();
+        let primitives = gerber_layer.gerber_primitives();
         println!("primitives");
         primitives
             .iter()
@@ -1961,7 +3174,7 @@ mod circle_aperture_tests {
 
         // When
         let layer = GerberLayer::new(commands);
-        let primitives = layer.primitives();
+        let primitives = layer.gerber_primitives();
 
         // Then
         assert_eq!(primitives.len(), 1);
@@ -1974,6 +3187,7 @@ mod circle_aperture_tests {
                 start_angle,
                 sweep_angle,
                 exposure,
+                tolerance: None,
             }) => {
                 assert_eq!(*c, center);
 
@@ -2009,6 +3223,739 @@ mod circle_aperture_tests {
     }
 }
 
+#[cfg(test)]
+mod polarity_tests {
+    use gerber_types::{
+        Aperture, ApertureMacro, Circle, CirclePrimitive, Command, ExtendedCode, FunctionCode, GCode, MacroBoolean,
+        MacroContent, MacroDecimal, Polarity, Rectangular, Unit,
+    };
+
+    use crate::testing::{aperture, dump_gerber_source, flash, header, interpolate};
+    use crate::types::Exposure;
+    use crate::{GerberLayer, RasterOptions, Resolution, MILLIMETERS_PER_INCH};
+
+    #[test]
+    fn test_flashes_and_regions_use_current_polarity() {
+        // given
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(1.0))));
+        commands.extend([
+            // dark flash
+            flash(0.0, 0.0),
+            Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)),
+            // clear flash
+            flash(1.0, 0.0),
+            // clear region
+            Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(true))),
+            interpolate(0.0, 1.0),
+            interpolate(1.0, 1.0),
+            interpolate(1.0, 0.0),
+            Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(false))),
+            Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Dark)),
+            // dark flash
+            flash(2.0, 0.0),
+        ]);
+
+        // and
+        dump_gerber_source(&commands);
+
+        // when
+        let layer = GerberLayer::new(commands);
+
+        // then
+        let exposures = layer
+            .gerber_primitives()
+            .iter()
+            .map(|primitive| primitive.exposure())
+            .collect::<Vec<_>>();
+
+        assert_eq!(exposures, vec![
+            Exposure::Add,
+            Exposure::CutOut,
+            Exposure::CutOut,
+            Exposure::Add
+        ]);
+    }
+
+    #[test]
+    fn test_clear_flash_of_macro_with_exposure_off_primitive() {
+        // given
+        // a 10x10mm square, and a clear flash of a ring, an 8mm circle with a 4mm exposure-off circle, in the center
+        let circle = |diameter: f64, exposure: bool| {
+            MacroContent::Circle(CirclePrimitive {
+                exposure: MacroBoolean::Value(exposure),
+                diameter: MacroDecimal::Value(diameter),
+                center: (MacroDecimal::Value(0.0), MacroDecimal::Value(0.0)),
+                angle: None,
+            })
+        };
+        let mut commands = header(Unit::Millimeters);
+        commands.push(Command::ExtendedCode(ExtendedCode::ApertureMacro(ApertureMacro {
+            name: "RING".to_string(),
+            content: vec![circle(8.0, true), circle(4.0, false)],
+        })));
+        commands.extend(aperture(10, Aperture::Rectangle(Rectangular::new(10.0, 10.0))));
+        commands.push(flash(5.0, 5.0));
+        commands.push(Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)));
+        commands.extend(aperture(11, Aperture::Macro("RING".to_string(), None)));
+        commands.push(flash(5.0, 5.0));
+        let layer = GerberLayer::new(commands);
+        let options = RasterOptions {
+            // 2 pixels per mm
+            resolution: Resolution::Dpi(MILLIMETERS_PER_INCH * 2.0),
+            color: [255, 0, 0, 255],
+            ..RasterOptions::default()
+        };
+
+        // when
+        let image = layer.render_to_image(&options).unwrap();
+
+        // then
+        // the exposure-off circle is a hole in the ring, it clears nothing
+        assert!(layer.gerber_primitives()[1..]
+            .iter()
+            .all(|primitive| primitive.exposure() == Exposure::CutOut));
+        // the square shows through the hole
+        assert_eq!(image.pixel(10, 10), [255, 0, 0, 255]);
+        // the ring is cleared
+        assert_eq!(image.pixel(16, 10), [0, 0, 0, 0]);
+        // the rest of the square is unaffected
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+    }
+}
+
+#[cfg(test)]
+mod try_new_tests {
+    use gerber_types::{ApertureBlock, Command, ExtendedCode, FunctionCode, GCode, StepAndRepeat, Unit};
+    use rstest::rstest;
+
+    use crate::{GerberLayer, GerberLayerError};
+
+    fn units() -> Command {
+        Command::ExtendedCode(ExtendedCode::Unit(Unit::Millimeters))
+    }
+
+    fn region(enabled: bool) -> Command {
+        Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(enabled)))
+    }
+
+    fn block_open(code: i32) -> Command {
+        Command::ExtendedCode(ExtendedCode::ApertureBlock(ApertureBlock::Open {
+            code,
+        }))
+    }
+
+    fn block_close() -> Command {
+        Command::ExtendedCode(ExtendedCode::ApertureBlock(ApertureBlock::Close))
+    }
+
+    fn step_repeat_open() -> Command {
+        Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Open {
+            repeat_x: 2,
+            repeat_y: 2,
+            distance_x: 1.0,
+            distance_y: 1.0,
+        }))
+    }
+
+    fn step_repeat_close() -> Command {
+        Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Close))
+    }
+
+    #[rstest]
+    #[case(vec![], Err(GerberLayerError::MissingUnits))]
+    #[case(vec![units()], Ok(()))]
+    #[case(vec![units(), region(true), region(false)], Ok(()))]
+    #[case(vec![units(), region(true)], Err(GerberLayerError::UnclosedRegion(1)))]
+    #[case(vec![units(), region(false)], Err(GerberLayerError::UnexpectedRegionEnd(1)))]
+    #[case(vec![units(), block_open(10), block_open(11), block_close(), block_close()], Ok(()))]
+    #[case(vec![units(), block_open(10), block_open(11), block_close()], Err(GerberLayerError::UnclosedApertureBlock { code: 10, index: 1 }))]
+    #[case(vec![units(), block_close()], Err(GerberLayerError::UnexpectedApertureBlockClose(1)))]
+    #[case(vec![units(), step_repeat_open(), step_repeat_close()], Ok(()))]
+    #[case(vec![units(), step_repeat_open()], Err(GerberLayerError::UnclosedStepAndRepeat(1)))]
+    #[case(vec![units(), step_repeat_close()], Err(GerberLayerError::UnexpectedStepAndRepeatClose(1)))]
+    fn test_try_new(#[case] commands: Vec<Command>, #[case] expected: Result<(), GerberLayerError>) {
+        // when
+        let result = GerberLayer::try_new(commands);
+
+        // then
+        assert_eq!(result.map(|_| ()), expected);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "parser")]
+mod region_repair_tests {
+    use super::*;
+
+    #[test]
+    fn test_region_diagnostics() {
+        // given
+        // a bow-tie, and a region without area
+        let source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+G01*
+G36*
+X0Y0D02*
+X2000000Y2000000D01*
+X2000000Y0D01*
+X0Y2000000D01*
+X0Y0D01*
+G37*
+G36*
+X5000000Y0D02*
+X6000000Y0D01*
+X7000000Y0D01*
+X5000000Y0D01*
+G37*
+M02*
+"#;
+        let commands = gerber_parser::parse(std::io::BufReader::new(source.as_bytes()))
+            .unwrap()
+            .into_commands();
+
+        // when
+        let layer = GerberLayer::new(commands);
+
+        // then
+        let diagnostics = layer.region_diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert!(matches!(diagnostics[0], RegionDiagnostic::SelfIntersecting {
+            polygons: 2,
+            ..
+        }));
+        assert!(matches!(diagnostics[1], RegionDiagnostic::ZeroArea(_)));
+
+        // and the bow-tie is split into two triangles
+        assert_eq!(layer.gerber_primitives().len(), 2);
+        for primitive in layer.gerber_primitives() {
+            let GerberPrimitive::Polygon(polygon) = primitive else {
+                panic!("expected a polygon");
+            };
+            assert_eq!(polygon.geometry.relative_vertices.len(), 3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tessellation_tests {
+    use gerber_types::{Command, ExtendedCode, FunctionCode, GCode, Unit};
+
+    use crate::layer::{GerberPrimitive, PolygonGerberPrimitive};
+    use crate::testing::interpolate;
+    use crate::GerberLayer;
+
+    #[test]
+    fn test_concave_region_is_tessellated() {
+        // given
+        let commands = vec![
+            Command::ExtendedCode(ExtendedCode::Unit(Unit::Millimeters)),
+            // 'L' shape
+            Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(true))),
+            interpolate(2.0, 0.0),
+            interpolate(2.0, 1.0),
+            interpolate(1.0, 1.0),
+            interpolate(1.0, 2.0),
+            interpolate(0.0, 2.0),
+            interpolate(0.0, 0.0),
+            Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(false))),
+        ];
+
+        // when
+        let layer = GerberLayer::new(commands);
+
+        // then
+        let [GerberPrimitive::Polygon(PolygonGerberPrimitive {
+            geometry, ..
+        })] = layer.gerber_primitives()
+        else {
+            panic!("expected a single polygon");
+        };
+        assert!(!geometry.is_convex);
+        assert!(geometry.tessellation.is_some());
+    }
+}
+
+#[cfg(test)]
+mod source_index_tests {
+    use gerber_types::{Aperture, ApertureDefinition, Circle, Command, DCode, ExtendedCode, FunctionCode, GCode, Unit};
+
+    use crate::testing::{flash, interpolate};
+    use crate::GerberLayer;
+
+    #[test]
+    fn test_primitives_are_tagged_with_source_command() {
+        // given
+        let commands = vec![
+            Command::ExtendedCode(ExtendedCode::Unit(Unit::Millimeters)),
+            Command::ExtendedCode(ExtendedCode::ApertureDefinition(ApertureDefinition::new(
+                10,
+                Aperture::Circle(Circle::new(1.0)),
+            ))),
+            Command::FunctionCode(FunctionCode::DCode(DCode::SelectAperture(10))),
+            // index 3
+            flash(0.0, 0.0),
+            // index 4
+            Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(true))),
+            interpolate(1.0, 0.0),
+            interpolate(1.0, 1.0),
+            Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(false))),
+        ];
+
+        // when
+        let layer = GerberLayer::new(commands);
+
+        // then
+        let source_indices = (0..layer.gerber_primitives().len())
+            .map(|index| {
+                layer
+                    .primitive_info(index)
+                    .unwrap()
+                    .source_index
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(source_indices, vec![3, 4]);
+
+        // and
+        assert!(matches!(
+            layer.command_for_primitive(1),
+            Some(Command::FunctionCode(FunctionCode::GCode(GCode::RegionMode(true))))
+        ));
+        assert_eq!(
+            layer
+                .primitive_info(1)
+                .unwrap()
+                .command_primitives,
+            1..2
+        );
+    }
+}
+
+#[cfg(test)]
+mod single_quadrant_arc_tests {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_6};
+
+    use nalgebra::{Point2, Vector2};
+    use rstest::rstest;
+
+    use crate::GerberLayer;
+
+    #[rstest]
+    // quarter circle around the origin, counter-clockwise
+    #[case((1.0, 0.0), (0.0, 1.0), (1.0, 0.0), false, (0.0, 0.0), 0.0, FRAC_PI_2)]
+    // same end points, clockwise, the center is on the other side
+    #[case((1.0, 0.0), (0.0, 1.0), (0.0, 1.0), true, (1.0, 1.0), -FRAC_PI_2, -FRAC_PI_2)]
+    // J is unsigned, the center is below the start point
+    #[case((0.0, 1.0), (-1.0, 0.0), (0.0, 1.0), false, (0.0, 0.0), FRAC_PI_2, FRAC_PI_2)]
+    // signs in the file are ignored
+    #[case((0.0, 1.0), (-1.0, 0.0), (0.0, -1.0), false, (0.0, 0.0), FRAC_PI_2, FRAC_PI_2)]
+    // less than 90 degrees, both offsets non-zero
+    #[case((3f64.sqrt(), 1.0), (0.0, 2.0), (3f64.sqrt(), 1.0), false, (0.0, 0.0), FRAC_PI_6, FRAC_PI_3)]
+    fn test_single_quadrant_arc(
+        #[case] start: (f64, f64),
+        #[case] end: (f64, f64),
+        #[case] offset: (f64, f64),
+        #[case] clockwise: bool,
+        #[case] expected_center: (f64, f64),
+        #[case] expected_start_angle: f64,
+        #[case] expected_sweep_angle: f64,
+    ) {
+        // when
+        let (center, radius, start_angle, sweep_angle) = GerberLayer::single_quadrant_arc(
+            Point2::new(start.0, start.1),
+            Point2::new(end.0, end.1),
+            Vector2::new(offset.0, offset.1),
+            clockwise,
+        );
+
+        // then
+        assert!((center - Point2::new(expected_center.0, expected_center.1)).norm() < 1e-9);
+        assert!((radius - (Point2::new(start.0, start.1) - center).norm()).abs() < 1e-9);
+        assert!((start_angle - expected_start_angle).abs() < 1e-9);
+        assert!((sweep_angle - expected_sweep_angle).abs() < 1e-9);
+    }
+}
+
+#[cfg(test)]
+mod step_repeat_tests {
+    use gerber_types::{Aperture, Circle, Command, ExtendedCode, Polarity, StepAndRepeat, Unit};
+    use nalgebra::{Point2, Vector2};
+
+    use crate::testing::{aperture, flash, header};
+    use crate::{GerberLayer, PrimitiveInstances};
+
+    #[test]
+    fn test_step_repeat_primitives_are_instanced() {
+        // given
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(1.0))));
+        commands.extend([
+            Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Open {
+                repeat_x: 2,
+                repeat_y: 3,
+                distance_x: 10.0,
+                distance_y: 5.0,
+            })),
+            flash(0.0, 0.0),
+            Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Close)),
+        ]);
+
+        // when
+        let layer = GerberLayer::new(commands);
+
+        // then
+        assert_eq!(layer.gerber_primitives().len(), 1);
+        assert_eq!(layer.instances(), &[PrimitiveInstances {
+            primitives: 0..1,
+            offsets: vec![
+                Vector2::new(0.0, 5.0),
+                Vector2::new(0.0, 10.0),
+                Vector2::new(10.0, 0.0),
+                Vector2::new(10.0, 5.0),
+                Vector2::new(10.0, 10.0),
+            ],
+        }]);
+
+        // and
+        assert_eq!(layer.aperture_usage(10).flashes, 6);
+        assert_eq!(layer.bounding_box().min, Point2::new(-0.5, -0.5));
+        assert_eq!(layer.bounding_box().max, Point2::new(10.5, 10.5));
+    }
+
+    #[test]
+    fn test_primitives_in_draw_order() {
+        // given
+        // a dark and a clear flash, repeated twice, followed by another flash
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(1.0))));
+        commands.extend([
+            Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Open {
+                repeat_x: 2,
+                repeat_y: 1,
+                distance_x: 10.0,
+                distance_y: 0.0,
+            })),
+            flash(0.0, 0.0),
+            Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)),
+            flash(0.0, 0.0),
+            Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Close)),
+            Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Dark)),
+            flash(5.0, 5.0),
+        ]);
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let draw_order = layer
+            .primitives_in_draw_order()
+            .collect::<Vec<_>>();
+
+        // then
+        // each copy is drawn in turn, so the clear flash of the first copy can not clear the second copy
+        let zero = Vector2::new(0.0, 0.0);
+        let copy = Vector2::new(10.0, 0.0);
+        assert_eq!(draw_order, vec![(0, zero), (1, zero), (0, copy), (1, copy), (2, zero)]);
+    }
+}
+
+#[cfg(test)]
+mod progress_tests {
+    use gerber_types::{Command, ExtendedCode, Unit};
+
+    use crate::{BuildProgress, GerberLayer, LayerOptions};
+
+    #[test]
+    fn test_progress_is_reported_once_per_percent() {
+        // given
+        let commands = vec![Command::ExtendedCode(ExtendedCode::Unit(Unit::Millimeters)); 1000];
+        let mut reports: Vec<BuildProgress> = vec![];
+
+        // when
+        GerberLayer::build_with_progress(commands, |progress| reports.push(progress));
+
+        // then
+        let percents = reports
+            .iter()
+            .map(BuildProgress::percent)
+            .collect::<Vec<_>>();
+        assert_eq!(percents, (0..=100).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_build_in_background() {
+        // given
+        let commands = vec![Command::ExtendedCode(ExtendedCode::Unit(Unit::Millimeters)); 1000];
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        // when
+        let build = GerberLayer::build_in_background(commands, LayerOptions::default(), move |progress| {
+            sender.send(progress.percent()).unwrap()
+        });
+        let layer = build.wait();
+
+        // then
+        assert_eq!(receiver.iter().collect::<Vec<_>>(), (0..=100).collect::<Vec<u8>>());
+        assert!(layer.is_empty());
+    }
+
+    #[test]
+    fn test_try_take() {
+        // given
+        let commands = vec![Command::ExtendedCode(ExtendedCode::Unit(Unit::Millimeters)); 10];
+        let mut build = GerberLayer::build_in_background(commands, LayerOptions::default(), |_| {});
+
+        // when
+        let layer = loop {
+            if let Some(layer) = build.try_take() {
+                break layer;
+            }
+            std::thread::yield_now();
+        };
+
+        // then
+        assert!(layer.is_empty());
+        assert!(build.is_finished());
+        assert_eq!(build.progress().percent(), 100);
+        assert!(build.try_take().is_none());
+    }
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod attribute_tests {
+    use std::io::BufReader;
+
+    use gerber_parser::parse;
+
+    use crate::attributes::Attribute;
+    use crate::GerberLayer;
+
+    fn build_layer(source: &str) -> GerberLayer {
+        let reader = BufReader::new(source.as_bytes());
+        let doc = parse(reader).unwrap();
+        GerberLayer::new(doc.into_commands())
+    }
+
+    #[test]
+    fn test_file_aperture_and_object_attributes() {
+        // given
+        let source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%TF.FileFunction,Copper,L1,Top*%
+%TA.AperFunction,SMDPad,CuDef*%
+%ADD10C,1.0*%
+%TD.AperFunction*%
+%ADD11C,0.5*%
+D10*
+%TO.N,GND*%
+X0Y0D03*
+%TD*%
+D11*
+X1000000Y0D03*
+M02*
+"#;
+
+        // when
+        let layer = build_layer(source);
+
+        // then
+        assert_eq!(
+            layer
+                .file_attributes()
+                .get(".FileFunction")
+                .map(|attribute| attribute.values.clone()),
+            Some(vec!["Copper".to_string(), "L1".to_string(), "Top".to_string()])
+        );
+
+        // and
+        let first = layer.primitive_attributes(0).unwrap();
+        assert_eq!(
+            first
+                .get(".AperFunction")
+                .and_then(Attribute::value),
+            Some("SMDPad")
+        );
+        assert_eq!(
+            first
+                .get(".N")
+                .and_then(Attribute::value),
+            Some("GND")
+        );
+
+        // and
+        let second = layer.primitive_attributes(1).unwrap();
+        assert!(second.is_empty());
+
+        // and
+        assert_eq!(layer.primitives_for_net("GND"), vec![0]);
+        assert_eq!(layer.nets(), vec!["GND"]);
+    }
+
+    #[test]
+    fn test_regions_take_aperture_attributes_from_the_dictionary() {
+        // given
+        let source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%TA.AperFunction,SMDPad,CuDef*%
+%ADD10C,1.0*%
+%TA.AperFunction,Conductor*%
+D10*
+X0Y0D03*
+G36*
+X0Y0D02*
+X1000000Y0D01*
+X1000000Y1000000D01*
+X0Y0D01*
+G37*
+M02*
+"#;
+
+        // when
+        let layer = build_layer(source);
+
+        // then
+        let flash = layer.primitive_attributes(0).unwrap();
+        assert_eq!(
+            flash
+                .get(".AperFunction")
+                .and_then(Attribute::value),
+            Some("SMDPad")
+        );
+
+        // and
+        let region = layer.primitive_attributes(1).unwrap();
+        assert_eq!(
+            region
+                .get(".AperFunction")
+                .and_then(Attribute::value),
+            Some("Conductor")
+        );
+    }
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod macro_aperture_tests {
+    use std::io::BufReader;
+
+    use gerber_parser::parse;
+    use nalgebra::Point2;
+
+    use crate::{Exposure, GerberLayer, PrimitiveView};
+
+    #[test]
+    fn test_macro_aperture_primitives_are_evaluated() {
+        // given
+        let source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%AMCIRCLES*
+$3=$1-$2*
+1,1,$1,0,0*
+1,1,$3,$2,0*%
+%ADD10CIRCLES,1.0X0.25*%
+%ADD11C,0.5*%
+D10*
+X0Y0D03*
+M02*
+"#;
+        let doc = parse(BufReader::new(source.as_bytes())).unwrap();
+
+        // when
+        let layer = GerberLayer::new(doc.into_commands());
+
+        // then
+        let primitives = layer
+            .macro_aperture_primitives(10)
+            .unwrap();
+        let circles = primitives
+            .map(|primitive| match primitive {
+                PrimitiveView::Circle(circle) => (circle.center(), circle.diameter(), primitive.exposure()),
+                _ => panic!("expected a circle"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(circles, vec![
+            (Point2::new(0.0, 0.0), 1.0, Exposure::Add),
+            (Point2::new(0.25, 0.0), 0.75, Exposure::Add),
+        ]);
+
+        // and
+        assert!(layer
+            .macro_aperture_primitives(11)
+            .is_none());
+    }
+}
+
+#[cfg(test)]
+mod arc_tolerance_tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn full_circle(radius: f64, tolerance: Option<f64>) -> ArcGerberPrimitive {
+        ArcGerberPrimitive {
+            center: Point2::new(0.0, 0.0),
+            radius,
+            width: 0.1,
+            start_angle: 0.0,
+            sweep_angle: 0.0,
+            exposure: Exposure::Add,
+            tolerance,
+        }
+    }
+
+    #[rstest]
+    #[case(full_circle(100.0, None), 33)]
+    #[case(full_circle(100.0, Some(0.01)), 224)]
+    // limited by the minimum number of segments
+    #[case(full_circle(0.1, Some(0.01)), 9)]
+    fn test_arc_steps(#[case] arc: ArcGerberPrimitive, #[case] expected: usize) {
+        // when
+        let points = arc.generate_points();
+
+        // then
+        assert_eq!(points.len(), expected);
+
+        // and the midpoint of each segment is within the tolerance of the arc
+        if let Some(tolerance) = arc.tolerance {
+            for segment in points.windows(2) {
+                let midpoint = Point2::from((segment[0].coords + segment[1].coords) / 2.0);
+                assert!(arc.radius - midpoint.coords.norm() <= tolerance);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "parser")]
+    fn test_layer_options_arc_tolerance() {
+        // given
+        let source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%ADD10C,1.000X0.500*%
+D10*
+X0Y0D03*
+M02*
+"#;
+        let commands = gerber_parser::parse(std::io::BufReader::new(source.as_bytes()))
+            .unwrap()
+            .into_commands();
+        let options = LayerOptions {
+            arc_tolerance: Some(0.001),
+            ..LayerOptions::default()
+        };
+
+        // when
+        let layer = GerberLayer::new_with_options(commands, options);
+
+        // then
+        let GerberPrimitive::Arc(arc) = &layer.gerber_primitives()[0] else {
+            panic!("expected an arc");
+        };
+        assert_eq!(arc.tolerance, Some(0.001));
+    }
+}
+
 #[cfg(test)]
 mod bounding_box_arc_tests {
     use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
@@ -2033,6 +3980,7 @@ mod bounding_box_arc_tests {
             start_angle,
             sweep_angle,
             exposure: Exposure::Add,
+            tolerance: None,
         })
     }
 
@@ -2047,6 +3995,7 @@ mod bounding_box_arc_tests {
             start_angle: 0.0_f64.to_radians(),
             sweep_angle: 0.0_f64.to_radians(),
             exposure: Exposure::Add,
+            tolerance: None,
         };
 
         // when
@@ -2079,7 +4028,7 @@ mod bounding_box_arc_tests {
         let arc = create_arc_primitive(center_x, center_y, radius, width, start_angle, sweep_angle);
         let primitives = vec![arc];
 
-        let bbox = GerberLayer::calculate_bounding_box(&primitives);
+        let bbox = GerberLayer::calculate_bounding_box(&primitives, &[]);
 
         // For a full circle, the bounds should be center +/- (radius + half_width)
         let half_width = width / 2.0;
@@ -2128,6 +4077,7 @@ mod bounding_box_arc_tests {
             start_angle: 0.0_f64.to_radians(),
             sweep_angle: 0.0_f64.to_radians(),
             exposure: Exposure::Add,
+            tolerance: None,
         };
 
         // when
@@ -2156,7 +4106,7 @@ mod bounding_box_arc_tests {
         let primitives = vec![arc];
 
         // Execute
-        let bbox = GerberLayer::calculate_bounding_box(&primitives);
+        let bbox = GerberLayer::calculate_bounding_box(&primitives, &[]);
 
         // Verify the bounding box contains the center point plus the arc
         let half_width = width / 2.0;
@@ -2207,7 +4157,7 @@ mod bounding_box_arc_tests {
         let primitives = vec![arc];
 
         // Execute
-        let bbox = GerberLayer::calculate_bounding_box(&primitives);
+        let bbox = GerberLayer::calculate_bounding_box(&primitives, &[]);
 
         // Same verification as for positive sweeps
         let half_width = width / 2.0;
@@ -2250,7 +4200,7 @@ mod bounding_box_arc_tests {
         let arc = create_arc_primitive(center_x, center_y, radius, width, start_angle, sweep_angle);
         let primitives = vec![arc];
 
-        let bbox = GerberLayer::calculate_bounding_box(&primitives);
+        let bbox = GerberLayer::calculate_bounding_box(&primitives, &[]);
 
         // Verify the bounds for offset center
         let half_width = width / 2.0;
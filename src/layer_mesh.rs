@@ -0,0 +1,327 @@
+use std::f64::consts::TAU;
+
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::GerberTransform;
+use crate::layer::{
+    CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGerberPrimitive,
+    RectangleGerberPrimitive,
+};
+use crate::{Exposure, NegativeArea};
+
+/// The number of segments used for circles, line caps and arc joins.
+pub(crate) const MESH_CIRCLE_SEGMENTS: usize = 32;
+
+/// Options for [`LayerMesh::new`].
+#[derive(Debug, Clone)]
+pub struct LayerMeshOptions {
+    /// Straight (not premultiplied) RGBA, the color of the vertices of dark primitives.
+    pub color: [u8; 4],
+    /// Straight (not premultiplied) RGBA, the color of the vertices of clear primitives, black by default, like
+    /// [`crate::GerberRenderer`].
+    pub clear_color: [u8; 4],
+    /// Applied to the vertices, the anchor is resolved using the bounding box of the layer.
+    pub transform: GerberTransform,
+    /// Builds the mesh of a negative layer, e.g. a soldermask layer, see [`NegativeArea`].
+    pub negative: Option<NegativeArea>,
+}
+
+impl Default for LayerMeshOptions {
+    fn default() -> Self {
+        Self {
+            color: [255, 255, 255, 255],
+            clear_color: [0, 0, 0, 255],
+            transform: GerberTransform::default(),
+            negative: None,
+        }
+    }
+}
+
+/// The triangles of a layer as plain buffers, for uploading to other engines, e.g. Bevy or three-d, without egui.
+///
+/// The buffers are in gerber coordinates, after the transform, with y up.  Step-and-repeat blocks are expanded.  The
+/// triangles are in drawing order, clear primitives only cover the dark primitives before them when the triangles are
+/// drawn in order without depth testing.  Polygons without a tessellation, see
+/// [`crate::TessellationFallback::Outline`], are not included.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayerMesh {
+    pub positions: Vec<[f32; 2]>,
+    /// Straight (not premultiplied) RGBA, one per position.
+    pub colors: Vec<[u8; 4]>,
+    /// Three per triangle, counter-clockwise or clockwise, depending on the primitive and the mirroring.
+    pub indices: Vec<u32>,
+}
+
+impl LayerMesh {
+    pub fn new(layer: &GerberLayer, options: &LayerMeshOptions) -> Self {
+        let triangles = Triangles::new(layer, options.negative.as_ref());
+        let matrix = options
+            .transform
+            .resolve(layer.bounding_box())
+            .to_matrix();
+
+        let positions = triangles
+            .positions
+            .iter()
+            .map(|position| {
+                let position = matrix.transform_point(position);
+                [position.x as f32, position.y as f32]
+            })
+            .collect();
+        let colors = triangles
+            .exposures
+            .iter()
+            .map(|exposure| match exposure {
+                Exposure::Add => options.color,
+                Exposure::CutOut => options.clear_color,
+            })
+            .collect();
+
+        Self {
+            positions,
+            colors,
+            indices: triangles.indices,
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+}
+
+/// The triangles of a layer, in gerber coordinates, with the exposure of each vertex, see [`LayerMesh`] and
+/// `GpuLayerMesh`.
+#[derive(Debug, Default)]
+pub(crate) struct Triangles {
+    pub(crate) positions: Vec<Point2<f64>>,
+    pub(crate) exposures: Vec<Exposure>,
+    pub(crate) indices: Vec<u32>,
+}
+
+impl Triangles {
+    /// Builds the triangles of a layer, as a negative if `negative` is set, the area is filled and the primitives are
+    /// added with their exposure inverted.
+    pub(crate) fn new(layer: &GerberLayer, negative: Option<&NegativeArea>) -> Self {
+        let mut triangles = Self::default();
+
+        let zero = Vector2::new(0.0, 0.0);
+        if let Some(fill) = negative.and_then(|area| layer.tessellated_negative_fill(area)) {
+            triangles.add_primitive(&fill, zero, Exposure::Add);
+        }
+        let exposure = |primitive: &GerberPrimitive| match negative {
+            Some(_) => primitive.exposure().inverted(),
+            None => primitive.exposure(),
+        };
+
+        let primitives = layer.gerber_primitives();
+        for (index, offset) in layer.primitives_in_draw_order() {
+            let primitive = &primitives[index];
+            triangles.add_primitive(primitive, offset, exposure(primitive));
+        }
+
+        triangles
+    }
+
+    /// The exposure of the primitive is ignored, `exposure` is used instead.
+    pub(crate) fn add_primitive(&mut self, primitive: &GerberPrimitive, offset: Vector2<f64>, exposure: Exposure) {
+        match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => self.add_circle(*center + offset, diameter / 2.0, exposure),
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let origin = *origin + offset;
+                let corners = [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)]
+                    .map(|(x, y)| origin + Vector2::new(x, y));
+                self.add_fan(&corners, exposure);
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => self.add_stroke(&[*start + offset, *end + offset], width / 2.0, exposure),
+            GerberPrimitive::Arc(arc) => {
+                let center = arc.center + offset;
+                let points = arc
+                    .generate_points()
+                    .into_iter()
+                    .map(|point| center + point.coords)
+                    .collect::<Vec<_>>();
+                self.add_stroke(&points, arc.width / 2.0, exposure);
+            }
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let center = *center + offset;
+                if geometry.is_convex {
+                    let vertices = geometry
+                        .relative_vertices
+                        .iter()
+                        .map(|vertex| center + vertex.coords)
+                        .collect::<Vec<_>>();
+                    self.add_fan(&vertices, exposure);
+                } else if let Some(tessellation) = &geometry.tessellation {
+                    let base = self.positions.len() as u32;
+                    self.positions.extend(
+                        tessellation
+                            .vertices
+                            .iter()
+                            .map(|[x, y]| center + Vector2::new(*x as f64, *y as f64)),
+                    );
+                    self.exposures
+                        .resize(self.positions.len(), exposure);
+                    self.indices.extend(
+                        tessellation
+                            .indices
+                            .iter()
+                            .map(|index| base + index),
+                    );
+                }
+            }
+        }
+    }
+
+    /// A convex polygon.
+    fn add_fan(&mut self, vertices: &[Point2<f64>], exposure: Exposure) {
+        if vertices.len() < 3 {
+            return;
+        }
+        let base = self.positions.len() as u32;
+        self.positions
+            .extend_from_slice(vertices);
+        self.exposures
+            .resize(self.positions.len(), exposure);
+        for index in 1..vertices.len() as u32 - 1 {
+            self.indices
+                .extend([base, base + index, base + index + 1]);
+        }
+    }
+
+    fn add_circle(&mut self, center: Point2<f64>, radius: f64, exposure: Exposure) {
+        let vertices = (0..MESH_CIRCLE_SEGMENTS)
+            .map(|index| {
+                let angle = TAU * index as f64 / MESH_CIRCLE_SEGMENTS as f64;
+                center + Vector2::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect::<Vec<_>>();
+        self.add_fan(&vertices, exposure);
+    }
+
+    /// A polyline with round caps and joins.
+    fn add_stroke(&mut self, points: &[Point2<f64>], radius: f64, exposure: Exposure) {
+        for point in points {
+            self.add_circle(*point, radius, exposure);
+        }
+        for segment in points.windows(2) {
+            let direction = segment[1] - segment[0];
+            let length = direction.norm();
+            if length == 0.0 {
+                continue;
+            }
+            let normal = Vector2::new(-direction.y, direction.x) * (radius / length);
+            self.add_fan(
+                &[
+                    segment[0] - normal,
+                    segment[1] - normal,
+                    segment[1] + normal,
+                    segment[0] + normal,
+                ],
+                exposure,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Command, ExtendedCode, Polarity, Rectangular, Unit};
+
+    use super::*;
+    use crate::testing::{aperture, flash, header};
+    use crate::Mirroring;
+
+    #[test]
+    fn test_triangles_of_rectangle_and_line() {
+        // given
+        let mut triangles = Triangles::default();
+        let zero = Vector2::new(0.0, 0.0);
+
+        // when
+        triangles.add_primitive(
+            &GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin: Point2::new(0.0, 0.0),
+                width: 2.0,
+                height: 1.0,
+                exposure: Exposure::CutOut,
+            }),
+            Vector2::new(10.0, 0.0),
+            Exposure::CutOut,
+        );
+        triangles.add_primitive(
+            &GerberPrimitive::Line(LineGerberPrimitive {
+                start: Point2::new(0.0, 0.0),
+                end: Point2::new(5.0, 0.0),
+                width: 1.0,
+                exposure: Exposure::Add,
+            }),
+            zero,
+            Exposure::Add,
+        );
+
+        // then
+        // the rectangle, two caps and the body of the line
+        assert_eq!(triangles.indices.len() / 3, 2 + 2 * (MESH_CIRCLE_SEGMENTS - 2) + 2);
+        assert_eq!(triangles.positions[0], Point2::new(10.0, 0.0));
+        assert_eq!(triangles.exposures[0], Exposure::CutOut);
+        assert_eq!(triangles.exposures[4], Exposure::Add);
+    }
+
+    #[test]
+    fn test_layer_mesh() {
+        // given
+        // a 2x2mm square at (1, 1), and a clear square over it
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Rectangle(Rectangular::new(2.0, 2.0))));
+        commands.extend([
+            flash(1.0, 1.0),
+            Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)),
+            flash(1.0, 1.0),
+        ]);
+        let layer = GerberLayer::new(commands);
+        let options = LayerMeshOptions {
+            color: [255, 0, 0, 255],
+            transform: GerberTransform {
+                mirroring: Mirroring {
+                    x: true,
+                    y: false,
+                },
+                ..GerberTransform::default()
+            },
+            ..LayerMeshOptions::default()
+        };
+
+        // when
+        let mesh = LayerMesh::new(&layer, &options);
+
+        // then
+        assert_eq!(mesh.triangle_count(), 4);
+        assert_eq!(mesh.positions.len(), mesh.colors.len());
+        assert_eq!(mesh.positions[0], [0.0, 0.0]);
+        assert_eq!(mesh.positions[1], [-2.0, 0.0]);
+        assert_eq!(mesh.colors[0], [255, 0, 0, 255]);
+        assert_eq!(mesh.colors[4], [0, 0, 0, 255]);
+    }
+}
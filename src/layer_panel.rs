@@ -0,0 +1,272 @@
+use egui::{Color32, Frame, Slider, Stroke, Ui};
+
+use crate::{ColorTheme, LayerFocus, LayerInfo, LayerSet};
+
+/// How a layer is displayed, see [`LayerPanel`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerDisplay {
+    pub visible: bool,
+    pub color: Color32,
+    /// From `0.0` to `1.0`, use it for [`crate::RenderConfiguration::opacity`], instead of changing the alpha of the
+    /// color.
+    pub opacity: f32,
+}
+
+/// A change made using a [`LayerPanel`], layers are identified by their index in the [`LayerSet`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerPanelEvent {
+    Visibility {
+        index: usize,
+        visible: bool,
+    },
+    Color {
+        index: usize,
+        color: Color32,
+    },
+    Opacity {
+        index: usize,
+        opacity: f32,
+    },
+    /// The focused layer changed, `None` if no layer is focused, see [`LayerPanel::focus`].
+    Focus {
+        index: Option<usize>,
+    },
+    /// The layer was moved from one position in the drawing order to another, see [`LayerPanel::order`].
+    Reordered {
+        index: usize,
+        from: usize,
+        to: usize,
+    },
+}
+
+/// A panel listing the layers of a [`LayerSet`], with a visibility checkbox, a color picker, an opacity slider and a
+/// focus toggle for each layer, and a handle for dragging the layers into another drawing order.
+///
+/// The layers are listed top-most first, i.e. in reverse drawing order.  The layer set isn't changed, keep the panel
+/// between frames, e.g. in your app state, and paint the layers using [`LayerPanel::visible_layers`], or react to the
+/// events returned by [`LayerPanel::show`], e.g. by updating the [`LayerInfo`] of the layer.
+#[derive(Debug, Clone, Default)]
+pub struct LayerPanel {
+    displays: Vec<LayerDisplay>,
+    /// Indices of the layers in drawing order.
+    order: Vec<usize>,
+    focused: Option<usize>,
+}
+
+impl LayerPanel {
+    /// The layers are shown in the drawing order of the layer set, with their visibility, color and opacity, see
+    /// [`LayerInfo`], layers without a color get the color of the theme.
+    pub fn new(layer_set: &LayerSet, theme: ColorTheme) -> Self {
+        Self {
+            displays: layer_set
+                .iter()
+                .map(|info| LayerDisplay {
+                    visible: info.visible,
+                    color: info.color32(theme),
+                    opacity: info.opacity,
+                })
+                .collect(),
+            order: (0..layer_set.len()).collect(),
+            focused: None,
+        }
+    }
+
+    pub fn display(&self, index: usize) -> Option<&LayerDisplay> {
+        self.displays.get(index)
+    }
+
+    pub fn display_mut(&mut self, index: usize) -> Option<&mut LayerDisplay> {
+        self.displays.get_mut(index)
+    }
+
+    /// The index of the focused layer, if any.
+    pub fn focused(&self) -> Option<usize> {
+        self.focused
+    }
+
+    pub fn set_focused(&mut self, index: Option<usize>) {
+        self.focused = index;
+    }
+
+    /// The x-ray mode for the focused layer, with the other layers dimmed to `dimmed_opacity`, `None` if no layer is
+    /// focused.  Use [`LayerFocus::painting_order`] with [`LayerPanel::order`] to paint the focused layer on top.
+    pub fn focus(&self, dimmed_opacity: f32) -> Option<LayerFocus> {
+        self.focused.map(|index| LayerFocus {
+            dimmed_opacity,
+            ..LayerFocus::new(index)
+        })
+    }
+
+    /// The indices of the layers in drawing order, bottom-most first.
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// The visible layers in drawing order, with how to display them.
+    pub fn visible_layers<'a>(
+        &'a self,
+        layer_set: &'a LayerSet,
+    ) -> impl Iterator<Item = (&'a LayerInfo, &'a LayerDisplay)> + 'a {
+        self.order
+            .iter()
+            .filter_map(|index| Some((layer_set.layers().get(*index)?, self.displays.get(*index)?)))
+            .filter(|(_, display)| display.visible)
+    }
+
+    /// Moves the layer at position `from` in the drawing order so it is drawn before the layer at position `to`, or
+    /// last if `to` is the number of layers.  Returns the new position of the layer.
+    pub fn move_layer(&mut self, from: usize, to: usize) -> usize {
+        let index = self.order.remove(from);
+        let to = match to > from {
+            true => to - 1,
+            false => to,
+        }
+        .min(self.order.len());
+        self.order.insert(to, index);
+        to
+    }
+
+    /// Shows the panel, returns the changes made this frame.
+    ///
+    /// If the number of layers changed, e.g. a layer was added, the panel is reset, see [`LayerPanel::new`], using the
+    /// [`ColorTheme::Classic`] colors.
+    pub fn show(&mut self, ui: &mut Ui, layer_set: &LayerSet) -> Vec<LayerPanelEvent> {
+        if self.displays.len() != layer_set.len() {
+            *self = Self::new(layer_set, ColorTheme::Classic);
+        }
+
+        let mut events = vec![];
+        // the position in the drawing order to move the dragged layer to
+        let mut drop_position = None;
+
+        let (_, dragged) = ui.dnd_drop_zone::<usize, ()>(Frame::NONE, |ui| {
+            for position in (0..self.order.len()).rev() {
+                let index = self.order[position];
+                let Some(info) = layer_set.layers().get(index) else {
+                    continue;
+                };
+                let display = &mut self.displays[index];
+
+                let row = ui
+                    .horizontal(|ui| {
+                        ui.dnd_drag_source(ui.id().with(("layer_panel", index)), position, |ui| {
+                            ui.label("☰");
+                        });
+                        if ui
+                            .checkbox(&mut display.visible, "")
+                            .changed()
+                        {
+                            events.push(LayerPanelEvent::Visibility {
+                                index,
+                                visible: display.visible,
+                            });
+                        }
+                        if ui
+                            .color_edit_button_srgba(&mut display.color)
+                            .changed()
+                        {
+                            events.push(LayerPanelEvent::Color {
+                                index,
+                                color: display.color,
+                            });
+                        }
+                        if ui
+                            .add(Slider::new(&mut display.opacity, 0.0..=1.0).show_value(false))
+                            .on_hover_text("Opacity")
+                            .changed()
+                        {
+                            events.push(LayerPanelEvent::Opacity {
+                                index,
+                                opacity: display.opacity,
+                            });
+                        }
+                        let focused = self.focused == Some(index);
+                        if ui
+                            .selectable_label(focused, "🔍")
+                            .on_hover_text("Focus, dims the other layers")
+                            .clicked()
+                        {
+                            self.focused = match focused {
+                                true => None,
+                                false => Some(index),
+                            };
+                            events.push(LayerPanelEvent::Focus {
+                                index: self.focused,
+                            });
+                        }
+                        ui.label(&info.name);
+                    })
+                    .response;
+
+                let pointer = ui.input(|i| i.pointer.interact_pos());
+                if let (Some(pointer), Some(_)) = (pointer, row.dnd_hover_payload::<usize>()) {
+                    // the list is top-most first, so dropping above a row draws the layer after it
+                    let above = pointer.y < row.rect.center().y;
+                    let y = match above {
+                        true => row.rect.top(),
+                        false => row.rect.bottom(),
+                    };
+                    let color = ui.visuals().selection.stroke.color;
+                    ui.painter()
+                        .hline(row.rect.x_range(), y, Stroke::new(2.0, color));
+                    drop_position = Some(match above {
+                        true => position + 1,
+                        false => position,
+                    });
+                }
+            }
+        });
+
+        if let (Some(from), Some(to)) = (dragged, drop_position) {
+            let from = *from;
+            let index = self.order[from];
+            let to = self.move_layer(from, to);
+            if to != from {
+                events.push(LayerPanelEvent::Reordered {
+                    index,
+                    from,
+                    to,
+                });
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{GerberLayer, LayerType};
+
+    #[rstest]
+    // to the top
+    #[case(0, 3, vec![1, 2, 0], 2)]
+    // to the bottom
+    #[case(2, 0, vec![2, 0, 1], 0)]
+    // onto itself
+    #[case(1, 1, vec![0, 1, 2], 1)]
+    #[case(1, 2, vec![0, 1, 2], 1)]
+    fn test_move_layer(
+        #[case] from: usize,
+        #[case] to: usize,
+        #[case] expected_order: Vec<usize>,
+        #[case] expected_position: usize,
+    ) {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::BottomCopper, "bottom", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::TopCopper, "top", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::Outline, "outline", GerberLayer::new(vec![]));
+        let mut panel = LayerPanel::new(&layer_set, ColorTheme::Classic);
+
+        // when
+        let position = panel.move_layer(from, to);
+
+        // then
+        assert_eq!(panel.order(), expected_order);
+        assert_eq!(position, expected_position);
+    }
+}
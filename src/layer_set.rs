@@ -0,0 +1,705 @@
+#[cfg(feature = "egui")]
+use egui::Color32;
+
+use crate::attributes::Attributes;
+use crate::geometry::{BoundingBox, GerberTransform, Mirroring};
+use crate::{ColorTheme, GerberLayer};
+
+/// The function of a layer in a PCB, used to order and color the layers of a [`LayerSet`], see
+/// [`LayerType::stack_position`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerType {
+    BottomPaste,
+    BottomSilkscreen,
+    BottomSolderMask,
+    BottomCopper,
+    /// 1-based inner layer number, `InnerCopper(1)` is the layer below the top copper layer.
+    InnerCopper(u32),
+    /// 1-based inner plane number, e.g. Altium's `.GP1`, the plane layers are numbered separately from the signal
+    /// layers, see [`LayerType::stack_position`].
+    InnerPlane(u32),
+    TopCopper,
+    TopSolderMask,
+    TopSilkscreen,
+    TopPaste,
+    Drill,
+    Outline,
+    Other,
+}
+
+impl LayerType {
+    /// Classifies a file using the naming conventions of KiCad, Altium/Protel, Eagle and DipTrace.
+    ///
+    /// Returns `None` if the file name does not match any known convention, e.g. README.txt.
+    pub fn from_filename(file_name: &str) -> Option<Self> {
+        let file_name = file_name.to_lowercase();
+        let (stem, extension) = match file_name.rsplit_once('.') {
+            Some((stem, extension)) => (stem, extension),
+            None => (file_name.as_str(), ""),
+        };
+
+        // Altium/Protel and Eagle use the extension for the layer function
+        let by_extension = match extension {
+            "gtl" | "cmp" | "top" => Some(LayerType::TopCopper),
+            "gbl" | "sol" | "bot" => Some(LayerType::BottomCopper),
+            "gts" | "stc" | "smt" => Some(LayerType::TopSolderMask),
+            "gbs" | "sts" | "smb" => Some(LayerType::BottomSolderMask),
+            "gto" | "plc" | "sst" => Some(LayerType::TopSilkscreen),
+            "gbo" | "pls" | "ssb" => Some(LayerType::BottomSilkscreen),
+            "gtp" | "crc" | "spt" => Some(LayerType::TopPaste),
+            "gbp" | "crs" | "spb" => Some(LayerType::BottomPaste),
+            "gko" | "gm1" | "gml" | "dim" | "outline" => Some(LayerType::Outline),
+            _ => match extension.strip_prefix("gp") {
+                Some(number) => inner_layer_number(number).map(LayerType::InnerPlane),
+                None => extension
+                    .strip_prefix('g')
+                    .and_then(inner_layer_number)
+                    .map(LayerType::InnerCopper),
+            },
+        };
+        if by_extension.is_some() {
+            return by_extension;
+        }
+
+        if !GERBER_EXTENSIONS.contains(&extension) {
+            return None;
+        }
+
+        // KiCad, e.g. `board-F_Cu.gbr`, `board-In1_Cu.gbr`, `board-Edge_Cuts.gbr`, `board-PTH.gbr`
+        let normalized = stem.replace(['-', '_', '.', ' '], "");
+        let ends_with_any = |suffixes: &[&str]| {
+            suffixes
+                .iter()
+                .any(|suffix| normalized.ends_with(suffix))
+        };
+
+        if let Some(number) = kicad_inner_layer_number(stem) {
+            return Some(LayerType::InnerCopper(number));
+        }
+
+        // the specific suffixes first, e.g. `soldermasktop` also ends with `top`
+        let layer_type = if ends_with_any(&["fmask", "topmask", "topsoldermask", "soldermasktop"]) {
+            LayerType::TopSolderMask
+        } else if ends_with_any(&["bmask", "bottommask", "bottomsoldermask", "soldermaskbottom"]) {
+            LayerType::BottomSolderMask
+        } else if ends_with_any(&[
+            "fsilks",
+            "fsilkscreen",
+            "topsilk",
+            "topsilkscreen",
+            "topoverlay",
+            "legendtop",
+        ]) {
+            LayerType::TopSilkscreen
+        } else if ends_with_any(&[
+            "bsilks",
+            "bsilkscreen",
+            "bottomsilk",
+            "bottomsilkscreen",
+            "bottomoverlay",
+            "legendbottom",
+        ]) {
+            LayerType::BottomSilkscreen
+        } else if ends_with_any(&["fpaste", "toppaste", "pastetop"]) {
+            LayerType::TopPaste
+        } else if ends_with_any(&["bpaste", "bottompaste", "pastebottom"]) {
+            LayerType::BottomPaste
+        } else if ends_with_any(&["edgecuts", "boardoutline", "outline", "profile", "mechanical1"]) {
+            LayerType::Outline
+        } else if ends_with_any(&["pth", "npth", "drl", "drill", "drills", "platedholes", "nonplatedholes"]) {
+            LayerType::Drill
+        } else if ends_with_any(&["fcu", "topcopper", "coppertop", "top", "toplayer"]) {
+            LayerType::TopCopper
+        } else if ends_with_any(&["bcu", "bottomcopper", "bottom", "bottomlayer"]) {
+            LayerType::BottomCopper
+        } else {
+            LayerType::Other
+        };
+
+        Some(layer_type)
+    }
+
+    /// Classifies a layer using the X2 `.FileFunction` file attribute, e.g. `%TF.FileFunction,Copper,L1,Top*%`.
+    ///
+    /// This is more reliable than the file name and should be preferred when present.
+    pub fn from_file_attributes(attributes: &Attributes) -> Option<Self> {
+        let values = &attributes.get(".FileFunction")?.values;
+
+        let side = |index: usize| values.get(index).map(String::as_str);
+
+        let layer_type = match values.first().map(String::as_str)? {
+            "Copper" => match side(2) {
+                Some("Top") => LayerType::TopCopper,
+                Some("Bot") => LayerType::BottomCopper,
+                _ => {
+                    // `L2` = inner layer 1
+                    let number = side(1)?
+                        .strip_prefix('L')?
+                        .parse::<u32>()
+                        .ok()?;
+                    LayerType::InnerCopper(number.saturating_sub(1).max(1))
+                }
+            },
+            "Soldermask" => match side(1) {
+                Some("Bot") => LayerType::BottomSolderMask,
+                _ => LayerType::TopSolderMask,
+            },
+            "Legend" => match side(1) {
+                Some("Bot") => LayerType::BottomSilkscreen,
+                _ => LayerType::TopSilkscreen,
+            },
+            "Paste" => match side(1) {
+                Some("Bot") => LayerType::BottomPaste,
+                _ => LayerType::TopPaste,
+            },
+            "Profile" => LayerType::Outline,
+            "Plated" | "NonPlated" => LayerType::Drill,
+            _ => LayerType::Other,
+        };
+
+        Some(layer_type)
+    }
+
+    /// The position of the layer in the stack, from the bottom of the board to the top, which is the order in which the
+    /// layers are drawn when viewed from the top, followed by the drill, outline and other layers.
+    ///
+    /// File names do not say where the planes are between the signal layers, so the inner planes are placed below the
+    /// inner signal layers.
+    pub fn stack_position(&self) -> u64 {
+        const PLANE: u64 = 4;
+        const INNER: u64 = PLANE + u32::MAX as u64 + 1;
+        const TOP: u64 = INNER + u32::MAX as u64 + 1;
+
+        match self {
+            LayerType::BottomPaste => 0,
+            LayerType::BottomSilkscreen => 1,
+            LayerType::BottomSolderMask => 2,
+            LayerType::BottomCopper => 3,
+            // `InnerCopper(1)` is the inner layer closest to the top
+            LayerType::InnerPlane(number) => PLANE + u64::from(u32::MAX - number),
+            LayerType::InnerCopper(number) => INNER + u64::from(u32::MAX - number),
+            LayerType::TopCopper => TOP,
+            LayerType::TopSolderMask => TOP + 1,
+            LayerType::TopSilkscreen => TOP + 2,
+            LayerType::TopPaste => TOP + 3,
+            LayerType::Drill => TOP + 4,
+            LayerType::Outline => TOP + 5,
+            LayerType::Other => TOP + 6,
+        }
+    }
+
+    /// The side of the board the layer is on, `None` for inner copper and planes, drill, outline and other layers.
+    pub fn side(&self) -> Option<BoardSide> {
+        match self {
+            LayerType::BottomPaste
+            | LayerType::BottomSilkscreen
+            | LayerType::BottomSolderMask
+            | LayerType::BottomCopper => Some(BoardSide::Bottom),
+            LayerType::TopCopper | LayerType::TopSolderMask | LayerType::TopSilkscreen | LayerType::TopPaste => {
+                Some(BoardSide::Top)
+            }
+            LayerType::InnerCopper(_)
+            | LayerType::InnerPlane(_)
+            | LayerType::Drill
+            | LayerType::Outline
+            | LayerType::Other => None,
+        }
+    }
+
+    /// The color of the layer in the [`ColorTheme::Classic`] theme.
+    #[cfg(feature = "egui")]
+    pub fn default_color(&self) -> Color32 {
+        ColorTheme::Classic.color(*self)
+    }
+}
+
+/// Which side of the board is viewed, see [`LayerSet::view_from`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BoardSide {
+    #[default]
+    Top,
+    /// The board is mirrored horizontally, as if it was flipped over.
+    Bottom,
+}
+
+impl BoardSide {
+    /// The other side, e.g. for a button that flips the board over.
+    pub fn opposite(&self) -> Self {
+        match self {
+            BoardSide::Top => BoardSide::Bottom,
+            BoardSide::Bottom => BoardSide::Top,
+        }
+    }
+
+    /// The transform for viewing the side, the bottom is mirrored around the center of `bounds`, so the board stays in
+    /// the same place.
+    pub fn transform(&self, bounds: &BoundingBox) -> GerberTransform {
+        GerberTransform {
+            mirroring: Mirroring {
+                x: *self == BoardSide::Bottom,
+                y: false,
+            },
+            origin: bounds.center().coords,
+            ..GerberTransform::default()
+        }
+    }
+}
+
+/// Extensions used for gerber files that don't encode the layer function in the extension.
+pub const GERBER_EXTENSIONS: [&str; 5] = ["gbr", "ger", "gbx", "pho", "art"];
+
+/// KiCad inner layers are named `In<n>_Cu` e.g. `board-In1_Cu.gbr`
+/// The 1-based number of an Altium/Protel inner layer extension without the prefix, e.g. `1` for `.G1` and `.GP1`.
+fn inner_layer_number(number: &str) -> Option<u32> {
+    number
+        .parse::<u32>()
+        .ok()
+        .filter(|number| *number > 0)
+}
+
+fn kicad_inner_layer_number(stem: &str) -> Option<u32> {
+    let index = stem.rfind("in")?;
+    let rest = stem[index + 2..].strip_suffix("_cu")?;
+    rest.parse::<u32>().ok()
+}
+
+/// A layer of a [`LayerSet`].
+#[derive(Debug)]
+pub struct LayerInfo {
+    pub layer_type: LayerType,
+    /// Typically the file name.
+    pub name: String,
+    pub layer: GerberLayer,
+    /// `true` by default, hidden layers are skipped by [`LayerSet::visible_layers`] and
+    /// [`LayerSet::combined_bounding_box`].
+    pub visible: bool,
+    /// Straight (not premultiplied) RGBA, `None` by default, to use the color of a [`ColorTheme`], see
+    /// [`LayerInfo::rgba`].
+    pub color: Option<[u8; 4]>,
+    /// From `0.0` to `1.0`, `1.0` by default, use it for the opacity of the render configuration, instead of changing
+    /// the alpha of the color, the raster renderers apply it to the color, see [`LayerInfo::with_opacity`].
+    pub opacity: f32,
+    /// The transform of this layer only, applied before the transform of the view, e.g. to align a layer that was
+    /// exported with an offset, see [`LayerInfo::combined_transform`].
+    pub transform: GerberTransform,
+}
+
+impl LayerInfo {
+    /// The color of the layer, or the color of its type in the theme if it has none.
+    pub fn rgba(&self, theme: ColorTheme) -> [u8; 4] {
+        self.color
+            .unwrap_or_else(|| theme.rgba(self.layer_type))
+    }
+
+    /// The color of the layer, or the color of its type in the theme if it has none.
+    #[cfg(feature = "egui")]
+    pub fn color32(&self, theme: ColorTheme) -> Color32 {
+        let [r, g, b, a] = self.rgba(theme);
+        Color32::from_rgba_unmultiplied(r, g, b, a)
+    }
+
+    /// The color with its alpha multiplied by the opacity of the layer, straight (not premultiplied) RGBA.
+    pub fn with_opacity(&self, rgba: [u8; 4]) -> [u8; 4] {
+        let [red, green, blue, alpha] = rgba;
+        let alpha = (alpha as f32 * self.opacity.clamp(0.0, 1.0)).round() as u8;
+        [red, green, blue, alpha]
+    }
+
+    /// The transform of the layer, resolved against the bounding box of the layer, e.g. for exporting the layer.
+    pub fn resolved_transform(&self) -> GerberTransform {
+        self.transform
+            .resolve(self.layer.bounding_box())
+    }
+
+    /// The transform of the layer followed by the transform of the view, e.g. [`SideView::transform`], both are
+    /// resolved against the bounding box of the layer.
+    pub fn combined_transform(&self, transform: &GerberTransform) -> GerberTransform {
+        self.resolved_transform()
+            .combine(&transform.resolve(self.layer.bounding_box()))
+    }
+
+    /// The bounding box of the layer, after the transform of the layer.
+    pub fn bounding_box(&self) -> BoundingBox {
+        self.layer
+            .bounding_box()
+            .apply_transform(&self.transform)
+    }
+}
+
+/// The layers of a [`LayerSet`], in drawing order, and the transform for viewing the board from one side, see
+/// [`LayerSet::view_from`].
+#[derive(Debug, Clone)]
+pub struct SideView<'a> {
+    pub side: BoardSide,
+    /// Use the same transform for all the layers, so they stay aligned.  Rotation, scaling and offsets can be added to
+    /// it, they are applied after the mirroring.
+    pub transform: GerberTransform,
+    pub layers: Vec<&'a LayerInfo>,
+}
+
+/// A set of layers, e.g. all the gerber files of a PCB, in drawing order.
+#[derive(Debug, Default)]
+pub struct LayerSet {
+    layers: Vec<LayerInfo>,
+}
+
+impl LayerSet {
+    /// Adds a layer, keeping the layers in drawing order, layers of the same type are kept in the order they were
+    /// added.
+    pub fn add(&mut self, layer_type: LayerType, name: impl Into<String>, layer: GerberLayer) {
+        let index = self
+            .layers
+            .partition_point(|existing| existing.layer_type.stack_position() <= layer_type.stack_position());
+        self.layers.insert(index, LayerInfo {
+            layer_type,
+            name: name.into(),
+            layer,
+            visible: true,
+            color: None,
+            opacity: 1.0,
+            transform: GerberTransform::default(),
+        });
+    }
+
+    /// The layers, in drawing order when viewed from the top.
+    pub fn layers(&self) -> &[LayerInfo] {
+        &self.layers
+    }
+
+    pub fn layers_mut(&mut self) -> &mut [LayerInfo] {
+        &mut self.layers
+    }
+
+    /// Returns the first layer of the given type.
+    pub fn get(&self, layer_type: LayerType) -> Option<&LayerInfo> {
+        self.layers
+            .iter()
+            .find(|info| info.layer_type == layer_type)
+    }
+
+    /// Returns the first layer of the given type, e.g. to hide it or change its color.
+    pub fn get_mut(&mut self, layer_type: LayerType) -> Option<&mut LayerInfo> {
+        self.layers
+            .iter_mut()
+            .find(|info| info.layer_type == layer_type)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LayerInfo> {
+        self.layers.iter()
+    }
+
+    /// The visible layers, in drawing order when viewed from the top.
+    pub fn visible_layers(&self) -> impl Iterator<Item = &LayerInfo> {
+        self.layers
+            .iter()
+            .filter(|info| info.visible)
+    }
+
+    /// Shows or hides all the layers of the given type, returns `false` if there are none.
+    pub fn set_visible(&mut self, layer_type: LayerType, visible: bool) -> bool {
+        let mut found = false;
+        for info in self
+            .layers
+            .iter_mut()
+            .filter(|info| info.layer_type == layer_type)
+        {
+            info.visible = visible;
+            found = true;
+        }
+        found
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// The bounding box of all the layers, after the transform of each layer, the layers are assumed to be in the same
+    /// units.
+    pub fn bounding_box(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::default();
+        for info in self
+            .layers
+            .iter()
+            .filter(|info| !info.layer.is_empty())
+        {
+            bounds.expand(&info.bounding_box());
+        }
+        bounds
+    }
+
+    /// The bounding box of the visible, non-empty, layers, after the transform of each layer, e.g. for fitting the
+    /// layers to the viewport, the layers are assumed to be in the same units.
+    pub fn combined_bounding_box(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::default();
+        for info in self
+            .visible_layers()
+            .filter(|info| !info.layer.is_empty())
+        {
+            bounds.expand(&info.bounding_box());
+        }
+        bounds
+    }
+
+    /// The bounding box of the board, i.e. of the outline layer, or of all the layers if there is no outline layer or it
+    /// is empty, e.g. for the board dimensions, after the transform of each layer.
+    pub fn board_bounding_box(&self) -> BoundingBox {
+        self.get(LayerType::Outline)
+            .filter(|info| !info.layer.is_empty())
+            .map(LayerInfo::bounding_box)
+            .unwrap_or_else(|| self.bounding_box())
+    }
+
+    /// Returns the layers of the side, and the layers that belong to neither side, e.g. drill and outline layers, in
+    /// drawing order, and the transform for viewing the board from the side, see [`LayerSet::view_from`].
+    ///
+    /// Mirrors the board, reverses the drawing order and hides the layers of the other side in one call, e.g.
+    /// `layer_set.view_side(side.opposite())` to flip the board over.
+    pub fn view_side(&self, side: BoardSide) -> SideView<'_> {
+        let mut view = self.view_from(side);
+        view.layers
+            .retain(|info| info.layer_type.side() != Some(side.opposite()));
+        view
+    }
+
+    /// Returns the layers in drawing order and the transform for viewing the board from the given side, instead of
+    /// mirroring and re-ordering the layers by hand.
+    ///
+    /// From the bottom the board is mirrored horizontally and the order of the layers of the board is reversed, so the
+    /// bottom layers are drawn on top.  Drill, outline and other layers are always drawn last.  To only show the layers
+    /// of one side use [`LayerSet::view_side`].
+    pub fn view_from(&self, side: BoardSide) -> SideView<'_> {
+        let (mut layers, overlays): (Vec<_>, Vec<_>) = self
+            .layers
+            .iter()
+            .partition(|info| info.layer_type.stack_position() < LayerType::Drill.stack_position());
+        if side == BoardSide::Bottom {
+            layers.reverse();
+        }
+        layers.extend(overlays);
+
+        SideView {
+            side,
+            transform: side.transform(&self.bounding_box()),
+            layers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Unit};
+    use nalgebra::{Point2, Vector2};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::testing::flash_layer;
+
+    /// A layer with a 2mm circle flashed at x,0.
+    fn circle_layer(x: f64) -> GerberLayer {
+        flash_layer(Unit::Millimeters, Aperture::Circle(Circle::new(2.0)), x, 0.0)
+    }
+
+    #[rstest]
+    // KiCad
+    #[case("board-F_Cu.gbr", Some(LayerType::TopCopper))]
+    #[case("board-B_Cu.gbr", Some(LayerType::BottomCopper))]
+    #[case("board-In2_Cu.gbr", Some(LayerType::InnerCopper(2)))]
+    #[case("board-F_Mask.gbr", Some(LayerType::TopSolderMask))]
+    #[case("board-B_Silkscreen.gbr", Some(LayerType::BottomSilkscreen))]
+    #[case("board-F_Paste.gbr", Some(LayerType::TopPaste))]
+    #[case("board-Edge_Cuts.gbr", Some(LayerType::Outline))]
+    #[case("board-NPTH.gbr", Some(LayerType::Drill))]
+    // Altium/Protel
+    #[case("board.GTL", Some(LayerType::TopCopper))]
+    #[case("board.G1", Some(LayerType::InnerCopper(1)))]
+    #[case("board.GP1", Some(LayerType::InnerPlane(1)))]
+    #[case("board.GP0", None)]
+    #[case("board.GBS", Some(LayerType::BottomSolderMask))]
+    #[case("board.GKO", Some(LayerType::Outline))]
+    // Eagle
+    #[case("board.cmp", Some(LayerType::TopCopper))]
+    #[case("board.plc", Some(LayerType::TopSilkscreen))]
+    #[case("board.crs", Some(LayerType::BottomPaste))]
+    // DipTrace
+    #[case("TopSilk.gbr", Some(LayerType::TopSilkscreen))]
+    #[case("Board_SolderMaskTop.gbr", Some(LayerType::TopSolderMask))]
+    #[case("Board_LegendBottom.gbr", Some(LayerType::BottomSilkscreen))]
+    #[case("Board_PasteTop.gbr", Some(LayerType::TopPaste))]
+    #[case("Board_Top.gbr", Some(LayerType::TopCopper))]
+    #[case("BoardOutline.gbr", Some(LayerType::Outline))]
+    #[case("TopAssembly.gbr", Some(LayerType::Other))]
+    // not gerber files
+    #[case("README.txt", None)]
+    #[case("board.drl", None)]
+    fn test_from_filename(#[case] file_name: &str, #[case] expected: Option<LayerType>) {
+        assert_eq!(LayerType::from_filename(file_name), expected);
+    }
+
+    #[test]
+    fn test_drawing_order() {
+        // given
+        let mut layer_set = LayerSet::default();
+
+        // when
+        layer_set.add(LayerType::Outline, "outline", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::TopCopper, "top", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::BottomCopper, "bottom", GerberLayer::new(vec![]));
+
+        // then
+        let names = layer_set
+            .iter()
+            .map(|info| info.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["bottom", "top", "outline"]);
+    }
+
+    #[rstest]
+    #[case(BoardSide::Top, vec!["bottom", "inner", "top", "drill", "outline"], false)]
+    #[case(BoardSide::Bottom, vec!["top", "inner", "bottom", "drill", "outline"], true)]
+    fn test_view_from(#[case] side: BoardSide, #[case] expected_names: Vec<&str>, #[case] expected_mirroring: bool) {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::Outline, "outline", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::Drill, "drill", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::TopCopper, "top", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::InnerCopper(1), "inner", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::BottomCopper, "bottom", GerberLayer::new(vec![]));
+
+        // when
+        let view = layer_set.view_from(side);
+
+        // then
+        let names = view
+            .layers
+            .iter()
+            .map(|info| info.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, expected_names);
+        assert_eq!(view.transform.mirroring.x, expected_mirroring);
+        assert!(!view.transform.mirroring.y);
+    }
+
+    #[rstest]
+    #[case(true, Point2::new(-1.0, -1.0), Point2::new(1.0, 1.0))]
+    // falls back to all the layers
+    #[case(false, Point2::new(9.0, -1.0), Point2::new(21.0, 1.0))]
+    fn test_board_bounding_box(
+        #[case] with_outline: bool,
+        #[case] expected_min: Point2<f64>,
+        #[case] expected_max: Point2<f64>,
+    ) {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::TopCopper, "top", circle_layer(10.0));
+        layer_set.add(LayerType::BottomCopper, "bottom", circle_layer(20.0));
+        if with_outline {
+            layer_set.add(LayerType::Outline, "outline", circle_layer(0.0));
+        }
+
+        // when
+        let bounds = layer_set.board_bounding_box();
+
+        // then
+        assert_eq!((bounds.min, bounds.max), (expected_min, expected_max));
+    }
+
+    #[test]
+    fn test_board_bounding_box_with_layer_transform() {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::TopCopper, "top", circle_layer(10.0));
+        layer_set.add(LayerType::Outline, "outline", circle_layer(0.0));
+        layer_set
+            .get_mut(LayerType::Outline)
+            .unwrap()
+            .transform
+            .offset = Vector2::new(0.0, 5.0);
+
+        // when
+        let bounds = layer_set.board_bounding_box();
+
+        // then
+        assert_eq!(
+            (bounds.min, bounds.max),
+            (Point2::new(-1.0, 4.0), Point2::new(1.0, 6.0))
+        );
+    }
+
+    #[test]
+    fn test_combined_bounding_box() {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::TopCopper, "top", circle_layer(10.0));
+        layer_set.add(LayerType::BottomCopper, "bottom", circle_layer(20.0));
+        layer_set.add(LayerType::Outline, "outline", GerberLayer::new(vec![]));
+        layer_set.set_visible(LayerType::BottomCopper, false);
+        layer_set
+            .get_mut(LayerType::TopCopper)
+            .unwrap()
+            .transform
+            .offset = Vector2::new(0.0, 5.0);
+
+        // when
+        let bounds = layer_set.combined_bounding_box();
+
+        // then
+        assert_eq!(
+            (bounds.min, bounds.max),
+            (Point2::new(9.0, 4.0), Point2::new(11.0, 6.0))
+        );
+    }
+
+    #[rstest]
+    #[case(BoardSide::Top, vec!["bottom", "plane 1", "inner 2", "inner 1", "top", "drill"])]
+    #[case(BoardSide::Bottom, vec!["top", "inner 1", "inner 2", "plane 1", "bottom", "drill"])]
+    fn test_view_from_inner_layers(#[case] side: BoardSide, #[case] expected_names: Vec<&str>) {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::Drill, "drill", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::InnerCopper(1), "inner 1", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::InnerPlane(1), "plane 1", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::TopCopper, "top", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::BottomCopper, "bottom", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::InnerCopper(2), "inner 2", GerberLayer::new(vec![]));
+
+        // when
+        let view = layer_set.view_from(side);
+
+        // then
+        let names = view
+            .layers
+            .iter()
+            .map(|info| info.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, expected_names);
+    }
+
+    #[rstest]
+    #[case(BoardSide::Top, vec!["inner", "top", "drill", "outline"], false)]
+    #[case(BoardSide::Bottom, vec!["inner", "bottom", "drill", "outline"], true)]
+    fn test_view_side(#[case] side: BoardSide, #[case] expected_names: Vec<&str>, #[case] expected_mirroring: bool) {
+        // given
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::Outline, "outline", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::Drill, "drill", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::TopCopper, "top", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::InnerCopper(1), "inner", GerberLayer::new(vec![]));
+        layer_set.add(LayerType::BottomCopper, "bottom", GerberLayer::new(vec![]));
+
+        // when
+        let view = layer_set.view_side(side);
+
+        // then
+        let names = view
+            .layers
+            .iter()
+            .map(|info| info.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, expected_names);
+        assert_eq!(view.transform.mirroring.x, expected_mirroring);
+    }
+}
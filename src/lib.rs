@@ -1,22 +1,117 @@
+mod apertures;
+mod attributes;
+mod canonical;
+mod clearance;
 mod color;
+mod components;
+mod coordinate_format;
+mod difference;
+mod drc;
+mod drill;
+mod dxf;
+mod export;
 mod expressions;
+mod extrusion;
 mod geometry;
+mod hit_test;
+mod image_transform;
+mod ipc2581;
 mod layer;
+mod layer_mesh;
+mod layer_set;
+mod netlist;
+mod outline;
+mod pdf;
+mod preview;
+mod primitive_view;
+mod raster;
+mod selection;
+mod snap;
 mod spacial;
+mod spatial_index;
 mod types;
+mod units;
+
+#[cfg(feature = "parser")]
+mod project;
+
+#[cfg(feature = "geo")]
+mod area;
+#[cfg(feature = "geo")]
+mod geo_interop;
+#[cfg(feature = "geo")]
+mod islands;
+#[cfg(feature = "geo")]
+mod offset;
+#[cfg(feature = "geo")]
+mod oriented_bounding_box;
+#[cfg(feature = "geo")]
+mod polygons;
+
+#[cfg(feature = "watch")]
+mod watch;
+
+#[cfg(feature = "drc-rules")]
+mod drc_rules;
+
+#[cfg(feature = "tiny-skia")]
+mod skia;
 
 #[cfg(feature = "egui")]
 mod renderer;
 
+#[cfg(feature = "egui")]
+mod aperture_table;
+
+#[cfg(feature = "egui")]
+mod comparison;
+
 #[cfg(feature = "egui")]
 mod drawing;
 
+#[cfg(feature = "egui")]
+mod inspector;
+
+#[cfg(feature = "egui")]
+mod layer_panel;
+
+#[cfg(feature = "egui")]
+mod minimap;
+
 #[cfg(feature = "egui")]
 mod ui;
 
+#[cfg(feature = "egui")]
+mod widget;
+
+#[cfg(feature = "wgpu")]
+mod gpu_renderer;
+
+#[cfg(feature = "egui")]
+pub use aperture_table::*;
+pub use apertures::*;
+#[cfg(feature = "geo")]
+pub use area::*;
+pub use attributes::*;
+pub use canonical::*;
+pub use clearance::*;
 pub use color::*;
 #[cfg(feature = "egui")]
+pub use comparison::*;
+pub use components::*;
+pub use coordinate_format::*;
+pub use difference::*;
+#[cfg(feature = "egui")]
 pub use drawing::*;
+pub use drc::*;
+#[cfg(feature = "drc-rules")]
+pub use drc_rules::*;
+pub use drill::*;
+pub use dxf::*;
+pub use extrusion::*;
+/// re-export 'geo' crate
+#[cfg(feature = "geo")]
+pub use geo;
 pub use geometry::*;
 /// re-export 'gerber_parser' crate
 #[cfg(feature = "parser")]
@@ -24,12 +119,54 @@ pub use gerber_parser;
 /// re-export 'gerber_types' crate
 #[cfg(feature = "types")]
 pub use gerber_types;
+#[cfg(feature = "wgpu")]
+pub use gpu_renderer::*;
+pub use hit_test::*;
+#[cfg(feature = "egui")]
+pub use inspector::*;
+pub use ipc2581::*;
+#[cfg(feature = "geo")]
+pub use islands::*;
 pub use layer::*;
+pub use layer_mesh::*;
+#[cfg(feature = "egui")]
+pub use layer_panel::*;
+pub use layer_set::*;
+#[cfg(feature = "egui")]
+pub use minimap::*;
+pub use netlist::*;
+#[cfg(feature = "geo")]
+pub use offset::*;
+#[cfg(feature = "geo")]
+pub use oriented_bounding_box::*;
+pub use outline::*;
+pub use pdf::*;
+#[cfg(feature = "geo")]
+pub use polygons::*;
+pub use preview::*;
+pub use primitive_view::*;
+#[cfg(feature = "parser")]
+pub use project::*;
+pub use raster::*;
 #[cfg(feature = "egui")]
 pub use renderer::*;
+pub use selection::*;
+#[cfg(feature = "tiny-skia")]
+pub use skia::*;
+pub use snap::*;
 pub use spacial::*;
+pub use spatial_index::*;
+/// re-export 'tiny_skia' crate
+#[cfg(feature = "tiny-skia")]
+pub use tiny_skia;
+pub use types::Exposure;
 #[cfg(feature = "egui")]
 pub use ui::*;
+pub use units::*;
+#[cfg(feature = "watch")]
+pub use watch::*;
+#[cfg(feature = "egui")]
+pub use widget::*;
 
 #[cfg(feature = "testing")]
 pub mod testing;
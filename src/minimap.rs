@@ -0,0 +1,154 @@
+use egui::{
+    Color32, ColorImage, Pos2, Rect, Response, Sense, Stroke, StrokeKind, TextureHandle, TextureOptions, Ui, Vec2,
+};
+use log::warn;
+use nalgebra::Point2;
+
+use crate::geometry::{BoundingBox, GerberTransform};
+use crate::{LayerInfo, LayerSet, RasterOptions, Resolution, ViewState};
+
+/// A small overview of the whole board, with a rectangle for the part of the board that is visible in the viewport.
+/// Clicking or dragging in the minimap centers the view on that point.
+///
+/// The layers are rendered once, at a low resolution, using [`LayerSet::render_to_image`], and the image is kept as a
+/// texture, so keep the minimap between frames, e.g. in your app state.  Call [`Minimap::invalidate`] when the layers
+/// or their colors change.  The layers are shown with the transform of each layer, see [`LayerInfo::transform`], the view
+/// must not use another transform, e.g. mirroring, for the rectangle of the visible part to line up.
+pub struct Minimap {
+    /// The size of the longest side of the rendered image, in pixels.
+    pub resolution: u32,
+    pub background: Color32,
+    /// The stroke of the rectangle that shows the visible part of the board.
+    pub viewport_stroke: Stroke,
+    cached: Option<CachedImage>,
+}
+
+struct CachedImage {
+    texture: TextureHandle,
+    /// The area of the layers covered by the texture, in gerber coordinates.
+    bounds: BoundingBox,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self {
+            resolution: 256,
+            background: Color32::from_black_alpha(192),
+            viewport_stroke: Stroke::new(1.5, Color32::YELLOW),
+            cached: None,
+        }
+    }
+}
+
+impl Minimap {
+    /// Drops the rendered image, so it is rendered again the next time the minimap is shown.
+    pub fn invalidate(&mut self) {
+        self.cached = None;
+    }
+
+    /// Shows the minimap in the `rect`, e.g. a corner of the `viewport`, the rect in which the layers are painted using
+    /// the `view`.  Clicks and drags in the minimap move the `view`, call this after handling the input of the
+    /// viewport, e.g. after [`crate::UiState::update`], so the minimap gets the input instead of the viewport.
+    ///
+    /// `color` returns the straight RGBA color of each layer, layers without a color are not shown, see
+    /// [`LayerSet::render_to_image`].
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        rect: Rect,
+        layer_set: &LayerSet,
+        color: impl Fn(&LayerInfo) -> Option<[u8; 4]>,
+        view: &mut ViewState,
+        viewport: Rect,
+    ) -> Response {
+        let response = ui.interact(rect, ui.id().with("minimap"), Sense::click_and_drag());
+        let painter = ui.painter().with_clip_rect(rect);
+        painter.rect_filled(rect, 0.0, self.background);
+
+        if self.cached.is_none() {
+            self.cached = self.render(ui, layer_set, color);
+        }
+        let Some(cached) = &self.cached else {
+            return response;
+        };
+
+        // the image is scaled to fit the minimap, preserving the aspect ratio, and centered
+        let bounds = &cached.bounds;
+        let size = Vec2::new(bounds.width() as f32, bounds.height() as f32);
+        let scale = (rect.width() / size.x).min(rect.height() / size.y);
+        let image_rect = Rect::from_center_size(rect.center(), size * scale);
+        painter.image(
+            cached.texture.id(),
+            image_rect,
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+            Color32::WHITE,
+        );
+
+        let to_minimap = |point: Point2<f64>| {
+            Pos2::new(
+                image_rect.left() + (point.x - bounds.min.x) as f32 * scale,
+                image_rect.bottom() - (point.y - bounds.min.y) as f32 * scale,
+            )
+        };
+        let visible = Rect::from_two_pos(
+            to_minimap(view.screen_to_gerber_coords(viewport.left_top())),
+            to_minimap(view.screen_to_gerber_coords(viewport.right_bottom())),
+        );
+        painter.rect_stroke(visible, 0.0, self.viewport_stroke, StrokeKind::Middle);
+
+        if response.clicked() || response.dragged() {
+            if let Some(position) = response.interact_pointer_pos() {
+                let target = Point2::new(
+                    bounds.min.x + ((position.x - image_rect.left()) / scale) as f64,
+                    bounds.min.y + ((image_rect.bottom() - position.y) / scale) as f64,
+                );
+                // the inverse of `ViewState::gerber_to_screen_coords`, for the center of the viewport
+                view.translation = viewport.center() - Vec2::new(target.x as f32, -(target.y as f32)) * view.scale;
+            }
+        }
+
+        response
+    }
+
+    fn render(
+        &self,
+        ui: &Ui,
+        layer_set: &LayerSet,
+        color: impl Fn(&LayerInfo) -> Option<[u8; 4]>,
+    ) -> Option<CachedImage> {
+        // the same layers as `LayerSet::render_to_image`, so the image covers the same area
+        let (_, bounds) = layer_set.raster_layers(&GerberTransform::default(), &color);
+        if bounds.is_empty() || bounds.width() <= 0.0 || bounds.height() <= 0.0 {
+            return None;
+        }
+
+        // an image with the aspect ratio of the layers, so it is covered exactly
+        let aspect_ratio = bounds.width() / bounds.height();
+        let (width, height) = match aspect_ratio >= 1.0 {
+            true => (self.resolution as f64, self.resolution as f64 / aspect_ratio),
+            false => (self.resolution as f64 * aspect_ratio, self.resolution as f64),
+        };
+        let options = RasterOptions {
+            resolution: Resolution::Fit {
+                width: (width.round() as u32).max(1),
+                height: (height.round() as u32).max(1),
+            },
+            ..RasterOptions::default()
+        };
+
+        let image = layer_set
+            .render_to_image(&options, color)
+            .inspect_err(|error| warn!("Unable to render minimap. error: {}", error))
+            .ok()?;
+        let texture = ui.ctx().load_texture(
+            "minimap",
+            ColorImage::from_rgba_unmultiplied([image.width as usize, image.height as usize], &image.pixels),
+            TextureOptions::LINEAR,
+        );
+
+        Some(CachedImage {
+            texture,
+            bounds,
+        })
+    }
+}
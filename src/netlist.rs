@@ -0,0 +1,367 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use gerber_types::Unit;
+use nalgebra::{Point2, Vector2};
+use thiserror::Error;
+
+use crate::{unit_conversion_factor, GerberLayer};
+
+#[derive(Error, Debug)]
+pub enum NetlistError {
+    #[error("IO error. path: {path:?}, cause: {cause}")]
+    Io { path: PathBuf, cause: std::io::Error },
+    #[error("Invalid test point record. line: {line}, cause: {cause}")]
+    InvalidRecord { line: usize, cause: String },
+}
+
+/// The kind of a test point record of an IPC-D-356 netlist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestPointKind {
+    /// `317`, a through-hole pad or via.
+    ThroughHole,
+    /// `327`, a surface mount pad.
+    SurfaceMount,
+    /// `367`, a non-plated tooling hole.
+    Tooling,
+}
+
+/// A test point of a [`Netlist`], i.e. a pad or via, with its net.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestPoint {
+    pub kind: TestPointKind,
+    /// The net name, long names are resolved, `N/C` for unconnected pads.
+    pub net: String,
+    /// Reference designator, e.g. `R1`, or `VIA` for vias.
+    pub reference: String,
+    /// Pin number, e.g. `1`, `A3`, empty for vias.
+    pub pin: String,
+    /// The center of the test point, in the units of the netlist.
+    pub position: Point2<f64>,
+    /// Midpoint of a net, i.e. not the end of a net, from the `M` flag.
+    pub midpoint: bool,
+    /// The diameter of the hole, in the units of the netlist.
+    pub drill: Option<f64>,
+    /// Whether the hole is plated, from the `P` and `U` flags.
+    pub plated: Option<bool>,
+    /// The access side, `0` for both sides, `1` for the primary (top) side, otherwise the layer number, e.g. the
+    /// number of the bottom layer.
+    pub access: u8,
+    /// The width and height of the pad, in the units of the netlist.
+    pub size: Option<Vector2<f64>>,
+    /// Degrees, counter-clockwise.
+    pub rotation: f64,
+}
+
+/// The nets and test points of a board, from an IPC-D-356 netlist file, e.g. for highlighting nets and cross-probing
+/// when the gerbers have no X2 net attributes.
+///
+/// Test points are matched to the primitives of a layer by position, see [`Netlist::primitives_of_net`] and
+/// [`Netlist::net_of_primitive`], so only the pads and vias of a net are found, not the traces connecting them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Netlist {
+    /// The units of the positions and sizes, from the `UNITS` parameter, inches by default.
+    pub units: Unit,
+    /// In the order of the file.
+    pub test_points: Vec<TestPoint>,
+}
+
+impl Netlist {
+    /// Parses an IPC-D-356 or IPC-D-356A netlist.
+    ///
+    /// The `317`, `327` and `367` test point records are used, long net names are resolved using the `NNAME`
+    /// parameters, other records, e.g. conductors and the board outline, are ignored.
+    pub fn parse(content: &str) -> Result<Self, NetlistError> {
+        // the size of a unit of the coordinates, in the units of the netlist
+        let mut units = Unit::Inches;
+        let mut resolution = 0.0001;
+        let mut long_names = HashMap::new();
+        let mut test_points = vec![];
+
+        for (index, line) in content.lines().enumerate() {
+            let record = line.get(..3).unwrap_or(line);
+            let kind = match record {
+                "317" => TestPointKind::ThroughHole,
+                "327" => TestPointKind::SurfaceMount,
+                "367" => TestPointKind::Tooling,
+                "999" => break,
+                _ if line.starts_with('P') => {
+                    let mut tokens = line[1..].split_whitespace();
+                    match tokens.next() {
+                        Some("UNITS") => {
+                            (units, resolution) = match tokens.collect::<Vec<_>>().as_slice() {
+                                ["CUST", "1"] | ["SI"] => (Unit::Millimeters, 0.001),
+                                _ => (Unit::Inches, 0.0001),
+                            };
+                        }
+                        Some(name) if name.starts_with("NNAME") => {
+                            long_names.insert(name.to_string(), tokens.collect::<Vec<_>>().join(" "));
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let mut test_point =
+                parse_test_point(kind, line, resolution).map_err(|cause| NetlistError::InvalidRecord {
+                    line: index + 1,
+                    cause,
+                })?;
+            if let Some(name) = long_names.get(&test_point.net) {
+                test_point.net = name.clone();
+            }
+            test_points.push(test_point);
+        }
+
+        Ok(Self {
+            units,
+            test_points,
+        })
+    }
+
+    /// Loads a netlist file, typically with a `.ipc` or `.d356` extension, see [`Netlist::parse`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NetlistError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|cause| NetlistError::Io {
+            path: path.to_path_buf(),
+            cause,
+        })?;
+
+        Self::parse(&content)
+    }
+
+    /// The unique net names, sorted.
+    pub fn nets(&self) -> Vec<&str> {
+        self.test_points
+            .iter()
+            .map(|test_point| test_point.net.as_str())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    pub fn test_points_of_net<'a>(&'a self, net: &'a str) -> impl Iterator<Item = &'a TestPoint> + 'a {
+        self.test_points
+            .iter()
+            .filter(move |test_point| test_point.net == net)
+    }
+
+    pub fn test_points_of_component<'a>(&'a self, reference: &'a str) -> impl Iterator<Item = &'a TestPoint> + 'a {
+        self.test_points
+            .iter()
+            .filter(move |test_point| test_point.reference == reference)
+    }
+
+    /// The test point nearest to the position, within the tolerance, both in the units of the netlist, e.g. for
+    /// showing the net under the cursor.
+    pub fn test_point_at(&self, position: Point2<f64>, tolerance: f64) -> Option<&TestPoint> {
+        self.test_points
+            .iter()
+            .map(|test_point| (test_point, (test_point.position - position).norm()))
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(test_point, _)| test_point)
+    }
+
+    /// Returns the indices of the primitives of the layer at the test points of the net, sorted, e.g. to highlight the
+    /// pads of a net using [`crate::GerberRenderer::paint_highlight`].
+    ///
+    /// Returns an empty list if the layer has no units.
+    pub fn primitives_of_net(&self, layer: &GerberLayer, net: &str) -> Vec<usize> {
+        let Some(scale) = self.scale_to_layer(layer) else {
+            return vec![];
+        };
+
+        let mut primitives = self
+            .test_points_of_net(net)
+            .flat_map(|test_point| layer.hit_test(test_point.position * scale, 0.0))
+            .collect::<Vec<_>>();
+        primitives.sort_unstable();
+        primitives.dedup();
+        primitives
+    }
+
+    /// Returns the net of the first test point inside the primitive, e.g. the primitive clicked on, for cross-probing.
+    pub fn net_of_primitive(&self, layer: &GerberLayer, index: usize) -> Option<&str> {
+        let scale = self.scale_to_layer(layer)?;
+
+        self.test_points
+            .iter()
+            .find(|test_point| {
+                layer
+                    .hit_test(test_point.position * scale, 0.0)
+                    .contains(&index)
+            })
+            .map(|test_point| test_point.net.as_str())
+    }
+
+    fn scale_to_layer(&self, layer: &GerberLayer) -> Option<f64> {
+        layer
+            .units()
+            .map(|units| unit_conversion_factor(self.units, units))
+    }
+}
+
+/// Parses the fixed columns of a test point record, e.g.
+/// `317GND              J1    -1    D0400PA00X+010000Y+020000X0600Y0600R000S0`.
+fn parse_test_point(kind: TestPointKind, line: &str, resolution: f64) -> Result<TestPoint, String> {
+    let field = |start: usize, end: usize| -> String {
+        line.chars()
+            .skip(start)
+            .take(end - start)
+            .collect::<String>()
+            .trim()
+            .to_string()
+    };
+
+    let mut test_point = TestPoint {
+        kind,
+        net: field(3, 17),
+        reference: field(20, 26),
+        pin: field(27, 31),
+        position: Point2::new(0.0, 0.0),
+        midpoint: false,
+        drill: None,
+        plated: None,
+        access: 0,
+        size: None,
+        rotation: 0.0,
+    };
+
+    // the rest of the record are flags, and letters followed by a number, the first X and Y are the position, the second
+    // X and Y are the size
+    let mut position = (None, None);
+    let mut size = (None, None);
+    let mut chars = line.chars().skip(31).peekable();
+    while let Some(letter) = chars.next() {
+        match letter {
+            'M' => test_point.midpoint = true,
+            'P' => test_point.plated = Some(true),
+            'U' => test_point.plated = Some(false),
+            'D' | 'A' | 'X' | 'Y' | 'R' | 'S' => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '+' || *c == '-') {
+                    number.push(c);
+                }
+                let value = number
+                    .parse::<i64>()
+                    .map_err(|_| format!("invalid number after '{letter}'"))? as f64;
+
+                match letter {
+                    'D' => test_point.drill = Some(value * resolution),
+                    'A' => test_point.access = value as u8,
+                    'X' if position.0.is_none() => position.0 = Some(value * resolution),
+                    'Y' if position.1.is_none() => position.1 = Some(value * resolution),
+                    'X' => size.0 = Some(value * resolution),
+                    'Y' => size.1 = Some(value * resolution),
+                    'R' => test_point.rotation = value,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let (Some(x), Some(y)) = position else {
+        return Err("missing position".to_string());
+    };
+    test_point.position = Point2::new(x, y);
+    if let (Some(width), Some(height)) = size {
+        test_point.size = Some(Vector2::new(width, height));
+    }
+
+    Ok(test_point)
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle};
+
+    use super::*;
+    use crate::testing::{aperture, flash, header};
+
+    /// A test point record with the fixed columns, `rest` starts at the midpoint column.
+    fn record(operation: &str, net: &str, reference: &str, pin: &str, rest: &str) -> String {
+        format!("{operation}{net:<14}   {reference:<6}-{pin:<4}{rest}")
+    }
+
+    fn netlist() -> String {
+        [
+            "C  IPC-D-356 netlist".to_string(),
+            "P  UNITS CUST 0".to_string(),
+            "P  NNAME1     A_VERY_LONG_NET_NAME".to_string(),
+            record("317", "GND", "J1", "1", " D0400PA00X+010000Y+020000X0600Y0600R000S0"),
+            record(
+                "327",
+                "NNAME1",
+                "R1",
+                "2",
+                "          A01X+015000Y-020000X0200Y0300R090S2",
+            ),
+            record("317", "GND", "VIA", "", "MD0157PA00X+030000Y+020000X0276Y0000R000S3"),
+            "999".to_string(),
+        ]
+        .join("\n")
+    }
+
+    #[test]
+    fn test_parse() {
+        // when
+        let netlist = Netlist::parse(&netlist()).unwrap();
+
+        // then
+        assert_eq!(netlist.units, Unit::Inches);
+        assert_eq!(netlist.test_points.len(), 3);
+        assert_eq!(netlist.nets(), vec!["A_VERY_LONG_NET_NAME", "GND"]);
+
+        let pad = &netlist.test_points[0];
+        assert_eq!(pad.kind, TestPointKind::ThroughHole);
+        assert_eq!(
+            (pad.net.as_str(), pad.reference.as_str(), pad.pin.as_str()),
+            ("GND", "J1", "1")
+        );
+        assert!((pad.position - Point2::new(1.0, 2.0)).norm() < 1e-9);
+        assert!((pad.drill.unwrap() - 0.04).abs() < 1e-9);
+        assert_eq!(pad.plated, Some(true));
+        assert!(!pad.midpoint);
+
+        let smd = &netlist.test_points[1];
+        assert_eq!(smd.kind, TestPointKind::SurfaceMount);
+        assert_eq!(smd.drill, None);
+        assert_eq!(smd.access, 1);
+        assert!((smd.position - Point2::new(1.5, -2.0)).norm() < 1e-9);
+        assert!((smd.size.unwrap() - Vector2::new(0.02, 0.03)).norm() < 1e-9);
+        assert_eq!(smd.rotation, 90.0);
+
+        let via = &netlist.test_points[2];
+        assert_eq!((via.reference.as_str(), via.pin.as_str()), ("VIA", ""));
+        assert!(via.midpoint);
+
+        // and
+        assert_eq!(
+            netlist
+                .test_point_at(Point2::new(1.51, -2.0), 0.02)
+                .map(|test_point| test_point.reference.as_str()),
+            Some("R1")
+        );
+    }
+
+    #[test]
+    fn test_primitives_of_net() {
+        // given
+        let netlist = Netlist::parse(&netlist()).unwrap();
+        // pads at the J1 and R1 test points, and one without a test point, in inches
+        let mut commands = header(Unit::Inches);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(0.06))));
+        commands.extend([flash(1.0, 2.0), flash(1.5, -2.0), flash(5.0, 5.0)]);
+        let layer = GerberLayer::new(commands);
+
+        // expect
+        assert_eq!(netlist.primitives_of_net(&layer, "GND"), vec![0]);
+        assert_eq!(netlist.primitives_of_net(&layer, "A_VERY_LONG_NET_NAME"), vec![1]);
+        assert_eq!(netlist.net_of_primitive(&layer, 1), Some("A_VERY_LONG_NET_NAME"));
+        assert_eq!(netlist.net_of_primitive(&layer, 2), None);
+    }
+}
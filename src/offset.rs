@@ -0,0 +1,77 @@
+use geo::{unary_union, Buffer, MultiPolygon};
+use nalgebra::Vector2;
+
+use crate::layer::{GerberLayer, GerberPrimitive};
+use crate::PrimitiveView;
+
+/// Inflates (positive `distance`) or deflates (negative `distance`) the polygons, with rounded corners.
+///
+/// Polygons, and holes, that are narrower than twice a negative distance disappear.
+pub fn offset_polygons(polygons: &MultiPolygon<f64>, distance: f64) -> MultiPolygon<f64> {
+    if distance == 0.0 {
+        return polygons.clone();
+    }
+    polygons.buffer(distance)
+}
+
+impl GerberLayer {
+    /// The merged polygons of the layer, see [`GerberLayer::merged_polygons`], offset by `distance`, e.g. for solder
+    /// mask expansion previews and clearance zones.
+    ///
+    /// See [`offset_polygons`].
+    pub fn offset_polygons(&self, distance: f64) -> MultiPolygon<f64> {
+        offset_polygons(&self.merged_polygons(), distance)
+    }
+}
+
+impl PrimitiveView<'_> {
+    /// The outline of the primitive offset by `distance`, e.g. for courtyards, ignoring the exposure.
+    ///
+    /// Circles and arcs are approximated, see [`crate::CIRCLE_SEGMENTS`].  See [`offset_polygons`].
+    pub fn offset_polygons(&self, distance: f64) -> MultiPolygon<f64> {
+        self.primitive()
+            .offset_polygons(distance)
+    }
+}
+
+impl GerberPrimitive {
+    /// The outline of the primitive offset by `distance`, ignoring the exposure.
+    pub(crate) fn offset_polygons(&self, distance: f64) -> MultiPolygon<f64> {
+        let polygons = unary_union(&self.to_polygons(Vector2::new(0.0, 0.0)));
+        offset_polygons(&polygons, distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use geo::Area;
+    use nalgebra::Point2;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::layer::RectangleGerberPrimitive;
+    use crate::Exposure;
+
+    #[rstest]
+    // the corners are rounded
+    #[case(1.0, 4.0 + 8.0 + PI)]
+    #[case(-0.5, 1.0)]
+    #[case(-1.5, 0.0)]
+    fn test_offset_polygons(#[case] distance: f64, #[case] expected: f64) {
+        // given
+        let square = GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+            origin: Point2::new(0.0, 0.0),
+            width: 2.0,
+            height: 2.0,
+            exposure: Exposure::Add,
+        });
+
+        // when
+        let polygons = square.offset_polygons(distance);
+
+        // then
+        assert!((polygons.unsigned_area() - expected).abs() < 0.05);
+    }
+}
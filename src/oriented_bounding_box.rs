@@ -0,0 +1,199 @@
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use geo::{ConvexHull, Coord, MultiPoint};
+use nalgebra::{Point2, Vector2};
+
+use crate::layer::GerberLayer;
+
+/// A rotated rectangle, see [`GerberLayer::oriented_bounding_box`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrientedBoundingBox {
+    pub center: Point2<f64>,
+    /// Along the direction of `angle`.
+    pub width: f64,
+    pub height: f64,
+    /// Counter-clockwise, in radians, between -π/4 (exclusive) and π/4 (inclusive), i.e. the smallest rotation that
+    /// aligns the rectangle with the axes.
+    pub angle: f64,
+}
+
+impl OrientedBoundingBox {
+    pub fn area(&self) -> f64 {
+        self.width * self.height
+    }
+
+    /// The corners, counter-clockwise.
+    pub fn vertices(&self) -> Vec<Point2<f64>> {
+        let (sin, cos) = self.angle.sin_cos();
+        let u = Vector2::new(cos, sin) * (self.width / 2.0);
+        let v = Vector2::new(-sin, cos) * (self.height / 2.0);
+        vec![
+            self.center - u - v,
+            self.center + u - v,
+            self.center + u + v,
+            self.center - u + v,
+        ]
+    }
+}
+
+/// Computes the minimum-area rotated rectangle that contains the points, using the rotating calipers method on the
+/// convex hull of the points.
+///
+/// Returns `None` if there are no points.
+pub fn oriented_bounding_box(points: &[Point2<f64>]) -> Option<OrientedBoundingBox> {
+    if points.is_empty() {
+        return None;
+    }
+
+    let hull = MultiPoint::from(
+        points
+            .iter()
+            .map(|point| Coord {
+                x: point.x,
+                y: point.y,
+            })
+            .collect::<Vec<_>>(),
+    )
+    .convex_hull();
+    let hull = hull
+        .exterior()
+        .coords()
+        .map(|coord| Point2::new(coord.x, coord.y))
+        .collect::<Vec<_>>();
+
+    // the minimum rectangle has a side that is collinear with an edge of the hull
+    let mut best: Option<OrientedBoundingBox> = None;
+    for edge in hull.windows(2) {
+        let direction = edge[1] - edge[0];
+        if direction.norm() == 0.0 {
+            continue;
+        }
+        let candidate = aligned_bounding_box(&hull, direction.y.atan2(direction.x));
+        if best
+            .as_ref()
+            .is_none_or(|best| candidate.area() < best.area())
+        {
+            best = Some(candidate);
+        }
+    }
+
+    // a single point
+    Some(best.unwrap_or_else(|| aligned_bounding_box(&hull, 0.0)))
+}
+
+/// The bounding box of the points, with the width along the direction of `angle`.
+fn aligned_bounding_box(points: &[Point2<f64>], angle: f64) -> OrientedBoundingBox {
+    let (sin, cos) = angle.sin_cos();
+    let u = Vector2::new(cos, sin);
+    let v = Vector2::new(-sin, cos);
+
+    let (mut min_u, mut max_u, mut min_v, mut max_v) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for point in points {
+        let (pu, pv) = (point.coords.dot(&u), point.coords.dot(&v));
+        min_u = min_u.min(pu);
+        max_u = max_u.max(pu);
+        min_v = min_v.min(pv);
+        max_v = max_v.max(pv);
+    }
+
+    let mut result = OrientedBoundingBox {
+        center: Point2::from(u * (min_u + max_u) / 2.0 + v * (min_v + max_v) / 2.0),
+        width: max_u - min_u,
+        height: max_v - min_v,
+        angle,
+    };
+
+    // rotating by a quarter turn swaps the width and height
+    while result.angle > FRAC_PI_4 {
+        result.angle -= FRAC_PI_2;
+        std::mem::swap(&mut result.width, &mut result.height);
+    }
+    while result.angle <= -FRAC_PI_4 {
+        result.angle += FRAC_PI_2;
+        std::mem::swap(&mut result.width, &mut result.height);
+    }
+
+    result
+}
+
+impl GerberLayer {
+    /// The minimum-area rotated rectangle that contains all the primitives of the layer, e.g. to orient a design for
+    /// panelization or pick-and-place.
+    ///
+    /// Circles and arcs are approximated, see [`crate::CIRCLE_SEGMENTS`].  Returns `None` if the layer is empty.
+    pub fn oriented_bounding_box(&self) -> Option<OrientedBoundingBox> {
+        let indices = (0..self.gerber_primitives().len()).collect::<Vec<_>>();
+        self.oriented_bounding_box_of(&indices)
+    }
+
+    /// The minimum-area rotated rectangle that contains the primitives with the given indices, including each copy of
+    /// step-and-repeat primitives.
+    ///
+    /// Indices that are out of range are ignored.
+    pub fn oriented_bounding_box_of(&self, indices: &[usize]) -> Option<OrientedBoundingBox> {
+        let mut points = vec![];
+        for index in indices {
+            let Some(primitive) = self.gerber_primitives().get(*index) else {
+                continue;
+            };
+            for offset in self.primitive_offsets(*index) {
+                for polygon in primitive.to_polygons(offset) {
+                    points.extend(
+                        polygon
+                            .exterior()
+                            .coords()
+                            .map(|coord| Point2::new(coord.x, coord.y)),
+                    );
+                }
+            }
+        }
+
+        oriented_bounding_box(&points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Command, FunctionCode, GCode, InterpolationMode, Unit};
+
+    use super::*;
+    use crate::testing::{aperture, header, interpolate, move_to};
+
+    #[test]
+    fn test_oriented_bounding_box() {
+        // given
+        // a line, 10 long, 2 wide, at ~36.87 degrees
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(2.0))));
+        commands.extend([
+            Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(InterpolationMode::Linear))),
+            move_to(0.0, 0.0),
+            interpolate(8.0, 6.0),
+        ]);
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let obb = layer.oriented_bounding_box().unwrap();
+
+        // then
+        // includes the round caps
+        assert!((obb.width - 12.0).abs() < 1e-6);
+        assert!((obb.height - 2.0).abs() < 1e-6);
+        assert!((obb.angle - 6.0_f64.atan2(8.0)).abs() < 1e-6);
+        assert!((obb.center - Point2::new(4.0, 3.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_oriented_bounding_box_of_axis_aligned_points() {
+        // given
+        let points = [(0.0, 0.0), (4.0, 0.0), (4.0, 2.0), (0.0, 2.0), (1.0, 1.0)].map(|(x, y)| Point2::new(x, y));
+
+        // when
+        let obb = oriented_bounding_box(&points).unwrap();
+
+        // then
+        assert!(obb.angle.abs() < 1e-9);
+        assert!((obb.width - 4.0).abs() < 1e-9);
+        assert!((obb.height - 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use nalgebra::Point2;
+
+use crate::geometry::{join_contours, tessellate_polygon, BoundingBox};
+use crate::layer::{
+    GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGeometry, PolygonGerberPrimitive,
+    RectangleGerberPrimitive,
+};
+use crate::Exposure;
+
+/// The contours of a board outline layer, see [`GerberLayer::board_outline`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BoardOutline {
+    /// Closed contours, largest area first, so the first contour is usually the board edge and the others are cut-outs.
+    ///
+    /// The vertices are counter-clockwise, and the first vertex is not repeated at the end.
+    pub contours: Vec<Vec<Point2<f64>>>,
+    /// Chains of lines and arcs that could not be closed, e.g. due to gaps larger than the tolerance.
+    pub open_chains: Vec<Vec<Point2<f64>>>,
+}
+
+/// The area that is filled when rendering a layer as a negative, e.g. soldermask layers, which describe the openings in
+/// the mask rather than the mask itself.
+///
+/// The fill is drawn first, then the primitives with their exposure inverted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NegativeArea {
+    /// The bounding box of the layer.
+    BoundingBox,
+    /// The first contour of the board outline, with the other contours as cut-outs, see
+    /// [`GerberLayer::board_outline`].
+    Outline(BoardOutline),
+}
+
+impl GerberLayer {
+    /// A dark primitive that covers the area, to draw before the primitives of a negative layer.
+    ///
+    /// The fill of an outline is a concave polygon without a tessellation, the rasterizers fill its contours directly,
+    /// see [`GerberLayer::tessellated_negative_fill`] for meshes.  Returns `None` if the area is empty, e.g. an empty
+    /// layer or an outline without contours.
+    pub(crate) fn negative_fill(&self, area: &NegativeArea) -> Option<GerberPrimitive> {
+        match area {
+            NegativeArea::BoundingBox => {
+                let bbox = self.try_bounding_box()?;
+                Some(GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                    origin: bbox.min,
+                    width: bbox.width(),
+                    height: bbox.height(),
+                    exposure: Exposure::Add,
+                }))
+            }
+            NegativeArea::Outline(outline) => {
+                let (board, cut_outs) = outline.contours.split_first()?;
+                let vertices = join_contours(board.clone(), cut_outs.to_vec());
+                Some(GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                    center: Point2::origin(),
+                    exposure: Exposure::Add,
+                    geometry: Arc::new(PolygonGeometry {
+                        relative_vertices: vertices,
+                        tessellation: None,
+                        is_convex: false,
+                    }),
+                }))
+            }
+        }
+    }
+
+    /// Like [`GerberLayer::negative_fill`], with the fill of an outline tessellated, for meshes.
+    ///
+    /// Tessellating a large outline is slow, so the fill should be kept while the area doesn't change, e.g. the
+    /// [`crate::GerberRenderer`] keeps it across rebuilds of the mesh of the layer.
+    pub(crate) fn tessellated_negative_fill(&self, area: &NegativeArea) -> Option<GerberPrimitive> {
+        match self.negative_fill(area)? {
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                exposure,
+                geometry,
+            }) if geometry.tessellation.is_none() => {
+                let geometry = Arc::unwrap_or_clone(geometry);
+                let tessellation = tessellate_polygon(&geometry.relative_vertices);
+                Some(GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                    center,
+                    exposure,
+                    geometry: Arc::new(PolygonGeometry {
+                        tessellation: Some(tessellation),
+                        ..geometry
+                    }),
+                }))
+            }
+            fill => Some(fill),
+        }
+    }
+
+    /// The bounding box of the layer, including the fill of the negative area, see [`GerberLayer::negative_fill`].
+    pub(crate) fn negative_bounding_box(&self, area: &NegativeArea) -> Option<BoundingBox> {
+        let bbox = self.try_bounding_box().cloned();
+        let fill = match area {
+            NegativeArea::BoundingBox => None,
+            NegativeArea::Outline(outline) => outline
+                .contours
+                .first()
+                .map(|board| BoundingBox::from_points(board)),
+        };
+        match (bbox, fill) {
+            (Some(bbox), Some(fill)) => Some(bbox.union(&fill)),
+            (bbox, fill) => bbox.or(fill),
+        }
+    }
+
+    /// Chains the lines and arcs of an outline layer, e.g. `Edge_Cuts`, into closed contours, for clipping, area
+    /// calculations and exports.
+    ///
+    /// The center-lines of the primitives are used, the width of the outline stroke is ignored.  End points that are
+    /// within `tolerance` of each other are joined.  Arcs are approximated by line segments, see
+    /// [`crate::ArcView::points`].  Other primitives, e.g. flashes and regions, are ignored.
+    pub fn board_outline(&self, tolerance: f64) -> BoardOutline {
+        let mut outline = BoardOutline::default();
+        let mut polylines = vec![];
+
+        for (index, primitive) in self
+            .gerber_primitives()
+            .iter()
+            .enumerate()
+        {
+            for offset in self.primitive_offsets(index) {
+                match primitive {
+                    GerberPrimitive::Line(LineGerberPrimitive {
+                        start,
+                        end,
+                        ..
+                    }) => polylines.push(vec![*start + offset, *end + offset]),
+                    GerberPrimitive::Arc(arc) => {
+                        let center = arc.center + offset;
+                        let points = arc
+                            .generate_points()
+                            .into_iter()
+                            .map(|point| center + point.coords)
+                            .collect::<Vec<_>>();
+                        match arc.is_full_circle() {
+                            true => outline
+                                .contours
+                                .push(close(points, tolerance)),
+                            false => polylines.push(points),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        while let Some(mut chain) = polylines.pop() {
+            let mut reversed = false;
+            loop {
+                if is_closed(&chain, tolerance) {
+                    outline
+                        .contours
+                        .push(close(chain, tolerance));
+                    break;
+                }
+
+                match take_nearest(&mut polylines, chain[chain.len() - 1], tolerance) {
+                    Some(next) => chain.extend(next.into_iter().skip(1)),
+                    // extend the other end of the chain, once
+                    None if !reversed => {
+                        reversed = true;
+                        chain.reverse();
+                    }
+                    None => {
+                        outline.open_chains.push(chain);
+                        break;
+                    }
+                }
+            }
+        }
+
+        outline
+            .contours
+            .sort_by(|a, b| signed_area(b).total_cmp(&signed_area(a)));
+
+        outline
+    }
+}
+
+fn is_closed(chain: &[Point2<f64>], tolerance: f64) -> bool {
+    chain.len() > 2 && (chain[chain.len() - 1] - chain[0]).norm() <= tolerance
+}
+
+/// Removes the repeated first vertex, and makes the contour counter-clockwise.
+fn close(mut contour: Vec<Point2<f64>>, tolerance: f64) -> Vec<Point2<f64>> {
+    if is_closed(&contour, tolerance) {
+        contour.pop();
+    }
+    if signed_area(&contour) < 0.0 {
+        contour.reverse();
+    }
+    contour
+}
+
+/// Removes and returns the polyline with an end point nearest to `point`, oriented so that it starts at `point`.
+fn take_nearest(polylines: &mut Vec<Vec<Point2<f64>>>, point: Point2<f64>, tolerance: f64) -> Option<Vec<Point2<f64>>> {
+    let (index, distance, reverse) = polylines
+        .iter()
+        .enumerate()
+        .flat_map(|(index, polyline)| {
+            [
+                (index, (polyline[0] - point).norm(), false),
+                (index, (polyline[polyline.len() - 1] - point).norm(), true),
+            ]
+        })
+        .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b))?;
+
+    if distance > tolerance {
+        return None;
+    }
+
+    let mut polyline = polylines.swap_remove(index);
+    if reverse {
+        polyline.reverse();
+    }
+    Some(polyline)
+}
+
+/// Positive for counter-clockwise contours.
+pub(crate) fn signed_area(contour: &[Point2<f64>]) -> f64 {
+    (0..contour.len())
+        .map(|index| {
+            let (a, b) = (contour[index], contour[(index + 1) % contour.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Command, FunctionCode, GCode, InterpolationMode, Unit};
+
+    use super::*;
+    use crate::testing::{aperture, header, interpolate, move_to};
+
+    fn line(start: (f64, f64), end: (f64, f64)) -> [Command; 2] {
+        [move_to(start.0, start.1), interpolate(end.0, end.1)]
+    }
+
+    #[test]
+    fn test_board_outline() {
+        // given
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(0.1))));
+        commands.extend([Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(
+            InterpolationMode::Linear,
+        )))]);
+        // a 10x10 square, drawn out of order, with a reversed edge and a small gap near (10,10)
+        commands.extend(line((10.0, 0.0), (10.0, 9.999)));
+        commands.extend(line((0.0, 0.0), (10.0, 0.0)));
+        commands.extend(line((0.0, 10.0), (10.0, 10.0)));
+        commands.extend(line((0.0, 10.0), (0.0, 0.0)));
+        // an unconnected line
+        commands.extend(line((20.0, 0.0), (30.0, 0.0)));
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let outline = layer.board_outline(0.01);
+
+        // then
+        assert_eq!(outline.contours.len(), 1);
+        let contour = &outline.contours[0];
+        assert_eq!(contour.len(), 4);
+        assert!((signed_area(contour) - 100.0).abs() < 0.01);
+        assert_eq!(outline.open_chains, vec![vec![
+            Point2::new(30.0, 0.0),
+            Point2::new(20.0, 0.0)
+        ]]);
+
+        // and
+        let Some(GerberPrimitive::Polygon(fill)) = layer.negative_fill(&NegativeArea::Outline(outline.clone())) else {
+            panic!("expected a polygon");
+        };
+        assert_eq!(fill.geometry.relative_vertices, *contour);
+        assert!(fill.geometry.tessellation.is_none());
+
+        // and
+        let Some(GerberPrimitive::Polygon(fill)) =
+            layer.tessellated_negative_fill(&NegativeArea::Outline(outline.clone()))
+        else {
+            panic!("expected a polygon");
+        };
+        assert!(fill
+            .geometry
+            .tessellation
+            .as_ref()
+            .is_some_and(|tessellation| tessellation.indices.len() == 6));
+    }
+}
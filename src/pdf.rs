@@ -0,0 +1,666 @@
+use gerber_types::Unit;
+use nalgebra::{Point2, Vector2};
+use thiserror::Error;
+
+use crate::geometry::{split_contours, BoundingBox};
+use crate::layer::{
+    CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGerberPrimitive,
+    RectangleGerberPrimitive,
+};
+use crate::{unit_conversion_factor, Exposure, MILLIMETERS_PER_INCH};
+
+/// PDF user space units, points, per millimeter.
+const POINTS_PER_MILLIMETER: f64 = 72.0 / MILLIMETERS_PER_INCH;
+
+/// The distance of the control points of a cubic bezier approximation of a quarter circle, relative to the radius.
+const BEZIER_CIRCLE: f64 = 0.552_284_75;
+
+/// A layer to draw on a page, see [`export_pdf`].
+#[derive(Debug, Clone)]
+pub struct PdfLayer<'a> {
+    pub layer: &'a GerberLayer,
+    /// RGB, dark primitives are filled with this color.
+    pub color: [u8; 3],
+}
+
+/// The layers of a page, drawn in order, on top of each other.
+#[derive(Debug, Clone, Default)]
+pub struct PdfPage<'a> {
+    pub layers: Vec<PdfLayer<'a>>,
+}
+
+/// Options for [`export_pdf`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfOptions {
+    /// Width and height of the pages, in millimeters, e.g. `(210.0, 297.0)` for A4.  `None` sizes each page to fit its
+    /// layers, plus the margin.
+    pub page_size: Option<(f64, f64)>,
+    /// In millimeters, only used when the page size is not specified.
+    pub margin: f64,
+    /// Mirrors the layers horizontally, e.g. for toner-transfer of top layers.
+    pub mirror: bool,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: None,
+            margin: 10.0,
+            mirror: false,
+        }
+    }
+}
+
+/// Options for [`export_tiled_pdf`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileOptions {
+    /// Width and height of the paper, in millimeters, e.g. `(210.0, 297.0)` for A4.
+    pub page_size: (f64, f64),
+    /// In millimeters, the border of the paper around the printed area of a tile, the crop marks are drawn in it.
+    pub margin: f64,
+    /// In millimeters, how much of the board is repeated on adjacent tiles, for aligning them when taping them together.
+    pub overlap: f64,
+    /// Marks the corners of the printed area of each tile, for cutting the tiles.
+    pub crop_marks: bool,
+    /// Mirrors the layers horizontally, before they are split into tiles.
+    pub mirror: bool,
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        Self {
+            page_size: (210.0, 297.0),
+            margin: 10.0,
+            overlap: 10.0,
+            crop_marks: true,
+            mirror: false,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PdfError {
+    #[error("Nothing to export, there are no pages.")]
+    NoPages,
+    #[error("Page has no layers, or the layers are empty. page: {0}")]
+    EmptyPage(usize),
+    #[error("Units of a layer are unknown, they are required for physical scaling. page: {page}, layer: {layer}")]
+    UnknownUnits { page: usize, layer: usize },
+    #[error(
+        "Printed area of a tile is not larger than the overlap. width: {width}, height: {height}, overlap: {overlap}"
+    )]
+    TileTooSmall { width: f64, height: f64, overlap: f64 },
+}
+
+/// Exports the pages to a PDF document, at an exact 1:1 scale, e.g. for toner-transfer or printed design reviews.
+///
+/// The layers are centered on each page, layers in inches and millimeters can be mixed.  Clear primitives are drawn in
+/// white, i.e. the color of the paper, since PDF has no way to erase what was drawn before.  Circles, lines and arcs are
+/// exact, arcs are drawn using the points of [`crate::ArcView::points`].
+///
+/// Print using "actual size", not "fit to page", to preserve the scale.
+pub fn export_pdf(pages: &[PdfPage], options: &PdfOptions) -> Result<Vec<u8>, PdfError> {
+    if pages.is_empty() {
+        return Err(PdfError::NoPages);
+    }
+
+    let pages = pages
+        .iter()
+        .enumerate()
+        .map(|(page_index, page)| page_content(page_index, page, options))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(write_pages(&pages))
+}
+
+/// Exports a page split into tiles, one per sheet of paper, at an exact 1:1 scale, so that boards larger than the paper
+/// can be printed, cut out and taped together, e.g. for mechanical fit checks against an enclosure.
+///
+/// The tiles are in reading order, left to right, then top to bottom, the top left corner of the layers is at the top
+/// left of the printed area of the first tile.  Adjacent tiles share `overlap` millimeters of the layers.  The layers are
+/// drawn as with [`export_pdf`].
+///
+/// Print using "actual size", not "fit to page", to preserve the scale.
+pub fn export_tiled_pdf(page: &PdfPage, options: &TileOptions) -> Result<Vec<u8>, PdfError> {
+    let (page_width, page_height) = options.page_size;
+    let printed = Vector2::new(page_width, page_height).add_scalar(-options.margin * 2.0);
+    if printed.x <= options.overlap || printed.y <= options.overlap {
+        return Err(PdfError::TileTooSmall {
+            width: printed.x,
+            height: printed.y,
+            overlap: options.overlap,
+        });
+    }
+
+    let (layers, bounds) = page_layers(0, page)?;
+    let size = Vector2::new(bounds.width(), bounds.height());
+
+    // the layers are drawn once, with the bottom left corner of their bounds at 0,0, each tile shows a part of them
+    let mut content = PageContent {
+        content: String::new(),
+        scale: 0.0,
+        origin: -bounds.min.coords * POINTS_PER_MILLIMETER,
+        mirror: options.mirror,
+        page_width: size.x * POINTS_PER_MILLIMETER,
+    };
+    draw_layers(&mut content, layers);
+
+    let step = printed.add_scalar(-options.overlap);
+    let tile_count = |size: f64, printed: f64, step: f64| match size <= printed {
+        true => 1,
+        false => ((size - options.overlap) / step).ceil() as usize,
+    };
+    let columns = tile_count(size.x, printed.x, step.x);
+    let rows = tile_count(size.y, printed.y, step.y);
+
+    let page_size = Vector2::new(page_width, page_height) * POINTS_PER_MILLIMETER;
+    let margin = options.margin * POINTS_PER_MILLIMETER;
+    let (printed, step, size) = (
+        printed * POINTS_PER_MILLIMETER,
+        step * POINTS_PER_MILLIMETER,
+        size * POINTS_PER_MILLIMETER,
+    );
+
+    let mut tiles = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            // the bottom left corner of the tile, relative to the layers
+            let corner = Vector2::new(column as f64 * step.x, size.y - row as f64 * step.y - printed.y);
+            // clips to the printed area, then moves the corner of the tile to the corner of the printed area
+            let mut tile = format!(
+                "q\n{} {} {} {} re W n\n1 0 0 1 {} {} cm\n{}Q\n",
+                number(margin),
+                number(margin),
+                number(printed.x),
+                number(printed.y),
+                number(margin - corner.x),
+                number(margin - corner.y),
+                content.content
+            );
+            if options.crop_marks {
+                tile.push_str(&crop_marks(margin, printed));
+            }
+            tiles.push((page_size, tile));
+        }
+    }
+
+    Ok(write_pages(&tiles))
+}
+
+/// Lines from each corner of the printed area outwards, into the margin, both horizontally and vertically.
+fn crop_marks(margin: f64, printed: Vector2<f64>) -> String {
+    let length = margin.min(5.0 * POINTS_PER_MILLIMETER);
+    if length <= 0.0 {
+        return String::new();
+    }
+
+    let mut marks = "0 0 0 RG 0.25 w 0 J\n".to_string();
+    for (x, y, outwards) in [
+        (margin, margin, Vector2::new(-1.0, -1.0)),
+        (margin + printed.x, margin, Vector2::new(1.0, -1.0)),
+        (margin, margin + printed.y, Vector2::new(-1.0, 1.0)),
+        (margin + printed.x, margin + printed.y, Vector2::new(1.0, 1.0)),
+    ] {
+        let (x, y, mark_x, mark_y) = (
+            number(x),
+            number(y),
+            number(x + outwards.x * length),
+            number(y + outwards.y * length),
+        );
+        marks.push_str(&format!("{x} {y} m\n{mark_x} {y} l\n{x} {y} m\n{x} {mark_y} l\nS\n"));
+    }
+    marks
+}
+
+/// Writes a document, each page is its size, in points, and its content stream.
+fn write_pages(pages: &[(Vector2<f64>, String)]) -> Vec<u8> {
+    let mut writer = PdfWriter::default();
+    // the catalog and the page tree are objects 1 and 2, each page is followed by its content stream
+    let page_ids = (0..pages.len())
+        .map(|index| 3 + index * 2)
+        .collect::<Vec<_>>();
+
+    writer.object(1, "<< /Type /Catalog /Pages 2 0 R >>");
+    let kids = page_ids
+        .iter()
+        .map(|id| format!("{} 0 R", id))
+        .collect::<Vec<_>>()
+        .join(" ");
+    writer.object(
+        2,
+        &format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, pages.len()),
+    );
+
+    for ((size, content), page_id) in pages.iter().zip(page_ids) {
+        writer.object(
+            page_id,
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R >>",
+                number(size.x),
+                number(size.y),
+                page_id + 1
+            ),
+        );
+        writer.object(
+            page_id + 1,
+            &format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+        );
+    }
+
+    writer.finish(1)
+}
+
+/// Returns the size of the page, in points, and the content stream.
+fn page_content(page_index: usize, page: &PdfPage, options: &PdfOptions) -> Result<(Vector2<f64>, String), PdfError> {
+    let (layers, bounds) = page_layers(page_index, page)?;
+
+    let size = match options.page_size {
+        Some((width, height)) => Vector2::new(width, height),
+        None => Vector2::new(
+            bounds.width() + options.margin * 2.0,
+            bounds.height() + options.margin * 2.0,
+        ),
+    } * POINTS_PER_MILLIMETER;
+
+    // centers the layers on the page
+    let center = bounds.center().coords * POINTS_PER_MILLIMETER;
+    let origin = size / 2.0 - center;
+
+    let mut content = PageContent {
+        content: String::new(),
+        scale: 0.0,
+        origin,
+        mirror: options.mirror,
+        page_width: size.x,
+    };
+    draw_layers(&mut content, layers);
+
+    Ok((size, content.content))
+}
+
+/// The non-empty layers of the page, with the factor from their units to millimeters, and their bounds, in millimeters.
+type PageLayers<'a> = (Vec<(&'a PdfLayer<'a>, f64)>, BoundingBox);
+
+fn page_layers<'a>(page_index: usize, page: &'a PdfPage<'a>) -> Result<PageLayers<'a>, PdfError> {
+    let layers = page
+        .layers
+        .iter()
+        .enumerate()
+        .filter(|(_, pdf_layer)| !pdf_layer.layer.is_empty())
+        .map(|(layer_index, pdf_layer)| {
+            let units = pdf_layer
+                .layer
+                .units()
+                .ok_or(PdfError::UnknownUnits {
+                    page: page_index,
+                    layer: layer_index,
+                })?;
+            Ok((pdf_layer, unit_conversion_factor(units, Unit::Millimeters)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut bounds = BoundingBox::default();
+    for (pdf_layer, to_millimeters) in &layers {
+        bounds.expand(
+            &pdf_layer
+                .layer
+                .bounding_box()
+                .scale(*to_millimeters),
+        );
+    }
+    if bounds.is_empty() {
+        return Err(PdfError::EmptyPage(page_index));
+    }
+
+    Ok((layers, bounds))
+}
+
+fn draw_layers(content: &mut PageContent, layers: Vec<(&PdfLayer, f64)>) {
+    // lines and arcs have round caps and joins
+    content.content.push_str("1 J 1 j\n");
+
+    for (pdf_layer, to_millimeters) in layers {
+        content.scale = to_millimeters * POINTS_PER_MILLIMETER;
+
+        let layer = pdf_layer.layer;
+        let primitives = layer.gerber_primitives();
+        for (index, offset) in layer.primitives_in_draw_order() {
+            content.primitive(&primitives[index], offset, pdf_layer.color);
+        }
+    }
+}
+
+struct PageContent {
+    content: String,
+    /// Layer units to points.
+    scale: f64,
+    /// The position of the origin of the layers, in points.
+    origin: Vector2<f64>,
+    mirror: bool,
+    page_width: f64,
+}
+
+impl PageContent {
+    fn primitive(&mut self, primitive: &GerberPrimitive, offset: Vector2<f64>, color: [u8; 3]) {
+        let color = match primitive.exposure() {
+            Exposure::Add => color,
+            Exposure::CutOut => [255, 255, 255],
+        };
+        let [red, green, blue] = color.map(|component| number(component as f64 / 255.0));
+        self.content
+            .push_str(&format!("{red} {green} {blue} rg {red} {green} {blue} RG\n"));
+
+        match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => {
+                self.circle(*center + offset, diameter / 2.0);
+                self.content.push_str("f\n");
+            }
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let origin = *origin + offset;
+                let corners = [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)]
+                    .map(|(x, y)| origin + Vector2::new(x, y));
+                self.path(&corners, true);
+                self.content.push_str("f\n");
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => {
+                self.stroke_width(*width);
+                self.path(&[*start + offset, *end + offset], false);
+                self.content.push_str("S\n");
+            }
+            GerberPrimitive::Arc(arc) => {
+                let center = arc.center + offset;
+                let points = arc
+                    .generate_points()
+                    .into_iter()
+                    .map(|point| center + point.coords)
+                    .collect::<Vec<_>>();
+                self.stroke_width(arc.width);
+                self.path(&points, arc.is_full_circle());
+                self.content.push_str("S\n");
+            }
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let center = *center + offset;
+                for contour in split_contours(&geometry.relative_vertices) {
+                    let contour = contour
+                        .into_iter()
+                        .map(|vertex| center + vertex.coords)
+                        .collect::<Vec<_>>();
+                    self.path(&contour, true);
+                }
+                // even-odd, like the tessellation, so cut-in holes are not filled
+                self.content.push_str("f*\n");
+            }
+        }
+    }
+
+    /// Layer coordinates to page coordinates, in points.
+    fn point(&self, point: Point2<f64>) -> String {
+        let mut position = point.coords * self.scale + self.origin;
+        if self.mirror {
+            position.x = self.page_width - position.x;
+        }
+        format!("{} {}", number(position.x), number(position.y))
+    }
+
+    fn stroke_width(&mut self, width: f64) {
+        self.content
+            .push_str(&format!("{} w\n", number(width * self.scale)));
+    }
+
+    fn path(&mut self, points: &[Point2<f64>], closed: bool) {
+        for (index, point) in points.iter().enumerate() {
+            let operator = match index {
+                0 => "m",
+                _ => "l",
+            };
+            let point = self.point(*point);
+            self.content
+                .push_str(&format!("{} {}\n", point, operator));
+        }
+        if closed {
+            self.content.push_str("h\n");
+        }
+    }
+
+    /// A circle made of four cubic bezier curves.
+    fn circle(&mut self, center: Point2<f64>, radius: f64) {
+        let control = radius * BEZIER_CIRCLE;
+        let point = |x: f64, y: f64| self.point(center + Vector2::new(x, y));
+
+        let mut path = format!("{} m\n", point(radius, 0.0));
+        for [(x1, y1), (x2, y2), (x3, y3)] in [
+            [(radius, control), (control, radius), (0.0, radius)],
+            [(-control, radius), (-radius, control), (-radius, 0.0)],
+            [(-radius, -control), (-control, -radius), (0.0, -radius)],
+            [(control, -radius), (radius, -control), (radius, 0.0)],
+        ] {
+            path.push_str(&format!("{} {} {} c\n", point(x1, y1), point(x2, y2), point(x3, y3)));
+        }
+        path.push_str("h\n");
+
+        self.content.push_str(&path);
+    }
+}
+
+/// Formats a number for a PDF, with up to 4 decimal places, without trailing zeros.
+fn number(value: f64) -> String {
+    let formatted = format!("{:.4}", value);
+    let trimmed = formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.');
+    match trimmed {
+        "-0" | "" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// Writes numbered objects and the cross-reference table.
+#[derive(Default)]
+struct PdfWriter {
+    output: Vec<u8>,
+    /// (object id, byte offset)
+    offsets: Vec<(usize, usize)>,
+}
+
+impl PdfWriter {
+    fn object(&mut self, id: usize, body: &str) {
+        if self.output.is_empty() {
+            // the comment with binary characters marks the file as binary, for transfer programs
+            self.output
+                .extend_from_slice(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n");
+        }
+        self.offsets
+            .push((id, self.output.len()));
+        self.output
+            .extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", id, body).as_bytes());
+    }
+
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        self.offsets.sort();
+        let size = self.offsets.len() + 1;
+
+        let xref = self.output.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", size);
+        for (_, offset) in &self.offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            size, root, xref
+        ));
+        self.output
+            .extend_from_slice(table.as_bytes());
+
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Rectangular};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::testing::flash_layer;
+
+    /// A 10x10mm square.
+    fn layer() -> GerberLayer {
+        flash_layer(
+            Unit::Millimeters,
+            Aperture::Rectangle(Rectangular::new(10.0, 10.0)),
+            5.0,
+            5.0,
+        )
+    }
+
+    #[test]
+    fn test_export_pdf() {
+        // given
+        let layer = layer();
+        let pages = [PdfPage {
+            layers: vec![PdfLayer {
+                layer: &layer,
+                color: [0, 0, 0],
+            }],
+        }];
+        let options = PdfOptions {
+            margin: 0.0,
+            ..PdfOptions::default()
+        };
+
+        // when
+        let pdf = export_pdf(&pages, &options).unwrap();
+
+        // then
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        // 10mm = 28.3465pt
+        assert!(text.contains("/MediaBox [0 0 28.3465 28.3465]"));
+        assert!(text.contains("0 0 m\n28.3465 0 l\n28.3465 28.3465 l\n0 28.3465 l\nh\nf\n"));
+
+        // and the cross-reference table is where the trailer says it is
+        let startxref = text
+            .lines()
+            .rev()
+            .nth(1)
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+        assert!(pdf[startxref..].starts_with(b"xref"));
+    }
+
+    #[test]
+    fn test_export_pdf_mirrored() {
+        // given
+        let layer = layer();
+        let pages = [PdfPage {
+            layers: vec![PdfLayer {
+                layer: &layer,
+                color: [0, 0, 0],
+            }],
+        }];
+        let options = PdfOptions {
+            page_size: Some((20.0, 10.0)),
+            mirror: true,
+            ..PdfOptions::default()
+        };
+
+        // when
+        let pdf = export_pdf(&pages, &options).unwrap();
+
+        // then
+        // centered, then mirrored, the first corner moves from the left edge of the square to the right edge
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("42.5197 0 m\n"));
+    }
+
+    #[test]
+    fn test_export_tiled_pdf() {
+        // given
+        let layer = layer();
+        let page = PdfPage {
+            layers: vec![PdfLayer {
+                layer: &layer,
+                color: [0, 0, 0],
+            }],
+        };
+        // an 8x8mm printed area, so the 10x10mm square needs 2x2 tiles
+        let options = TileOptions {
+            page_size: (10.0, 10.0),
+            margin: 1.0,
+            overlap: 2.0,
+            ..TileOptions::default()
+        };
+
+        // when
+        let pdf = export_tiled_pdf(&page, &options).unwrap();
+
+        // then
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Count 4 >>"));
+        assert_eq!(
+            text.matches("/MediaBox [0 0 28.3465 28.3465]")
+                .count(),
+            4
+        );
+        // the first tile shows the top left of the square, which is 2mm above the printed area
+        assert!(text.contains("2.8346 2.8346 22.6772 22.6772 re W n\n1 0 0 1 2.8346 -2.8346 cm\n"));
+        // the last tile shows the bottom right, the printed area starts 6mm into the square, and ends 4mm below it
+        assert!(text.contains("1 0 0 1 -14.1732 14.1732 cm\n"));
+    }
+
+    #[test]
+    fn test_export_tiled_pdf_tile_too_small() {
+        // given
+        let layer = layer();
+        let page = PdfPage {
+            layers: vec![PdfLayer {
+                layer: &layer,
+                color: [0, 0, 0],
+            }],
+        };
+        let options = TileOptions {
+            page_size: (30.0, 30.0),
+            margin: 10.0,
+            overlap: 10.0,
+            ..TileOptions::default()
+        };
+
+        // expect
+        assert_eq!(
+            export_tiled_pdf(&page, &options),
+            Err(PdfError::TileTooSmall {
+                width: 10.0,
+                height: 10.0,
+                overlap: 10.0
+            })
+        );
+    }
+
+    #[rstest]
+    #[case(0.0, "0")]
+    #[case(-0.00001, "0")]
+    #[case(1.5, "1.5")]
+    #[case(28.346456, "28.3465")]
+    fn test_number(#[case] value: f64, #[case] expected: &str) {
+        assert_eq!(number(value), expected);
+    }
+}
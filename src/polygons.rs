@@ -0,0 +1,246 @@
+use std::f64::consts::{PI, TAU};
+
+use geo::{unary_union, BooleanOps, Coord, LineString, MultiPolygon, Polygon};
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::split_contours;
+use crate::layer::{
+    CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGerberPrimitive,
+    RectangleGerberPrimitive,
+};
+use crate::{Exposure, PrimitiveView};
+
+/// The number of segments used to approximate a full circle when converting primitives to polygons.
+pub const CIRCLE_SEGMENTS: usize = 64;
+
+impl GerberLayer {
+    /// Merges the primitives of the layer into a set of polygons with holes, e.g. the copper of a copper layer.
+    ///
+    /// The primitives are combined in drawing order, additive primitives are unioned and cut-outs, e.g. clear (LPC)
+    /// objects, are subtracted from the primitives drawn before them.  Each copy of a step-and-repeat primitive is
+    /// included, in the order of [`GerberLayer::primitives_in_draw_order`].  Circles and arcs are approximated, see [`CIRCLE_SEGMENTS`].
+    pub fn merged_polygons(&self) -> MultiPolygon<f64> {
+        let mut result = MultiPolygon::new(vec![]);
+
+        // consecutive primitives with the same exposure are combined in a single operation, which is much faster than
+        // combining them one at a time.
+        let mut batch: Vec<Polygon<f64>> = vec![];
+        let mut batch_exposure = Exposure::Add;
+
+        let primitives = self.gerber_primitives();
+        for (index, offset) in self.primitives_in_draw_order() {
+            let primitive = &primitives[index];
+            let exposure = primitive.exposure();
+            if exposure != batch_exposure && !batch.is_empty() {
+                result = apply_batch(&result, &batch, batch_exposure);
+                batch.clear();
+            }
+            batch_exposure = exposure;
+
+            batch.extend(primitive.to_polygons(offset));
+        }
+        if !batch.is_empty() {
+            result = apply_batch(&result, &batch, batch_exposure);
+        }
+
+        result
+    }
+}
+
+fn apply_batch(result: &MultiPolygon<f64>, batch: &[Polygon<f64>], exposure: Exposure) -> MultiPolygon<f64> {
+    let batch = unary_union(batch);
+    match exposure {
+        Exposure::Add => result.union(&batch),
+        Exposure::CutOut => result.difference(&batch),
+    }
+}
+
+impl PrimitiveView<'_> {
+    /// Converts the primitive into polygons, moved by `offset`, ignoring the exposure, e.g. for each copy of a
+    /// step-and-repeat primitive, see [`GerberLayer::primitive_offsets`].
+    ///
+    /// Some primitives, e.g. stroked arcs, are converted into multiple overlapping polygons, see
+    /// [`PrimitiveView::to_multi_polygon`] for non-overlapping polygons.
+    pub fn to_polygons(&self, offset: Vector2<f64>) -> Vec<Polygon<f64>> {
+        self.primitive().to_polygons(offset)
+    }
+}
+
+impl GerberPrimitive {
+    /// Converts the primitive into polygons, ignoring the exposure.
+    ///
+    /// Some primitives, e.g. stroked arcs, are converted into multiple overlapping polygons, see
+    /// [`GerberPrimitive::to_multi_polygon`] for non-overlapping polygons.
+    pub(crate) fn to_polygons(&self, offset: Vector2<f64>) -> Vec<Polygon<f64>> {
+        match self {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => vec![circle_polygon(*center + offset, diameter / 2.0)],
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let origin = *origin + offset;
+                let corners = [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)]
+                    .map(|(x, y)| origin + Vector2::new(x, y));
+                vec![Polygon::new(ring(corners), vec![])]
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => vec![stroke_polygon(*start + offset, *end + offset, width / 2.0)],
+            GerberPrimitive::Arc(arc) => {
+                let center = arc.center + offset;
+                let half_width = arc.width / 2.0;
+
+                if arc.is_full_circle() {
+                    let outer = circle_polygon(center, arc.radius + half_width);
+                    if arc.radius <= half_width {
+                        return vec![outer];
+                    }
+                    let inner = circle_polygon(center, arc.radius - half_width);
+                    return vec![Polygon::new(outer.exterior().clone(), vec![inner.exterior().clone()])];
+                }
+
+                let points = arc
+                    .generate_points()
+                    .into_iter()
+                    .map(|point| center + point.coords)
+                    .collect::<Vec<_>>();
+                points
+                    .windows(2)
+                    .map(|segment| stroke_polygon(segment[0], segment[1], half_width))
+                    .collect()
+            }
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let center = *center + offset;
+                let mut contours = split_contours(&geometry.relative_vertices)
+                    .into_iter()
+                    .map(|contour| {
+                        ring(
+                            contour
+                                .into_iter()
+                                .map(|vertex| center + vertex.coords),
+                        )
+                    });
+                let Some(exterior) = contours.next() else {
+                    return vec![];
+                };
+                vec![Polygon::new(exterior, contours.collect())]
+            }
+        }
+    }
+}
+
+fn ring(points: impl IntoIterator<Item = Point2<f64>>) -> LineString<f64> {
+    LineString::new(
+        points
+            .into_iter()
+            .map(|point| Coord {
+                x: point.x,
+                y: point.y,
+            })
+            .collect(),
+    )
+}
+
+fn circle_polygon(center: Point2<f64>, radius: f64) -> Polygon<f64> {
+    let points = (0..CIRCLE_SEGMENTS).map(|index| {
+        let angle = TAU * index as f64 / CIRCLE_SEGMENTS as f64;
+        center + Vector2::new(angle.cos(), angle.sin()) * radius
+    });
+    Polygon::new(ring(points), vec![])
+}
+
+/// A line with round caps, i.e. a stadium.
+fn stroke_polygon(start: Point2<f64>, end: Point2<f64>, radius: f64) -> Polygon<f64> {
+    let direction = end - start;
+    if direction.norm() == 0.0 {
+        return circle_polygon(start, radius);
+    }
+
+    let angle = direction.y.atan2(direction.x);
+    let half_segments = CIRCLE_SEGMENTS / 2;
+    let cap = |center: Point2<f64>, start_angle: f64| {
+        (0..=half_segments).map(move |index| {
+            let angle = start_angle + PI * index as f64 / half_segments as f64;
+            center + Vector2::new(angle.cos(), angle.sin()) * radius
+        })
+    };
+
+    // counter-clockwise; around the end, from the right side to the left side, then around the start
+    let points = cap(end, angle - PI / 2.0).chain(cap(start, angle + PI / 2.0));
+    Polygon::new(ring(points), vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use geo::Area;
+    use gerber_types::{Aperture, Command, ExtendedCode, Polarity, Rectangular, StepAndRepeat, Unit};
+
+    use super::*;
+    use crate::testing::{aperture, flash, header};
+
+    #[test]
+    fn test_merged_polygons() {
+        // given
+        let mut commands = header(Unit::Millimeters);
+        // two overlapping 2x2 squares
+        commands.extend(aperture(10, Aperture::Rectangle(Rectangular::new(2.0, 2.0))));
+        commands.extend([flash(0.0, 0.0), flash(1.0, 0.0)]);
+        // a 1x1 hole in the middle of the first square
+        commands.push(Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)));
+        commands.extend(aperture(11, Aperture::Rectangle(Rectangular::new(1.0, 1.0))));
+        commands.push(flash(0.0, 0.0));
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let polygons = layer.merged_polygons();
+
+        // then
+        assert_eq!(polygons.0.len(), 1);
+        assert_eq!(polygons.0[0].interiors().len(), 1);
+        assert!((polygons.unsigned_area() - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_merged_polygons_step_and_repeat() {
+        // given
+        // a 2x2 square with a 0.5x0.5 hole, repeated twice, the second copy covers the hole of the first copy
+        let mut commands = header(Unit::Millimeters);
+        commands.push(Command::ExtendedCode(ExtendedCode::StepAndRepeat(
+            StepAndRepeat::Open {
+                repeat_x: 2,
+                repeat_y: 1,
+                distance_x: 1.0,
+                distance_y: 0.0,
+            },
+        )));
+        commands.extend(aperture(10, Aperture::Rectangle(Rectangular::new(2.0, 2.0))));
+        commands.push(flash(0.0, 0.0));
+        commands.push(Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)));
+        commands.extend(aperture(11, Aperture::Rectangle(Rectangular::new(0.5, 0.5))));
+        commands.push(flash(0.5, 0.0));
+        commands.push(Command::ExtendedCode(ExtendedCode::StepAndRepeat(StepAndRepeat::Close)));
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let polygons = layer.merged_polygons();
+
+        // then
+        // only the hole of the second copy remains
+        assert_eq!(polygons.0.len(), 1);
+        assert_eq!(polygons.0[0].interiors().len(), 1);
+        assert!((polygons.unsigned_area() - (6.0 - 0.25)).abs() < 1e-9);
+    }
+}
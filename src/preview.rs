@@ -0,0 +1,283 @@
+use nalgebra::Vector2;
+
+use crate::geometry::{BoundingBox, GerberTransform};
+use crate::layer::{GerberPrimitive, RectangleGerberPrimitive};
+use crate::raster::{premultiply, Canvas};
+use crate::{
+    BlendMode, BoardSide, Exposure, LayerInfo, LayerSet, LayerType, NegativeArea, RasterError, RasterImage,
+    RasterOptions, Resolution,
+};
+
+/// The colors of the materials of a board, straight (not premultiplied) RGBA, see [`LayerSet::render_preview`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardColors {
+    /// The bare board, e.g. FR4, visible where there is no copper and no soldermask.
+    pub substrate: [u8; 4],
+    /// Copper covered by soldermask, the soldermask color is blended over it, so use a translucent soldermask color.
+    pub copper: [u8; 4],
+    /// Copper exposed by the openings in the soldermask, e.g. the surface finish, like HASL or ENIG.
+    pub finish: [u8; 4],
+    pub soldermask: [u8; 4],
+    pub silkscreen: [u8; 4],
+}
+
+impl BoardColors {
+    /// Green soldermask, white silkscreen, and a HASL finish.
+    pub const GREEN: BoardColors = BoardColors {
+        substrate: [150, 140, 90, 255],
+        copper: [184, 115, 51, 255],
+        finish: [200, 200, 200, 255],
+        soldermask: [0, 100, 40, 220],
+        silkscreen: [245, 245, 245, 255],
+    };
+
+    /// Black soldermask, white silkscreen, and an ENIG finish.
+    pub const BLACK: BoardColors = BoardColors {
+        substrate: [150, 140, 90, 255],
+        copper: [184, 115, 51, 255],
+        finish: [212, 175, 55, 255],
+        soldermask: [20, 20, 20, 235],
+        silkscreen: [245, 245, 245, 255],
+    };
+}
+
+impl Default for BoardColors {
+    fn default() -> Self {
+        Self::GREEN
+    }
+}
+
+/// Options for [`LayerSet::render_preview`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewOptions {
+    pub resolution: Resolution,
+    pub side: BoardSide,
+    pub colors: BoardColors,
+    /// Straight (not premultiplied) RGBA, visible outside the board and through the drill holes.
+    pub background: [u8; 4],
+    /// The maximum gap between the lines and arcs of the outline layer, see [`crate::GerberLayer::board_outline`].
+    pub outline_tolerance: f64,
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        Self {
+            resolution: Resolution::Dpi(300.0),
+            side: BoardSide::Top,
+            colors: BoardColors::default(),
+            background: [0, 0, 0, 0],
+            outline_tolerance: 0.01,
+        }
+    }
+}
+
+impl LayerSet {
+    /// Renders a realistic preview of one side of the board, like the previews of online gerber viewers and PCB
+    /// manufacturers.
+    ///
+    /// The copper, soldermask and silkscreen layers of the side are used, along with the drill and outline layers:
+    /// * The board is clipped to the contours of the outline layer, or to the bounding box of the layers when there is
+    ///   no outline layer, or the outline has no closed contours.
+    /// * The soldermask covers the board, except for its openings, i.e. the primitives of the soldermask layer, where
+    ///   the copper gets the finish color.  Without a soldermask layer all the copper has the finish color.
+    /// * The silkscreen is drawn on top, except in the soldermask openings, like most manufacturers do.
+    /// * The drills are punched through the board, showing the background.
+    ///
+    /// The transform and the opacity of each layer are applied, e.g. a half transparent silkscreen layer shows the
+    /// soldermask below it.  Paste layers are not used.  All the layers are assumed to be in the same units.
+    pub fn render_preview(&self, options: &PreviewOptions) -> Result<RasterImage, RasterError> {
+        let (copper, soldermask, silkscreen) = match options.side {
+            BoardSide::Top => (LayerType::TopCopper, LayerType::TopSolderMask, LayerType::TopSilkscreen),
+            BoardSide::Bottom => (
+                LayerType::BottomCopper,
+                LayerType::BottomSolderMask,
+                LayerType::BottomSilkscreen,
+            ),
+        };
+        let layers_of_type = |layer_type: LayerType| {
+            self.iter()
+                .filter(move |info| info.layer_type == layer_type && !info.layer.is_empty())
+        };
+
+        let outline = layers_of_type(LayerType::Outline).next();
+        let used_layers = [copper, soldermask, silkscreen, LayerType::Drill]
+            .into_iter()
+            .flat_map(layers_of_type)
+            .chain(outline)
+            .collect::<Vec<_>>();
+
+        let first = used_layers
+            .first()
+            .ok_or(RasterError::Empty)?;
+        let bounds = match outline {
+            Some(outline) => outline.bounding_box(),
+            None => {
+                let mut bounds = BoundingBox::default();
+                for info in &used_layers {
+                    bounds.expand(&info.bounding_box());
+                }
+                bounds
+            }
+        };
+
+        // the layers are mirrored around the center of the board, so the image covers the same area
+        let transform = options.side.transform(&bounds);
+
+        let raster_options = RasterOptions {
+            resolution: options.resolution,
+            background: options.background,
+            transform,
+            ..RasterOptions::default()
+        };
+        let mut canvas = Canvas::new(&bounds, first.layer.units(), &raster_options)?;
+
+        let coverage = |layer_type: LayerType| -> Option<Vec<f32>> {
+            let mut layers = layers_of_type(layer_type).peekable();
+            layers.peek()?;
+            let mut coverage = vec![0.0; canvas.pixels.len()];
+            for info in layers {
+                let mut mask = canvas.mask(&info.combined_transform(&transform));
+                mask.draw_layer(&info.layer, None);
+                let opacity = info.opacity.clamp(0.0, 1.0);
+                for (total, layer) in coverage
+                    .iter_mut()
+                    .zip(mask.into_coverage())
+                {
+                    // the union of the layers of the same type
+                    *total += layer * opacity * (1.0 - *total);
+                }
+            }
+            Some(coverage)
+        };
+        let copper_coverage = coverage(copper);
+        let opening_coverage = coverage(soldermask);
+        let silkscreen_coverage = coverage(silkscreen);
+        let drill_coverage = coverage(LayerType::Drill);
+        let board = board_coverage(&canvas, &transform, outline, &bounds, options.outline_tolerance);
+
+        let at = |coverage: &Option<Vec<f32>>, index: usize| {
+            coverage
+                .as_ref()
+                .map_or(0.0, |coverage| coverage[index])
+        };
+        let colors = &options.colors;
+        for (index, pixel) in canvas.pixels.iter_mut().enumerate() {
+            let board = board[index] * (1.0 - at(&drill_coverage, index));
+            if board <= 0.0 {
+                continue;
+            }
+
+            let (copper, opening) = (at(&copper_coverage, index), at(&opening_coverage, index));
+            let soldermask = match opening_coverage.is_some() {
+                true => 1.0 - opening,
+                false => 0.0,
+            };
+
+            let layers = [
+                (colors.copper, copper),
+                (colors.soldermask, soldermask),
+                (colors.finish, copper * (1.0 - soldermask)),
+                (colors.silkscreen, at(&silkscreen_coverage, index) * (1.0 - opening)),
+            ];
+            let mut color = premultiply(colors.substrate, 1.0);
+            for (layer_color, coverage) in layers {
+                if coverage > 0.0 {
+                    color = BlendMode::Normal.blend(premultiply(layer_color, coverage), color);
+                }
+            }
+
+            *pixel = BlendMode::Normal.blend(color.map(|channel| channel * board), *pixel);
+        }
+
+        Ok(canvas.into_image())
+    }
+}
+
+/// The area of the board, the closed contours of the outline layer, or the bounds if there are none.
+///
+/// The bounds are after the transform of the layers, only the `transform` of the view is applied to them.
+fn board_coverage(
+    canvas: &Canvas,
+    transform: &GerberTransform,
+    outline: Option<&LayerInfo>,
+    bounds: &BoundingBox,
+    tolerance: f64,
+) -> Vec<f32> {
+    let (fill, transform) = outline
+        .and_then(|outline| {
+            let layer = &outline.layer;
+            let fill = layer.negative_fill(&NegativeArea::Outline(layer.board_outline(tolerance)))?;
+            Some((fill, outline.combined_transform(transform)))
+        })
+        .unwrap_or_else(|| {
+            let fill = GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin: bounds.min,
+                width: bounds.width(),
+                height: bounds.height(),
+                exposure: Exposure::Add,
+            });
+            (fill, *transform)
+        });
+
+    let mut mask = canvas.mask(&transform);
+    mask.draw_primitive(&fill, Vector2::new(0.0, 0.0), Exposure::Add);
+
+    mask.into_coverage()
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Rectangular, Unit};
+
+    use super::*;
+    use crate::testing::flash_layer;
+    use crate::MILLIMETERS_PER_INCH;
+
+    #[test]
+    fn test_render_preview() {
+        // given
+        // a 10x10mm copper plane, with a 4mm soldermask opening and a 1mm drill in the center
+        let mut layer_set = LayerSet::default();
+        layer_set.add(
+            LayerType::TopCopper,
+            "copper",
+            flash_layer(
+                Unit::Millimeters,
+                Aperture::Rectangle(Rectangular::new(10.0, 10.0)),
+                5.0,
+                5.0,
+            ),
+        );
+        layer_set.add(
+            LayerType::TopSolderMask,
+            "mask",
+            flash_layer(Unit::Millimeters, Aperture::Circle(Circle::new(4.0)), 5.0, 5.0),
+        );
+        layer_set.add(
+            LayerType::Drill,
+            "drill",
+            flash_layer(Unit::Millimeters, Aperture::Circle(Circle::new(1.0)), 5.0, 5.0),
+        );
+        let options = PreviewOptions {
+            // 10 pixels per mm
+            resolution: Resolution::Dpi(MILLIMETERS_PER_INCH * 10.0),
+            ..PreviewOptions::default()
+        };
+
+        // when
+        let image = layer_set
+            .render_preview(&options)
+            .unwrap();
+
+        // then
+        assert_eq!((image.width, image.height), (100, 100));
+        // exposed copper, in the opening
+        assert_eq!(image.pixel(65, 45), BoardColors::GREEN.finish);
+        // the drill
+        assert_eq!(image.pixel(50, 50), [0, 0, 0, 0]);
+        // covered copper
+        let covered = image.pixel(5, 5);
+        assert_ne!(covered, BoardColors::GREEN.finish);
+        assert_eq!(covered[3], 255);
+    }
+}
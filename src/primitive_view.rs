@@ -0,0 +1,271 @@
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::{split_contours, BoundingBox};
+use crate::layer::{
+    ArcGerberPrimitive, CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGeometry,
+    PolygonGerberPrimitive, RectangleGerberPrimitive,
+};
+use crate::Exposure;
+
+/// A read-only view of a primitive of a layer, see [`GerberLayer::primitives`].
+///
+/// Coordinates are in the units of the layer, see [`GerberLayer::units`], with the image transform applied.
+#[derive(Debug, Clone, Copy)]
+pub enum PrimitiveView<'a> {
+    Circle(CircleView<'a>),
+    Rectangle(RectangleView<'a>),
+    Line(LineView<'a>),
+    Arc(ArcView<'a>),
+    Polygon(PolygonView<'a>),
+}
+
+impl<'a> PrimitiveView<'a> {
+    pub(crate) fn new(primitive: &'a GerberPrimitive) -> Self {
+        match primitive {
+            GerberPrimitive::Circle(circle) => PrimitiveView::Circle(CircleView {
+                primitive,
+                circle,
+            }),
+            GerberPrimitive::Rectangle(rectangle) => PrimitiveView::Rectangle(RectangleView {
+                primitive,
+                rectangle,
+            }),
+            GerberPrimitive::Line(line) => PrimitiveView::Line(LineView {
+                primitive,
+                line,
+            }),
+            GerberPrimitive::Arc(arc) => PrimitiveView::Arc(ArcView {
+                primitive,
+                arc,
+            }),
+            GerberPrimitive::Polygon(polygon) => PrimitiveView::Polygon(PolygonView {
+                primitive,
+                polygon,
+            }),
+        }
+    }
+
+    /// The primitive being viewed.
+    pub(crate) fn primitive(&self) -> &'a GerberPrimitive {
+        match self {
+            PrimitiveView::Circle(view) => view.primitive,
+            PrimitiveView::Rectangle(view) => view.primitive,
+            PrimitiveView::Line(view) => view.primitive,
+            PrimitiveView::Arc(view) => view.primitive,
+            PrimitiveView::Polygon(view) => view.primitive,
+        }
+    }
+
+    /// Whether the primitive adds (dark) or removes (clear) material.
+    pub fn exposure(&self) -> Exposure {
+        self.primitive().exposure()
+    }
+
+    /// The bounding box of the primitive, including the width of lines and arcs.
+    pub fn bounding_box(&self) -> BoundingBox {
+        self.primitive().bounding_box()
+    }
+}
+
+/// A flash of a circular aperture.
+#[derive(Debug, Clone, Copy)]
+pub struct CircleView<'a> {
+    primitive: &'a GerberPrimitive,
+    circle: &'a CircleGerberPrimitive,
+}
+
+impl CircleView<'_> {
+    pub fn center(&self) -> Point2<f64> {
+        self.circle.center
+    }
+
+    pub fn diameter(&self) -> f64 {
+        self.circle.diameter
+    }
+}
+
+/// An axis-aligned rectangle, e.g. a flash of a rectangular aperture.
+#[derive(Debug, Clone, Copy)]
+pub struct RectangleView<'a> {
+    primitive: &'a GerberPrimitive,
+    rectangle: &'a RectangleGerberPrimitive,
+}
+
+impl RectangleView<'_> {
+    /// The bottom-left corner.
+    pub fn origin(&self) -> Point2<f64> {
+        self.rectangle.origin
+    }
+
+    pub fn width(&self) -> f64 {
+        self.rectangle.width
+    }
+
+    pub fn height(&self) -> f64 {
+        self.rectangle.height
+    }
+
+    pub fn center(&self) -> Point2<f64> {
+        self.rectangle.origin + Vector2::new(self.rectangle.width, self.rectangle.height) / 2.0
+    }
+}
+
+/// A draw with a circular aperture, i.e. a line with round ends.
+#[derive(Debug, Clone, Copy)]
+pub struct LineView<'a> {
+    primitive: &'a GerberPrimitive,
+    line: &'a LineGerberPrimitive,
+}
+
+impl LineView<'_> {
+    pub fn start(&self) -> Point2<f64> {
+        self.line.start
+    }
+
+    pub fn end(&self) -> Point2<f64> {
+        self.line.end
+    }
+
+    pub fn width(&self) -> f64 {
+        self.line.width
+    }
+}
+
+/// A circular arc drawn with a circular aperture.
+#[derive(Debug, Clone, Copy)]
+pub struct ArcView<'a> {
+    primitive: &'a GerberPrimitive,
+    arc: &'a ArcGerberPrimitive,
+}
+
+impl ArcView<'_> {
+    pub fn center(&self) -> Point2<f64> {
+        self.arc.center
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.arc.radius
+    }
+
+    pub fn width(&self) -> f64 {
+        self.arc.width
+    }
+
+    /// In radians, counter-clockwise from the positive x-axis.
+    pub fn start_angle(&self) -> f64 {
+        self.arc.start_angle
+    }
+
+    /// In radians, positive is counter-clockwise, see [`ArcView::is_full_circle`].
+    pub fn sweep_angle(&self) -> f64 {
+        self.arc.sweep_angle
+    }
+
+    /// `true` if the start and end points coincide, the sweep angle is then either zero or a full turn.
+    pub fn is_full_circle(&self) -> bool {
+        self.arc.is_full_circle()
+    }
+
+    /// The points along the center-line of the arc, from the start to the end, approximated by line segments.
+    pub fn points(&self) -> Vec<Point2<f64>> {
+        self.arc
+            .generate_points()
+            .into_iter()
+            .map(|point| self.arc.center + point.coords)
+            .collect()
+    }
+}
+
+/// A filled polygon, e.g. a region or a macro primitive.
+#[derive(Debug, Clone, Copy)]
+pub struct PolygonView<'a> {
+    primitive: &'a GerberPrimitive,
+    polygon: &'a PolygonGerberPrimitive,
+}
+
+impl<'a> PolygonView<'a> {
+    /// The position of the polygon, e.g. the position of a macro flash, the vertices are relative to this.
+    pub fn center(&self) -> Point2<f64> {
+        self.polygon.center
+    }
+
+    /// The vertices, relative to the center, counter-clockwise.  Regions with holes have cut-ins, see
+    /// [`PolygonView::contours`].
+    pub fn relative_vertices(&self) -> &'a [Point2<f64>] {
+        &self.polygon.geometry.relative_vertices
+    }
+
+    /// The contours of the polygon, the outer contour and the holes, with the cut-ins removed, in layer coordinates.
+    pub fn contours(&self) -> Vec<Vec<Point2<f64>>> {
+        split_contours(self.relative_vertices())
+            .into_iter()
+            .map(|contour| {
+                contour
+                    .into_iter()
+                    .map(|vertex| self.polygon.center + vertex.coords)
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn is_convex(&self) -> bool {
+        self.polygon.geometry.is_convex
+    }
+
+    /// The vertices and the precomputed tessellation, if any, e.g. to reuse the triangles of concave polygons.
+    pub fn geometry(&self) -> &'a PolygonGeometry {
+        &self.polygon.geometry
+    }
+}
+
+impl GerberLayer {
+    /// The primitives of the layer, in drawing order, for analysis by external tools.
+    ///
+    /// The position of a primitive is its index, as used by e.g. [`GerberLayer::hit_test`].  The primitives of
+    /// step-and-repeat blocks are included once, see [`GerberLayer::primitive_offsets`] for the positions of the copies.
+    /// Circles and arcs are exact, regions and macro primitives are polygons.
+    pub fn primitives(&self) -> impl ExactSizeIterator<Item = PrimitiveView<'_>> {
+        self.gerber_primitives()
+            .iter()
+            .map(PrimitiveView::new)
+    }
+
+    /// The primitive at `index`, see [`GerberLayer::primitives`].
+    pub fn primitive(&self, index: usize) -> Option<PrimitiveView<'_>> {
+        self.gerber_primitives()
+            .get(index)
+            .map(PrimitiveView::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Unit};
+
+    use super::*;
+    use crate::testing::{aperture, flash, header, interpolate, move_to};
+
+    #[test]
+    fn test_primitives() {
+        // given
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(0.5))));
+        commands.extend([flash(1.0, 2.0), move_to(0.0, 0.0), interpolate(3.0, 0.0)]);
+        let layer = GerberLayer::new(commands);
+
+        // when
+        let primitives = layer.primitives().collect::<Vec<_>>();
+
+        // then
+        let [PrimitiveView::Circle(circle), PrimitiveView::Line(line)] = primitives.as_slice() else {
+            panic!("expected a circle and a line, got {primitives:?}");
+        };
+        assert_eq!((circle.center(), circle.diameter()), (Point2::new(1.0, 2.0), 0.5));
+        assert_eq!(
+            (line.start(), line.end(), line.width()),
+            (Point2::new(0.0, 0.0), Point2::new(3.0, 0.0), 0.5)
+        );
+        assert_eq!(primitives[0].exposure(), Exposure::Add);
+        assert!(layer.primitive(2).is_none());
+    }
+}
@@ -0,0 +1,307 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use gerber_parser::parse;
+use log::{info, warn};
+use thiserror::Error;
+
+use crate::layer_set::{LayerSet, LayerType};
+use crate::GerberLayer;
+
+#[derive(Error, Debug)]
+pub enum ProjectError {
+    #[error("IO error. path: {path:?}, cause: {cause}")]
+    Io { path: PathBuf, cause: std::io::Error },
+    #[error("No gerber files found. path: {0:?}")]
+    NoGerberFiles(PathBuf),
+    #[error("Parse error. path: {path:?}, cause: {cause}")]
+    Parse { path: PathBuf, cause: String },
+    /// From [`load_zip`], which reads from a stream that has no path, [`load_zip_file`] reports
+    /// [`ProjectError::NoGerberFiles`] with the path of the archive instead.
+    #[cfg(feature = "zip")]
+    #[error("No gerber files found in archive")]
+    NoGerberFilesInArchive,
+    #[cfg(feature = "zip")]
+    #[error("Zip error. cause: {0}")]
+    Zip(#[from] zip::result::ZipError),
+}
+
+/// Loads all the gerber files in a directory (non-recursive) into a [`LayerSet`].
+///
+/// Each file is classified using its `.FileFunction` attribute if present, otherwise using its file name, see
+/// [`LayerType::from_filename`]. Files that are not recognised as gerber files are ignored, as are files that fail to
+/// parse, e.g. Excellon drill files and job files.
+///
+/// With the `rayon` feature the files are parsed and built in parallel.
+pub fn load_directory(path: impl AsRef<Path>) -> Result<LayerSet, ProjectError> {
+    #[cfg(feature = "rayon")]
+    use rayon::prelude::*;
+
+    let path = path.as_ref();
+    let io_error = |cause| ProjectError::Io {
+        path: path.to_path_buf(),
+        cause,
+    };
+
+    let mut file_paths = std::fs::read_dir(path)
+        .map_err(io_error)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    // read_dir order is platform dependent
+    file_paths.sort();
+
+    #[cfg(feature = "rayon")]
+    let file_paths_iter = file_paths.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let file_paths_iter = file_paths.iter();
+
+    // in the same order as the paths, so layers of the same type are always added in the same order
+    let layers = file_paths_iter
+        .filter_map(|file_path| {
+            let file_name = file_path
+                .file_name()
+                .and_then(|name| name.to_str())?;
+
+            let file = match File::open(file_path) {
+                Ok(file) => file,
+                Err(cause) => {
+                    return Some(Err(ProjectError::Io {
+                        path: file_path.clone(),
+                        cause,
+                    }))
+                }
+            };
+
+            load_layer(file_name, file).map(|(layer_type, layer)| Ok((layer_type, file_name, layer)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut layer_set = LayerSet::default();
+    for (layer_type, file_name, layer) in layers {
+        layer_set.add(layer_type, file_name, layer);
+    }
+
+    if layer_set.is_empty() {
+        return Err(ProjectError::NoGerberFiles(path.to_path_buf()));
+    }
+
+    Ok(layer_set)
+}
+
+/// Loads a single gerber file, regardless of its name.
+pub fn load_file(path: impl AsRef<Path>) -> Result<GerberLayer, ProjectError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|cause| ProjectError::Io {
+        path: path.to_path_buf(),
+        cause,
+    })?;
+
+    let doc = parse(BufReader::new(file)).map_err(|cause| ProjectError::Parse {
+        path: path.to_path_buf(),
+        cause: format!("{:?}", cause),
+    })?;
+
+    Ok(GerberLayer::new(doc.into_commands()))
+}
+
+/// Loads all the gerber files in a zip archive file into a [`LayerSet`], see [`load_zip`].
+#[cfg(feature = "zip")]
+pub fn load_zip_file(path: impl AsRef<Path>) -> Result<LayerSet, ProjectError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|cause| ProjectError::Io {
+        path: path.to_path_buf(),
+        cause,
+    })?;
+
+    load_zip(BufReader::new(file)).map_err(|error| match error {
+        ProjectError::NoGerberFilesInArchive => ProjectError::NoGerberFiles(path.to_path_buf()),
+        _ => error,
+    })
+}
+
+/// Loads all the gerber files in a zip archive into a [`LayerSet`], the files are parsed in memory.
+///
+/// Files in sub-directories are included, files are classified in the same way as [`load_directory`].  With the
+/// `rayon` feature the files are parsed and built in parallel, after they have been decompressed.
+#[cfg(feature = "zip")]
+pub fn load_zip(reader: impl Read + std::io::Seek) -> Result<LayerSet, ProjectError> {
+    #[cfg(feature = "rayon")]
+    use rayon::prelude::*;
+
+    let mut archive = zip::ZipArchive::new(reader)?;
+
+    // (file name, contents), the entries of an archive can only be read one at a time
+    let mut files = vec![];
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if !entry.is_file() {
+            continue;
+        }
+
+        let Some(entry_path) = entry.enclosed_name() else {
+            warn!("Ignoring zip entry with unsafe path. name: {}", entry.name());
+            continue;
+        };
+        // resource forks added by macOS
+        if entry_path.starts_with("__MACOSX") {
+            continue;
+        }
+        let Some(file_name) = entry_path
+            .file_name()
+            .and_then(|name| name.to_str())
+        else {
+            continue;
+        };
+        let file_name = file_name.to_string();
+
+        let mut contents = vec![];
+        entry
+            .read_to_end(&mut contents)
+            .map_err(|cause| ProjectError::Io {
+                path: entry_path,
+                cause,
+            })?;
+        files.push((file_name, contents));
+    }
+
+    #[cfg(feature = "rayon")]
+    let files_iter = files.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let files_iter = files.iter();
+
+    let layers = files_iter
+        .filter_map(|(file_name, contents)| {
+            load_layer(file_name, contents.as_slice()).map(|(layer_type, layer)| (layer_type, file_name, layer))
+        })
+        .collect::<Vec<_>>();
+
+    let mut layer_set = LayerSet::default();
+    for (layer_type, file_name, layer) in layers {
+        layer_set.add(layer_type, file_name.as_str(), layer);
+    }
+
+    if layer_set.is_empty() {
+        return Err(ProjectError::NoGerberFilesInArchive);
+    }
+
+    Ok(layer_set)
+}
+
+/// Classifies and parses a single file, returns `None` if the file isn't a gerber file or could not be parsed.
+pub(crate) fn load_layer(file_name: &str, reader: impl Read) -> Option<(LayerType, GerberLayer)> {
+    let Some(filename_layer_type) = LayerType::from_filename(file_name) else {
+        info!("Ignoring file with unrecognised name. file_name: {}", file_name);
+        return None;
+    };
+
+    let doc = parse(BufReader::new(reader))
+        .inspect_err(|error| warn!("Unable to parse file. file_name: {}, error: {:?}", file_name, error))
+        .ok()?;
+    let layer = GerberLayer::new(doc.into_commands());
+
+    let layer_type = LayerType::from_file_attributes(layer.file_attributes()).unwrap_or(filename_layer_type);
+
+    Some((layer_type, layer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_function_takes_precedence_over_file_name() {
+        // given
+        let source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%TF.FileFunction,Soldermask,Bot*%
+%ADD10C,0.100*%
+D10*
+X0Y0D03*
+M02*
+"#;
+
+        // when
+        let (layer_type, _layer) = load_layer("board-F_Mask.gbr", source.as_bytes()).unwrap();
+
+        // then
+        assert_eq!(layer_type, LayerType::BottomSolderMask);
+    }
+
+    #[test]
+    fn test_unrecognised_file_name() {
+        // expect
+        assert!(load_layer("README.md", "Not a gerber file".as_bytes()).is_none());
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_load_zip() {
+        use std::io::{Cursor, Write};
+
+        use zip::write::SimpleFileOptions;
+        use zip::{CompressionMethod, ZipWriter};
+
+        // given
+        let source = r#"
+%FSLAX26Y26*%
+%MOMM*%
+%ADD10C,0.100*%
+D10*
+X0Y0D03*
+M02*
+"#;
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for name in ["gerbers/board-Edge_Cuts.gbr", "gerbers/board-F_Cu.gbr", "README.txt"] {
+            writer
+                .start_file(name, options)
+                .unwrap();
+            writer
+                .write_all(source.as_bytes())
+                .unwrap();
+        }
+        let archive = writer.finish().unwrap();
+
+        // when
+        let layer_set = load_zip(Cursor::new(archive.into_inner())).unwrap();
+
+        // then
+        let layers = layer_set
+            .iter()
+            .map(|info| (info.layer_type, info.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(layers, vec![
+            (LayerType::TopCopper, "board-F_Cu.gbr"),
+            (LayerType::Outline, "board-Edge_Cuts.gbr"),
+        ]);
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_load_zip_without_gerber_files() {
+        use std::io::{Cursor, Write};
+
+        use zip::write::SimpleFileOptions;
+        use zip::ZipWriter;
+
+        // given
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("README.txt", SimpleFileOptions::default())
+            .unwrap();
+        writer
+            .write_all(b"Not a gerber file")
+            .unwrap();
+        let archive = writer.finish().unwrap();
+
+        // when
+        let result = load_zip(Cursor::new(archive.into_inner()));
+
+        // then
+        assert!(matches!(result, Err(ProjectError::NoGerberFilesInArchive)));
+    }
+}
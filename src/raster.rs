@@ -0,0 +1,816 @@
+use std::f64::consts::PI;
+
+use gerber_types::Unit;
+use nalgebra::{Matrix3, Point2, Vector2};
+use thiserror::Error;
+
+use crate::geometry::{split_contours, BoundingBox, GerberTransform};
+use crate::layer::{
+    CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGerberPrimitive,
+    RectangleGerberPrimitive,
+};
+use crate::{BlendMode, Exposure, LayerInfo, LayerSet, NegativeArea, MILLIMETERS_PER_INCH};
+
+/// Images with more pixels than this are not rendered, about 16k x 16k.
+const MAX_PIXELS: u64 = 1 << 28;
+
+/// The maximum distance, in pixels, between a circle and the polygon used to draw it.
+const CIRCLE_TOLERANCE: f64 = 0.1;
+
+/// Each row of pixels is sampled this many times, horizontal coverage is exact.
+const SUBSAMPLES: usize = 4;
+
+/// The size of the image, see [`RasterOptions`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    /// Pixels per inch, requires the units of the layer, see [`GerberLayer::units`].
+    Dpi(f64),
+    /// The image has exactly this size, the layer is scaled to fit, preserving the aspect ratio, and centered.
+    Fit { width: u32, height: u32 },
+}
+
+/// Options for [`GerberLayer::render_to_image`] and [`LayerSet::render_to_image`].
+#[derive(Debug, Clone)]
+pub struct RasterOptions {
+    pub resolution: Resolution,
+    /// Straight (not premultiplied) RGBA, ignored by [`LayerSet::render_to_image`].
+    pub color: [u8; 4],
+    /// Straight (not premultiplied) RGBA.
+    pub background: [u8; 4],
+    pub transform: GerberTransform,
+    /// Renders the layer as a negative, see [`NegativeArea`], ignored by [`LayerSet::render_to_image`].
+    pub negative: Option<NegativeArea>,
+    /// Partially covered pixels are blended, when disabled each pixel is either fully covered or not at all, e.g. for
+    /// manufacturing masks and pixel-accurate comparisons.
+    pub anti_aliasing: bool,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        Self {
+            resolution: Resolution::Dpi(300.0),
+            color: [255, 255, 255, 255],
+            background: [0, 0, 0, 0],
+            transform: GerberTransform::default(),
+            negative: None,
+            anti_aliasing: true,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RasterError {
+    #[error("Nothing to render, the layers are empty.")]
+    Empty,
+    #[error("Units of the layer are unknown, they are required for a DPI resolution.")]
+    UnknownUnits,
+    #[error("Image size is invalid. width: {width}, height: {height}")]
+    InvalidSize { width: u64, height: u64 },
+}
+
+/// An RGBA image, see [`GerberLayer::render_to_image`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterImage {
+    pub width: u32,
+    pub height: u32,
+    /// Straight (not premultiplied) RGBA, 4 bytes per pixel, row by row, starting with the top row.
+    pub pixels: Vec<u8>,
+}
+
+impl RasterImage {
+    /// Panics if the coordinates are outside the image.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        [
+            self.pixels[index],
+            self.pixels[index + 1],
+            self.pixels[index + 2],
+            self.pixels[index + 3],
+        ]
+    }
+}
+
+impl GerberLayer {
+    /// Rasterizes the layer to an image, without egui, e.g. for generating previews on a server.
+    ///
+    /// Clear primitives erase the dark primitives drawn before them, so the background shows through.  The image
+    /// covers the bounding box of the transformed layer.
+    pub fn render_to_image(&self, options: &RasterOptions) -> Result<RasterImage, RasterError> {
+        let bounds = self
+            .try_bounding_box()
+            .ok_or(RasterError::Empty)?
+            .transform_vertices(&options.transform);
+
+        let mut canvas = Canvas::new(&BoundingBox::from_points(&bounds), self.units(), options)?;
+        canvas.draw_layer(self, options.color, &options.transform, options.negative.as_ref());
+
+        Ok(canvas.into_image())
+    }
+
+    /// Rasterizes the part of the layer within the `region`, in gerber coordinates after the transform, at a fixed
+    /// scale instead of a [`Resolution`], e.g. so the egui renderer can composite layers with clear primitives.
+    ///
+    /// The image is rounded up to whole pixels, the top left corner of the image is the top left corner of the region.
+    /// Returns the image, and the area it covers, in gerber coordinates after the transform.
+    pub(crate) fn render_region_to_image(
+        &self,
+        mut options: RasterOptions,
+        region: &BoundingBox,
+        pixels_per_unit: f64,
+    ) -> Result<(RasterImage, BoundingBox), RasterError> {
+        let width = (region.width() * pixels_per_unit)
+            .ceil()
+            .max(1.0);
+        let height = (region.height() * pixels_per_unit)
+            .ceil()
+            .max(1.0);
+        let bounds = BoundingBox {
+            min: Point2::new(region.min.x, region.max.y - height / pixels_per_unit),
+            max: Point2::new(region.min.x + width / pixels_per_unit, region.max.y),
+        };
+
+        // the bounds have the size of the image, so they are covered exactly
+        options.resolution = Resolution::Fit {
+            width: width as u32,
+            height: height as u32,
+        };
+        let mut canvas = Canvas::new(&bounds, self.units(), &options)?;
+        canvas.draw_layer(self, options.color, &options.transform, options.negative.as_ref());
+
+        Ok((canvas.into_image(), bounds))
+    }
+}
+
+impl LayerSet {
+    /// Rasterizes the layers to an image, in drawing order, without egui, see [`GerberLayer::render_to_image`].
+    ///
+    /// `color` returns the straight RGBA color of each layer, layers without a color are skipped.  The opacity and the
+    /// transform of each layer are applied, the transform of the options is applied after the transform of the layer,
+    /// see [`LayerInfo::combined_transform`].  All the layers are assumed to be in the same units.  The image covers the
+    /// bounding boxes of all the drawn layers.
+    pub fn render_to_image(
+        &self,
+        options: &RasterOptions,
+        color: impl Fn(&LayerInfo) -> Option<[u8; 4]>,
+    ) -> Result<RasterImage, RasterError> {
+        let (layers, bounds) = self.raster_layers(&options.transform, color);
+        let first = layers
+            .first()
+            .ok_or(RasterError::Empty)?;
+
+        let mut canvas = Canvas::new(&bounds, first.info.layer.units(), options)?;
+        for RasterLayer {
+            info,
+            color,
+            transform,
+        } in layers
+        {
+            canvas.draw_layer(&info.layer, color, &transform, None);
+        }
+
+        Ok(canvas.into_image())
+    }
+
+    /// The non-empty layers with a color, and the bounds of all of them after their transform.
+    pub(crate) fn raster_layers(
+        &self,
+        transform: &GerberTransform,
+        color: impl Fn(&LayerInfo) -> Option<[u8; 4]>,
+    ) -> (Vec<RasterLayer<'_>>, BoundingBox) {
+        let layers = self
+            .iter()
+            .filter(|info| !info.layer.is_empty())
+            .filter_map(|info| {
+                color(info).map(|color| RasterLayer {
+                    info,
+                    color: info.with_opacity(color),
+                    transform: info.combined_transform(transform),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut bounds = BoundingBox::default();
+        for layer in &layers {
+            let vertices = layer
+                .info
+                .layer
+                .bounding_box()
+                .transform_vertices(&layer.transform);
+            bounds.expand(&BoundingBox::from_points(&vertices));
+        }
+
+        (layers, bounds)
+    }
+}
+
+/// A layer drawn by [`LayerSet::render_to_image`].
+pub(crate) struct RasterLayer<'a> {
+    pub(crate) info: &'a LayerInfo,
+    /// With the opacity of the layer applied.
+    pub(crate) color: [u8; 4],
+    /// Resolved, the transform of the layer followed by the transform of the options.
+    pub(crate) transform: GerberTransform,
+}
+
+/// The size of an image, and the mapping from gerber coordinates to its pixels, see [`Resolution`].
+pub(crate) struct ImageGeometry {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    /// Gerber coordinates, after the transform, to pixel coordinates.
+    pub(crate) to_pixels: Matrix3<f64>,
+    pub(crate) pixels_per_unit: f64,
+}
+
+impl ImageGeometry {
+    /// The image covers the `bounds`, which are in gerber coordinates, after the transform.
+    pub(crate) fn new(bounds: &BoundingBox, units: Option<Unit>, resolution: Resolution) -> Result<Self, RasterError> {
+        let (bounds_width, bounds_height) = (bounds.width(), bounds.height());
+
+        let (width, height, pixels_per_unit, margin) = match resolution {
+            Resolution::Dpi(dpi) => {
+                let pixels_per_unit = match units.ok_or(RasterError::UnknownUnits)? {
+                    Unit::Millimeters => dpi / MILLIMETERS_PER_INCH,
+                    Unit::Inches => dpi,
+                };
+                let width = (bounds_width * pixels_per_unit)
+                    .ceil()
+                    .max(1.0);
+                let height = (bounds_height * pixels_per_unit)
+                    .ceil()
+                    .max(1.0);
+                (width as u64, height as u64, pixels_per_unit, Vector2::new(0.0, 0.0))
+            }
+            Resolution::Fit {
+                width,
+                height,
+            } => {
+                let pixels_per_unit = [width as f64 / bounds_width, height as f64 / bounds_height]
+                    .into_iter()
+                    .filter(|scale| scale.is_finite())
+                    .reduce(f64::min)
+                    .unwrap_or(1.0);
+                let margin = Vector2::new(
+                    (width as f64 - bounds_width * pixels_per_unit) / 2.0,
+                    (height as f64 - bounds_height * pixels_per_unit) / 2.0,
+                );
+                (width as u64, height as u64, pixels_per_unit, margin)
+            }
+        };
+
+        if width == 0 || height == 0 || width.saturating_mul(height) > MAX_PIXELS || !pixels_per_unit.is_finite() {
+            return Err(RasterError::InvalidSize {
+                width,
+                height,
+            });
+        }
+
+        // y is flipped, the top row of the image is the maximum y
+        let to_pixels = Matrix3::new(
+            pixels_per_unit,
+            0.0,
+            margin.x - bounds.min.x * pixels_per_unit,
+            0.0,
+            -pixels_per_unit,
+            margin.y + bounds.max.y * pixels_per_unit,
+            0.0,
+            0.0,
+            1.0,
+        );
+
+        Ok(Self {
+            width: width as usize,
+            height: height as usize,
+            to_pixels,
+            pixels_per_unit,
+        })
+    }
+}
+
+/// The image being rendered, premultiplied RGBA.
+pub(crate) struct Canvas {
+    width: usize,
+    height: usize,
+    pub(crate) pixels: Vec<[f32; 4]>,
+    /// Gerber coordinates, after the transform, to pixel coordinates.
+    to_pixels: Matrix3<f64>,
+    pixels_per_unit: f64,
+    anti_aliasing: bool,
+}
+
+impl Canvas {
+    pub(crate) fn new(bounds: &BoundingBox, units: Option<Unit>, options: &RasterOptions) -> Result<Self, RasterError> {
+        let ImageGeometry {
+            width,
+            height,
+            to_pixels,
+            pixels_per_unit,
+        } = ImageGeometry::new(bounds, units, options.resolution)?;
+
+        let background = premultiply(options.background, 1.0);
+        Ok(Self {
+            width,
+            height,
+            pixels: vec![background; width * height],
+            to_pixels,
+            pixels_per_unit,
+            anti_aliasing: options.anti_aliasing,
+        })
+    }
+
+    /// An empty mask the size of the canvas, the `transform` must be resolved, see [`GerberTransform::resolve`].
+    pub(crate) fn mask(&self, transform: &GerberTransform) -> Mask {
+        Mask {
+            width: self.width,
+            height: self.height,
+            coverage: vec![0.0; self.width * self.height],
+            to_pixels: self.to_pixels * transform.to_matrix(),
+            pixels_per_unit: self.pixels_per_unit * transform.scale.abs(),
+            anti_aliasing: self.anti_aliasing,
+        }
+    }
+
+    /// Draws the layer into a coverage mask, then blends the mask in the color over the image.
+    fn draw_layer(
+        &mut self,
+        layer: &GerberLayer,
+        color: [u8; 4],
+        transform: &GerberTransform,
+        negative: Option<&NegativeArea>,
+    ) {
+        let mut mask = self.mask(&transform.resolve(layer.bounding_box()));
+        mask.draw_layer(layer, negative);
+
+        for (pixel, coverage) in self
+            .pixels
+            .iter_mut()
+            .zip(mask.into_coverage())
+        {
+            if coverage > 0.0 {
+                *pixel = BlendMode::Normal.blend(premultiply(color, coverage), *pixel);
+            }
+        }
+    }
+
+    pub(crate) fn into_image(self) -> RasterImage {
+        let pixels = self
+            .pixels
+            .into_iter()
+            .flat_map(|[red, green, blue, alpha]| {
+                let unpremultiply = |value: f32| match alpha > 0.0 {
+                    true => ((value / alpha).clamp(0.0, 1.0) * 255.0).round() as u8,
+                    false => 0,
+                };
+                [
+                    unpremultiply(red),
+                    unpremultiply(green),
+                    unpremultiply(blue),
+                    (alpha.clamp(0.0, 1.0) * 255.0).round() as u8,
+                ]
+            })
+            .collect();
+
+        RasterImage {
+            width: self.width as u32,
+            height: self.height as u32,
+            pixels,
+        }
+    }
+}
+
+/// Converts a straight RGBA color to premultiplied, with the alpha multiplied by `coverage`.
+pub(crate) fn premultiply(color: [u8; 4], coverage: f32) -> [f32; 4] {
+    let alpha = color[3] as f32 / 255.0 * coverage;
+    [
+        color[0] as f32 / 255.0 * alpha,
+        color[1] as f32 / 255.0 * alpha,
+        color[2] as f32 / 255.0 * alpha,
+        alpha,
+    ]
+}
+
+/// The coverage of a single layer, from 0.0 to 1.0 for each pixel.
+pub(crate) struct Mask {
+    width: usize,
+    height: usize,
+    coverage: Vec<f32>,
+    /// Gerber coordinates, before the transform, to pixel coordinates.
+    to_pixels: Matrix3<f64>,
+    pixels_per_unit: f64,
+    anti_aliasing: bool,
+}
+
+impl Mask {
+    /// The coverage of each pixel, 0 or 1 when anti-aliasing is disabled, see [`RasterOptions::anti_aliasing`].
+    pub(crate) fn into_coverage(self) -> Vec<f32> {
+        match self.anti_aliasing {
+            true => self.coverage,
+            false => self
+                .coverage
+                .into_iter()
+                .map(|coverage| if coverage >= 0.5 { 1.0 } else { 0.0 })
+                .collect(),
+        }
+    }
+
+    /// Draws the primitives of the layer, as a negative if `negative` is set, see [`NegativeArea`].
+    pub(crate) fn draw_layer(&mut self, layer: &GerberLayer, negative: Option<&NegativeArea>) {
+        let zero = Vector2::new(0.0, 0.0);
+        if let Some(fill) = negative.and_then(|area| layer.negative_fill(area)) {
+            self.draw_primitive(&fill, zero, Exposure::Add);
+        }
+        let exposure = |primitive: &GerberPrimitive| match negative {
+            Some(_) => primitive.exposure().inverted(),
+            None => primitive.exposure(),
+        };
+
+        let primitives = layer.gerber_primitives();
+        for (index, offset) in layer.primitives_in_draw_order() {
+            let primitive = &primitives[index];
+            self.draw_primitive(primitive, offset, exposure(primitive));
+        }
+    }
+
+    /// The exposure of the primitive is ignored, `exposure` is used instead.
+    pub(crate) fn draw_primitive(&mut self, primitive: &GerberPrimitive, offset: Vector2<f64>, exposure: Exposure) {
+        match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => {
+                let circle = self.circle(*center + offset, diameter / 2.0);
+                self.fill(&[circle], exposure);
+            }
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let origin = *origin + offset;
+                let corners = [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)]
+                    .map(|(x, y)| origin + Vector2::new(x, y))
+                    .to_vec();
+                self.fill(&[corners], exposure);
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => {
+                let stadium = self.stadium(*start + offset, *end + offset, width / 2.0);
+                self.fill(&[stadium], exposure);
+            }
+            GerberPrimitive::Arc(arc) => {
+                let center = arc.center + offset;
+                let points = arc
+                    .generate_points()
+                    .into_iter()
+                    .map(|point| center + point.coords)
+                    .collect::<Vec<_>>();
+                for segment in points.windows(2) {
+                    let stadium = self.stadium(segment[0], segment[1], arc.width / 2.0);
+                    self.fill(&[stadium], exposure);
+                }
+            }
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let center = *center + offset;
+                let contours = split_contours(&geometry.relative_vertices)
+                    .into_iter()
+                    .map(|contour| {
+                        contour
+                            .into_iter()
+                            .map(|vertex| center + vertex.coords)
+                            .collect()
+                    })
+                    .collect::<Vec<_>>();
+                self.fill(&contours, exposure);
+            }
+        }
+    }
+
+    /// The number of segments needed to keep the polygon within [`CIRCLE_TOLERANCE`] of the circle.
+    fn circle_segments(&self, radius: f64) -> usize {
+        let radius = radius * self.pixels_per_unit;
+        if radius <= CIRCLE_TOLERANCE {
+            return 8;
+        }
+        ((PI / (1.0 - CIRCLE_TOLERANCE / radius).acos()).ceil() as usize).clamp(8, 1024)
+    }
+
+    fn circle(&self, center: Point2<f64>, radius: f64) -> Vec<Point2<f64>> {
+        let segments = self.circle_segments(radius);
+        (0..segments)
+            .map(|index| {
+                let angle = 2.0 * PI * index as f64 / segments as f64;
+                center + Vector2::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect()
+    }
+
+    /// A line with round caps, counter-clockwise.
+    fn stadium(&self, start: Point2<f64>, end: Point2<f64>, radius: f64) -> Vec<Point2<f64>> {
+        let direction = end - start;
+        if direction.norm() == 0.0 {
+            return self.circle(start, radius);
+        }
+
+        let angle = direction.y.atan2(direction.x);
+        let steps = self.circle_segments(radius).div_ceil(2);
+        let cap = |center: Point2<f64>, start_angle: f64| {
+            (0..=steps).map(move |step| {
+                let angle = start_angle + PI * step as f64 / steps as f64;
+                center + Vector2::new(angle.cos(), angle.sin()) * radius
+            })
+        };
+
+        cap(end, angle - PI / 2.0)
+            .chain(cap(start, angle + PI / 2.0))
+            .collect()
+    }
+
+    /// Fills the contours using the even-odd rule, contours are in gerber coordinates.
+    fn fill(&mut self, contours: &[Vec<Point2<f64>>], exposure: Exposure) {
+        let contours = contours
+            .iter()
+            .map(|contour| {
+                contour
+                    .iter()
+                    .map(|point| self.to_pixels.transform_point(point))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let bounds = BoundingBox::from_points(&contours.concat());
+        if bounds.is_empty() || bounds.max.x < 0.0 || bounds.max.y < 0.0 {
+            return;
+        }
+        let (min_x, max_x) = (bounds.min.x.max(0.0) as usize, bounds.max.x as usize);
+        let (min_y, max_y) = (bounds.min.y.max(0.0) as usize, bounds.max.y as usize);
+        if min_x >= self.width || min_y >= self.height {
+            return;
+        }
+        let (max_x, max_y) = (max_x.min(self.width - 1), max_y.min(self.height - 1));
+
+        let mut row = vec![0.0_f32; max_x - min_x + 1];
+        let mut crossings = vec![];
+        for y in min_y..=max_y {
+            row.fill(0.0);
+            for subsample in 0..SUBSAMPLES {
+                let sample_y = y as f64 + (subsample as f64 + 0.5) / SUBSAMPLES as f64;
+
+                crossings.clear();
+                for contour in &contours {
+                    for (index, start) in contour.iter().enumerate() {
+                        let end = contour[(index + 1) % contour.len()];
+                        if (start.y > sample_y) != (end.y > sample_y) {
+                            crossings.push(start.x + (sample_y - start.y) * (end.x - start.x) / (end.y - start.y));
+                        }
+                    }
+                }
+                crossings.sort_by(f64::total_cmp);
+
+                for span in crossings.chunks_exact(2) {
+                    add_span(&mut row, min_x, span[0], span[1], 1.0 / SUBSAMPLES as f32);
+                }
+            }
+
+            let coverage = &mut self.coverage[y * self.width + min_x..=y * self.width + max_x];
+            for (pixel, sample) in coverage.iter_mut().zip(&row) {
+                let sample = sample.min(1.0);
+                *pixel = match exposure {
+                    Exposure::Add => *pixel + sample * (1.0 - *pixel),
+                    Exposure::CutOut => *pixel * (1.0 - sample),
+                };
+            }
+        }
+    }
+}
+
+/// Adds the horizontal coverage of the span from `left` to `right` to the row, which starts at pixel `offset`.
+fn add_span(row: &mut [f32], offset: usize, left: f64, right: f64, weight: f32) {
+    let left = (left - offset as f64).max(0.0);
+    let right = (right - offset as f64).min(row.len() as f64);
+    if right <= left {
+        return;
+    }
+
+    let (first, last) = (left as usize, right as usize);
+    if first == last {
+        row[first] += (right - left) as f32 * weight;
+        return;
+    }
+
+    row[first] += (first as f64 + 1.0 - left) as f32 * weight;
+    for pixel in &mut row[first + 1..last] {
+        *pixel += weight;
+    }
+    if last < row.len() {
+        row[last] += (right - last as f64) as f32 * weight;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Command, ExtendedCode, Polarity, Rectangular};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::testing::{aperture, flash, flash_layer, header};
+    use crate::LayerType;
+
+    #[test]
+    fn test_render_to_image() {
+        // given
+        // a 10x10mm square with a clear 4mm circle in the center
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Rectangle(Rectangular::new(10.0, 10.0))));
+        commands.push(flash(5.0, 5.0));
+        commands.push(Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)));
+        commands.extend(aperture(11, Aperture::Circle(Circle::new(4.0))));
+        commands.push(flash(5.0, 5.0));
+        let layer = GerberLayer::new(commands);
+        let options = RasterOptions {
+            // 1 pixel per mm
+            resolution: Resolution::Dpi(MILLIMETERS_PER_INCH),
+            color: [255, 0, 0, 255],
+            ..RasterOptions::default()
+        };
+
+        // when
+        let image = layer.render_to_image(&options).unwrap();
+
+        // then
+        assert_eq!((image.width, image.height), (10, 10));
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(9, 9), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(5, 5), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_render_region_to_image() {
+        // given
+        // a 10x10mm square with a clear 4mm circle in the center
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Rectangle(Rectangular::new(10.0, 10.0))));
+        commands.push(flash(5.0, 5.0));
+        commands.push(Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)));
+        commands.extend(aperture(11, Aperture::Circle(Circle::new(4.0))));
+        commands.push(flash(5.0, 5.0));
+        let layer = GerberLayer::new(commands);
+        let options = RasterOptions {
+            color: [255, 0, 0, 255],
+            ..RasterOptions::default()
+        };
+        let region = BoundingBox {
+            min: Point2::new(2.0, 2.5),
+            max: Point2::new(7.5, 8.0),
+        };
+
+        // when
+        // 1 pixel per mm
+        let (image, bounds) = layer
+            .render_region_to_image(options, &region, 1.0)
+            .unwrap();
+
+        // then
+        // rounded up to whole pixels, keeping the top left corner
+        assert_eq!((image.width, image.height), (6, 6));
+        assert_eq!(bounds, BoundingBox {
+            min: Point2::new(2.0, 2.0),
+            max: Point2::new(8.0, 8.0),
+        });
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        // the clear circle erases the square, so the layers below show through
+        assert_eq!(image.pixel(3, 3), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_negative_layer_shows_the_layer_below_through_its_openings() {
+        // given
+        // a soldermask layer with two 2x2mm openings, at opposite corners of its 10x10mm bounding box
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Rectangle(Rectangular::new(2.0, 2.0))));
+        commands.push(flash(1.0, 1.0));
+        commands.push(flash(9.0, 9.0));
+        let mask = GerberLayer::new(commands);
+        // a copper pad below the first opening
+        let copper = flash_layer(
+            Unit::Millimeters,
+            Aperture::Rectangle(Rectangular::new(2.0, 2.0)),
+            1.0,
+            1.0,
+        );
+        let options = RasterOptions {
+            // 1 pixel per mm
+            resolution: Resolution::Dpi(MILLIMETERS_PER_INCH),
+            ..RasterOptions::default()
+        };
+        let mut canvas = Canvas::new(mask.bounding_box(), mask.units(), &options).unwrap();
+
+        // when
+        canvas.draw_layer(&copper, [255, 0, 0, 255], &GerberTransform::default(), None);
+        canvas.draw_layer(
+            &mask,
+            [0, 255, 0, 255],
+            &GerberTransform::default(),
+            Some(&NegativeArea::BoundingBox),
+        );
+        let image = canvas.into_image();
+
+        // then
+        assert_eq!(image.pixel(5, 5), [0, 255, 0, 255]);
+        // the copper shows through the opening above it
+        assert_eq!(image.pixel(0, 9), [255, 0, 0, 255]);
+        // and the background through the other opening
+        assert_eq!(image.pixel(9, 0), [0, 0, 0, 0]);
+    }
+
+    #[rstest]
+    #[case(true, true)]
+    #[case(false, false)]
+    fn test_render_anti_aliasing(#[case] anti_aliasing: bool, #[case] expect_partial_coverage: bool) {
+        // given
+        // a 5mm circle, its edge partially covers pixels
+        let layer = flash_layer(Unit::Millimeters, Aperture::Circle(Circle::new(5.0)), 5.0, 5.0);
+        let options = RasterOptions {
+            // 4 pixels per mm
+            resolution: Resolution::Dpi(MILLIMETERS_PER_INCH * 4.0),
+            anti_aliasing,
+            ..RasterOptions::default()
+        };
+
+        // when
+        let image = layer.render_to_image(&options).unwrap();
+
+        // then
+        let partial = image
+            .pixels
+            .chunks_exact(4)
+            .any(|pixel| pixel[3] != 0 && pixel[3] != 255);
+        assert_eq!(partial, expect_partial_coverage);
+    }
+
+    #[test]
+    fn test_render_layer_set_with_layer_transform_and_opacity() {
+        // given
+        // two 2x2mm squares, the second one is moved next to the first one and is half transparent
+        let square = || {
+            flash_layer(
+                Unit::Millimeters,
+                Aperture::Rectangle(Rectangular::new(2.0, 2.0)),
+                1.0,
+                1.0,
+            )
+        };
+        let mut layer_set = LayerSet::default();
+        layer_set.add(LayerType::BottomCopper, "bottom", square());
+        layer_set.add(LayerType::TopCopper, "top", square());
+        let top = layer_set
+            .get_mut(LayerType::TopCopper)
+            .unwrap();
+        top.transform.offset = Vector2::new(2.0, 0.0);
+        top.opacity = 0.5;
+        let options = RasterOptions {
+            // 1 pixel per mm
+            resolution: Resolution::Dpi(MILLIMETERS_PER_INCH),
+            ..RasterOptions::default()
+        };
+
+        // when
+        let image = layer_set
+            .render_to_image(&options, |_| Some([255, 0, 0, 255]))
+            .unwrap();
+
+        // then
+        assert_eq!((image.width, image.height), (4, 2));
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(3, 0), [255, 0, 0, 128]);
+    }
+
+    #[test]
+    fn test_render_empty_layer() {
+        // expect
+        assert_eq!(
+            GerberLayer::new(vec![]).render_to_image(&RasterOptions::default()),
+            Err(RasterError::Empty)
+        );
+    }
+
+    #[test]
+    fn test_add_span() {
+        // given
+        let mut row = vec![0.0; 4];
+
+        // when
+        add_span(&mut row, 10, 10.5, 12.25, 1.0);
+
+        // then
+        assert_eq!(row, vec![0.5, 1.0, 0.25, 0.0]);
+    }
+}
@@ -1,27 +1,70 @@
-use std::sync::Arc;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use egui::epaint::emath::Align2;
 use egui::epaint::{
-    Color32, ColorMode, FontId, Mesh, PathShape, PathStroke, Pos2, Rect, Shape, Stroke, StrokeKind, Vec2, Vertex,
+    Color32, ColorImage, ColorMode, FontId, Mesh, PathShape, PathStroke, Pos2, Rect, RectShape, Shape, Stroke,
+    StrokeKind, TessellationOptions, Tessellator, TextureHandle, TextureOptions, Vec2, Vertex,
 };
 use egui::Painter;
-use nalgebra::Matrix3;
+use log::{debug, warn};
+use nalgebra::{Matrix3, Point2, Vector2};
 
-use crate::geometry::{GerberTransform, Matrix3Pos2Ext, Matrix3TransformExt};
+use crate::geometry::{split_contours, BoundingBox, GerberTransform, Matrix3Pos2Ext, Matrix3TransformExt};
 use crate::layer::GerberPrimitive;
-use crate::{color, GerberLayer, ViewState};
+use crate::{color, Exposure, GerberLayer, NegativeArea, OverlayColors, PrimitiveSummary, RasterOptions, ViewState};
 use crate::{
     ArcGerberPrimitive, CircleGerberPrimitive, LineGerberPrimitive, PolygonGerberPrimitive, RectangleGerberPrimitive,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RenderConfiguration {
     /// Gives each shape a unique color.
     pub use_unique_shape_colors: bool,
     /// Draws the shape number in the center of the shape.
     pub use_shape_numbering: bool,
+    pub shape_numbering: NumberingStyle,
     /// Draws the vertex number at the start of each line.
     pub use_vertex_numbering: bool,
+    pub vertex_numbering: NumberingStyle,
+    /// Draws the primitives on this net using the `net_highlight_color`, requires `.N` object attributes.
+    pub highlighted_net: Option<String>,
+    pub net_highlight_color: Color32,
+    /// Gives each island of connected copper a unique color, one entry per primitive, see `Islands::primitive_islands`.
+    pub primitive_islands: Option<Vec<Option<usize>>>,
+    /// Renders the layer as a negative, e.g. for soldermask layers, the area is filled and the primitives are drawn
+    /// with their exposure inverted, so openings are drawn like clear primitives, and the layers below show through
+    /// them, see [`GerberRenderer`].
+    pub negative: Option<NegativeArea>,
+    /// From 0.0 to 1.0, applied in addition to the alpha of the base color, e.g. for a layer transparency slider.
+    ///
+    /// Where primitives overlap they are blended with each other, so overlapping areas are more opaque, except in
+    /// composited layers, see [`GerberRenderer`].
+    pub opacity: f32,
+    /// Strokes the boundaries of the primitives instead of filling them, e.g. to inspect overlapping geometry or the
+    /// contours of regions.  Clear primitives are outlined in the base color at half the alpha.
+    pub wireframe: bool,
+    /// The minimum width of lines and arcs, in screen points, e.g. `1.0` so thin traces remain visible when zoomed
+    /// out, `0.0` to always draw them at their actual width.
+    pub min_stroke_width: f32,
+    pub draw_order: DrawOrder,
+    /// Outlines the bounding box of each primitive using the `bounding_box_color`, e.g. to debug bounding boxes and
+    /// culling.
+    pub show_bounding_boxes: bool,
+    pub bounding_box_color: Color32,
+    /// The maximum number of primitives tessellated per frame, `None` for no limit, composited layers are always
+    /// rasterized at once, see [`GerberRenderer`].
+    ///
+    /// When a mesh is rebuilt, larger layers are built over several frames, so the UI stays responsive during the first
+    /// paint of huge layers.  The primitives built so far are painted over a placeholder, the bounding box of the layer
+    /// in the base color at a low alpha, see [`GerberRenderer::is_building`].  Numbered layers are always built at once.
+    pub primitive_budget: Option<usize>,
+    /// The feathering of the edges of filled shapes and strokes, instead of whatever the egui context uses.
+    pub anti_aliasing: AntiAliasing,
 }
 
 impl Default for RenderConfiguration {
@@ -29,13 +72,498 @@ impl Default for RenderConfiguration {
         Self {
             use_unique_shape_colors: false,
             use_shape_numbering: false,
+            shape_numbering: NumberingStyle {
+                color: Color32::GREEN,
+                font_size: 16.0,
+                min_font_size: 10.0,
+                max_font_size: 32.0,
+                min_zoom: 0.0,
+            },
             use_vertex_numbering: false,
+            vertex_numbering: NumberingStyle {
+                color: Color32::RED,
+                font_size: 8.0,
+                min_font_size: 6.0,
+                max_font_size: 16.0,
+                min_zoom: 0.0,
+            },
+            highlighted_net: None,
+            net_highlight_color: Color32::from_rgb(255, 0, 255),
+            primitive_islands: None,
+            negative: None,
+            opacity: 1.0,
+            wireframe: false,
+            min_stroke_width: 0.0,
+            draw_order: DrawOrder::File,
+            show_bounding_boxes: false,
+            bounding_box_color: Color32::from_rgba_unmultiplied(0, 255, 255, 64),
+            primitive_budget: None,
+            anti_aliasing: AntiAliasing::Inherit,
+        }
+    }
+}
+
+impl RenderConfiguration {
+    /// Uses the colors for the shape and vertex numbers and the bounding boxes, e.g. to follow the dark or light mode of
+    /// egui, see [`OverlayColors::from_visuals`].
+    pub fn set_overlay_colors(&mut self, colors: &OverlayColors) {
+        self.shape_numbering.color = colors.shape_numbers;
+        self.vertex_numbering.color = colors.vertex_numbers;
+        self.bounding_box_color = colors.bounding_box.gamma_multiply(0.25);
+    }
+
+    /// Whether the layer is rasterized into a texture, which is composited over the layers below, instead of being
+    /// tessellated, see [`GerberRenderer`].
+    ///
+    /// Only layers with clear primitives, and negative layers, that are filled in the base color in file order are
+    /// composited, the other options color, outline or reorder individual primitives.
+    fn is_composited(&self, layer: &GerberLayer) -> bool {
+        let is_filled = !self.use_unique_shape_colors
+            && self.highlighted_net.is_none()
+            && self.primitive_islands.is_none()
+            && !self.wireframe
+            && !self.show_bounding_boxes
+            && self.draw_order == DrawOrder::File;
+
+        is_filled
+            && (self.negative.is_some()
+                || layer
+                    .gerber_primitives()
+                    .iter()
+                    .any(|primitive| primitive.exposure() == Exposure::CutOut))
+    }
+
+    /// A hash of the fields that the cached meshes depend on, for the cache key of [`GerberRenderer::paint_layer`].
+    ///
+    /// The islands and the negative outline can be large, so they are hashed rather than cloned into the key.  The
+    /// opacity is applied by egui when painting, the primitive budget only spreads a build over several frames, and
+    /// numbered layers are never cached, so those fields are left out.
+    fn mesh_hash(&self) -> u64 {
+        let Self {
+            use_unique_shape_colors,
+            use_shape_numbering: _,
+            shape_numbering: _,
+            use_vertex_numbering: _,
+            vertex_numbering: _,
+            highlighted_net,
+            net_highlight_color,
+            primitive_islands,
+            negative,
+            opacity: _,
+            wireframe,
+            min_stroke_width,
+            draw_order,
+            show_bounding_boxes,
+            bounding_box_color,
+            primitive_budget: _,
+            anti_aliasing,
+        } = self;
+
+        let mut hasher = DefaultHasher::new();
+        use_unique_shape_colors.hash(&mut hasher);
+        highlighted_net.hash(&mut hasher);
+        net_highlight_color.hash(&mut hasher);
+        primitive_islands.hash(&mut hasher);
+        negative_hash(negative).hash(&mut hasher);
+        wireframe.hash(&mut hasher);
+        min_stroke_width
+            .to_bits()
+            .hash(&mut hasher);
+        draw_order.hash(&mut hasher);
+        show_bounding_boxes.hash(&mut hasher);
+        bounding_box_color.hash(&mut hasher);
+        match anti_aliasing {
+            AntiAliasing::Inherit => 0_u8.hash(&mut hasher),
+            AntiAliasing::Disabled => 1_u8.hash(&mut hasher),
+            AntiAliasing::Feathering(size) => (2_u8, size.to_bits()).hash(&mut hasher),
+        }
+        hasher.finish()
+    }
+}
+
+/// A hash of the negative area, see [`RenderConfiguration::negative`], to find out if the tessellated fill of a
+/// negative layer can be reused.
+fn negative_hash(negative: &Option<NegativeArea>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match negative {
+        None => 0_u8.hash(&mut hasher),
+        Some(NegativeArea::BoundingBox) => 1_u8.hash(&mut hasher),
+        Some(NegativeArea::Outline(outline)) => {
+            2_u8.hash(&mut hasher);
+            for contour in &outline.contours {
+                contour.len().hash(&mut hasher);
+                for point in contour {
+                    (point.x.to_bits(), point.y.to_bits()).hash(&mut hasher);
+                }
+            }
+        }
+    }
+    hasher.finish()
+}
+
+/// How the shape or vertex numbers are drawn, see [`RenderConfiguration::shape_numbering`].
+///
+/// The text grows and shrinks with the zoom, within limits, so it remains readable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberingStyle {
+    pub color: Color32,
+    /// The font size at 100% zoom, i.e. when the scale of the view is its base scale, in screen points.
+    pub font_size: f32,
+    /// The font size is never smaller than this, in screen points.
+    pub min_font_size: f32,
+    /// The font size is never larger than this, in screen points.
+    pub max_font_size: f32,
+    /// The numbers are hidden when zoomed out further than this, e.g. `0.5` for 50%, so the numbers of dense layers
+    /// don't cover the layer.  `0.0` to always show them.
+    pub min_zoom: f32,
+}
+
+impl NumberingStyle {
+    /// The font size at the zoom of the view, `None` if the numbers are hidden.
+    fn font_size(&self, view: &ViewState) -> Option<f32> {
+        let zoom = match view.base_scale > 0.0 {
+            true => view.scale / view.base_scale,
+            false => 1.0,
+        };
+        (zoom >= self.min_zoom).then(|| {
+            (self.font_size * zoom).clamp(
+                self.min_font_size,
+                self.max_font_size
+                    .max(self.min_font_size),
+            )
+        })
+    }
+}
+
+/// The order in which the primitives of a layer are painted, later primitives are painted on top.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DrawOrder {
+    /// The order in which the primitives were created, which is required for clear primitives to only cover the dark
+    /// primitives before them.
+    #[default]
+    File,
+    /// Dark primitives first, then clear primitives, so clear primitives are never covered by dark primitives.
+    Exposure,
+    /// By the area of their bounding boxes, largest first, so small pads stay visible on top of pours.
+    AreaDescending,
+}
+
+impl DrawOrder {
+    /// Sorts a list of primitive indices and step-and-repeat offsets, which are in file order.  The sort is stable, so
+    /// primitives that compare equal stay in file order.
+    fn sort(&self, primitives: &[GerberPrimitive], draw_list: &mut [(usize, Option<Vector2<f64>>)]) {
+        match self {
+            DrawOrder::File => {}
+            DrawOrder::Exposure => {
+                draw_list.sort_by_key(|(index, _)| primitives[*index].exposure() == Exposure::CutOut)
+            }
+            DrawOrder::AreaDescending => draw_list.sort_by_cached_key(|(index, _)| {
+                let bbox = primitives[*index].bounding_box();
+                // the area is not negative, so the bits are in the same order as the values
+                Reverse((bbox.width() * bbox.height()).to_bits())
+            }),
+        }
+    }
+}
+
+/// How the edges of the tessellated shapes are anti-aliased, see [`RenderConfiguration::anti_aliasing`].
+///
+/// egui anti-aliases by feathering, a thin band of translucent triangles along the edges of each shape.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AntiAliasing {
+    /// Uses the tessellation options of the egui context, see [`egui::Context::tessellation_options`].
+    #[default]
+    Inherit,
+    /// Hard edges, e.g. for screenshots of masks where each pixel must be either covered or not.
+    Disabled,
+    /// Feathering with the given width, in physical pixels, egui uses `1.0` by default.
+    Feathering(f32),
+}
+
+impl AntiAliasing {
+    /// Whether the edges of composited layers are anti-aliased, see [`RasterOptions::anti_aliasing`].
+    fn is_enabled(&self, ctx: &egui::Context) -> bool {
+        match *self {
+            AntiAliasing::Inherit => ctx.tessellation_options(|options| options.feathering),
+            AntiAliasing::Disabled => false,
+            AntiAliasing::Feathering(size) => size > 0.0,
         }
     }
+
+    fn apply(&self, options: &mut TessellationOptions) {
+        match *self {
+            AntiAliasing::Inherit => {}
+            AntiAliasing::Disabled => options.feathering = false,
+            AntiAliasing::Feathering(size) => {
+                options.feathering = size > 0.0;
+                options.feathering_size_in_pixels = size;
+            }
+        }
+    }
+}
+
+/// An x-ray mode for a stack of layers, the focused layer is painted at full intensity, the other layers are dimmed,
+/// making it easier to trace a single layer, e.g. the inner copper of a dense board.
+///
+/// Layers are identified by their index, e.g. in the [`crate::LayerSet`].  Dimming uses
+/// [`RenderConfiguration::opacity`], so the meshes of the layers stay cached when the focus changes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerFocus {
+    /// The index of the focused layer.
+    pub layer: usize,
+    /// The opacity of the other layers, from `0.0` to `1.0`, `0.2` by default.
+    pub dimmed_opacity: f32,
+    /// Paints the focused layer after the other layers, so it is not covered by them, enabled by default.
+    pub on_top: bool,
 }
 
+impl LayerFocus {
+    pub fn new(layer: usize) -> Self {
+        Self {
+            layer,
+            dimmed_opacity: 0.2,
+            on_top: true,
+        }
+    }
+
+    /// The opacity of the layer, applied in addition to the opacity of its configuration.
+    pub fn opacity(&self, layer: usize) -> f32 {
+        match layer == self.layer {
+            true => 1.0,
+            false => self.dimmed_opacity.clamp(0.0, 1.0),
+        }
+    }
+
+    /// The configuration of the layer, with its opacity multiplied by [`Self::opacity`].
+    pub fn configuration(&self, layer: usize, configuration: &RenderConfiguration) -> RenderConfiguration {
+        RenderConfiguration {
+            opacity: configuration.opacity * self.opacity(layer),
+            ..configuration.clone()
+        }
+    }
+
+    /// The layer indices in painting order, given the indices in drawing order, with the focused layer last if
+    /// [`Self::on_top`] is set.
+    pub fn painting_order(&self, order: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let mut order: Vec<usize> = order.into_iter().collect();
+        if self.on_top {
+            if let Some(position) = order
+                .iter()
+                .position(|layer| *layer == self.layer)
+            {
+                let layer = order.remove(position);
+                order.push(layer);
+            }
+        }
+        order
+    }
+}
+
+/// Paints layers using egui shapes.
+///
+/// Each layer is tessellated into a single mesh which is cached and reused across frames, keep the renderer between
+/// frames, e.g. in your app state, to benefit from the cache.  The mesh is rebuilt when the layer, color,
+/// configuration, transform or zoom changes.  Meshes of layers that were not painted in the previous pass are dropped.
+///
+/// Primitives outside the viewport are culled using the spatial index of the layer, see [`GerberLayer::query_region`].
+/// The culled region has a margin of a viewport in each direction, so small pans do not cause a rebuild.
+///
+/// egui blends each shape over the shapes painted before it, so a tessellated clear primitive can't erase the dark
+/// primitives below it.  Layers with clear primitives, e.g. negative planes and macro cut-outs, and negative layers,
+/// see [`RenderConfiguration::negative`], are therefore rasterized offscreen into a texture instead, where the clear
+/// primitives erase the dark primitives drawn before them, and the texture is composited over the layers below, see
+/// [`GerberLayer::render_to_image`].  The texture covers the visible part of the layer, at the resolution of the
+/// screen, with a margin of half a viewport in each direction, and is cached in the same way as the meshes.  Layers
+/// that are colored, outlined or reordered per primitive, e.g. with [`RenderConfiguration::use_unique_shape_colors`],
+/// [`RenderConfiguration::wireframe`] or a [`DrawOrder`] other than the file order, are tessellated, and their clear
+/// primitives are painted in black.  The [`RenderConfiguration::min_stroke_width`] is not applied to composited layers.
+///
+/// Use [`GerberRenderer::cache_stats`] to find out how often, and why, meshes are rebuilt, and
+/// [`GerberRenderer::invalidate`] to force a rebuild or to release the mesh of a layer immediately.
 #[derive(Default)]
-pub struct GerberRenderer {}
+pub struct GerberRenderer {
+    cache: Mutex<MeshCache>,
+}
+
+/// Why the mesh of a layer was rebuilt, see [`CacheStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebuildReason {
+    /// The layer was not cached, e.g. the first time it was painted, after it was invalidated, or after it was not
+    /// painted for a pass.
+    NotCached,
+    Color,
+    Configuration,
+    Transform,
+    /// The scale of the view, or the pixels per point, changed.
+    Zoom,
+    /// The view was panned outside of the region that was not culled.
+    Culling,
+}
+
+/// Counters for the mesh cache of a [`GerberRenderer`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of layers with a cached mesh.
+    pub layers: usize,
+    /// The number of times a cached mesh was painted.
+    pub hits: u64,
+    /// The number of times a mesh was built.
+    pub rebuilds: u64,
+    /// The id of the layer, and the reason, of the most recent rebuild.
+    pub last_rebuild: Option<(u64, RebuildReason)>,
+}
+
+/// What a [`GerberRenderer`] painted in a pass, e.g. for a performance HUD, see [`GerberRenderer::render_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// The egui pass, see [`egui::Context::cumulative_pass_nr`].
+    pub pass: u64,
+    /// The number of times a layer was painted.
+    pub layers: usize,
+    /// The primitives of the painted layers, including the copies of step-and-repeat blocks.
+    pub primitives: usize,
+    /// The primitives that were skipped because they were outside the viewport, see [`GerberRenderer`].
+    pub culled: usize,
+    /// The primitives in the painted meshes, for cached meshes this is the number of primitives when they were built.
+    pub drawn: usize,
+    /// The vertices of the painted meshes.
+    pub vertices: usize,
+    /// The triangles of the painted meshes.
+    pub triangles: usize,
+    /// The time spent building meshes, included in the `paint_time`.
+    pub build_time: Duration,
+    /// The time spent in [`GerberRenderer::paint_layer`].
+    pub paint_time: Duration,
+}
+
+#[derive(Default)]
+struct MeshCache {
+    layers: HashMap<u64, CachedLayer>,
+    stats: CacheStats,
+    render_stats: RenderStats,
+}
+
+impl MeshCache {
+    /// The stats of the pass, they are reset when the first layer of a pass is painted.
+    fn render_stats(&mut self, pass: u64) -> &mut RenderStats {
+        if self.render_stats.pass != pass {
+            self.render_stats = RenderStats {
+                pass,
+                ..RenderStats::default()
+            };
+        }
+        &mut self.render_stats
+    }
+
+    /// Returns the reason the cached mesh of the layer can't be used, if any.
+    fn check(&self, layer_id: u64, key: &CacheKey, visible_region: Option<&BoundingBox>) -> Option<RebuildReason> {
+        let Some(cached) = self.layers.get(&layer_id) else {
+            return Some(RebuildReason::NotCached);
+        };
+
+        if cached.key.base_color != key.base_color {
+            Some(RebuildReason::Color)
+        } else if cached.key.configuration != key.configuration {
+            Some(RebuildReason::Configuration)
+        } else if cached.key.transform_matrix != key.transform_matrix {
+            Some(RebuildReason::Transform)
+        } else if cached.key.scale != key.scale || cached.key.pixels_per_point != key.pixels_per_point {
+            Some(RebuildReason::Zoom)
+        } else {
+            let is_visible = match (&cached.culling_region, visible_region) {
+                (None, _) => true,
+                (Some(culling_region), Some(visible_region)) => culling_region.contains_box(visible_region),
+                (Some(_), None) => false,
+            };
+            (!is_visible).then_some(RebuildReason::Culling)
+        }
+    }
+}
+
+struct CachedLayer {
+    key: CacheKey,
+    /// The region, in gerber coordinates, outside of which primitives were culled, `None` if nothing was culled.
+    culling_region: Option<BoundingBox>,
+    /// Tessellated with a view translation of zero.
+    mesh: Mesh,
+    /// The mesh with the view translation it was last painted with, reused while the view is not panned.
+    painted: Option<(Vec2, Arc<Mesh>)>,
+    /// The rasterized layer, instead of the mesh, for composited layers.
+    texture: Option<LayerTexture>,
+    /// Kept when the mesh is rebuilt, e.g. when zooming, since tessellating a board outline is slow.
+    negative_fill: Option<NegativeFill>,
+    /// The primitives that are not in the mesh yet, see [`RenderConfiguration::primitive_budget`].
+    pending: Option<PendingPrimitives>,
+    /// The number of primitives that were culled, and that are in the mesh.
+    culled: usize,
+    drawn: usize,
+    last_pass: u64,
+}
+
+impl CachedLayer {
+    /// The mesh, translated for painting, the translated mesh is only rebuilt when the translation or the mesh changes.
+    fn translated_mesh(&mut self, translation: Vec2) -> Arc<Mesh> {
+        match &self.painted {
+            Some((painted_translation, mesh)) if *painted_translation == translation => mesh.clone(),
+            _ => {
+                let mut mesh = self.mesh.clone();
+                mesh.translate(translation);
+                let mesh = Arc::new(mesh);
+                self.painted = Some((translation, mesh.clone()));
+                mesh
+            }
+        }
+    }
+}
+
+/// A composited layer, see [`RenderConfiguration::is_composited`].
+struct LayerTexture {
+    texture: TextureHandle,
+    /// The area covered by the texture, in gerber coordinates, after the transform.
+    bounds: BoundingBox,
+}
+
+/// The tessellated fill of a negative layer, see [`GerberLayer::tessellated_negative_fill`].
+struct NegativeFill {
+    /// See [`negative_hash`].
+    hash: u64,
+    fill: Option<GerberPrimitive>,
+}
+
+impl NegativeFill {
+    /// Reuses the `previous` fill of the layer if the negative area is unchanged, `None` if the layer is not negative.
+    fn reuse_or_tessellate(
+        previous: Option<NegativeFill>,
+        layer: &GerberLayer,
+        configuration: &RenderConfiguration,
+    ) -> Option<NegativeFill> {
+        let area = configuration.negative.as_ref()?;
+        let hash = negative_hash(&configuration.negative);
+        previous
+            .filter(|previous| previous.hash == hash)
+            .or_else(|| {
+                Some(NegativeFill {
+                    hash,
+                    fill: layer.tessellated_negative_fill(area),
+                })
+            })
+    }
+}
+
+struct PendingPrimitives {
+    draw_list: Vec<(usize, Option<Vector2<f64>>)>,
+    /// The index of the first entry of the draw list that is not in the mesh.
+    next: usize,
+}
+
+struct CacheKey {
+    base_color: Color32,
+    /// See [`RenderConfiguration::mesh_hash`].
+    configuration: u64,
+    transform_matrix: Matrix3<f64>,
+    scale: f32,
+    pixels_per_point: f32,
+}
 
 impl GerberRenderer {
     #[profiling::function]
@@ -48,44 +576,752 @@ impl GerberRenderer {
         configuration: &RenderConfiguration,
         transform: &GerberTransform,
     ) {
-        let transform_matrix = transform.to_matrix();
+        let start = Instant::now();
+        let transform_matrix = transform
+            .resolve(layer.bounding_box())
+            .to_matrix();
 
-        for (index, primitive) in layer.primitives().iter().enumerate() {
-            let color = match configuration.use_unique_shape_colors {
-                true => color::generate_pastel_color(index as u64),
-                false => base_color,
-            };
+        // the shapes are built without the translation, so that panning can re-use the cached mesh.
+        let origin_view = ViewState {
+            translation: Vec2::ZERO,
+            ..view
+        };
 
-            let shape_number = match configuration.use_shape_numbering {
-                true => Some(index),
-                false => None,
-            };
+        let visible_region = visible_region(painter.clip_rect(), &view, &transform_matrix);
 
-            match primitive {
-                GerberPrimitive::Circle(circle) => {
-                    circle.render(painter, &view, &transform_matrix, color, shape_number, configuration)
-                }
-                GerberPrimitive::Rectangle(rect) => {
-                    rect.render(painter, &view, &transform_matrix, color, shape_number, configuration)
-                }
-                GerberPrimitive::Line(line) => {
-                    line.render(painter, &view, &transform_matrix, color, shape_number, configuration)
-                }
-                GerberPrimitive::Arc(arc) => {
-                    arc.render(painter, &view, &transform_matrix, color, shape_number, configuration)
+        // egui applies the opacity when the shapes are painted, so the cached mesh does not depend on it.
+        let mut painter = painter.clone();
+        painter.multiply_opacity(configuration.opacity.clamp(0.0, 1.0));
+        let painter = &painter;
+
+        let ctx = painter.ctx();
+        let pass = ctx.cumulative_pass_nr();
+        let primitive_count = primitive_count(layer);
+
+        // text uses the font texture, which can change between frames, so numbered shapes are not cached.
+        if configuration.use_shape_numbering || configuration.use_vertex_numbering {
+            let mut builder = MeshBuilder::new(painter, configuration.anti_aliasing);
+            let fill = configuration
+                .negative
+                .as_ref()
+                .and_then(|area| layer.tessellated_negative_fill(area));
+            self.paint_negative_fill(
+                &mut builder,
+                &origin_view,
+                fill.as_ref(),
+                base_color,
+                configuration,
+                &transform_matrix,
+            );
+            let draw_list = self.draw_list(layer, configuration, visible_region.as_ref());
+            self.paint_draw_list(
+                &mut builder,
+                &origin_view,
+                layer,
+                base_color,
+                configuration,
+                &transform_matrix,
+                &draw_list,
+            );
+            let mut mesh = builder.mesh;
+            mesh.translate(view.translation);
+
+            let mut cache = self.cache.lock().unwrap();
+            let stats = cache.render_stats(pass);
+            stats.add_mesh(
+                &mesh,
+                primitive_count,
+                primitive_count - draw_list.len(),
+                draw_list.len(),
+            );
+            stats.build_time += start.elapsed();
+
+            painter.add(Shape::Mesh(Arc::new(mesh)));
+            stats.paint_time += start.elapsed();
+            return;
+        }
+        let key = CacheKey {
+            base_color,
+            configuration: configuration.mesh_hash(),
+            transform_matrix,
+            scale: view.scale,
+            pixels_per_point: painter.pixels_per_point(),
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        cache
+            .layers
+            .retain(|_, cached| cached.last_pass + 1 >= pass);
+
+        let mut build_time = Duration::ZERO;
+        match cache.check(layer.id(), &key, visible_region.as_ref()) {
+            None => cache.stats.hits += 1,
+            Some(reason) => {
+                let build_start = Instant::now();
+                debug!("Rebuilding layer mesh. layer: {}, reason: {:?}", layer.id(), reason);
+                cache.stats.rebuilds += 1;
+                cache.stats.last_rebuild = Some((layer.id(), reason));
+
+                if configuration.is_composited(layer) {
+                    let (culling_region, texture) = self.rasterize_layer(
+                        painter,
+                        &view,
+                        layer,
+                        base_color,
+                        configuration,
+                        transform,
+                        &transform_matrix,
+                        visible_region.as_ref(),
+                    );
+                    cache
+                        .layers
+                        .insert(layer.id(), CachedLayer {
+                            key,
+                            culling_region,
+                            mesh: Mesh::default(),
+                            painted: None,
+                            texture,
+                            negative_fill: None,
+                            culled: 0,
+                            drawn: primitive_count,
+                            pending: None,
+                            last_pass: pass,
+                        });
+                } else {
+                    // the margin allows panning by up to a viewport in each direction before the mesh needs rebuilding
+                    let culling_region = visible_region
+                        .map(|region| region.inflate(region.width().max(region.height())))
+                        .filter(|region| !region.contains_box(layer.bounding_box()));
+
+                    let previous = cache
+                        .layers
+                        .remove(&layer.id())
+                        .and_then(|cached| cached.negative_fill);
+                    let negative_fill = NegativeFill::reuse_or_tessellate(previous, layer, configuration);
+
+                    let mut builder = MeshBuilder::new(painter, configuration.anti_aliasing);
+                    self.paint_negative_fill(
+                        &mut builder,
+                        &origin_view,
+                        negative_fill
+                            .as_ref()
+                            .and_then(|negative_fill| negative_fill.fill.as_ref()),
+                        base_color,
+                        configuration,
+                        &transform_matrix,
+                    );
+                    let draw_list = self.draw_list(layer, configuration, culling_region.as_ref());
+                    cache
+                        .layers
+                        .insert(layer.id(), CachedLayer {
+                            key,
+                            culling_region,
+                            mesh: builder.mesh,
+                            painted: None,
+                            texture: None,
+                            negative_fill,
+                            culled: primitive_count - draw_list.len(),
+                            drawn: 0,
+                            pending: Some(PendingPrimitives {
+                                draw_list,
+                                next: 0,
+                            }),
+                            last_pass: pass,
+                        });
                 }
-                GerberPrimitive::Polygon(polygon) => {
-                    polygon.render(painter, &view, &transform_matrix, color, shape_number, configuration)
+                build_time += build_start.elapsed();
+            }
+        }
+
+        let cached = cache
+            .layers
+            .get_mut(&layer.id())
+            .unwrap();
+        cached.last_pass = pass;
+
+        if let Some(pending) = &mut cached.pending {
+            let build_start = Instant::now();
+            let budget = configuration
+                .primitive_budget
+                .unwrap_or(usize::MAX)
+                .max(1);
+            let end = pending
+                .next
+                .saturating_add(budget)
+                .min(pending.draw_list.len());
+
+            let mut builder = MeshBuilder::new(painter, configuration.anti_aliasing);
+            builder.mesh = std::mem::take(&mut cached.mesh);
+            self.paint_draw_list(
+                &mut builder,
+                &origin_view,
+                layer,
+                base_color,
+                configuration,
+                &transform_matrix,
+                &pending.draw_list[pending.next..end],
+            );
+            cached.mesh = builder.mesh;
+            cached.painted = None;
+            pending.next = end;
+            cached.drawn = end;
+            build_time += build_start.elapsed();
+
+            if end < pending.draw_list.len() {
+                paint_placeholder(painter, &view, layer, base_color, &transform_matrix);
+                ctx.request_repaint();
+            } else {
+                cached.pending = None;
+            }
+        }
+
+        let mesh = cached.translated_mesh(view.translation);
+        if let Some(LayerTexture {
+            texture,
+            bounds,
+        }) = &cached.texture
+        {
+            let image_rect = Rect::from_two_pos(
+                view.gerber_to_screen_coords(Point2::new(bounds.min.x, bounds.max.y)),
+                view.gerber_to_screen_coords(Point2::new(bounds.max.x, bounds.min.y)),
+            );
+            painter.image(
+                texture.id(),
+                image_rect,
+                Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                Color32::WHITE,
+            );
+        }
+        let (culled, drawn) = (cached.culled, cached.drawn);
+
+        let stats = cache.render_stats(pass);
+        stats.add_mesh(&mesh, primitive_count, culled, drawn);
+        stats.build_time += build_time;
+
+        painter.add(Shape::Mesh(mesh));
+        stats.paint_time += start.elapsed();
+    }
+
+    /// Drops the cached mesh of the layer, see [`GerberLayer::id`], so it is rebuilt the next time it's painted.
+    pub fn invalidate(&self, layer_id: u64) {
+        self.cache
+            .lock()
+            .unwrap()
+            .layers
+            .remove(&layer_id);
+    }
+
+    /// Drops the cached meshes of all layers.
+    pub fn invalidate_all(&self) {
+        self.cache
+            .lock()
+            .unwrap()
+            .layers
+            .clear();
+    }
+
+    /// Returns `true` if the mesh of the layer is being built over several frames, see
+    /// [`RenderConfiguration::primitive_budget`].
+    pub fn is_building(&self, layer_id: u64) -> bool {
+        self.cache
+            .lock()
+            .unwrap()
+            .layers
+            .get(&layer_id)
+            .is_some_and(|cached| cached.pending.is_some())
+    }
+
+    /// The stats of the most recent pass in which a layer was painted, call after painting the layers to get the stats
+    /// of the current pass, or before to get the stats of the previous one.
+    pub fn render_stats(&self) -> RenderStats {
+        self.cache.lock().unwrap().render_stats
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        let cache = self.cache.lock().unwrap();
+        CacheStats {
+            layers: cache.layers.len(),
+            ..cache.stats
+        }
+    }
+
+    /// Highlights the primitive under the `position`, in screen coordinates, e.g. the cursor, and returns its summary so
+    /// that it can be shown in a tooltip.
+    ///
+    /// Call after painting the layer, with the same view and transform, so the highlight is drawn on top.  `tolerance`
+    /// is in screen points, e.g. to make thin traces easier to hover.  Only the hovered copy of a step-and-repeat
+    /// primitive is highlighted.  The highlight is not cached.
+    #[allow(clippy::too_many_arguments)]
+    pub fn paint_hover(
+        &self,
+        painter: &egui::Painter,
+        view: ViewState,
+        layer: &GerberLayer,
+        transform: &GerberTransform,
+        position: Pos2,
+        tolerance: f32,
+        highlight_color: Color32,
+    ) -> Option<PrimitiveSummary> {
+        let transform_matrix = transform
+            .resolve(layer.bounding_box())
+            .to_matrix();
+        let inverse = transform_matrix.try_inverse()?;
+
+        let point = inverse.transform_point(&view.screen_to_gerber_coords(position));
+        // the transform may also scale the layer
+        let tolerance = inverse
+            .transform_vector(&Vector2::new((tolerance / view.scale) as f64, 0.0))
+            .norm();
+
+        let hit = layer.hovered_primitive(point, tolerance)?;
+
+        let mut builder = MeshBuilder::new(painter, AntiAliasing::Inherit);
+        render_primitive(
+            &mut builder,
+            &view,
+            &layer.gerber_primitives()[hit.index],
+            &(transform_matrix * Matrix3::new_translation(&hit.offset)),
+            highlight_color,
+            None,
+            &RenderConfiguration::default(),
+        );
+        painter.add(Shape::Mesh(Arc::new(builder.mesh)));
+
+        layer.primitive_summary(hit.index)
+    }
+
+    /// Highlights the primitives, e.g. the flashes of an aperture, see [`GerberLayer::flashes_of_aperture`].
+    ///
+    /// Call after painting the layer, with the same view and transform, so the highlight is drawn on top.  All the
+    /// step-and-repeat copies of the primitives are highlighted, indices that are not primitives of the layer are
+    /// ignored.  The highlight is not cached.
+    pub fn paint_highlight(
+        &self,
+        painter: &egui::Painter,
+        view: ViewState,
+        layer: &GerberLayer,
+        transform: &GerberTransform,
+        primitives: &[usize],
+        highlight_color: Color32,
+    ) {
+        let transform_matrix = transform
+            .resolve(layer.bounding_box())
+            .to_matrix();
+
+        let mut builder = MeshBuilder::new(painter, AntiAliasing::Inherit);
+        for index in primitives {
+            let Some(primitive) = layer.gerber_primitives().get(*index) else {
+                continue;
+            };
+            for offset in layer.primitive_offsets(*index) {
+                render_primitive(
+                    &mut builder,
+                    &view,
+                    primitive,
+                    &(transform_matrix * Matrix3::new_translation(&offset)),
+                    highlight_color,
+                    None,
+                    &RenderConfiguration::default(),
+                );
+            }
+        }
+        painter.add(Shape::Mesh(Arc::new(builder.mesh)));
+    }
+
+    /// Rasterizes the visible part of a composited layer into a texture, see [`RenderConfiguration::is_composited`].
+    ///
+    /// Returns the culling region, in gerber coordinates before the transform, and the texture, `None` if nothing
+    /// is visible.
+    #[allow(clippy::too_many_arguments)]
+    #[profiling::function]
+    fn rasterize_layer(
+        &self,
+        painter: &Painter,
+        view: &ViewState,
+        layer: &GerberLayer,
+        base_color: Color32,
+        configuration: &RenderConfiguration,
+        transform: &GerberTransform,
+        transform_matrix: &Matrix3<f64>,
+        visible_region: Option<&BoundingBox>,
+    ) -> (Option<BoundingBox>, Option<LayerTexture>) {
+        let content = match &configuration.negative {
+            Some(area) => layer.negative_bounding_box(area),
+            None => layer.try_bounding_box().cloned(),
+        };
+        let Some(content) = content else {
+            return (None, None);
+        };
+
+        // the margin allows panning by up to half a viewport in each direction before the layer is rasterized again
+        let culling_region = visible_region
+            .map(|region| region.inflate(region.width().max(region.height()) / 2.0))
+            .filter(|region| !region.contains_box(&content));
+        let region = match &culling_region {
+            Some(culling_region) => BoundingBox {
+                min: content.min.sup(&culling_region.min),
+                max: content.max.inf(&culling_region.max),
+            },
+            None => content,
+        };
+        if region.min.x >= region.max.x || region.min.y >= region.max.y {
+            return (culling_region, None);
+        }
+        let corners = region
+            .vertices()
+            .into_iter()
+            .map(|corner| transform_matrix.transform_point(&corner))
+            .collect::<Vec<_>>();
+
+        let options = RasterOptions {
+            color: base_color.to_srgba_unmultiplied(),
+            transform: *transform,
+            negative: configuration.negative.clone(),
+            anti_aliasing: configuration
+                .anti_aliasing
+                .is_enabled(painter.ctx()),
+            ..RasterOptions::default()
+        };
+        let pixels_per_unit = view.scale as f64 * painter.pixels_per_point() as f64;
+        let texture = layer
+            .render_region_to_image(options, &BoundingBox::from_points(&corners), pixels_per_unit)
+            .inspect_err(|error| warn!("Unable to rasterize layer. layer: {}, error: {error}", layer.id()))
+            .ok()
+            .map(|(image, bounds)| LayerTexture {
+                texture: painter.ctx().load_texture(
+                    format!("gerber_layer_{}", layer.id()),
+                    ColorImage::from_rgba_unmultiplied([image.width as usize, image.height as usize], &image.pixels),
+                    TextureOptions::LINEAR,
+                ),
+                bounds,
+            });
+
+        (culling_region, texture)
+    }
+
+    fn paint_negative_fill(
+        &self,
+        builder: &mut MeshBuilder,
+        view: &ViewState,
+        fill: Option<&GerberPrimitive>,
+        base_color: Color32,
+        configuration: &RenderConfiguration,
+        transform_matrix: &Matrix3<f64>,
+    ) {
+        if let Some(fill) = fill {
+            render_primitive(builder, view, fill, transform_matrix, base_color, None, configuration);
+        }
+    }
+
+    /// The index and step-and-repeat offset of each primitive to paint, in the order of the
+    /// [`RenderConfiguration::draw_order`].
+    ///
+    /// Primitives entirely outside the `region`, in gerber coordinates, are skipped, `None` for all primitives.
+    fn draw_list(
+        &self,
+        layer: &GerberLayer,
+        configuration: &RenderConfiguration,
+        region: Option<&BoundingBox>,
+    ) -> Vec<(usize, Option<Vector2<f64>>)> {
+        // The spatial index returns a primitive if any of its copies intersect the region, so the culling of copies
+        // is conservative.
+        let visible_primitives = |offset: &Vector2<f64>| {
+            region.map(|region| {
+                layer.query_region(&BoundingBox {
+                    min: region.min - *offset,
+                    max: region.max - *offset,
+                })
+            })
+        };
+        let is_visible = |visible: &Option<Vec<usize>>, index: usize| {
+            visible
+                .as_ref()
+                .is_none_or(|visible| visible.binary_search(&index).is_ok())
+        };
+
+        let primitives = layer.gerber_primitives();
+        let zero = Vector2::new(0.0, 0.0);
+        let visible = visible_primitives(&zero);
+        // the copies of a step-and-repeat block are drawn one at a time, so the region is queried once per copy
+        let mut copy_offset = None;
+        let mut copy_visible = None;
+
+        // the index and step-and-repeat offset of each visible primitive, in file order
+        let mut draw_list = vec![];
+        for (index, offset) in layer.primitives_in_draw_order() {
+            if offset == zero {
+                if is_visible(&visible, index) {
+                    draw_list.push((index, None));
                 }
+                continue;
+            }
+
+            if copy_offset != Some(offset) {
+                copy_offset = Some(offset);
+                copy_visible = visible_primitives(&offset);
             }
+            if is_visible(&copy_visible, index) {
+                draw_list.push((index, Some(offset)));
+            }
+        }
+
+        configuration
+            .draw_order
+            .sort(primitives, &mut draw_list);
+
+        draw_list
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[profiling::function]
+    fn paint_draw_list(
+        &self,
+        builder: &mut MeshBuilder,
+        view: &ViewState,
+        layer: &GerberLayer,
+        base_color: Color32,
+        configuration: &RenderConfiguration,
+        transform_matrix: &Matrix3<f64>,
+        draw_list: &[(usize, Option<Vector2<f64>>)],
+    ) {
+        let primitives = layer.gerber_primitives();
+        for &(index, offset) in draw_list {
+            let instance_matrix = offset.map(|offset| transform_matrix * Matrix3::new_translation(&offset));
+            self.paint_primitive(
+                builder,
+                view,
+                layer,
+                index,
+                &primitives[index],
+                base_color,
+                configuration,
+                instance_matrix
+                    .as_ref()
+                    .unwrap_or(transform_matrix),
+            );
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn paint_primitive(
+        &self,
+        painter: &mut MeshBuilder,
+        view: &ViewState,
+        layer: &GerberLayer,
+        index: usize,
+        primitive: &GerberPrimitive,
+        base_color: Color32,
+        configuration: &RenderConfiguration,
+        transform_matrix: &Matrix3<f64>,
+    ) {
+        let color = match configuration.use_unique_shape_colors {
+            true => color::generate_pastel_color(index as u64),
+            false => base_color,
+        };
+
+        let color = match configuration
+            .primitive_islands
+            .as_ref()
+            .and_then(|islands| islands.get(index).copied().flatten())
+        {
+            Some(island) => color::generate_pastel_color(island as u64),
+            None => color,
+        };
+
+        let color = match &configuration.highlighted_net {
+            Some(net)
+                if layer
+                    .primitive_attributes(index)
+                    .is_some_and(|attributes| attributes.is_on_net(net)) =>
+            {
+                configuration.net_highlight_color
+            }
+            _ => color,
+        };
+
+        let exposure = match configuration.negative {
+            Some(_) => primitive.exposure().inverted(),
+            None => primitive.exposure(),
+        };
+        let color = match (configuration.wireframe, exposure) {
+            (true, Exposure::CutOut) => color.gamma_multiply(0.5),
+            _ => exposure.to_color(&color),
+        };
+
+        let shape_number = match configuration.use_shape_numbering {
+            true => Some(index),
+            false => None,
+        };
+
+        render_primitive(
+            painter,
+            view,
+            primitive,
+            transform_matrix,
+            color,
+            shape_number,
+            configuration,
+        );
+
+        if configuration.show_bounding_boxes {
+            let corners = primitive
+                .bounding_box()
+                .vertices()
+                .into_iter()
+                .map(|corner| {
+                    let position = Pos2::new(corner.x as f32, -(corner.y as f32));
+                    (view.translation + transform_matrix.transform_pos2(position) * view.scale).to_pos2()
+                })
+                .collect();
+            painter.add(Shape::closed_line(
+                corners,
+                Stroke::new(1.0, configuration.bounding_box_color),
+            ));
+        }
+    }
+}
+
+/// Renders the primitive in the color, the exposure of the primitive is ignored.
+fn render_primitive(
+    painter: &mut MeshBuilder,
+    view: &ViewState,
+    primitive: &GerberPrimitive,
+    transform_matrix: &Matrix3<f64>,
+    color: Color32,
+    shape_number: Option<usize>,
+    configuration: &RenderConfiguration,
+) {
+    match primitive {
+        GerberPrimitive::Circle(circle) => {
+            circle.render(painter, view, transform_matrix, color, shape_number, configuration)
+        }
+        GerberPrimitive::Rectangle(rect) => {
+            rect.render(painter, view, transform_matrix, color, shape_number, configuration)
+        }
+        GerberPrimitive::Line(line) => line.render(painter, view, transform_matrix, color, shape_number, configuration),
+        GerberPrimitive::Arc(arc) => arc.render(painter, view, transform_matrix, color, shape_number, configuration),
+        GerberPrimitive::Polygon(polygon) => {
+            polygon.render(painter, view, transform_matrix, color, shape_number, configuration)
         }
     }
 }
 
+impl RenderStats {
+    fn add_mesh(&mut self, mesh: &Mesh, primitives: usize, culled: usize, drawn: usize) {
+        self.layers += 1;
+        self.primitives += primitives;
+        self.culled += culled;
+        self.drawn += drawn;
+        self.vertices += mesh.vertices.len();
+        self.triangles += mesh.indices.len() / 3;
+    }
+}
+
+/// The number of primitives of the layer, including the copies of step-and-repeat blocks.
+fn primitive_count(layer: &GerberLayer) -> usize {
+    let copies = layer
+        .instances()
+        .iter()
+        .map(|instance| instance.primitives.len() * instance.offsets.len())
+        .sum::<usize>();
+    layer.gerber_primitives().len() + copies
+}
+
+/// Fills the bounding box of the layer, while its mesh is being built.
+fn paint_placeholder(
+    painter: &Painter,
+    view: &ViewState,
+    layer: &GerberLayer,
+    base_color: Color32,
+    transform_matrix: &Matrix3<f64>,
+) {
+    let corners = layer
+        .bounding_box()
+        .vertices()
+        .into_iter()
+        .map(|corner| {
+            let position = Pos2::new(corner.x as f32, -(corner.y as f32));
+            (view.translation + transform_matrix.transform_pos2(position) * view.scale).to_pos2()
+        })
+        .collect();
+    painter.add(Shape::convex_polygon(
+        corners,
+        base_color.gamma_multiply(PLACEHOLDER_ALPHA),
+        Stroke::NONE,
+    ));
+}
+
+/// The region of the layer, in gerber coordinates before the transform, that is visible in the viewport.
+///
+/// Returns `None` if the transform cannot be inverted, e.g. a scale of zero.
+fn visible_region(viewport: Rect, view: &ViewState, transform_matrix: &Matrix3<f64>) -> Option<BoundingBox> {
+    let inverse = transform_matrix.try_inverse()?;
+    let corners = [
+        viewport.left_top(),
+        viewport.right_top(),
+        viewport.right_bottom(),
+        viewport.left_bottom(),
+    ]
+    .map(|corner| inverse.transform_point(&view.screen_to_gerber_coords(corner)));
+
+    Some(BoundingBox::from_points(&corners))
+}
+
+/// Has the same drawing methods as [`Painter`], but tessellates the shapes into a single mesh, in the same way egui
+/// would when painting them, instead of painting them.
+struct MeshBuilder<'a> {
+    painter: &'a Painter,
+    tessellator: Tessellator,
+    mesh: Mesh,
+}
+
+impl<'a> MeshBuilder<'a> {
+    fn new(painter: &'a Painter, anti_aliasing: AntiAliasing) -> Self {
+        let ctx = painter.ctx();
+        let mut options = ctx.tessellation_options(|options| *options);
+        anti_aliasing.apply(&mut options);
+        let tessellator = Tessellator::new(
+            painter.pixels_per_point(),
+            options,
+            ctx.fonts(|fonts| fonts.font_image_size()),
+            vec![],
+        );
+
+        Self {
+            painter,
+            tessellator,
+            mesh: Mesh::default(),
+        }
+    }
+
+    fn add(&mut self, shape: impl Into<Shape>) {
+        self.tessellator
+            .tessellate_shape(shape.into(), &mut self.mesh);
+    }
+
+    fn circle(&mut self, center: Pos2, radius: f32, fill_color: Color32, stroke: Stroke) {
+        self.add(Shape::circle_filled(center, radius, fill_color));
+        if stroke != Stroke::NONE {
+            self.add(Shape::circle_stroke(center, radius, stroke));
+        }
+    }
+
+    fn rect(&mut self, rect: Rect, corner_radius: f32, fill_color: Color32, stroke: Stroke, stroke_kind: StrokeKind) {
+        self.add(RectShape::new(rect, corner_radius, fill_color, stroke, stroke_kind));
+    }
+
+    fn text(&mut self, position: Pos2, anchor: Align2, text: String, font_id: FontId, color: Color32) {
+        let shape = self
+            .painter
+            .fonts(|fonts| Shape::text(fonts, position, anchor, text, font_id, color));
+        self.add(shape);
+    }
+}
+
 trait Renderable {
     fn render(
         &self,
-        painter: &Painter,
+        painter: &mut MeshBuilder,
         view: &ViewState,
         transform_matrix: &Matrix3<f64>,
         color: Color32,
@@ -98,28 +1334,32 @@ impl Renderable for CircleGerberPrimitive {
     #[cfg_attr(feature = "profile-renderables", profiling::function)]
     fn render(
         &self,
-        painter: &Painter,
+        painter: &mut MeshBuilder,
         view: &ViewState,
         transform_matrix: &Matrix3<f64>,
         color: Color32,
         shape_number: Option<usize>,
-        _configuration: &RenderConfiguration,
+        configuration: &RenderConfiguration,
     ) {
         let Self {
             center,
             diameter,
-            exposure,
+            exposure: _,
         } = self;
 
-        let color = exposure.to_color(&color);
-
         let screen_center = Pos2::new(center.x as f32, -(center.y as f32));
 
         let center = view.translation.to_pos2() + transform_matrix.transform_pos2(screen_center) * view.scale;
 
         let radius = (*diameter as f32 / 2.0) * view.scale;
-        #[cfg(feature = "egui")]
-        painter.circle(center, radius, color, Stroke::NONE);
+        match configuration.wireframe {
+            true => painter.add(Shape::circle_stroke(
+                center,
+                radius,
+                Stroke::new(WIREFRAME_STROKE_WIDTH, color),
+            )),
+            false => painter.circle(center, radius, color, Stroke::NONE),
+        }
 
         draw_shape_number(
             painter,
@@ -127,6 +1367,7 @@ impl Renderable for CircleGerberPrimitive {
             transform_matrix,
             ShapeNumberPosition::Transformed(center),
             shape_number,
+            &configuration.shape_numbering,
         );
     }
 }
@@ -135,22 +1376,25 @@ impl Renderable for RectangleGerberPrimitive {
     #[cfg_attr(feature = "profile-renderables", profiling::function)]
     fn render(
         &self,
-        painter: &Painter,
+        painter: &mut MeshBuilder,
         view: &ViewState,
         transform_matrix: &Matrix3<f64>,
         color: Color32,
         shape_number: Option<usize>,
-        _configuration: &RenderConfiguration,
+        configuration: &RenderConfiguration,
     ) {
+        let (fill, stroke) = match configuration.wireframe {
+            true => (Color32::TRANSPARENT, Stroke::new(WIREFRAME_STROKE_WIDTH, color)),
+            false => (color, Stroke::NONE),
+        };
+
         let Self {
             origin,
             width,
             height,
-            exposure,
+            exposure: _,
         } = self;
 
-        let color = exposure.to_color(&color);
-
         // Calculate center-based position
         let screen_center = Pos2::new(
             origin.x as f32 + *width as f32 / 2.0,     // Add half width to get center
@@ -177,8 +1421,8 @@ impl Renderable for RectangleGerberPrimitive {
             painter.rect(
                 Rect::from_min_size(top_left, size),
                 0.0,
-                color,
-                Stroke::NONE,
+                fill,
+                stroke,
                 StrokeKind::Middle,
             );
         } else {
@@ -203,7 +1447,7 @@ impl Renderable for RectangleGerberPrimitive {
                 })
                 .collect();
 
-            painter.add(Shape::convex_polygon(screen_corners, color, Stroke::NONE));
+            painter.add(Shape::convex_polygon(screen_corners, fill, stroke));
         }
 
         draw_shape_number(
@@ -212,6 +1456,7 @@ impl Renderable for RectangleGerberPrimitive {
             transform_matrix,
             ShapeNumberPosition::Transformed(center),
             shape_number,
+            &configuration.shape_numbering,
         );
     }
 }
@@ -220,20 +1465,19 @@ impl Renderable for LineGerberPrimitive {
     #[cfg_attr(feature = "profile-renderables", profiling::function)]
     fn render(
         &self,
-        painter: &Painter,
+        painter: &mut MeshBuilder,
         view: &ViewState,
         transform_matrix: &Matrix3<f64>,
         color: Color32,
         shape_number: Option<usize>,
-        _configuration: &RenderConfiguration,
+        configuration: &RenderConfiguration,
     ) {
         let Self {
             start,
             end,
             width,
-            exposure,
+            exposure: _,
         } = self;
-        let color = exposure.to_color(&color);
 
         let start_position = Pos2::new(start.x as f32, -(start.y as f32));
         let end_position = Pos2::new(end.x as f32, -(end.y as f32));
@@ -243,14 +1487,15 @@ impl Renderable for LineGerberPrimitive {
         let transformed_end_position =
             (view.translation + transform_matrix.transform_pos2(end_position) * view.scale).to_pos2();
 
-        painter.line_segment(
-            [transformed_start_position, transformed_end_position],
-            Stroke::new((*width as f32) * view.scale, color),
-        );
-        // Draw circles at either end of the line.
-        let radius = (*width as f32 / 2.0) * view.scale;
-        painter.circle(transformed_start_position, radius, color, Stroke::NONE);
-        painter.circle(transformed_end_position, radius, color, Stroke::NONE);
+        let stroke_width = (*width as f32 * view.scale).max(configuration.min_stroke_width);
+        let radius = stroke_width / 2.0;
+        let outline = stadium_outline(transformed_start_position, transformed_end_position, radius);
+        match configuration.wireframe {
+            true => painter.add(Shape::closed_line(outline, Stroke::new(WIREFRAME_STROKE_WIDTH, color))),
+            // a single shape, instead of a line segment and a circle at each end, which also avoids blending the ends
+            // twice when the color is translucent.
+            false => painter.add(Shape::convex_polygon(outline, color, Stroke::NONE)),
+        }
 
         if shape_number.is_some() {
             let screen_center = (transformed_start_position + transformed_end_position.to_vec2()) / 2.0;
@@ -260,6 +1505,7 @@ impl Renderable for LineGerberPrimitive {
                 transform_matrix,
                 ShapeNumberPosition::Transformed(screen_center),
                 shape_number,
+                &configuration.shape_numbering,
             );
         }
     }
@@ -269,20 +1515,19 @@ impl Renderable for ArcGerberPrimitive {
     #[cfg_attr(feature = "profile-renderables", profiling::function)]
     fn render(
         &self,
-        painter: &Painter,
+        painter: &mut MeshBuilder,
         view: &ViewState,
         transform_matrix: &Matrix3<f64>,
         color: Color32,
         shape_number: Option<usize>,
-        _configuration: &RenderConfiguration,
+        configuration: &RenderConfiguration,
     ) {
         let Self {
             center,
             width,
-            exposure,
+            radius,
             ..
         } = self;
-        let color = exposure.to_color(&color);
         let screen_center = Pos2::new(center.x as f32, -(center.y as f32));
 
         let points = self
@@ -300,16 +1545,37 @@ impl Renderable for ArcGerberPrimitive {
 
         let center_point = points[steps / 2];
 
-        painter.add(Shape::Path(PathShape {
-            points,
-            closed: self.is_full_circle(),
-            fill: Color32::TRANSPARENT,
-            stroke: PathStroke {
-                width: *width as f32 * view.scale,
-                color: ColorMode::Solid(color),
-                kind: StrokeKind::Middle,
-            },
-        }));
+        let stroke_width = (*width as f32 * view.scale).max(configuration.min_stroke_width);
+
+        if configuration.wireframe {
+            let screen_center =
+                (view.translation + transform_matrix.transform_pos2(screen_center) * view.scale).to_pos2();
+            let radius = *radius as f32 * view.scale;
+            let half_width = stroke_width / 2.0;
+            let stroke = Stroke::new(WIREFRAME_STROKE_WIDTH, color);
+            let (outer_radius, inner_radius) = (radius + half_width, (radius - half_width).max(0.0));
+
+            if self.is_full_circle() {
+                painter.add(Shape::circle_stroke(screen_center, outer_radius, stroke));
+                painter.add(Shape::circle_stroke(screen_center, inner_radius, stroke));
+            } else {
+                painter.add(Shape::closed_line(
+                    arc_outline(screen_center, &points, outer_radius, inner_radius),
+                    stroke,
+                ));
+            }
+        } else {
+            painter.add(Shape::Path(PathShape {
+                points,
+                closed: self.is_full_circle(),
+                fill: Color32::TRANSPARENT,
+                stroke: PathStroke {
+                    width: stroke_width,
+                    color: ColorMode::Solid(color),
+                    kind: StrokeKind::Middle,
+                },
+            }));
+        }
 
         // draw the shape number at the center of the arc, not at the origin of the arc, which for arcs with a
         // large radius but small sweep could be way off the screen.
@@ -319,6 +1585,7 @@ impl Renderable for ArcGerberPrimitive {
             transform_matrix,
             ShapeNumberPosition::Transformed(center_point),
             shape_number,
+            &configuration.shape_numbering,
         );
     }
 }
@@ -327,7 +1594,7 @@ impl Renderable for PolygonGerberPrimitive {
     #[cfg_attr(feature = "profile-renderables", profiling::function)]
     fn render(
         &self,
-        painter: &Painter,
+        painter: &mut MeshBuilder,
         view: &ViewState,
         transform_matrix: &Matrix3<f64>,
         color: Color32,
@@ -336,14 +1603,31 @@ impl Renderable for PolygonGerberPrimitive {
     ) {
         let Self {
             center,
-            exposure,
+            exposure: _,
             geometry,
         } = self;
-        let color = exposure.to_color(&color);
 
         let screen_center = Pos2::new(center.x as f32, -(center.y as f32));
 
-        if geometry.is_convex {
+        if configuration.wireframe || (!geometry.is_convex && geometry.tessellation.is_none()) {
+            // the contours, for wireframes, or when not tessellated, see `TessellationFallback::Outline`
+            let stroke_width = match configuration.wireframe {
+                true => WIREFRAME_STROKE_WIDTH,
+                false => 1.0,
+            };
+            for contour in split_contours(&geometry.relative_vertices) {
+                let screen_vertices: Vec<Pos2> = contour
+                    .iter()
+                    .map(|v| {
+                        let local = Vec2::new(v.x as f32, -v.y as f32);
+                        (view.translation + transform_matrix.transform_pos2(screen_center + local) * view.scale)
+                            .to_pos2()
+                    })
+                    .collect();
+
+                painter.add(Shape::closed_line(screen_vertices, Stroke::new(stroke_width, color)));
+            }
+        } else if geometry.is_convex {
             // Direct convex rendering
             let screen_vertices: Vec<Pos2> = geometry
                 .relative_vertices
@@ -383,7 +1667,10 @@ impl Renderable for PolygonGerberPrimitive {
             })));
         }
 
-        if configuration.use_vertex_numbering {
+        let vertex_font_size = configuration
+            .vertex_numbering
+            .font_size(view);
+        if let (true, Some(font_size)) = (configuration.use_vertex_numbering, vertex_font_size) {
             let debug_vertices: Vec<Pos2> = geometry
                 .relative_vertices
                 .iter()
@@ -401,8 +1688,8 @@ impl Renderable for PolygonGerberPrimitive {
                     *pos,
                     Align2::CENTER_CENTER,
                     format!("{}", i),
-                    FontId::monospace(8.0),
-                    Color32::RED,
+                    FontId::monospace(font_size),
+                    configuration.vertex_numbering.color,
                 );
             }
         }
@@ -413,18 +1700,96 @@ impl Renderable for PolygonGerberPrimitive {
             transform_matrix,
             ShapeNumberPosition::Untransformed(screen_center),
             shape_number,
+            &configuration.shape_numbering,
         );
     }
 }
 
+/// The alpha of the placeholder of a layer that is being built, relative to the base color.
+const PLACEHOLDER_ALPHA: f32 = 0.25;
+
+/// The width of the strokes of [`RenderConfiguration::wireframe`], in points.
+const WIREFRAME_STROKE_WIDTH: f32 = 1.0;
+
+/// The number of segments of the half circle at each end of a line or arc outline, more for larger radii, so the
+/// segments stay under a tenth of a point from the circle.
+fn cap_segments(radius: f32) -> usize {
+    ((radius.sqrt() * 4.0).ceil() as usize).clamp(4, 64)
+}
+
+/// The outline of a line with round ends, in screen coordinates.
+fn stadium_outline(start: Pos2, end: Pos2, radius: f32) -> Vec<Pos2> {
+    let direction = end - start;
+    if direction.length() == 0.0 {
+        let segments = cap_segments(radius);
+        return (0..segments * 2)
+            .map(|segment| start + Vec2::angled(PI * segment as f32 / segments as f32) * radius)
+            .collect();
+    }
+
+    let normal = direction.normalized().rot90() * radius;
+    let mut outline = vec![start + normal, end + normal];
+    outline.extend(round_cap(end, end + normal, direction));
+    outline.extend([end - normal, start - normal]);
+    outline.extend(round_cap(start, start - normal, -direction));
+    outline
+}
+
+/// The outline of an arc with round ends, `points` are the points of the center-line, in screen coordinates.
+fn arc_outline(center: Pos2, points: &[Pos2], outer_radius: f32, inner_radius: f32) -> Vec<Pos2> {
+    let at_radius = |point: &Pos2, radius: f32| center + (*point - center).normalized() * radius;
+    let (first, last) = (points[0], points[points.len() - 1]);
+
+    let mut outline = points
+        .iter()
+        .map(|point| at_radius(point, outer_radius))
+        .collect::<Vec<_>>();
+    outline.extend(round_cap(
+        last,
+        at_radius(&last, outer_radius),
+        last - points[points.len() - 2],
+    ));
+    outline.extend(
+        points
+            .iter()
+            .rev()
+            .map(|point| at_radius(point, inner_radius)),
+    );
+    outline.extend(round_cap(first, at_radius(&first, inner_radius), first - points[1]));
+    outline
+}
+
+/// The points between `from` and the opposite point around `center`, excluding both, on the half circle that bulges
+/// in the `outward` direction.
+fn round_cap(center: Pos2, from: Pos2, outward: Vec2) -> Vec<Pos2> {
+    let start = from - center;
+    let radius = start.length();
+    let start_angle = start.y.atan2(start.x);
+    // the direction in which the middle of the half circle is on the outward side
+    let direction = match Vec2::angled(start_angle + FRAC_PI_2).dot(outward) >= 0.0 {
+        true => 1.0,
+        false => -1.0,
+    };
+
+    let segments = cap_segments(radius);
+    (1..segments)
+        .map(|segment| {
+            let angle = start_angle + direction * PI * segment as f32 / segments as f32;
+            center + Vec2::angled(angle) * radius
+        })
+        .collect()
+}
+
 fn draw_shape_number(
-    painter: &Painter,
+    painter: &mut MeshBuilder,
     view: &ViewState,
     transform_matrix: &Matrix3<f64>,
     position: ShapeNumberPosition,
     shape_number: Option<usize>,
+    style: &NumberingStyle,
 ) {
     let Some(shape_number) = shape_number else { return };
+    let Some(font_size) = style.font_size(view) else { return };
 
     let position = match position {
         ShapeNumberPosition::Transformed(position) => position,
@@ -436,8 +1801,8 @@ fn draw_shape_number(
         position,
         Align2::CENTER_CENTER,
         format!("{}", shape_number),
-        FontId::monospace(16.0),
-        Color32::GREEN,
+        FontId::monospace(font_size),
+        style.color,
     );
 }
 
@@ -445,3 +1810,41 @@ enum ShapeNumberPosition {
     Transformed(Pos2),
     Untransformed(Pos2),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mesh_hash_ignores_opacity() {
+        // given
+        let configuration = RenderConfiguration::default();
+
+        // when
+        let dimmed = RenderConfiguration {
+            opacity: 0.5,
+            ..configuration.clone()
+        };
+
+        // then
+        assert_eq!(dimmed.mesh_hash(), configuration.mesh_hash());
+    }
+
+    #[test]
+    fn test_mesh_hash_of_islands() {
+        // given
+        let configuration = RenderConfiguration {
+            primitive_islands: Some(vec![Some(0), Some(0), None]),
+            ..RenderConfiguration::default()
+        };
+
+        // when
+        let other_islands = RenderConfiguration {
+            primitive_islands: Some(vec![Some(0), Some(1), None]),
+            ..configuration.clone()
+        };
+
+        // then
+        assert_ne!(other_islands.mesh_hash(), configuration.mesh_hash());
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::BTreeSet;
+
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::BoundingBox;
+use crate::hit_test::distance_to_segment;
+use crate::layer::{
+    CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGerberPrimitive,
+    RectangleGerberPrimitive,
+};
+
+/// How primitives are selected by [`GerberLayer::primitives_in_rect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// The primitive must be fully inside the rectangle, e.g. when dragging left-to-right in most CAD tools.
+    Contained,
+    /// Any part of the primitive must be inside the rectangle, e.g. when dragging right-to-left in most CAD tools.
+    Intersecting,
+}
+
+impl GerberLayer {
+    /// Returns the indices of the primitives selected by the rectangle, in drawing order, e.g. for marquee selection.
+    ///
+    /// The rectangle is in gerber coordinates.  A step-and-repeat primitive is selected if any of its copies is selected.
+    /// For [`SelectionMode::Intersecting`] the geometry of the primitives is tested, not just their bounding boxes,
+    /// arcs are approximated by line segments.
+    pub fn primitives_in_rect(&self, rect: &BoundingBox, mode: SelectionMode) -> Vec<usize> {
+        self.query_region(rect)
+            .into_iter()
+            .filter(|index| {
+                let primitive = &self.gerber_primitives()[*index];
+                self.primitive_offsets(*index)
+                    .iter()
+                    .any(|offset| {
+                        // move the rectangle instead of the primitive
+                        let rect = BoundingBox {
+                            min: rect.min - *offset,
+                            max: rect.max - *offset,
+                        };
+                        match mode {
+                            SelectionMode::Contained => rect.contains_box(&primitive.bounding_box()),
+                            SelectionMode::Intersecting => intersects(&rect, primitive),
+                        }
+                    })
+            })
+            .collect()
+    }
+}
+
+/// A selected primitive, see [`SelectionState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SelectedPrimitive {
+    /// The index of the layer, e.g. in the [`crate::LayerSet`], or in the layers passed to
+    /// `UiState::handle_selection`.
+    pub layer: usize,
+    /// The index of the primitive in the layer, see [`GerberLayer::primitives`].
+    pub primitive: usize,
+}
+
+/// The primitives added to and removed from a [`SelectionState`] by one change, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelectionChange {
+    pub added: Vec<SelectedPrimitive>,
+    pub removed: Vec<SelectedPrimitive>,
+}
+
+impl SelectionChange {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Identifies a listener added using [`SelectionState::on_change`], for removing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SelectionListenerId(u64);
+
+type SelectionListener = Box<dyn FnMut(&SelectionChange, &BTreeSet<SelectedPrimitive>)>;
+
+/// A set of selected primitives, of one or more layers, with listeners that are called when it changes, e.g. to update
+/// an inspector or a property panel in the host application.
+///
+/// Every method that changes the selection returns the change, empty if nothing changed, and calls the listeners with
+/// it, listeners are not called for empty changes.  The selection is usually managed by `UiState::handle_selection`,
+/// i.e. by clicking, ctrl-clicking and dragging a marquee.
+#[derive(Default)]
+pub struct SelectionState {
+    selected: BTreeSet<SelectedPrimitive>,
+    listeners: Vec<(SelectionListenerId, SelectionListener)>,
+    next_listener_id: u64,
+}
+
+impl std::fmt::Debug for SelectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SelectionState")
+            .field("selected", &self.selected)
+            .field("listeners", &self.listeners.len())
+            .finish()
+    }
+}
+
+impl SelectionState {
+    /// Calls the listener after each change, with the change and the new selection.
+    pub fn on_change(
+        &mut self,
+        listener: impl FnMut(&SelectionChange, &BTreeSet<SelectedPrimitive>) + 'static,
+    ) -> SelectionListenerId {
+        let id = SelectionListenerId(self.next_listener_id);
+        self.next_listener_id += 1;
+        self.listeners
+            .push((id, Box::new(listener)));
+        id
+    }
+
+    /// Returns `false` if there is no listener with the id, e.g. it was already removed.
+    pub fn remove_listener(&mut self, id: SelectionListenerId) -> bool {
+        let count = self.listeners.len();
+        self.listeners
+            .retain(|(listener_id, _)| *listener_id != id);
+        self.listeners.len() != count
+    }
+
+    /// The selected primitives, ordered by layer and primitive index.
+    pub fn selected(&self) -> &BTreeSet<SelectedPrimitive> {
+        &self.selected
+    }
+
+    pub fn is_selected(&self, layer: usize, primitive: usize) -> bool {
+        self.selected
+            .contains(&SelectedPrimitive {
+                layer,
+                primitive,
+            })
+    }
+
+    /// The indices of the selected primitives of the layer, e.g. for [`crate::GerberRenderer::paint_highlight`].
+    pub fn primitives_of_layer(&self, layer: usize) -> Vec<usize> {
+        self.selected
+            .iter()
+            .filter(|selected| selected.layer == layer)
+            .map(|selected| selected.primitive)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.selected.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.selected.len()
+    }
+
+    /// Replaces the selection.
+    pub fn set(&mut self, primitives: impl IntoIterator<Item = SelectedPrimitive>) -> SelectionChange {
+        let selected: BTreeSet<_> = primitives.into_iter().collect();
+        let change = SelectionChange {
+            added: selected
+                .difference(&self.selected)
+                .copied()
+                .collect(),
+            removed: self
+                .selected
+                .difference(&selected)
+                .copied()
+                .collect(),
+        };
+        self.selected = selected;
+        self.notify(change)
+    }
+
+    /// Adds to the selection.
+    pub fn add(&mut self, primitives: impl IntoIterator<Item = SelectedPrimitive>) -> SelectionChange {
+        let added = primitives
+            .into_iter()
+            .filter(|primitive| self.selected.insert(*primitive))
+            .collect();
+        self.notify(SelectionChange {
+            added,
+            removed: vec![],
+        })
+    }
+
+    /// Removes from the selection.
+    pub fn remove(&mut self, primitives: impl IntoIterator<Item = SelectedPrimitive>) -> SelectionChange {
+        let removed = primitives
+            .into_iter()
+            .filter(|primitive| self.selected.remove(primitive))
+            .collect();
+        self.notify(SelectionChange {
+            added: vec![],
+            removed,
+        })
+    }
+
+    /// Selects the primitives that are not selected and deselects the ones that are, e.g. for ctrl-click.
+    pub fn toggle(&mut self, primitives: impl IntoIterator<Item = SelectedPrimitive>) -> SelectionChange {
+        let mut change = SelectionChange::default();
+        for primitive in primitives {
+            match self.selected.remove(&primitive) {
+                true => change.removed.push(primitive),
+                false => {
+                    self.selected.insert(primitive);
+                    change.added.push(primitive);
+                }
+            }
+        }
+        self.notify(change)
+    }
+
+    pub fn clear(&mut self) -> SelectionChange {
+        self.set([])
+    }
+
+    fn notify(&mut self, change: SelectionChange) -> SelectionChange {
+        if !change.is_empty() {
+            for (_, listener) in &mut self.listeners {
+                listener(&change, &self.selected);
+            }
+        }
+        change
+    }
+}
+
+fn intersects(rect: &BoundingBox, primitive: &GerberPrimitive) -> bool {
+    if rect.contains_box(&primitive.bounding_box()) {
+        return true;
+    }
+
+    match primitive {
+        GerberPrimitive::Circle(CircleGerberPrimitive {
+            center,
+            diameter,
+            ..
+        }) => distance_to_rect(*center, rect) <= diameter / 2.0,
+        GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+            origin,
+            width,
+            height,
+            ..
+        }) => rect.intersects(&BoundingBox {
+            min: *origin,
+            max: *origin + Vector2::new(*width, *height),
+        }),
+        GerberPrimitive::Line(LineGerberPrimitive {
+            start,
+            end,
+            width,
+            ..
+        }) => segment_distance_to_rect(*start, *end, rect) <= width / 2.0,
+        GerberPrimitive::Arc(arc) => {
+            let points = arc
+                .generate_points()
+                .into_iter()
+                .map(|point| arc.center + point.coords)
+                .collect::<Vec<_>>();
+            points
+                .windows(2)
+                .any(|segment| segment_distance_to_rect(segment[0], segment[1], rect) <= arc.width / 2.0)
+        }
+        GerberPrimitive::Polygon(PolygonGerberPrimitive {
+            center,
+            geometry,
+            ..
+        }) => {
+            // either an edge crosses the rectangle, or the rectangle is entirely inside the polygon
+            let vertices = &geometry.relative_vertices;
+            let relative_rect = BoundingBox {
+                min: rect.min - center.coords,
+                max: rect.max - center.coords,
+            };
+            let edge_intersects = (0..vertices.len()).any(|index| {
+                let end = vertices[(index + 1) % vertices.len()];
+                segment_distance_to_rect(vertices[index], end, &relative_rect) == 0.0
+            });
+            edge_intersects || primitive.distance_to(rect.min) == 0.0
+        }
+    }
+}
+
+fn distance_to_rect(point: Point2<f64>, rect: &BoundingBox) -> f64 {
+    let dx = (rect.min.x - point.x).max(point.x - rect.max.x);
+    let dy = (rect.min.y - point.y).max(point.y - rect.max.y);
+    Vector2::new(dx.max(0.0), dy.max(0.0)).norm()
+}
+
+/// Zero if the segment crosses or is inside the rectangle.
+fn segment_distance_to_rect(start: Point2<f64>, end: Point2<f64>, rect: &BoundingBox) -> f64 {
+    let corners = rect.vertices();
+    let crosses_edge = (0..4).any(|index| segments_intersect(start, end, corners[index], corners[(index + 1) % 4]));
+    if crosses_edge || distance_to_rect(start, rect) == 0.0 {
+        return 0.0;
+    }
+
+    let from_endpoints = distance_to_rect(start, rect).min(distance_to_rect(end, rect));
+    corners
+        .iter()
+        .map(|corner| distance_to_segment(*corner, start, end))
+        .fold(from_endpoints, f64::min)
+}
+
+fn segments_intersect(a: Point2<f64>, b: Point2<f64>, c: Point2<f64>, d: Point2<f64>) -> bool {
+    let cross = |o: Point2<f64>, p: Point2<f64>, q: Point2<f64>| (p - o).perp(&(q - o));
+    let (d1, d2) = (cross(c, d, a), cross(c, d, b));
+    let (d3, d4) = (cross(a, b, c), cross(a, b, d));
+
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::Exposure;
+
+    fn rect(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> BoundingBox {
+        BoundingBox {
+            min: Point2::new(min_x, min_y),
+            max: Point2::new(max_x, max_y),
+        }
+    }
+
+    #[rstest]
+    // crosses the middle of the line, but doesn't contain either end
+    #[case(rect(4.0, -1.0, 6.0, 1.0), true)]
+    // inside the bounding box of the sloped line, but not touching the line
+    #[case(rect(7.0, 0.5, 8.0, 0.6), false)]
+    fn test_line_intersects(#[case] rect: BoundingBox, #[case] expected: bool) {
+        // given
+        let line = GerberPrimitive::Line(LineGerberPrimitive {
+            start: Point2::new(0.0, 0.0),
+            end: Point2::new(10.0, 0.5),
+            width: 0.1,
+            exposure: Exposure::Add,
+        });
+
+        // expect
+        assert_eq!(intersects(&rect, &line), expected);
+    }
+
+    #[rstest]
+    #[case(rect(-2.0, -2.0, 2.0, 2.0), true)]
+    #[case(rect(-0.5, -0.5, 2.0, 2.0), false)]
+    fn test_contains(#[case] rect: BoundingBox, #[case] expected: bool) {
+        // given
+        let circle = GerberPrimitive::Circle(CircleGerberPrimitive {
+            center: Point2::new(0.0, 0.0),
+            diameter: 2.0,
+            exposure: Exposure::Add,
+        });
+
+        // expect
+        assert_eq!(rect.contains_box(&circle.bounding_box()), expected);
+    }
+
+    #[test]
+    fn test_selection_state() {
+        // given
+        let mut selection = SelectionState::default();
+        let changes = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let listener_changes = changes.clone();
+        let id = selection.on_change(move |change, _| {
+            listener_changes
+                .borrow_mut()
+                .push(change.clone())
+        });
+        let primitive = |layer: usize, primitive: usize| SelectedPrimitive {
+            layer,
+            primitive,
+        };
+
+        // when
+        selection.set([primitive(0, 1), primitive(1, 2)]);
+        selection.toggle([primitive(0, 1), primitive(0, 3)]);
+        // no change
+        selection.add([primitive(1, 2)]);
+
+        // then
+        assert_eq!(selection.primitives_of_layer(0), vec![3]);
+        assert!(selection.is_selected(1, 2));
+        assert_eq!(*changes.borrow(), vec![
+            SelectionChange {
+                added: vec![primitive(0, 1), primitive(1, 2)],
+                removed: vec![],
+            },
+            SelectionChange {
+                added: vec![primitive(0, 3)],
+                removed: vec![primitive(0, 1)],
+            },
+        ]);
+
+        // and
+        assert!(selection.remove_listener(id));
+        selection.clear();
+        assert_eq!(changes.borrow().len(), 2);
+    }
+}
@@ -0,0 +1,333 @@
+use nalgebra::{Matrix3, Point2, Vector2};
+use tiny_skia::{
+    Color, FillRule, LineCap, LineJoin, Mask, MaskType, Paint, PathBuilder, Pixmap, Rect, Stroke, Transform,
+};
+
+use crate::geometry::{split_contours, BoundingBox, GerberTransform};
+use crate::layer::{
+    CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGerberPrimitive,
+    RectangleGerberPrimitive,
+};
+use crate::raster::{ImageGeometry, RasterLayer};
+use crate::{Exposure, LayerInfo, LayerSet, NegativeArea, RasterError, RasterOptions};
+
+impl GerberLayer {
+    /// Rasterizes the layer to a tiny-skia pixmap, without egui, see [`GerberLayer::render_to_image`].
+    ///
+    /// Uses the same options, the same image size and the same geometry as [`GerberLayer::render_to_image`], lines and
+    /// arcs are stroked with round caps and polygons are filled using the even-odd rule.  The pixmap can be encoded,
+    /// e.g. using [`Pixmap::encode_png`], or drawn into other pixmaps.
+    pub fn render_to_pixmap(&self, options: &RasterOptions) -> Result<Pixmap, RasterError> {
+        let bounds = self
+            .try_bounding_box()
+            .ok_or(RasterError::Empty)?
+            .transform_vertices(&options.transform);
+
+        let geometry = ImageGeometry::new(&BoundingBox::from_points(&bounds), self.units(), options.resolution)?;
+        let mut pixmap = new_pixmap(&geometry, options)?;
+        draw_layer(
+            &mut pixmap,
+            &geometry,
+            self,
+            options.color,
+            &options.transform,
+            options.negative.as_ref(),
+            options.anti_aliasing,
+        );
+
+        Ok(pixmap)
+    }
+}
+
+impl LayerSet {
+    /// Rasterizes the layers to a tiny-skia pixmap, in drawing order, see [`LayerSet::render_to_image`].
+    pub fn render_to_pixmap(
+        &self,
+        options: &RasterOptions,
+        color: impl Fn(&LayerInfo) -> Option<[u8; 4]>,
+    ) -> Result<Pixmap, RasterError> {
+        let (layers, bounds) = self.raster_layers(&options.transform, color);
+        let first = layers
+            .first()
+            .ok_or(RasterError::Empty)?;
+
+        let geometry = ImageGeometry::new(&bounds, first.info.layer.units(), options.resolution)?;
+        let mut pixmap = new_pixmap(&geometry, options)?;
+        for RasterLayer {
+            info,
+            color,
+            transform,
+        } in layers
+        {
+            draw_layer(
+                &mut pixmap,
+                &geometry,
+                &info.layer,
+                color,
+                &transform,
+                None,
+                options.anti_aliasing,
+            );
+        }
+
+        Ok(pixmap)
+    }
+}
+
+fn new_pixmap(geometry: &ImageGeometry, options: &RasterOptions) -> Result<Pixmap, RasterError> {
+    let mut pixmap = Pixmap::new(geometry.width as u32, geometry.height as u32).ok_or(RasterError::InvalidSize {
+        width: geometry.width as u64,
+        height: geometry.height as u64,
+    })?;
+    let [red, green, blue, alpha] = options.background;
+    pixmap.fill(Color::from_rgba8(red, green, blue, alpha));
+
+    Ok(pixmap)
+}
+
+/// Draws the layer into an opaque mask, where clear primitives erase the dark primitives drawn before them, then fills
+/// the pixmap with the color through the mask, like [`GerberLayer::render_to_image`].
+fn draw_layer(
+    pixmap: &mut Pixmap,
+    geometry: &ImageGeometry,
+    layer: &GerberLayer,
+    color: [u8; 4],
+    transform: &GerberTransform,
+    negative: Option<&NegativeArea>,
+    anti_aliasing: bool,
+) {
+    let Some(mut coverage) = Pixmap::new(pixmap.width(), pixmap.height()) else {
+        return;
+    };
+    let transform = transform.resolve(layer.bounding_box());
+    let mut painter = PixmapPainter {
+        pixmap: &mut coverage,
+        to_pixels: geometry.to_pixels * transform.to_matrix(),
+        pixels_per_unit: geometry.pixels_per_unit * transform.scale.abs(),
+        anti_aliasing,
+    };
+
+    let zero = Vector2::new(0.0, 0.0);
+    if let Some(fill) = negative.and_then(|area| layer.negative_fill(area)) {
+        painter.draw_primitive(&fill, zero, Exposure::Add);
+    }
+    let exposure = |primitive: &GerberPrimitive| match negative {
+        Some(_) => primitive.exposure().inverted(),
+        None => primitive.exposure(),
+    };
+
+    let primitives = layer.gerber_primitives();
+    for (index, offset) in layer.primitives_in_draw_order() {
+        let primitive = &primitives[index];
+        painter.draw_primitive(primitive, offset, exposure(primitive));
+    }
+
+    let mask = Mask::from_pixmap(coverage.as_ref(), MaskType::Alpha);
+    let Some(rect) = Rect::from_xywh(0.0, 0.0, pixmap.width() as f32, pixmap.height() as f32) else {
+        return;
+    };
+    let mut paint = Paint::default();
+    let [red, green, blue, alpha] = color;
+    paint.set_color_rgba8(red, green, blue, alpha);
+    pixmap.fill_rect(rect, &paint, Transform::identity(), Some(&mask));
+}
+
+/// Draws primitives in opaque white, or erases them, the paths are built in pixel coordinates.
+struct PixmapPainter<'a> {
+    pixmap: &'a mut Pixmap,
+    /// Gerber coordinates, before the transform, to pixel coordinates.
+    to_pixels: Matrix3<f64>,
+    pixels_per_unit: f64,
+    anti_aliasing: bool,
+}
+
+impl PixmapPainter<'_> {
+    /// The exposure of the primitive is ignored, `exposure` is used instead.
+    fn draw_primitive(&mut self, primitive: &GerberPrimitive, offset: Vector2<f64>, exposure: Exposure) {
+        let mut path = PathBuilder::new();
+        match primitive {
+            GerberPrimitive::Circle(CircleGerberPrimitive {
+                center,
+                diameter,
+                ..
+            }) => {
+                let center = self.point(*center + offset);
+                path.push_circle(center.0, center.1, self.length(diameter / 2.0));
+                self.fill(path, exposure);
+            }
+            GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                origin,
+                width,
+                height,
+                ..
+            }) => {
+                let origin = *origin + offset;
+                let corners = [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)]
+                    .map(|(x, y)| origin + Vector2::new(x, y));
+                self.push_contour(&mut path, &corners);
+                self.fill(path, exposure);
+            }
+            GerberPrimitive::Line(LineGerberPrimitive {
+                start,
+                end,
+                width,
+                ..
+            }) => {
+                self.stroke(&[*start + offset, *end + offset], *width, exposure);
+            }
+            GerberPrimitive::Arc(arc) => {
+                let center = arc.center + offset;
+                let points = arc
+                    .generate_points()
+                    .into_iter()
+                    .map(|point| center + point.coords)
+                    .collect::<Vec<_>>();
+                self.stroke(&points, arc.width, exposure);
+            }
+            GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                center,
+                geometry,
+                ..
+            }) => {
+                let center = *center + offset;
+                for contour in split_contours(&geometry.relative_vertices) {
+                    let contour = contour
+                        .into_iter()
+                        .map(|vertex| center + vertex.coords)
+                        .collect::<Vec<_>>();
+                    self.push_contour(&mut path, &contour);
+                }
+                self.fill(path, exposure);
+            }
+        }
+    }
+
+    fn point(&self, point: Point2<f64>) -> (f32, f32) {
+        let point = self.to_pixels.transform_point(&point);
+        (point.x as f32, point.y as f32)
+    }
+
+    fn length(&self, length: f64) -> f32 {
+        (length * self.pixels_per_unit) as f32
+    }
+
+    fn push_contour(&self, path: &mut PathBuilder, contour: &[Point2<f64>]) {
+        let mut points = contour
+            .iter()
+            .map(|point| self.point(*point));
+        let Some((x, y)) = points.next() else {
+            return;
+        };
+        path.move_to(x, y);
+        for (x, y) in points {
+            path.line_to(x, y);
+        }
+        path.close();
+    }
+
+    fn paint(&self, exposure: Exposure) -> Paint<'static> {
+        let mut paint = Paint {
+            anti_alias: self.anti_aliasing,
+            ..Paint::default()
+        };
+        paint.set_color_rgba8(255, 255, 255, 255);
+        if exposure == Exposure::CutOut {
+            paint.blend_mode = tiny_skia::BlendMode::Clear;
+        }
+        paint
+    }
+
+    /// Fills the contours of the path using the even-odd rule.
+    fn fill(&mut self, path: PathBuilder, exposure: Exposure) {
+        let Some(path) = path.finish() else {
+            return;
+        };
+        let paint = self.paint(exposure);
+        self.pixmap
+            .fill_path(&path, &paint, FillRule::EvenOdd, Transform::identity(), None);
+    }
+
+    /// Strokes a polyline with round caps and joins, a single point is drawn as a circle.
+    fn stroke(&mut self, points: &[Point2<f64>], width: f64, exposure: Exposure) {
+        let Some(first) = points.first() else {
+            return;
+        };
+        if points
+            .iter()
+            .all(|point| point == first)
+        {
+            let mut path = PathBuilder::new();
+            let (x, y) = self.point(*first);
+            path.push_circle(x, y, self.length(width / 2.0));
+            self.fill(path, exposure);
+            return;
+        }
+
+        let mut path = PathBuilder::new();
+        let (x, y) = self.point(*first);
+        path.move_to(x, y);
+        for point in &points[1..] {
+            let (x, y) = self.point(*point);
+            path.line_to(x, y);
+        }
+        let Some(path) = path.finish() else {
+            return;
+        };
+
+        let stroke = Stroke {
+            width: self.length(width),
+            line_cap: LineCap::Round,
+            line_join: LineJoin::Round,
+            ..Stroke::default()
+        };
+        let paint = self.paint(exposure);
+        self.pixmap
+            .stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, Command, ExtendedCode, Polarity, Rectangular, Unit};
+
+    use super::*;
+    use crate::testing::{aperture, flash, header};
+    use crate::{Resolution, MILLIMETERS_PER_INCH};
+
+    #[test]
+    fn test_render_to_pixmap() {
+        // given
+        // a 10x10mm square with a clear 4mm circle in the center
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Rectangle(Rectangular::new(10.0, 10.0))));
+        commands.push(flash(5.0, 5.0));
+        commands.push(Command::ExtendedCode(ExtendedCode::LoadPolarity(Polarity::Clear)));
+        commands.extend(aperture(11, Aperture::Circle(Circle::new(4.0))));
+        commands.push(flash(5.0, 5.0));
+        let layer = GerberLayer::new(commands);
+        let options = RasterOptions {
+            // 1 pixel per mm
+            resolution: Resolution::Dpi(MILLIMETERS_PER_INCH),
+            color: [255, 0, 0, 255],
+            ..RasterOptions::default()
+        };
+
+        // when
+        let pixmap = layer
+            .render_to_pixmap(&options)
+            .unwrap();
+
+        // then
+        // the same size as `render_to_image`
+        let image = layer.render_to_image(&options).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (image.width, image.height));
+
+        let pixel = |x: u32, y: u32| {
+            let color = pixmap.pixel(x, y).unwrap().demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        };
+        assert_eq!(pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(9, 9), [255, 0, 0, 255]);
+        assert_eq!(pixel(5, 5), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use gerber_types::{Command, DCode, FunctionCode, Operation};
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::{split_contours, BoundingBox};
+use crate::hit_test::square_around;
+use crate::layer::{
+    CircleGerberPrimitive, GerberLayer, GerberPrimitive, LineGerberPrimitive, PolygonGerberPrimitive,
+    RectangleGerberPrimitive,
+};
+use crate::LayerSet;
+
+/// The kind of a [`SnapTarget`], in order of priority, when targets are the same distance from the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SnapKind {
+    /// The center of a flashed aperture, i.e. the flash position.
+    PadCenter,
+    /// The center of a circle, rectangle, arc or polygon that is not a pad.
+    Center,
+    /// The end of a line or an arc, the corner of a rectangle or the vertex of a polygon.
+    Vertex,
+    /// The middle of a line, an arc or an edge of a polygon.
+    Midpoint,
+    /// An intersection of the grid, see [`SnapSettings::grid`].
+    Grid,
+}
+
+/// A position the cursor snaps to, see [`GerberLayer::snap`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapTarget {
+    /// In gerber coordinates.
+    pub position: Point2<f64>,
+    pub kind: SnapKind,
+    /// The index of the primitive the target belongs to, `None` for grid intersections.
+    pub primitive: Option<usize>,
+}
+
+/// The grid used for [`SnapKind::Grid`] targets, in the units of the layer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapGrid {
+    pub spacing: f64,
+    /// A grid intersection, e.g. a datum, the grid is aligned to 0,0 by default.
+    pub origin: Point2<f64>,
+}
+
+impl SnapGrid {
+    pub fn new(spacing: f64) -> Self {
+        Self {
+            spacing,
+            origin: Point2::new(0.0, 0.0),
+        }
+    }
+
+    /// The nearest grid intersection, `None` if the spacing is not positive.
+    pub fn nearest(&self, point: Point2<f64>) -> Option<Point2<f64>> {
+        (self.spacing > 0.0).then(|| {
+            let cell = ((point - self.origin) / self.spacing).map(f64::round);
+            self.origin + cell * self.spacing
+        })
+    }
+}
+
+/// Options for [`GerberLayer::snap`], e.g. for measurement and placement tools.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapSettings {
+    /// Targets further than this from the cursor, in screen pixels, are ignored, `10.0` by default.
+    pub radius: f32,
+    /// `None` by default, no grid targets.
+    pub grid: Option<SnapGrid>,
+    pub pad_centers: bool,
+    pub centers: bool,
+    pub vertices: bool,
+    pub midpoints: bool,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            radius: 10.0,
+            grid: None,
+            pad_centers: true,
+            centers: true,
+            vertices: true,
+            midpoints: true,
+        }
+    }
+}
+
+impl SnapSettings {
+    fn enabled(&self, kind: SnapKind) -> bool {
+        match kind {
+            SnapKind::PadCenter => self.pad_centers,
+            SnapKind::Center => self.centers,
+            SnapKind::Vertex => self.vertices,
+            SnapKind::Midpoint => self.midpoints,
+            SnapKind::Grid => self.grid.is_some(),
+        }
+    }
+}
+
+impl GerberLayer {
+    /// Returns the snap target nearest to the point, within [`SnapSettings::radius`], e.g. the pad center under the
+    /// cursor.
+    ///
+    /// The point is in gerber coordinates, `scale` is the number of screen pixels per gerber unit, e.g.
+    /// `ViewState::scale`, so the radius stays the same on screen at any zoom.  Targets of primitives are preferred
+    /// over grid intersections, the grid is only used when no primitive target is in range.  Of targets at the same
+    /// distance, the kind listed first in [`SnapKind`] is returned.  Step-and-repeat copies are included.
+    pub fn snap(&self, point: Point2<f64>, scale: f32, settings: &SnapSettings) -> Option<SnapTarget> {
+        let radius = settings.radius as f64 / scale as f64;
+        if !radius.is_finite() {
+            return None;
+        }
+
+        self.snap_targets(point, radius, settings)
+            .into_iter()
+            .map(|target| ((target.position - point).norm(), target))
+            .filter(|(distance, _)| *distance <= radius)
+            .min_by(|(a, a_target), (b, b_target)| {
+                a.total_cmp(b)
+                    .then(a_target.kind.cmp(&b_target.kind))
+            })
+            .map(|(_, target)| target)
+            .or_else(|| snap_to_grid(point, radius, settings))
+    }
+
+    /// Returns the targets of the primitives near the point, candidates for [`GerberLayer::snap`], some may be further
+    /// than `radius` from the point.
+    pub fn snap_targets(&self, point: Point2<f64>, radius: f64, settings: &SnapSettings) -> Vec<SnapTarget> {
+        let mut targets = vec![];
+        let mut flashes = HashSet::new();
+        for index in self.query_region(&square_around(point, radius)) {
+            // one target for all the primitives of a flash, e.g. of a macro aperture
+            let (index, flash) = match self.flash_primitives(index) {
+                Some(primitives) => (primitives.start, Some(primitives)),
+                None => (index, None),
+            };
+
+            let mut add = |kind: SnapKind, position: Point2<f64>| {
+                if settings.enabled(kind) {
+                    targets.extend(
+                        self.primitive_offsets(index)
+                            .into_iter()
+                            .map(|offset| SnapTarget {
+                                position: position + offset,
+                                kind,
+                                primitive: Some(index),
+                            }),
+                    );
+                }
+            };
+
+            if let Some(primitives) = flash {
+                if flashes.insert(index) {
+                    let mut bbox = BoundingBox::default();
+                    for primitive in &self.gerber_primitives()[primitives] {
+                        bbox.expand(&primitive.bounding_box());
+                    }
+                    add(SnapKind::PadCenter, bbox.center());
+                }
+                continue;
+            }
+
+            match &self.gerber_primitives()[index] {
+                GerberPrimitive::Circle(CircleGerberPrimitive {
+                    center, ..
+                }) => add(SnapKind::Center, *center),
+                GerberPrimitive::Rectangle(RectangleGerberPrimitive {
+                    origin,
+                    width,
+                    height,
+                    ..
+                }) => {
+                    add(SnapKind::Center, *origin + Vector2::new(width / 2.0, height / 2.0));
+                    for (x, y) in [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)] {
+                        add(SnapKind::Vertex, *origin + Vector2::new(x, y));
+                    }
+                }
+                GerberPrimitive::Line(LineGerberPrimitive {
+                    start,
+                    end,
+                    ..
+                }) => {
+                    add(SnapKind::Vertex, *start);
+                    add(SnapKind::Vertex, *end);
+                    add(SnapKind::Midpoint, start + (end - start) / 2.0);
+                }
+                GerberPrimitive::Arc(arc) => {
+                    let point_at = |angle: f64| arc.center + Vector2::new(angle.cos(), angle.sin()) * arc.radius;
+                    add(SnapKind::Center, arc.center);
+                    if !arc.is_full_circle() {
+                        add(SnapKind::Vertex, point_at(arc.start_angle));
+                        add(SnapKind::Vertex, point_at(arc.start_angle + arc.sweep_angle));
+                        add(SnapKind::Midpoint, point_at(arc.start_angle + arc.sweep_angle / 2.0));
+                    }
+                }
+                GerberPrimitive::Polygon(PolygonGerberPrimitive {
+                    center,
+                    geometry,
+                    ..
+                }) => {
+                    add(SnapKind::Center, *center);
+                    for contour in split_contours(&geometry.relative_vertices) {
+                        for (vertex_index, vertex) in contour.iter().enumerate() {
+                            let next = contour[(vertex_index + 1) % contour.len()];
+                            add(SnapKind::Vertex, center + vertex.coords);
+                            add(SnapKind::Midpoint, center + (vertex.coords + next.coords) / 2.0);
+                        }
+                    }
+                }
+            }
+        }
+        targets
+    }
+
+    /// The primitives created by the same flash as the primitive, `None` if the primitive was not flashed, e.g. a line.
+    /// The flash position is the center of their bounding box, for standard apertures.
+    fn flash_primitives(&self, index: usize) -> Option<Range<usize>> {
+        match self.command_for_primitive(index)? {
+            Command::FunctionCode(FunctionCode::DCode(DCode::Operation(Operation::Flash(..)))) => self
+                .primitive_info(index)
+                .map(|info| info.command_primitives.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl LayerSet {
+    /// Returns the snap target nearest to the point, of all the layers, see [`GerberLayer::snap`].  The layers are
+    /// assumed to be in the same units and aligned.
+    pub fn snap(&self, point: Point2<f64>, scale: f32, settings: &SnapSettings) -> Option<SnapTarget> {
+        let object_settings = SnapSettings {
+            grid: None,
+            ..settings.clone()
+        };
+        self.iter()
+            .filter_map(|info| {
+                info.layer
+                    .snap(point, scale, &object_settings)
+            })
+            .min_by(|a, b| {
+                (a.position - point)
+                    .norm()
+                    .total_cmp(&(b.position - point).norm())
+                    .then(a.kind.cmp(&b.kind))
+            })
+            .or_else(|| snap_to_grid(point, settings.radius as f64 / scale as f64, settings))
+    }
+}
+
+fn snap_to_grid(point: Point2<f64>, radius: f64, settings: &SnapSettings) -> Option<SnapTarget> {
+    let position = settings.grid?.nearest(point)?;
+    ((position - point).norm() <= radius).then_some(SnapTarget {
+        position,
+        kind: SnapKind::Grid,
+        primitive: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use gerber_types::{Aperture, Circle, GCode, InterpolationMode, Unit};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::testing::{aperture, flash, header, interpolate, move_to};
+
+    #[rstest]
+    #[case(Point2::new(0.5, 0.5), Some((Point2::new(0.0, 0.0), SnapKind::PadCenter)))]
+    #[case(Point2::new(10.5, 0.5), Some((Point2::new(10.0, 0.0), SnapKind::Vertex)))]
+    #[case(Point2::new(15.5, 0.5), Some((Point2::new(15.0, 0.0), SnapKind::Midpoint)))]
+    // out of range of the line, snaps to the grid
+    #[case(Point2::new(14.5, 4.5), Some((Point2::new(15.0, 5.0), SnapKind::Grid)))]
+    #[case(Point2::new(12.5, 2.5), None)]
+    fn test_snap(#[case] point: Point2<f64>, #[case] expected: Option<(Point2<f64>, SnapKind)>) {
+        // given
+        // a pad at 0,0 and a line from 10,0 to 20,0
+        let mut commands = header(Unit::Millimeters);
+        commands.extend(aperture(10, Aperture::Circle(Circle::new(2.0))));
+        commands.extend([
+            Command::FunctionCode(FunctionCode::GCode(GCode::InterpolationMode(InterpolationMode::Linear))),
+            flash(0.0, 0.0),
+            move_to(10.0, 0.0),
+            interpolate(20.0, 0.0),
+        ]);
+        let layer = GerberLayer::new(commands);
+        let settings = SnapSettings {
+            grid: Some(SnapGrid::new(5.0)),
+            ..SnapSettings::default()
+        };
+
+        // when
+        // 10 pixels per mm, so the radius is 1mm
+        let target = layer.snap(point, 10.0, &settings);
+
+        // then
+        assert_eq!(target.map(|target| (target.position, target.kind)), expected);
+    }
+}
@@ -0,0 +1,165 @@
+use nalgebra::{Point2, Vector2};
+
+use crate::geometry::BoundingBox;
+
+/// The maximum number of cells in each direction, limits the memory used for layers with many primitives.
+const MAX_CELLS_PER_AXIS: usize = 1024;
+
+/// A uniform grid over the bounding boxes of the primitives of a layer, for fast region and point queries, e.g. for
+/// hit-testing and culling.
+///
+/// Each copy of a step-and-repeat primitive has its own entry, queries return the index of the primitive.
+#[derive(Debug, Clone)]
+pub struct SpatialIndex {
+    bounds: BoundingBox,
+    cell_size: Vector2<f64>,
+    columns: usize,
+    rows: usize,
+    /// indices into `entries`, one list per cell, row-major
+    cells: Vec<Vec<usize>>,
+    /// (primitive index, bounding box)
+    entries: Vec<(usize, BoundingBox)>,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self {
+            bounds: BoundingBox::default(),
+            cell_size: Vector2::new(0.0, 0.0),
+            columns: 0,
+            rows: 0,
+            cells: vec![],
+            entries: vec![],
+        }
+    }
+}
+
+impl SpatialIndex {
+    /// Builds the index from `(primitive index, bounding box)` pairs, empty bounding boxes are ignored.
+    pub fn new(entries: Vec<(usize, BoundingBox)>) -> Self {
+        let entries = entries
+            .into_iter()
+            .filter(|(_, bbox)| !bbox.is_empty())
+            .collect::<Vec<_>>();
+
+        let mut bounds = BoundingBox::default();
+        for (_, bbox) in &entries {
+            bounds.expand(bbox);
+        }
+        if entries.is_empty() {
+            return Self::default();
+        }
+
+        // approximately one entry per cell
+        let cells_per_axis = ((entries.len() as f64).sqrt().ceil() as usize).clamp(1, MAX_CELLS_PER_AXIS);
+        let columns = match bounds.width() > 0.0 {
+            true => cells_per_axis,
+            false => 1,
+        };
+        let rows = match bounds.height() > 0.0 {
+            true => cells_per_axis,
+            false => 1,
+        };
+        let cell_size = Vector2::new(bounds.width() / columns as f64, bounds.height() / rows as f64);
+
+        let mut index = Self {
+            bounds,
+            cell_size,
+            columns,
+            rows,
+            cells: vec![Vec::new(); columns * rows],
+            entries: vec![],
+        };
+
+        for (entry_index, (_, bbox)) in entries.iter().enumerate() {
+            let (columns, rows) = index.cell_ranges(bbox);
+            for row in rows {
+                for column in columns.clone() {
+                    index.cells[row * index.columns + column].push(entry_index);
+                }
+            }
+        }
+        index.entries = entries;
+
+        index
+    }
+
+    /// Returns the indices of the primitives whose bounding box intersects the region, sorted and without duplicates.
+    pub fn query_region(&self, region: &BoundingBox) -> Vec<usize> {
+        if self.entries.is_empty() || !self.bounds.intersects(region) {
+            return vec![];
+        }
+
+        let mut result = vec![];
+        let (columns, rows) = self.cell_ranges(region);
+        for row in rows {
+            for column in columns.clone() {
+                result.extend(
+                    self.cells[row * self.columns + column]
+                        .iter()
+                        .map(|entry_index| &self.entries[*entry_index])
+                        .filter(|(_, bbox)| bbox.intersects(region))
+                        .map(|(primitive_index, _)| *primitive_index),
+                );
+            }
+        }
+        result.sort_unstable();
+        result.dedup();
+
+        result
+    }
+
+    /// Returns the indices of the primitives whose bounding box contains the point, sorted and without duplicates.
+    pub fn query_point(&self, point: Point2<f64>) -> Vec<usize> {
+        self.query_region(&BoundingBox {
+            min: point,
+            max: point,
+        })
+    }
+
+    fn cell_ranges(&self, bbox: &BoundingBox) -> (std::ops::RangeInclusive<usize>, std::ops::RangeInclusive<usize>) {
+        let cell = |value: f64, min: f64, size: f64, count: usize| -> usize {
+            if size <= 0.0 {
+                return 0;
+            }
+            (((value - min) / size).floor().max(0.0) as usize).min(count - 1)
+        };
+
+        let columns = cell(bbox.min.x, self.bounds.min.x, self.cell_size.x, self.columns)
+            ..=cell(bbox.max.x, self.bounds.min.x, self.cell_size.x, self.columns);
+        let rows = cell(bbox.min.y, self.bounds.min.y, self.cell_size.y, self.rows)
+            ..=cell(bbox.max.y, self.bounds.min.y, self.cell_size.y, self.rows);
+
+        (columns, rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> BoundingBox {
+        BoundingBox {
+            min: Point2::new(min_x, min_y),
+            max: Point2::new(max_x, max_y),
+        }
+    }
+
+    #[test]
+    fn test_query_region_and_point() {
+        // given
+        let index = SpatialIndex::new(vec![
+            (0, bbox(0.0, 0.0, 1.0, 1.0)),
+            (1, bbox(5.0, 5.0, 6.0, 6.0)),
+            (2, bbox(0.5, 0.5, 5.5, 5.5)),
+            // a second copy of primitive 0
+            (0, bbox(9.0, 9.0, 10.0, 10.0)),
+        ]);
+
+        // expect
+        assert_eq!(index.query_point(Point2::new(0.75, 0.75)), vec![0, 2]);
+        assert_eq!(index.query_point(Point2::new(9.5, 9.5)), vec![0]);
+        assert_eq!(index.query_point(Point2::new(3.0, 8.0)), Vec::<usize>::new());
+        assert_eq!(index.query_region(&bbox(4.0, 4.0, 9.5, 9.5)), vec![0, 1, 2]);
+    }
+}
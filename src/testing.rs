@@ -1,6 +1,11 @@
 use std::io::BufWriter;
 
-use gerber_types::{Command, GerberCode};
+use gerber_types::{
+    Aperture, ApertureDefinition, Command, CoordinateFormat, CoordinateNumber, Coordinates, DCode, ExtendedCode,
+    FunctionCode, GerberCode, Operation, Unit,
+};
+
+use crate::GerberLayer;
 
 pub fn dump_gerber_source(commands: &Vec<Command>) {
     let gerber_source = gerber_commands_to_source(commands);
@@ -18,6 +23,56 @@ pub fn gerber_commands_to_source(commands: &Vec<Command>) -> String {
     gerber_source
 }
 
+/// The coordinate format (FS) and units (MO) of a layer, the coordinates of the other helpers use the same format.
+pub fn header(unit: Unit) -> Vec<Command> {
+    vec![
+        Command::ExtendedCode(ExtendedCode::CoordinateFormat(CoordinateFormat::new(2, 4))),
+        Command::ExtendedCode(ExtendedCode::Unit(unit)),
+    ]
+}
+
+/// Defines an aperture (AD) and selects it (Dnn).
+pub fn aperture(code: i32, aperture: Aperture) -> Vec<Command> {
+    vec![
+        Command::ExtendedCode(ExtendedCode::ApertureDefinition(ApertureDefinition::new(
+            code, aperture,
+        ))),
+        Command::FunctionCode(FunctionCode::DCode(DCode::SelectAperture(code))),
+    ]
+}
+
+pub fn operation(operation: fn(Coordinates) -> Operation, x: f64, y: f64) -> Command {
+    let coordinates = Coordinates::new(
+        CoordinateNumber::try_from(x).unwrap(),
+        CoordinateNumber::try_from(y).unwrap(),
+        CoordinateFormat::new(2, 4),
+    );
+    Command::FunctionCode(FunctionCode::DCode(DCode::Operation(operation(coordinates))))
+}
+
+/// D03
+pub fn flash(x: f64, y: f64) -> Command {
+    operation(Operation::Flash, x, y)
+}
+
+/// D02
+pub fn move_to(x: f64, y: f64) -> Command {
+    operation(Operation::Move, x, y)
+}
+
+/// D01, without an offset, i.e. a line in linear interpolation mode.
+pub fn interpolate(x: f64, y: f64) -> Command {
+    operation(|coordinates| Operation::Interpolate(coordinates, None), x, y)
+}
+
+/// A layer with a single flash of `aperture`, as D10, at `x`, `y`.
+pub fn flash_layer(unit: Unit, aperture: Aperture, x: f64, y: f64) -> GerberLayer {
+    let mut commands = header(unit);
+    commands.extend(self::aperture(10, aperture));
+    commands.push(flash(x, y));
+    GerberLayer::new(commands)
+}
+
 pub mod geometry {
     use std::f64::consts::PI;
 
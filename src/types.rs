@@ -1,3 +1,4 @@
+use gerber_types::Polarity;
 use nalgebra::Point2;
 
 pub(crate) enum Winding {
@@ -22,8 +23,10 @@ impl Winding {
     }
 }
 
+/// Whether a primitive adds to the image, or cuts out of it, e.g. a clear (LPC) object or a macro primitive with
+/// exposure off.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) enum Exposure {
+pub enum Exposure {
     CutOut,
     Add,
 }
@@ -36,3 +39,37 @@ impl From<bool> for Exposure {
         }
     }
 }
+
+impl Exposure {
+    /// Convert the current load polarity (LPD/LPC) into an exposure.
+    ///
+    /// `inverted` is used when replaying a block aperture that was flashed with clear polarity.
+    pub(crate) fn from_polarity(polarity: Polarity, inverted: bool) -> Self {
+        let dark = matches!(polarity, Polarity::Dark);
+        Exposure::from(dark ^ inverted)
+    }
+
+    /// The opposite exposure, e.g. for rendering a layer as a negative, see [`crate::NegativeArea`].
+    pub fn inverted(self) -> Self {
+        match self {
+            Exposure::Add => Exposure::CutOut,
+            Exposure::CutOut => Exposure::Add,
+        }
+    }
+}
+
+#[cfg(test)]
+mod polarity_tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(Polarity::Dark, false, Exposure::Add)]
+    #[case(Polarity::Clear, false, Exposure::CutOut)]
+    #[case(Polarity::Dark, true, Exposure::CutOut)]
+    #[case(Polarity::Clear, true, Exposure::Add)]
+    fn test_from_polarity(#[case] polarity: Polarity, #[case] inverted: bool, #[case] expected: Exposure) {
+        assert_eq!(Exposure::from_polarity(polarity, inverted), expected);
+    }
+}
@@ -1,10 +1,25 @@
-use egui::{Pos2, Rect, Response, Ui, Vec2};
+use egui::{
+    Color32, Key, LayerId, Modifiers, Order, PointerButton, Pos2, Rect, Response, Stroke, StrokeKind, Ui, Vec2,
+};
 use gerber_types::Unit;
 use log::trace;
-use nalgebra::Point2;
+use nalgebra::{Point2, Vector2};
 
-use crate::geometry::BoundingBox;
-use crate::{Invert, ToPos2};
+use crate::geometry::{BoundingBox, GerberTransform};
+use crate::{
+    GerberLayer, GridUnit, Invert, SelectedPrimitive, SelectionChange, SelectionMode, SelectionState, ToPos2,
+    MILLIMETERS_PER_INCH,
+};
+
+/// A reasonable duration for animated navigation, in seconds, e.g. the animation to the rectangle selected by a
+/// box-zoom, see [`UiState::animate_to`].
+pub const DEFAULT_ANIMATION_DURATION: f64 = 0.25;
+
+/// Selections smaller than this, in screen points, are ignored, e.g. accidental drags.
+const BOX_ZOOM_MIN_SIZE: f32 = 4.0;
+
+/// Primitives within this distance of a click, in screen points, are selected, e.g. thin traces.
+const SELECTION_TOLERANCE: f32 = 2.0;
 
 #[derive(Debug, Default)]
 pub struct UiState {
@@ -14,16 +29,259 @@ pub struct UiState {
 
     // only valid if the mouse is over the viewport
     pub cursor_gerber_coords: Option<Point2<f64>>,
+
+    /// How [`Self::cursor_readout`] formats the cursor position.
+    pub readout: CoordinateReadout,
+
+    /// How [`Self::handle_zooming`] zooms.
+    pub zoom_settings: ZoomSettings,
+
+    /// Which buttons [`Self::handle_panning`] pans with.
+    pub pan_settings: PanSettings,
+
+    /// Prevents panning and zooming the content off screen, `None` by default, see [`ViewState::clamp_to_bounds`].
+    pub pan_clamp: Option<PanClamp>,
+
+    /// The primitives selected by [`Self::handle_selection`], add listeners to react to changes.
+    pub selection: SelectionState,
+
+    /// Dragging with the primary mouse button measures instead of panning, see [`Self::handle_measuring`].
+    pub measure_mode: bool,
+
+    /// The current, or last, measurement, see [`Self::handle_measuring`].
+    pub measurement: Option<Measurement>,
+
+    /// The screen position where the box-zoom selection started, while selecting.
+    box_zoom_start: Option<Pos2>,
+    /// The screen position where the marquee selection started, while selecting.
+    marquee_start: Option<Pos2>,
+    /// True while dragging a measurement.
+    measuring: bool,
+    zoom_animation: Option<ZoomAnimation>,
+}
+
+/// Which buttons pan the view, see [`UiState::handle_panning`].  Applications with a selection tool usually disable the
+/// primary button, so that dragging with it selects, and pan with the middle button or space and the primary button.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PanSettings {
+    /// Dragging with the primary mouse button pans, enabled by default.
+    pub primary_button: bool,
+    /// Dragging with the middle mouse button pans, enabled by default.
+    pub middle_button: bool,
+    /// Dragging with the primary mouse button while holding space pans, enabled by default.
+    pub space_and_primary_button: bool,
+}
+
+impl Default for PanSettings {
+    fn default() -> Self {
+        Self {
+            primary_button: true,
+            middle_button: true,
+            space_and_primary_button: true,
+        }
+    }
+}
+
+/// What the scroll wheel does, see [`ZoomSettings::scroll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScrollBehavior {
+    /// The scroll wheel zooms.
+    #[default]
+    Zoom,
+    /// The scroll wheel pans, e.g. for touchpads, ctrl (cmd on macOS) and the scroll wheel, or pinch gestures, zoom.
+    ZoomWithCtrl,
+}
+
+/// How [`UiState::handle_zooming`] zooms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoomSettings {
+    /// The change of scale for each step of the scroll wheel, `1.1` by default.
+    pub factor: f32,
+    /// Scrolling up zooms out, instead of in.
+    pub invert_scroll: bool,
+    pub scroll: ScrollBehavior,
+    /// The smallest scale, relative to [`ViewState::base_scale`], i.e. the scale that fits the content, `0.01` by
+    /// default.
+    pub min_zoom: f32,
+    /// The largest scale, relative to [`ViewState::base_scale`], `10000.0` by default.
+    pub max_zoom: f32,
+}
+
+impl Default for ZoomSettings {
+    fn default() -> Self {
+        Self {
+            factor: 1.1,
+            invert_scroll: false,
+            scroll: ScrollBehavior::Zoom,
+            min_zoom: 0.01,
+            max_zoom: 10000.0,
+        }
+    }
+}
+
+impl ZoomSettings {
+    /// Limits the scale to the `min_zoom` and `max_zoom`.
+    ///
+    /// Unlike `f32::clamp` this does not panic, `max_zoom` wins if it is less than `min_zoom` and NaN limits are
+    /// ignored.
+    pub fn clamp_scale(&self, scale: f32, base_scale: f32) -> f32 {
+        scale
+            .max(base_scale * self.min_zoom)
+            .min(base_scale * self.max_zoom)
+    }
+}
+
+/// Keeps the content on screen, so it can't be lost by panning it away, see [`UiState::pan_clamp`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PanClamp {
+    /// The bounding box of the content, in gerber coordinates, e.g. of all the layers after their transform.
+    pub bounds: BoundingBox,
+    /// How much of the content, in screen points, stays visible along each axis.
+    pub margin: f32,
+}
+
+/// How a position is formatted for display, e.g. the cursor position in a status bar, see [`UiState::cursor_readout`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordinateReadout {
+    pub unit: GridUnit,
+    /// The units of the gerber coordinates, i.e. of the layers, see [`crate::GerberLayer::units`].
+    pub layer_units: Unit,
+    /// The number of decimal places, `None` for the default of the unit, 3 for millimeters, 1 for mils and 4 for
+    /// inches.
+    pub precision: Option<usize>,
+    /// Positions are shown relative to the datum, in gerber coordinates, e.g. a point picked by the user, `None` for
+    /// absolute positions.  [`UiState::update`] sets it to [`ViewState::datum`].
+    pub datum: Option<Point2<f64>>,
+}
+
+impl Default for CoordinateReadout {
+    fn default() -> Self {
+        Self {
+            unit: GridUnit::Millimeters,
+            layer_units: Unit::Millimeters,
+            precision: None,
+            datum: None,
+        }
+    }
+}
+
+impl CoordinateReadout {
+    /// The position in the `unit`, relative to the datum, if any.
+    pub fn convert(&self, position: Point2<f64>) -> Point2<f64> {
+        let relative = match self.datum {
+            Some(datum) => Point2::from(position - datum),
+            None => position,
+        };
+        relative
+            / self
+                .unit
+                .layer_units_per_unit(self.layer_units)
+    }
+
+    /// Formats the position, e.g. `X: 1.250 mm, Y: -3.000 mm`, or `dX: 1.250 mm, dY: -3.000 mm` relative to the datum.
+    pub fn format(&self, position: Point2<f64>) -> String {
+        let (x, y) = self.format_axes(position);
+        format!("{x}, {y}")
+    }
+
+    /// Formats each coordinate of the position, e.g. `X: 1.250 mm` and `Y: -3.000 mm`, see [`Self::format`].
+    pub fn format_axes(&self, position: Point2<f64>) -> (String, String) {
+        let converted = self.convert(position);
+        let precision = self.precision();
+        let prefix = match self.datum {
+            Some(_) => "d",
+            None => "",
+        };
+        let symbol = self.unit.symbol();
+
+        (
+            format!("{prefix}X: {x:.precision$} {symbol}", x = converted.x),
+            format!("{prefix}Y: {y:.precision$} {symbol}", y = converted.y),
+        )
+    }
+
+    /// Formats a distance, in the units of the layers, e.g. `12.500 mm`, the datum is not used.
+    pub fn format_length(&self, length: f64) -> String {
+        let length = length
+            / self
+                .unit
+                .layer_units_per_unit(self.layer_units);
+        format!(
+            "{length:.precision$} {symbol}",
+            precision = self.precision(),
+            symbol = self.unit.symbol()
+        )
+    }
+
+    fn precision(&self) -> usize {
+        self.precision
+            .unwrap_or(match self.unit {
+                GridUnit::Millimeters => 3,
+                GridUnit::Mils => 1,
+                GridUnit::Inches => 4,
+            })
+    }
+}
+
+/// A measurement between two points, see [`UiState::handle_measuring`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    /// In gerber coordinates.
+    pub start: Point2<f64>,
+    /// In gerber coordinates.
+    pub end: Point2<f64>,
+}
+
+impl Measurement {
+    /// In the units of the layers.
+    pub fn length(&self) -> f64 {
+        (self.end - self.start).norm()
+    }
+
+    /// In degrees, counter-clockwise from the positive x axis, from `-180` to `180`.
+    pub fn angle(&self) -> f64 {
+        let delta = self.end - self.start;
+        delta.y.atan2(delta.x).to_degrees()
+    }
+
+    /// Formats the length, the x and y distances and the angle, e.g. `L: 5.000 mm, dX: 3.000 mm, dY: 4.000 mm,
+    /// A: 53.13°`, using the unit and precision of the readout.
+    pub fn format(&self, readout: &CoordinateReadout) -> String {
+        let delta = self.end - self.start;
+        format!(
+            "L: {}, dX: {}, dY: {}, A: {:.2}°",
+            readout.format_length(self.length()),
+            readout.format_length(delta.x),
+            readout.format_length(delta.y),
+            self.angle()
+        )
+    }
+}
+
+/// An animation of the view, from one view to another, see [`UiState::animate_to`].
+#[derive(Debug, Clone, Copy)]
+struct ZoomAnimation {
+    from: ViewState,
+    to: ViewState,
+    /// The screen position that stays at the center of the viewport, the center of the viewport when it started.
+    center: Pos2,
+    start_time: f64,
+    /// In seconds.
+    duration: f64,
 }
 
 impl UiState {
     pub fn update(&mut self, ui: &Ui, viewport: &Rect, response: &Response, view_state: &mut ViewState) {
+        self.update_animation(view_state, ui);
         self.update_cursor_position(view_state, &response, ui);
+        self.handle_box_zoom(view_state, viewport, response, ui);
+        self.handle_measuring(view_state, response, ui);
         self.handle_panning(view_state, &response, ui);
         self.handle_zooming(view_state, &response, ui);
 
         self.center_screen_pos = viewport.center();
         self.origin_screen_pos = view_state.gerber_to_screen_coords(Point2::new(0.0, 0.0));
+        self.readout.datum = view_state.datum;
 
         trace!(
             "update. view_state: {:?}, viewport: {:?}, cursor_gerber_coords: {:?}",
@@ -45,12 +303,408 @@ impl UiState {
         }
     }
 
+    /// Pans the view by dragging, with the buttons of [`Self::pan_settings`].
     pub fn handle_panning(&mut self, view_state: &mut ViewState, response: &Response, ui: &Ui) {
-        if response.dragged_by(egui::PointerButton::Primary) {
+        if self.box_zoom_start.is_some() || self.marquee_start.is_some() || self.measuring {
+            return;
+        }
+        let settings = &self.pan_settings;
+        let space = settings.space_and_primary_button && ui.input(|i| i.key_down(Key::Space));
+        let panning = (settings.primary_button || space) && response.dragged_by(PointerButton::Primary)
+            || settings.middle_button && response.dragged_by(PointerButton::Middle);
+
+        if panning {
             let delta = response.drag_delta();
             view_state.translation += delta;
+            self.zoom_animation = None;
             ui.ctx().clear_animations();
+            self.apply_pan_clamp(view_state, &response.rect);
+        }
+    }
+
+    /// Moves the view so the content is on screen, if [`Self::pan_clamp`] is set, called after panning and zooming.
+    pub fn apply_pan_clamp(&self, view_state: &mut ViewState, viewport: &Rect) {
+        if let Some(clamp) = &self.pan_clamp {
+            view_state.clamp_to_bounds(*viewport, &clamp.bounds, clamp.margin);
+        }
+    }
+
+    /// Advances the animation started by [`Self::animate_to`], or by a box-zoom, call this each frame before using
+    /// the view, [`Self::update`] does this.
+    pub fn update_animation(&mut self, view_state: &mut ViewState, ui: &Ui) {
+        let Some(animation) = self.zoom_animation else {
+            return;
+        };
+        let t = (ui.input(|i| i.time) - animation.start_time) / animation.duration;
+        // the datum is not animated, it may be changed during the animation
+        let datum = view_state.datum;
+        if t >= 1.0 || !t.is_finite() {
+            *view_state = ViewState {
+                datum,
+                ..animation.to
+            };
+            self.zoom_animation = None;
+        } else {
+            *view_state = ViewState {
+                datum,
+                ..animation.interpolate(t.max(0.0) as f32)
+            };
+            ui.ctx().request_repaint();
+        }
+    }
+
+    /// Animates the view from `view_state` to `target` over `duration` seconds, instead of jumping to it, e.g. after
+    /// fitting the view, going to a zoom preset or to a position.  The view is changed by [`Self::update_animation`].
+    /// Panning, zooming or starting another animation stops the animation.
+    ///
+    /// The gerber position at the center of the viewport moves in a straight line, the scale changes geometrically.
+    ///
+    /// A `duration` of zero, or less, changes the view immediately.
+    pub fn animate_to(
+        &mut self,
+        view_state: &mut ViewState,
+        target: ViewState,
+        viewport: &Rect,
+        duration: f64,
+        ui: &Ui,
+    ) {
+        if duration <= 0.0 {
+            *view_state = target;
+            self.zoom_animation = None;
+            return;
+        }
+        self.zoom_animation = Some(ZoomAnimation {
+            from: *view_state,
+            to: target,
+            center: viewport.center(),
+            start_time: ui.input(|i| i.time),
+            duration,
+        });
+        ui.ctx().request_repaint();
+    }
+
+    /// Animates [`ViewState::fit_view`], see [`Self::animate_to`].
+    pub fn animate_fit_view(
+        &mut self,
+        view_state: &mut ViewState,
+        viewport: &Rect,
+        bbox: &BoundingBox,
+        initial_zoom_factor: f32,
+        duration: f64,
+        ui: &Ui,
+    ) {
+        let mut target = *view_state;
+        target.fit_view(*viewport, bbox, initial_zoom_factor);
+        self.animate_to(view_state, target, viewport, duration, ui);
+    }
+
+    /// Animates [`ViewState::zoom_to`], e.g. for zoom presets, keeping the gerber position at the center
+    /// of the viewport, see [`Self::animate_to`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn animate_zoom_level_percent(
+        &mut self,
+        view_state: &mut ViewState,
+        viewport: &Rect,
+        zoom_level: f32,
+        units: Unit,
+        display_info: &DisplayInfo,
+        duration: f64,
+        ui: &Ui,
+    ) {
+        let mut target = *view_state;
+        target.zoom_to(zoom_level, viewport.center(), units, display_info);
+        self.animate_to(view_state, target, viewport, duration, ui);
+    }
+
+    /// Animates the view so that `position`, in gerber coordinates, is at the center of the viewport, without
+    /// changing the scale, see [`Self::animate_to`].
+    pub fn animate_goto(
+        &mut self,
+        view_state: &mut ViewState,
+        viewport: &Rect,
+        position: Point2<f64>,
+        duration: f64,
+        ui: &Ui,
+    ) {
+        let mut target = *view_state;
+        target.translation += viewport.center() - view_state.gerber_to_screen_coords(position);
+        self.animate_to(view_state, target, viewport, duration, ui);
+    }
+
+    /// Zooms to a rectangle, selected by dragging with the secondary mouse button, or with the primary mouse button
+    /// while holding shift.  The view is animated to fit the rectangle, panning or zooming stops the animation.
+    ///
+    /// The selection is drawn on top of the layers while dragging.
+    pub fn handle_box_zoom(&mut self, view_state: &mut ViewState, viewport: &Rect, response: &Response, ui: &Ui) {
+        let shift = ui.input(|i| {
+            i.modifiers
+                .matches_exact(Modifiers::SHIFT)
+        });
+        if response.drag_started_by(PointerButton::Secondary)
+            || shift && response.drag_started_by(PointerButton::Primary)
+        {
+            self.box_zoom_start = ui.input(|i| i.pointer.press_origin());
+        }
+
+        let Some(start) = self.box_zoom_start else {
+            return;
+        };
+        let Some(current) = response
+            .interact_pointer_pos()
+            .or_else(|| ui.input(|i| i.pointer.interact_pos()))
+        else {
+            self.box_zoom_start = None;
+            return;
+        };
+        let selection = Rect::from_two_pos(start, current).intersect(*viewport);
+
+        if !response.drag_stopped() {
+            // the layers are usually painted after this, so use a layer on top of them
+            let painter = ui
+                .painter_at(*viewport)
+                .with_layer_id(LayerId::new(Order::Foreground, response.id.with("box_zoom")));
+            painter.rect(
+                selection,
+                0.0,
+                Color32::from_white_alpha(16),
+                Stroke::new(1.0, Color32::WHITE),
+                StrokeKind::Inside,
+            );
+            return;
+        }
+
+        self.box_zoom_start = None;
+        if selection.width() < BOX_ZOOM_MIN_SIZE || selection.height() < BOX_ZOOM_MIN_SIZE {
+            return;
+        }
+
+        let bbox = BoundingBox::from_points(&[
+            view_state.screen_to_gerber_coords(selection.min),
+            view_state.screen_to_gerber_coords(selection.max),
+        ]);
+        let mut target = *view_state;
+        target.scale = self.zoom_settings.clamp_scale(
+            f32::min(
+                viewport.width() / bbox.width() as f32,
+                viewport.height() / bbox.height() as f32,
+            ),
+            view_state.base_scale,
+        );
+        target.center_view(*viewport, &bbox);
+
+        self.animate_to(view_state, target, viewport, DEFAULT_ANIMATION_DURATION, ui);
+    }
+
+    /// Selects primitives of the layers by clicking, and by dragging a marquee with the primary mouse button, the
+    /// selection is kept in [`Self::selection`].  Returns the change, empty if the selection did not change.
+    ///
+    /// Clicking selects the top-most primitive under the cursor, of the top-most layer, the layers are in drawing order,
+    /// or clears the selection if there is none.  Dragging left-to-right selects the primitives inside the marquee,
+    /// right-to-left the primitives touching it.  Holding ctrl, or cmd on macOS, toggles the clicked primitive, or adds
+    /// the primitives in the marquee, instead of replacing the selection.
+    ///
+    /// The marquee is only available when panning with the primary button is disabled, see [`PanSettings`].  The layers
+    /// use the same transform, as when painting them, with a rotated transform the marquee selects the primitives in
+    /// the bounding box of the rotated marquee.  For layers with their own transform use
+    /// [`Self::handle_layer_selection`].
+    pub fn handle_selection(
+        &mut self,
+        view_state: &ViewState,
+        layers: &[&GerberLayer],
+        transform: &GerberTransform,
+        response: &Response,
+        ui: &Ui,
+    ) -> SelectionChange {
+        let layers = layers
+            .iter()
+            .map(|layer| (*layer, *transform))
+            .collect::<Vec<_>>();
+        self.handle_layer_selection(view_state, &layers, response, ui)
+    }
+
+    /// Like [`Self::handle_selection`], with a transform for each layer, e.g. [`crate::LayerInfo::combined_transform`].
+    pub fn handle_layer_selection(
+        &mut self,
+        view_state: &ViewState,
+        layers: &[(&GerberLayer, GerberTransform)],
+        response: &Response,
+        ui: &Ui,
+    ) -> SelectionChange {
+        let (command, shift, space) = ui.input(|i| (i.modifiers.command, i.modifiers.shift, i.key_down(Key::Space)));
+        // gerber coordinates to layer coordinates, for each layer
+        let inverses = layers
+            .iter()
+            .map(|(layer, transform)| {
+                transform
+                    .resolve(layer.bounding_box())
+                    .to_matrix()
+                    .try_inverse()
+            })
+            .collect::<Vec<_>>();
+
+        if response.clicked_by(PointerButton::Primary) && !shift {
+            let Some(position) = response.interact_pointer_pos() else {
+                return SelectionChange::default();
+            };
+            let point = view_state.screen_to_gerber_coords(position);
+            let hit = layers
+                .iter()
+                .zip(&inverses)
+                .enumerate()
+                .rev()
+                .find_map(|(index, ((layer, _), inverse))| {
+                    let inverse = inverse.as_ref()?;
+                    // the transform may also scale the layer
+                    let tolerance = inverse
+                        .transform_vector(&Vector2::new((SELECTION_TOLERANCE / view_state.scale) as f64, 0.0))
+                        .norm();
+                    let hit = layer.hovered_primitive(inverse.transform_point(&point), tolerance)?;
+                    Some(SelectedPrimitive {
+                        layer: index,
+                        primitive: hit.index,
+                    })
+                });
+
+            return match (hit, command) {
+                (Some(hit), true) => self.selection.toggle([hit]),
+                (Some(hit), false) => self.selection.set([hit]),
+                (None, true) => SelectionChange::default(),
+                (None, false) => self.selection.clear(),
+            };
+        }
+
+        if !self.pan_settings.primary_button
+            && !shift
+            && !space
+            && self.box_zoom_start.is_none()
+            && response.drag_started_by(PointerButton::Primary)
+        {
+            self.marquee_start = ui.input(|i| i.pointer.press_origin());
+        }
+
+        let Some(start) = self.marquee_start else {
+            return SelectionChange::default();
+        };
+        let Some(current) = response
+            .interact_pointer_pos()
+            .or_else(|| ui.input(|i| i.pointer.interact_pos()))
+        else {
+            self.marquee_start = None;
+            return SelectionChange::default();
+        };
+        let marquee = Rect::from_two_pos(start, current).intersect(response.rect);
+        let mode = match current.x >= start.x {
+            true => SelectionMode::Contained,
+            false => SelectionMode::Intersecting,
+        };
+
+        if !response.drag_stopped() {
+            // like CAD tools, a solid outline for contained, a green one for intersecting
+            let color = match mode {
+                SelectionMode::Contained => Color32::from_rgb(80, 160, 255),
+                SelectionMode::Intersecting => Color32::from_rgb(80, 220, 120),
+            };
+            let painter = ui
+                .painter_at(response.rect)
+                .with_layer_id(LayerId::new(Order::Foreground, response.id.with("marquee")));
+            painter.rect(
+                marquee,
+                0.0,
+                color.gamma_multiply(0.1),
+                Stroke::new(1.0, color),
+                StrokeKind::Inside,
+            );
+            return SelectionChange::default();
+        }
+
+        self.marquee_start = None;
+        if marquee.width() < BOX_ZOOM_MIN_SIZE && marquee.height() < BOX_ZOOM_MIN_SIZE {
+            return SelectionChange::default();
+        }
+
+        let corners = [
+            marquee.left_top(),
+            marquee.right_top(),
+            marquee.right_bottom(),
+            marquee.left_bottom(),
+        ]
+        .map(|corner| view_state.screen_to_gerber_coords(corner));
+        let mut selected = vec![];
+        for (index, ((layer, _), inverse)) in layers.iter().zip(&inverses).enumerate() {
+            let Some(inverse) = inverse else {
+                continue;
+            };
+            let rect = BoundingBox::from_points(&corners.map(|corner| inverse.transform_point(&corner)));
+            selected.extend(
+                layer
+                    .primitives_in_rect(&rect, mode)
+                    .into_iter()
+                    .map(|primitive| SelectedPrimitive {
+                        layer: index,
+                        primitive,
+                    }),
+            );
+        }
+
+        match command {
+            true => self.selection.add(selected),
+            false => self.selection.set(selected),
+        }
+    }
+
+    /// The cursor position formatted using [`Self::readout`], `None` if the cursor is not over the viewport.
+    pub fn cursor_readout(&self) -> Option<String> {
+        self.cursor_gerber_coords
+            .map(|position| self.readout.format(position))
+    }
+
+    /// Measures by dragging with the primary mouse button while [`Self::measure_mode`] is set, instead of panning, see
+    /// [`PanSettings`] for the other buttons.  Returns the measurement while dragging, to show it live, e.g. using
+    /// [`crate::draw_measurement`].
+    ///
+    /// The last measurement is kept in [`Self::measurement`] after the drag stops, until the next one starts or the
+    /// viewport is clicked.  Dragging while holding space still pans.
+    pub fn handle_measuring(&mut self, view_state: &ViewState, response: &Response, ui: &Ui) -> Option<Measurement> {
+        if !self.measure_mode {
+            self.measuring = false;
+            return None;
         }
+
+        let space = ui.input(|i| i.key_down(Key::Space));
+        if response.clicked_by(PointerButton::Primary) {
+            self.measurement = None;
+        }
+        if response.drag_started_by(PointerButton::Primary) && !space && self.box_zoom_start.is_none() {
+            if let Some(origin) = ui.input(|i| i.pointer.press_origin()) {
+                let start = view_state.screen_to_gerber_coords(origin);
+                self.measurement = Some(Measurement {
+                    start,
+                    end: start,
+                });
+                self.measuring = true;
+            }
+        }
+        if !self.measuring {
+            return None;
+        }
+
+        if let (Some(measurement), Some(position)) = (&mut self.measurement, response.interact_pointer_pos()) {
+            measurement.end = view_state.screen_to_gerber_coords(position);
+        }
+        if response.drag_stopped() || !response.dragged() {
+            self.measuring = false;
+        }
+        self.measurement
+    }
+
+    /// True while a measurement is being dragged, see [`Self::handle_measuring`].
+    pub fn is_measuring(&self) -> bool {
+        self.measuring
+    }
+
+    /// True while the view is being animated, e.g. after a box-zoom or [`Self::animate_to`].
+    pub fn is_animating(&self) -> bool {
+        self.zoom_animation.is_some()
     }
 
     pub fn handle_zooming(&mut self, view_state: &mut ViewState, response: &Response, ui: &Ui) {
@@ -59,32 +713,79 @@ impl UiState {
             return;
         }
 
-        let zoom_factor = 1.1;
-        let scroll_delta = ui.input(|i| i.raw_scroll_delta.y);
+        let ZoomSettings {
+            factor,
+            invert_scroll,
+            scroll,
+            ..
+        } = self.zoom_settings;
+        let (scroll_delta, zoom_delta) = ui.input(|i| (i.raw_scroll_delta, i.zoom_delta()));
+
+        let zoom = match scroll {
+            ScrollBehavior::Zoom if scroll_delta.y > 0.0 => factor,
+            ScrollBehavior::Zoom if scroll_delta.y < 0.0 => 1.0 / factor,
+            ScrollBehavior::Zoom => 1.0,
+            ScrollBehavior::ZoomWithCtrl => {
+                if scroll_delta != Vec2::ZERO {
+                    self.zoom_animation = None;
+                    view_state.translation += scroll_delta;
+                    self.apply_pan_clamp(view_state, &response.rect);
+                }
+                zoom_delta
+            }
+        };
+        let zoom = match invert_scroll {
+            true => 1.0 / zoom,
+            false => zoom,
+        };
 
-        if scroll_delta != 0.0 {
+        if zoom != 1.0 {
+            self.zoom_animation = None;
             let old_scale = view_state.scale;
-            let new_scale = if scroll_delta > 0.0 {
-                old_scale * zoom_factor
-            } else {
-                old_scale / zoom_factor
-            };
+            let new_scale = self
+                .zoom_settings
+                .clamp_scale(old_scale * zoom, view_state.base_scale);
 
+            view_state.scale = new_scale;
             if let Some(hover_pos) = response.hover_pos() {
-                let mouse_world = (hover_pos - view_state.translation) / old_scale;
-                view_state.translation = hover_pos - mouse_world * new_scale;
+                view_state.zoom_around(hover_pos, old_scale);
             }
-
-            view_state.scale = new_scale;
+            self.apply_pan_clamp(view_state, &response.rect);
         }
     }
 }
 
+impl ZoomAnimation {
+    /// The scale changes geometrically, and the gerber position at the center of the viewport moves linearly, so the
+    /// zoom looks steady.
+    fn interpolate(&self, t: f32) -> ViewState {
+        let scale = self.from.scale * (self.to.scale / self.from.scale).powf(t);
+        let from_center = self
+            .from
+            .screen_to_gerber_coords(self.center);
+        let to_center = self
+            .to
+            .screen_to_gerber_coords(self.center);
+        let center = from_center + (to_center - from_center) * t as f64;
+
+        let mut view = ViewState {
+            scale,
+            ..self.to
+        };
+        view.translation = self.center - view.gerber_to_screen_coords(center) + view.translation;
+        view
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ViewState {
     pub translation: Vec2,
     pub scale: f32,
     pub base_scale: f32, // Scale that represents 100% zoom
+    /// A reference point, in gerber coordinates, e.g. picked by the user, that positions are shown relative to,
+    /// [`UiState::update`] copies it to [`CoordinateReadout::datum`], see [`crate::MarkerStyle::datum`] for drawing it.
+    pub datum: Option<Point2<f64>>,
 }
 
 impl Default for ViewState {
@@ -93,6 +794,7 @@ impl Default for ViewState {
             translation: Vec2::ZERO,
             scale: 1.0,
             base_scale: 1.0,
+            datum: None,
         }
     }
 }
@@ -140,6 +842,40 @@ impl ViewState {
         self.center_view(viewport, bbox);
     }
 
+    /// Adjusts the translation after the scale was changed from `old_scale`, so that the gerber position at
+    /// `screen_pos` stays there, e.g. the position under the cursor when zooming.
+    pub fn zoom_around(&mut self, screen_pos: Pos2, old_scale: f32) {
+        let gerber_pos = (screen_pos - self.translation) / old_scale;
+        self.translation = screen_pos - gerber_pos * self.scale;
+    }
+
+    /// Moves the view so that at least `margin` screen points of the content, along each axis, are in the viewport,
+    /// or all of it, if the content is smaller.  The scale is not changed.
+    pub fn clamp_to_bounds(&mut self, viewport: Rect, bbox: &BoundingBox, margin: f32) {
+        if bbox.is_empty() {
+            return;
+        }
+        let content = Rect::from_two_pos(
+            self.gerber_to_screen_coords(bbox.min),
+            self.gerber_to_screen_coords(bbox.max),
+        );
+
+        let offset = |content_min: f32, content_max: f32, viewport_min: f32, viewport_max: f32| {
+            let margin = margin.min(content_max - content_min);
+            if content_max < viewport_min + margin {
+                viewport_min + margin - content_max
+            } else if content_min > viewport_max - margin {
+                viewport_max - margin - content_min
+            } else {
+                0.0
+            }
+        };
+        self.translation += Vec2::new(
+            offset(content.min.x, content.max.x, viewport.min.x, viewport.max.x),
+            offset(content.min.y, content.max.y, viewport.min.y, viewport.max.y),
+        );
+    }
+
     pub fn center_view(&mut self, viewport: Rect, bbox: &BoundingBox) {
         let center = bbox.center();
 
@@ -150,14 +886,7 @@ impl ViewState {
     }
 
     pub fn zoom_level_percent(&self, units: Unit, display_info: &DisplayInfo) -> f32 {
-        // Get effective pixels per inch
-        let device_ppi = display_info.effective_ppi();
-
-        // Calculate what 100% zoom should be (reference scale)
-        let reference_scale = match units {
-            Unit::Millimeters => device_ppi / 25.4, // Convert to pixels per mm
-            Unit::Inches => device_ppi,             // pixels per inch
-        };
+        let reference_scale = reference_scale(units, display_info);
 
         // Calculate zoom percentage
         let zoom_level = (self.scale / reference_scale) * 100.0;
@@ -172,14 +901,7 @@ impl ViewState {
     }
 
     pub fn set_zoom_level_percent(&mut self, zoom_level: f32, units: Unit, display_info: &DisplayInfo) -> f32 {
-        // Get effective pixels per inch
-        let device_ppi = display_info.effective_ppi();
-
-        // Calculate the reference scale for 100% zoom
-        let reference_scale = match units {
-            Unit::Millimeters => device_ppi / 25.4, // Convert to pixels per mm
-            Unit::Inches => device_ppi,             // pixels per inch
-        };
+        let reference_scale = reference_scale(units, display_info);
 
         // Set the scale based on the desired zoom percentage
         self.scale = reference_scale * (zoom_level / 100.0);
@@ -188,10 +910,90 @@ impl ViewState {
         // Return the actual zoom level (might be different due to rounding)
         self.zoom_level_percent(units, display_info)
     }
+
+    /// Like [`Self::set_zoom_level_percent`], but keeps the gerber position at `anchor`, a screen position, e.g. the
+    /// center of the viewport or the cursor, where it is.
+    pub fn zoom_to(&mut self, zoom_level: f32, anchor: Pos2, units: Unit, display_info: &DisplayInfo) -> f32 {
+        let old_scale = self.scale;
+        let zoom_level = self.set_zoom_level_percent(zoom_level, units, display_info);
+        self.zoom_around(anchor, old_scale);
+        zoom_level
+    }
+
+    /// Zooms to a preset, see [`ZoomPreset`].  The percentages are anchored at `anchor`, see [`Self::zoom_to`],
+    /// [`ZoomPreset::Fit`] and [`ZoomPreset::Fill`] center the content, the bounding box of the layers to view.
+    pub fn apply_zoom_preset(
+        &mut self,
+        preset: ZoomPreset,
+        viewport: Rect,
+        bbox: &BoundingBox,
+        anchor: Pos2,
+        units: Unit,
+        display_info: &DisplayInfo,
+    ) {
+        match preset {
+            ZoomPreset::Percent(zoom_level) => {
+                self.zoom_to(zoom_level, anchor, units, display_info);
+            }
+            ZoomPreset::Fit => self.fit_view(viewport, bbox, 1.0),
+            ZoomPreset::Fill => {
+                self.scale = f32::max(
+                    viewport.width() / bbox.width() as f32,
+                    viewport.height() / bbox.height() as f32,
+                );
+                self.center_view(viewport, bbox);
+            }
+        }
+    }
+}
+
+/// The scale at 100% zoom, i.e. real size, in screen pixels per gerber unit.
+fn reference_scale(units: Unit, display_info: &DisplayInfo) -> f32 {
+    // Get effective pixels per inch
+    let device_ppi = display_info.effective_ppi();
+
+    match units {
+        Unit::Millimeters => device_ppi / MILLIMETERS_PER_INCH as f32, // Convert to pixels per mm
+        Unit::Inches => device_ppi,                                    // pixels per inch
+    }
+}
+
+/// A zoom level for zoom menus and toolbar buttons, see [`ViewState::apply_zoom_preset`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZoomPreset {
+    /// A zoom level in percent, where 100% is real size, see [`ViewState::zoom_level_percent`].
+    Percent(f32),
+    /// The whole content is visible, with a margin, like [`ViewState::fit_view`].
+    Fit,
+    /// The content fills the viewport, some of it may be cut off.
+    Fill,
+}
+
+impl ZoomPreset {
+    /// 25%, 50%, 100%, 200%, 400%, fit and fill.
+    pub const ALL: [ZoomPreset; 7] = [
+        ZoomPreset::Percent(25.0),
+        ZoomPreset::Percent(50.0),
+        ZoomPreset::Percent(100.0),
+        ZoomPreset::Percent(200.0),
+        ZoomPreset::Percent(400.0),
+        ZoomPreset::Fit,
+        ZoomPreset::Fill,
+    ];
+
+    /// A label for menus, e.g. `200%` or `Fit`.
+    pub fn label(&self) -> String {
+        match self {
+            ZoomPreset::Percent(zoom_level) => format!("{zoom_level}%"),
+            ZoomPreset::Fit => "Fit".to_string(),
+            ZoomPreset::Fill => "Fill".to_string(),
+        }
+    }
 }
 
 /// Struct to hold display information including DPI values
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DisplayInfo {
     /// DPI along the horizontal axis (pixels per inch)
     pub dpi_x: f32,
@@ -239,3 +1041,119 @@ impl DisplayInfo {
         self.dpi_y = dpi_y;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(0.01, 10000.0, 0.001, 0.02)]
+    #[case(0.01, 10000.0, 50.0, 50.0)]
+    #[case(0.01, 10000.0, 30000.0, 20000.0)]
+    #[case(10.0, 1.0, 5.0, 2.0)]
+    #[case(f32::NAN, f32::NAN, 5.0, 5.0)]
+    fn test_clamp_scale(#[case] min_zoom: f32, #[case] max_zoom: f32, #[case] scale: f32, #[case] expected: f32) {
+        // given
+        let zoom_settings = ZoomSettings {
+            min_zoom,
+            max_zoom,
+            ..ZoomSettings::default()
+        };
+
+        // expect
+        assert_eq!(zoom_settings.clamp_scale(scale, 2.0), expected);
+    }
+
+    #[rstest]
+    #[case(GridUnit::Millimeters, None, None, "X: 25.400 mm, Y: -12.700 mm")]
+    #[case(GridUnit::Mils, None, None, "X: 1000.0 mil, Y: -500.0 mil")]
+    #[case(GridUnit::Inches, Some(2), None, "X: 1.00 in, Y: -0.50 in")]
+    #[case(
+        GridUnit::Millimeters,
+        Some(1),
+        Some(Point2::new(25.4, 0.0)),
+        "dX: 0.0 mm, dY: -12.7 mm"
+    )]
+    fn test_coordinate_readout(
+        #[case] unit: GridUnit,
+        #[case] precision: Option<usize>,
+        #[case] datum: Option<Point2<f64>>,
+        #[case] expected: &str,
+    ) {
+        // given
+        let readout = CoordinateReadout {
+            unit,
+            layer_units: Unit::Millimeters,
+            precision,
+            datum,
+        };
+
+        // expect
+        assert_eq!(readout.format(Point2::new(25.4, -12.7)), expected);
+    }
+
+    #[rstest]
+    #[case(Point2::new(3.0, 4.0), "L: 5.000 mm, dX: 3.000 mm, dY: 4.000 mm, A: 53.13°")]
+    #[case(Point2::new(-2.0, 0.0), "L: 2.000 mm, dX: -2.000 mm, dY: 0.000 mm, A: 180.00°")]
+    fn test_measurement(#[case] end: Point2<f64>, #[case] expected: &str) {
+        // given
+        let measurement = Measurement {
+            start: Point2::new(0.0, 0.0),
+            end,
+        };
+
+        // expect
+        assert_eq!(measurement.format(&CoordinateReadout::default()), expected);
+    }
+
+    #[test]
+    fn test_zoom_to() {
+        // given
+        let mut view = ViewState {
+            translation: Vec2::new(10.0, 20.0),
+            scale: 1.0,
+            base_scale: 1.0,
+            datum: None,
+        };
+        let display_info = DisplayInfo::new().with_dpi(254.0, 254.0);
+        let anchor = Pos2::new(100.0, 50.0);
+        let anchored = view.screen_to_gerber_coords(anchor);
+
+        // when
+        let zoom_level = view.zoom_to(200.0, anchor, Unit::Millimeters, &display_info);
+
+        // then
+        // 254 dpi is 10 pixels per mm
+        assert!((zoom_level - 200.0).abs() < 1e-3);
+        assert!((view.scale - 20.0).abs() < 1e-4);
+        assert!((view.gerber_to_screen_coords(anchored) - anchor).length() < 1e-3);
+    }
+
+    #[rstest]
+    // on screen
+    #[case(Vec2::new(100.0, 100.0), Vec2::new(100.0, 100.0))]
+    // panned off the left, and the top, of the viewport
+    #[case(Vec2::new(-500.0, -500.0), Vec2::new(-90.0, 10.0))]
+    // panned off the right of the viewport
+    #[case(Vec2::new(500.0, 100.0), Vec2::new(190.0, 100.0))]
+    fn test_clamp_to_bounds(#[case] translation: Vec2, #[case] expected: Vec2) {
+        // given
+        // 100x100 points of content, from 0,0 to 10,10 at 10 points per unit, in a 200x200 viewport
+        let mut view = ViewState {
+            translation,
+            scale: 10.0,
+            base_scale: 10.0,
+            datum: None,
+        };
+        let viewport = Rect::from_min_size(Pos2::ZERO, Vec2::splat(200.0));
+        let bbox = BoundingBox::from_points(&[Point2::new(0.0, 0.0), Point2::new(10.0, 10.0)]);
+
+        // when
+        view.clamp_to_bounds(viewport, &bbox, 10.0);
+
+        // then
+        assert_eq!(view.translation, expected);
+    }
+}
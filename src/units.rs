@@ -0,0 +1,32 @@
+use gerber_types::Unit;
+
+pub const MILLIMETERS_PER_INCH: f64 = 25.4;
+
+/// Returns the factor to multiply a value in `from` units by to get a value in `to` units.
+pub fn unit_conversion_factor(from: Unit, to: Unit) -> f64 {
+    match (from, to) {
+        (Unit::Inches, Unit::Millimeters) => MILLIMETERS_PER_INCH,
+        (Unit::Millimeters, Unit::Inches) => 1.0 / MILLIMETERS_PER_INCH,
+        _ => 1.0,
+    }
+}
+
+pub fn convert_units(value: f64, from: Unit, to: Unit) -> f64 {
+    value * unit_conversion_factor(from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(1.0, Unit::Inches, Unit::Millimeters, 25.4)]
+    #[case(25.4, Unit::Millimeters, Unit::Inches, 1.0)]
+    #[case(2.0, Unit::Millimeters, Unit::Millimeters, 2.0)]
+    #[case(2.0, Unit::Inches, Unit::Inches, 2.0)]
+    fn test_convert_units(#[case] value: f64, #[case] from: Unit, #[case] to: Unit, #[case] expected: f64) {
+        assert!((convert_units(value, from, to) - expected).abs() < 1e-9);
+    }
+}
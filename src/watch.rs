@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use log::{debug, info, warn};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use thiserror::Error;
+
+use crate::project::load_file;
+use crate::GerberLayer;
+
+#[derive(Error, Debug)]
+pub enum WatchError {
+    #[error("IO error. path: {path:?}, cause: {cause}")]
+    Io { path: PathBuf, cause: std::io::Error },
+    #[error("Watch error. cause: {0}")]
+    Notify(#[from] notify::Error),
+}
+
+/// Watches gerber files and rebuilds the layer of a file when it changes on disk.
+///
+/// The callback is called from a background thread, with the path of the file, as passed to [`LayerWatcher::watch`],
+/// and the rebuilt layer. Typically the callback sends the layer to the UI thread and requests a repaint.
+///
+/// Only the changed files are reloaded. Files that fail to parse, e.g. because the EDA tool is part-way through
+/// writing them, are logged and skipped, the next change will reload them again.
+///
+/// The directory of each file is watched, rather than the file itself, so that files which are replaced rather than
+/// modified, as some tools do, continue to be watched.
+pub struct LayerWatcher {
+    watcher: RecommendedWatcher,
+    /// canonical path -> path as passed to `watch`
+    files: Arc<Mutex<HashMap<PathBuf, PathBuf>>>,
+    /// canonical directory path -> number of watched files in the directory
+    directories: HashMap<PathBuf, usize>,
+}
+
+impl LayerWatcher {
+    pub fn new(mut on_reload: impl FnMut(&Path, GerberLayer) + Send + 'static) -> Result<Self, WatchError> {
+        let files: Arc<Mutex<HashMap<PathBuf, PathBuf>>> = Arc::default();
+
+        let watcher = notify::recommended_watcher({
+            let files = files.clone();
+            move |result: notify::Result<Event>| {
+                let event = match result {
+                    Ok(event) => event,
+                    Err(error) => {
+                        warn!("Watch error. error: {}", error);
+                        return;
+                    }
+                };
+                if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    return;
+                }
+
+                // a single event can refer to the same file more than once, e.g. renames
+                let changed: HashSet<PathBuf> = {
+                    let files = files.lock().unwrap();
+                    event
+                        .paths
+                        .iter()
+                        .filter_map(|path| std::fs::canonicalize(path).ok())
+                        .filter_map(|path| files.get(&path).cloned())
+                        .collect()
+                };
+
+                for path in changed {
+                    debug!("Reloading layer. path: {:?}", path);
+                    match load_file(&path) {
+                        Ok(layer) => on_reload(&path, layer),
+                        Err(error) => warn!("Unable to reload layer. error: {}", error),
+                    }
+                }
+            }
+        })?;
+
+        Ok(Self {
+            watcher,
+            files,
+            directories: HashMap::default(),
+        })
+    }
+
+    /// Starts watching a file, the file must exist.
+    pub fn watch(&mut self, path: impl AsRef<Path>) -> Result<(), WatchError> {
+        let path = path.as_ref();
+        let (canonical_path, directory) = Self::canonical_paths(path)?;
+
+        if self
+            .files
+            .lock()
+            .unwrap()
+            .contains_key(&canonical_path)
+        {
+            // already watched
+            return Ok(());
+        }
+
+        // only register the file once its directory is watched, so that a failed call can be retried
+        let count = self
+            .directories
+            .get(&directory)
+            .copied()
+            .unwrap_or_default();
+        if count == 0 {
+            self.watcher
+                .watch(&directory, RecursiveMode::NonRecursive)?;
+        }
+        self.directories
+            .insert(directory, count + 1);
+        self.files
+            .lock()
+            .unwrap()
+            .insert(canonical_path, path.to_path_buf());
+
+        info!("Watching file. path: {:?}", path);
+        Ok(())
+    }
+
+    /// Stops watching a file, files that were deleted can be unwatched using the path passed to
+    /// [`LayerWatcher::watch`].
+    pub fn unwatch(&mut self, path: impl AsRef<Path>) -> Result<(), WatchError> {
+        let path = path.as_ref();
+        let mut files = self.files.lock().unwrap();
+        let (canonical_path, directory) = match Self::canonical_paths(path) {
+            Ok(paths) => paths,
+            // the file no longer exists, find it by the path it was watched with
+            Err(error) => {
+                let Some(canonical_path) = files
+                    .iter()
+                    .find(|(_, watched_path)| watched_path.as_path() == path)
+                    .map(|(canonical_path, _)| canonical_path.clone())
+                else {
+                    return Err(error);
+                };
+                let directory = Self::directory(&canonical_path);
+                (canonical_path, directory)
+            }
+        };
+
+        if files.remove(&canonical_path).is_none() {
+            return Ok(());
+        }
+        drop(files);
+
+        if let Some(count) = self.directories.get_mut(&directory) {
+            *count -= 1;
+            if *count == 0 {
+                self.directories.remove(&directory);
+                self.watcher.unwatch(&directory)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The watched files, as passed to [`LayerWatcher::watch`].
+    pub fn watched_files(&self) -> Vec<PathBuf> {
+        self.files
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    fn canonical_paths(path: &Path) -> Result<(PathBuf, PathBuf), WatchError> {
+        let canonical_path = std::fs::canonicalize(path).map_err(|cause| WatchError::Io {
+            path: path.to_path_buf(),
+            cause,
+        })?;
+        let directory = Self::directory(&canonical_path);
+
+        Ok((canonical_path, directory))
+    }
+
+    fn directory(canonical_path: &Path) -> PathBuf {
+        canonical_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| canonical_path.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watcher() -> LayerWatcher {
+        LayerWatcher::new(|_, _| {}).unwrap()
+    }
+
+    #[test]
+    fn test_watch_and_unwatch() {
+        // given
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("board-F_Cu.gbr");
+        std::fs::write(&path, "").unwrap();
+        let mut watcher = watcher();
+
+        // when
+        watcher.watch(&path).unwrap();
+
+        // then
+        assert_eq!(watcher.watched_files(), vec![path.clone()]);
+        assert_eq!(
+            watcher
+                .directories
+                .values()
+                .sum::<usize>(),
+            1
+        );
+
+        // when
+        watcher.unwatch(&path).unwrap();
+
+        // then
+        assert!(watcher.watched_files().is_empty());
+        assert!(watcher.directories.is_empty());
+    }
+
+    #[test]
+    fn test_watch_duplicate() {
+        // given
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("board-F_Cu.gbr");
+        std::fs::write(&path, "").unwrap();
+        let mut watcher = watcher();
+        watcher.watch(&path).unwrap();
+
+        // when
+        // the same file, via another path
+        watcher
+            .watch(
+                directory
+                    .path()
+                    .join(".")
+                    .join("board-F_Cu.gbr"),
+            )
+            .unwrap();
+
+        // then
+        assert_eq!(watcher.watched_files(), vec![path.clone()]);
+        assert_eq!(
+            watcher
+                .directories
+                .values()
+                .sum::<usize>(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_unwatch_deleted_file() {
+        // given
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("board-F_Cu.gbr");
+        std::fs::write(&path, "").unwrap();
+        let mut watcher = watcher();
+        watcher.watch(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // when
+        watcher.unwatch(&path).unwrap();
+
+        // then
+        assert!(watcher.watched_files().is_empty());
+        assert!(watcher.directories.is_empty());
+    }
+
+    #[test]
+    fn test_watch_missing_file() {
+        // given
+        let directory = tempfile::tempdir().unwrap();
+        let mut watcher = watcher();
+
+        // expect
+        assert!(matches!(
+            watcher.watch(directory.path().join("missing.gbr")),
+            Err(WatchError::Io { .. })
+        ));
+        assert!(watcher.watched_files().is_empty());
+    }
+}
@@ -0,0 +1,529 @@
+use egui::{Color32, Response, Sense, Ui};
+use nalgebra::Point2;
+
+use crate::geometry::{BoundingBox, GerberTransform};
+use crate::{
+    draw_bounding_box_dimensions, draw_crosshair, draw_cursor_guides, draw_measurement, draw_outline, draw_rulers,
+    draw_styled_marker, ColorTheme, CursorGuideSettings, DimensionSettings, GerberLayer, GerberRenderer, LayerFocus,
+    LayerInfo, LayerSet, MarkerStyle, Measurement, MeasurementSettings, PanClamp, PrimitiveSummary,
+    RenderConfiguration, RulerSettings, SelectionChange, UiState, ViewState, DEFAULT_ANIMATION_DURATION,
+};
+
+/// The state of a [`GerberViewerWidget`] that is kept between frames, e.g. in your app state.
+pub struct GerberViewerState {
+    pub view: ViewState,
+    pub ui_state: UiState,
+    pub renderer: GerberRenderer,
+    needs_fit: bool,
+}
+
+impl Default for GerberViewerState {
+    fn default() -> Self {
+        Self {
+            view: ViewState::default(),
+            ui_state: UiState::default(),
+            renderer: GerberRenderer::default(),
+            needs_fit: true,
+        }
+    }
+}
+
+impl GerberViewerState {
+    /// Fits the layers to the viewport the next time the widget is shown, e.g. after loading other layers.
+    pub fn request_fit(&mut self) {
+        self.needs_fit = true;
+    }
+}
+
+/// What happened in a [`GerberViewerWidget`] this frame.
+pub struct GerberViewerResponse {
+    /// The response of the viewport.
+    pub response: Response,
+    /// The cursor position in gerber coordinates, `None` if the cursor is not over the viewport.
+    pub cursor_gerber_coords: Option<Point2<f64>>,
+    /// The primitive under the cursor, see [`GerberViewerWidget::hover_highlight`].
+    pub hovered: Option<PrimitiveSummary>,
+    /// The change of [`UiState::selection`] this frame, see [`GerberViewerWidget::selection`].
+    pub selection_change: SelectionChange,
+    /// The measurement being dragged, see [`GerberViewerWidget::measure`].
+    pub measurement: Option<Measurement>,
+}
+
+/// A viewer for gerber layers, with panning, zooming, fitting and overlays, instead of wiring up [`ViewState`],
+/// [`UiState`] and [`GerberRenderer`] in each app.
+///
+/// Create one each frame, using the [`GerberViewerState`] kept in your app state, e.g.
+/// ```ignore
+/// GerberViewerWidget::new(&mut self.viewer_state)
+///     .layer(&self.top_copper, Color32::from_rgb(184, 115, 51))
+///     .layer(&self.top_silkscreen, Color32::WHITE)
+///     .origin_crosshair(Some(Color32::BLUE))
+///     .show(ui);
+/// ```
+/// The widget fills the available space.  The layers are fitted to the viewport the first time the widget is shown,
+/// after [`GerberViewerState::request_fit`], and when the viewport is double-clicked.
+pub struct GerberViewerWidget<'a> {
+    state: &'a mut GerberViewerState,
+    layers: Vec<ViewerLayer<'a>>,
+    configuration: RenderConfiguration,
+    focus: Option<LayerFocus>,
+    transform: GerberTransform,
+    fit_zoom_factor: f32,
+    animation_duration: f64,
+    pan: bool,
+    zoom: bool,
+    box_zoom: bool,
+    pan_clamp: Option<f32>,
+    double_click_to_fit: bool,
+    origin_crosshair: Option<Color32>,
+    center_crosshair: Option<Color32>,
+    datum_marker: Option<MarkerStyle>,
+    bounding_box: Option<Color32>,
+    dimensions: Option<DimensionSettings>,
+    hover_highlight: Option<Color32>,
+    selection: Option<Color32>,
+    measure: Option<MeasurementSettings>,
+    rulers: Option<RulerSettings>,
+    cursor_guides: Option<CursorGuideSettings>,
+}
+
+impl<'a> GerberViewerWidget<'a> {
+    pub fn new(state: &'a mut GerberViewerState) -> Self {
+        Self {
+            state,
+            layers: vec![],
+            configuration: RenderConfiguration::default(),
+            focus: None,
+            transform: GerberTransform::default(),
+            fit_zoom_factor: 1.0,
+            animation_duration: DEFAULT_ANIMATION_DURATION,
+            pan: true,
+            zoom: true,
+            box_zoom: true,
+            pan_clamp: None,
+            double_click_to_fit: true,
+            origin_crosshair: None,
+            center_crosshair: None,
+            datum_marker: None,
+            bounding_box: None,
+            dimensions: None,
+            hover_highlight: None,
+            selection: None,
+            measure: None,
+            rulers: None,
+            cursor_guides: None,
+        }
+    }
+
+    /// Adds a layer, layers are painted in the order they are added.
+    pub fn layer(mut self, layer: &'a GerberLayer, color: Color32) -> Self {
+        self.layers.push(ViewerLayer {
+            layer,
+            color,
+            info: None,
+        });
+        self
+    }
+
+    /// Adds layers, e.g. from a [`LayerSet`] with the colors of a [`ColorTheme`].
+    pub fn layers(mut self, layers: impl IntoIterator<Item = (&'a GerberLayer, Color32)>) -> Self {
+        for (layer, color) in layers {
+            self = self.layer(layer, color);
+        }
+        self
+    }
+
+    /// Adds a layer of a [`LayerSet`], with its own transform and opacity, see [`LayerInfo::combined_transform`].
+    pub fn layer_info(mut self, info: &'a LayerInfo, color: Color32) -> Self {
+        self.layers.push(ViewerLayer {
+            layer: &info.layer,
+            color,
+            info: Some(info),
+        });
+        self
+    }
+
+    /// Adds the visible layers of a [`LayerSet`], in drawing order, with their colors, or the colors of the `theme`, and
+    /// their own transform and opacity, see [`GerberViewerWidget::layer_info`].
+    pub fn layer_set(mut self, layer_set: &'a LayerSet, theme: ColorTheme) -> Self {
+        for info in layer_set.visible_layers() {
+            self = self.layer_info(info, info.color32(theme));
+        }
+        self
+    }
+
+    /// The configuration used for all the layers.
+    pub fn configuration(mut self, configuration: RenderConfiguration) -> Self {
+        self.configuration = configuration;
+        self
+    }
+
+    /// Paints the focused layer at full intensity and dims the other layers, the layers are identified by the order
+    /// they were added, see [`LayerFocus`].
+    pub fn focus(mut self, focus: Option<LayerFocus>) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    /// The transform used for all the layers, e.g. [`crate::SideView::transform`], after the transform of each layer of a
+    /// [`LayerSet`].
+    pub fn transform(mut self, transform: GerberTransform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// The zoom after fitting the layers to the viewport, e.g. `0.5` for 50%, `1.0` by default.
+    pub fn fit_zoom_factor(mut self, fit_zoom_factor: f32) -> Self {
+        self.fit_zoom_factor = fit_zoom_factor;
+        self
+    }
+
+    /// The duration, in seconds, of the animation when fitting the layers by double-clicking, `0.0` to change the view
+    /// immediately, see [`UiState::animate_to`].
+    pub fn animation_duration(mut self, animation_duration: f64) -> Self {
+        self.animation_duration = animation_duration;
+        self
+    }
+
+    /// Panning by dragging with the primary or middle mouse button, or space and the primary mouse button, enabled by
+    /// default, see [`crate::PanSettings`] for choosing the buttons.
+    pub fn pan(mut self, pan: bool) -> Self {
+        self.pan = pan;
+        self
+    }
+
+    /// Zooming around the cursor with the scroll wheel, enabled by default.
+    pub fn zoom(mut self, zoom: bool) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    /// Zooming to a rectangle, selected by dragging with the secondary mouse button, or with shift and the primary mouse
+    /// button, enabled by default, see [`UiState::handle_box_zoom`].
+    pub fn box_zoom(mut self, box_zoom: bool) -> Self {
+        self.box_zoom = box_zoom;
+        self
+    }
+
+    /// Keeps at least `margin` screen points of the layers in the viewport when panning and zooming, so they can't be
+    /// lost, `None` by default, see [`PanClamp`].
+    pub fn pan_clamp(mut self, margin: Option<f32>) -> Self {
+        self.pan_clamp = margin;
+        self
+    }
+
+    /// Fitting the layers to the viewport by double-clicking, enabled by default.
+    pub fn double_click_to_fit(mut self, double_click_to_fit: bool) -> Self {
+        self.double_click_to_fit = double_click_to_fit;
+        self
+    }
+
+    /// Draws a crosshair at the gerber origin, 0,0.
+    pub fn origin_crosshair(mut self, color: Option<Color32>) -> Self {
+        self.origin_crosshair = color;
+        self
+    }
+
+    /// Draws a crosshair at the center of the viewport.
+    pub fn center_crosshair(mut self, color: Option<Color32>) -> Self {
+        self.center_crosshair = color;
+        self
+    }
+
+    /// Draws a marker at [`ViewState::datum`], if set, e.g. [`MarkerStyle::datum`].  The cursor position is shown
+    /// relative to the datum, see [`crate::CoordinateReadout::datum`].
+    pub fn datum_marker(mut self, style: Option<MarkerStyle>) -> Self {
+        self.datum_marker = style;
+        self
+    }
+
+    /// Outlines the bounding box of all the layers, after the transform.
+    pub fn bounding_box(mut self, color: Option<Color32>) -> Self {
+        self.bounding_box = color;
+        self
+    }
+
+    /// Draws the width and height of the bounding box of all the layers, after the transform, formatted using
+    /// [`UiState::readout`], see [`draw_bounding_box_dimensions`].
+    pub fn dimensions(mut self, settings: Option<DimensionSettings>) -> Self {
+        self.dimensions = settings;
+        self
+    }
+
+    /// Highlights the primitive under the cursor, of the top-most layer, see [`GerberRenderer::paint_hover`] and
+    /// [`GerberViewerResponse::hovered`].
+    pub fn hover_highlight(mut self, color: Option<Color32>) -> Self {
+        self.hover_highlight = color;
+        self
+    }
+
+    /// Selecting primitives by clicking, and by dragging a marquee if panning with the primary button is disabled, the
+    /// selected primitives are highlighted in the color, see [`UiState::handle_selection`].  The selected primitives
+    /// are identified by the index of their layer in the order the layers were added.
+    pub fn selection(mut self, color: Option<Color32>) -> Self {
+        self.selection = color;
+        self
+    }
+
+    /// Measuring by dragging with the primary mouse button, instead of panning, the measurement is drawn with its
+    /// length, x and y distances and angle, see [`UiState::handle_measuring`] and [`draw_measurement`].
+    pub fn measure(mut self, settings: Option<MeasurementSettings>) -> Self {
+        self.measure = settings;
+        self
+    }
+
+    /// Draws rulers along the top and left edges of the viewport, see [`draw_rulers`].
+    pub fn rulers(mut self, settings: Option<RulerSettings>) -> Self {
+        self.rulers = settings;
+        self
+    }
+
+    /// Draws guide lines through the cursor across the viewport, labelled with the cursor position formatted using
+    /// [`UiState::readout`], see [`draw_cursor_guides`].
+    pub fn cursor_guides(mut self, settings: Option<CursorGuideSettings>) -> Self {
+        self.cursor_guides = settings;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> GerberViewerResponse {
+        let Self {
+            state,
+            layers,
+            configuration,
+            focus,
+            transform,
+            fit_zoom_factor,
+            animation_duration,
+            pan,
+            zoom,
+            box_zoom,
+            pan_clamp,
+            double_click_to_fit,
+            origin_crosshair,
+            center_crosshair,
+            datum_marker,
+            bounding_box,
+            dimensions,
+            hover_highlight,
+            selection,
+            measure,
+            rulers,
+            cursor_guides,
+        } = self;
+
+        let response = ui.allocate_rect(ui.available_rect_before_wrap(), Sense::click_and_drag());
+        let viewport = response.rect;
+
+        // the transform of each layer, followed by the transform of the widget
+        let transforms = layers
+            .iter()
+            .map(|layer| layer.transform(&transform))
+            .collect::<Vec<_>>();
+
+        let mut bounds = BoundingBox::default();
+        for (layer, transform) in layers.iter().zip(&transforms) {
+            if layer.layer.is_empty() {
+                continue;
+            }
+            let vertices = layer
+                .layer
+                .bounding_box()
+                .transform_vertices(transform);
+            bounds.expand(&BoundingBox::from_points(&vertices));
+        }
+
+        let ui_state = &mut state.ui_state;
+        ui_state.pan_clamp = pan_clamp
+            .filter(|_| !bounds.is_empty())
+            .map(|margin| PanClamp {
+                bounds: bounds.clone(),
+                margin,
+            });
+        if state.needs_fit && !bounds.is_empty() {
+            state
+                .view
+                .fit_view(viewport, &bounds, fit_zoom_factor);
+            state.needs_fit = false;
+        } else if double_click_to_fit && response.double_clicked() && !bounds.is_empty() {
+            ui_state.animate_fit_view(
+                &mut state.view,
+                &viewport,
+                &bounds,
+                fit_zoom_factor,
+                animation_duration,
+                ui,
+            );
+        }
+        ui_state.update_animation(&mut state.view, ui);
+        ui_state.update_cursor_position(&state.view, &response, ui);
+        if box_zoom {
+            ui_state.handle_box_zoom(&mut state.view, &viewport, &response, ui);
+        }
+        let selection_change = match selection {
+            Some(_) => {
+                let selectable = layers
+                    .iter()
+                    .zip(&transforms)
+                    .map(|(layer, transform)| (layer.layer, *transform))
+                    .collect::<Vec<_>>();
+                ui_state.handle_layer_selection(&state.view, &selectable, &response, ui)
+            }
+            None => SelectionChange::default(),
+        };
+        ui_state.measure_mode = measure.is_some();
+        let measurement = ui_state.handle_measuring(&state.view, &response, ui);
+        if pan {
+            ui_state.handle_panning(&mut state.view, &response, ui);
+        }
+        if zoom {
+            ui_state.handle_zooming(&mut state.view, &response, ui);
+        }
+        ui_state.center_screen_pos = viewport.center();
+        ui_state.origin_screen_pos = state
+            .view
+            .gerber_to_screen_coords(Point2::new(0.0, 0.0));
+        ui_state.readout.datum = state.view.datum;
+        if !response.hovered() {
+            ui_state.cursor_gerber_coords = None;
+        }
+
+        let painter = ui.painter().with_clip_rect(viewport);
+        let painting_order = match &focus {
+            Some(focus) => focus.painting_order(0..layers.len()),
+            None => (0..layers.len()).collect(),
+        };
+        for index in painting_order {
+            let layer = &layers[index];
+            let configuration = match &focus {
+                Some(focus) => focus.configuration(index, &configuration),
+                None => configuration.clone(),
+            };
+            let configuration = RenderConfiguration {
+                opacity: configuration.opacity * layer.opacity(),
+                ..configuration
+            };
+            state.renderer.paint_layer(
+                &painter,
+                state.view,
+                layer.layer,
+                layer.color,
+                &configuration,
+                &transforms[index],
+            );
+        }
+
+        if let Some(color) = selection {
+            for (index, (layer, transform)) in layers
+                .iter()
+                .zip(&transforms)
+                .enumerate()
+            {
+                let primitives = state
+                    .ui_state
+                    .selection
+                    .primitives_of_layer(index);
+                if !primitives.is_empty() {
+                    state
+                        .renderer
+                        .paint_highlight(&painter, state.view, layer.layer, transform, &primitives, color);
+                }
+            }
+        }
+
+        let hovered = match (hover_highlight, response.hover_pos()) {
+            (Some(highlight_color), Some(position)) => layers
+                .iter()
+                .zip(&transforms)
+                .rev()
+                .find_map(|(layer, transform)| {
+                    state.renderer.paint_hover(
+                        &painter,
+                        state.view,
+                        layer.layer,
+                        transform,
+                        position,
+                        2.0,
+                        highlight_color,
+                    )
+                }),
+            _ => None,
+        };
+
+        if let Some(color) = bounding_box.filter(|_| !bounds.is_empty()) {
+            let vertices = bounds
+                .vertices()
+                .into_iter()
+                .map(|vertex| {
+                    state
+                        .view
+                        .gerber_to_screen_coords(vertex)
+                })
+                .collect();
+            draw_outline(&painter, vertices, color);
+        }
+        if let Some(settings) = &dimensions {
+            draw_bounding_box_dimensions(&painter, &state.view, settings, &bounds, &state.ui_state.readout);
+        }
+        if let Some(color) = origin_crosshair {
+            draw_crosshair(&painter, state.ui_state.origin_screen_pos, color);
+        }
+        if let Some(color) = center_crosshair {
+            draw_crosshair(&painter, state.ui_state.center_screen_pos, color);
+        }
+        if let (Some(style), Some(datum)) = (&datum_marker, state.view.datum) {
+            draw_styled_marker(&painter, &state.view, datum, style);
+        }
+        if let (Some(settings), Some(measurement)) = (&measure, &state.ui_state.measurement) {
+            draw_measurement(&painter, &state.view, settings, measurement, &state.ui_state.readout);
+        }
+        if let Some(settings) = &cursor_guides {
+            draw_cursor_guides(
+                &painter,
+                viewport,
+                &state.view,
+                settings,
+                state.ui_state.cursor_gerber_coords,
+                Some(&state.ui_state.readout),
+            );
+        }
+        if let Some(settings) = &rulers {
+            draw_rulers(
+                &painter,
+                viewport,
+                &state.view,
+                settings,
+                state.ui_state.cursor_gerber_coords,
+            );
+        }
+
+        GerberViewerResponse {
+            response,
+            cursor_gerber_coords: state.ui_state.cursor_gerber_coords,
+            hovered,
+            selection_change,
+            measurement,
+        }
+    }
+}
+
+/// A layer of a [`GerberViewerWidget`].
+struct ViewerLayer<'a> {
+    layer: &'a GerberLayer,
+    color: Color32,
+    /// For the transform and opacity of layers of a [`LayerSet`].
+    info: Option<&'a LayerInfo>,
+}
+
+impl ViewerLayer<'_> {
+    fn transform(&self, transform: &GerberTransform) -> GerberTransform {
+        match self.info {
+            Some(info) => info.combined_transform(transform),
+            None => *transform,
+        }
+    }
+
+    fn opacity(&self) -> f32 {
+        self.info
+            .map_or(1.0, |info| info.opacity)
+    }
+}